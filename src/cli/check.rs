@@ -1,15 +1,321 @@
+use codespan::{CodeMap, FileMap};
+use codespan_reporting::{self, Diagnostic, Severity};
 use failure::Error;
-use std::path::PathBuf;
+use std::collections::hash_map::{Entry, HashMap};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use library;
+use semantics::lint::{self, NamingConvention};
+use semantics::CheckedModule;
+use syntax::core::{self, RcTerm};
+use syntax::parse;
+use syntax::translation::ToCore;
+use syntax::var::GenId;
 
 /// Options for the `check` subcommand
 #[derive(Debug, StructOpt)]
 pub struct Opts {
-    /// Files to check
+    /// Check the example programs that are embedded in Pikelet
+    #[structopt(long = "examples")]
+    pub examples: bool,
+
+    /// Enable opt-in lints, reported as warnings
+    #[structopt(long = "lint", raw(possible_values = "&[\"naming\", \"unused-imports\"]"))]
+    pub lints: Vec<String>,
+
+    /// Warn about the names exposed by imports that are never used, like
+    /// `--lint unused-imports`
+    #[structopt(long = "warn-unused")]
+    pub warn_unused: bool,
+
+    /// The naming convention to enforce with `--lint naming`
+    #[structopt(long = "naming-convention", parse(try_from_str), default_value = "kebab-case",
+                raw(possible_values = "&[\"kebab-case\", \"snake_case\", \"camelCase\"]"))]
+    pub naming_convention: NamingConvention,
+
+    /// The format to report the results of checking in. `json` prints a
+    /// summary object on the last line of the output.
+    #[structopt(long = "message-format", default_value = "human",
+                raw(possible_values = "&[\"human\", \"json\"]"))]
+    pub message_format: String,
+
+    /// The maximum number of errors to report, or `0` to report all of them
+    #[structopt(long = "max-errors", default_value = "20")]
+    pub max_errors: usize,
+
+    /// Print the core syntax of each definition that was checked. `sexp`
+    /// prints the definitions as they were written, and `core` prints them
+    /// after they have been elaborated.
+    #[structopt(long = "emit", raw(possible_values = "&[\"sexp\", \"core\"]"))]
+    pub emit: Option<String>,
+
+    /// The id to start generating names from. Checking the same files with
+    /// the same seed generates the same names.
+    #[structopt(long = "seed")]
+    pub seed: Option<u32>,
+
+    /// Files or directories to check, or `-` to read from stdin. Directories
+    /// are searched recursively for `.pi` files.
     #[structopt(name = "FILE", parse(from_os_str))]
     pub files: Vec<PathBuf>,
 }
 
 /// Run the `check` subcommand with the given options
-pub fn run(_check_opts: Opts) -> Result<(), Error> {
-    unimplemented!()
+pub fn run(opts: Opts) -> Result<(), Error> {
+    let stdout = io::stdout();
+    run_to(opts, &mut stdout.lock())
+}
+
+/// Run the `check` subcommand with the given options, writing the emitted
+/// definitions and the summary to `out`
+pub fn run_to<W: Write>(opts: Opts, out: &mut W) -> Result<(), Error> {
+    if opts.examples {
+        return check_examples();
+    }
+
+    if let Some(seed) = opts.seed {
+        GenId::reset(seed);
+    }
+
+    let start = Instant::now();
+    let mut codemap = CodeMap::new();
+    let mut failures = 0;
+    let mut module_paths = HashMap::new();
+    let mut summary = Summary::default();
+
+    for path in super::expand_paths(&opts.files)? {
+        let file = super::add_filemap(&mut codemap, &path, io::stdin())?;
+        summary.files_checked += 1;
+
+        if opts.lints.iter().any(|lint| lint == "naming") {
+            let (module, _) = parse::module(&file);
+            for warning in lint::naming(&module, opts.naming_convention) {
+                let diagnostic = warning.to_diagnostic();
+                if summary.record(&diagnostic, opts.max_errors) {
+                    codespan_reporting::emit(&codemap, &diagnostic);
+                }
+            }
+        }
+
+        if opts.warn_unused || opts.lints.iter().any(|lint| lint == "unused-imports") {
+            let (module, errors) = parse::module(&file);
+            // Modules that fail to parse are reported when they are checked
+            if errors.is_empty() {
+                for warning in lint::unused_imports(&module.to_core()) {
+                    let diagnostic = warning.to_diagnostic();
+                    if summary.record(&diagnostic, opts.max_errors) {
+                        codespan_reporting::emit(&codemap, &diagnostic);
+                    }
+                }
+            }
+        }
+
+        let root = path.parent().unwrap_or_else(|| Path::new(""));
+        match ::load_file_with_imports(&mut codemap, &file, root) {
+            Ok(module) => {
+                for diagnostic in module.warnings.iter().map(|warning| warning.to_diagnostic()) {
+                    if summary.record(&diagnostic, opts.max_errors) {
+                        codespan_reporting::emit(&codemap, &diagnostic);
+                    }
+                }
+
+                match module_paths.entry(module.name) {
+                    Entry::Vacant(entry) => {
+                        match opts.emit.as_ref().map(String::as_str) {
+                            Some("sexp") => emit_sexp(out, &file)?,
+                            Some("core") => emit_core(out, &module)?,
+                            Some(_) | None => {},
+                        }
+                        entry.insert(path);
+                    },
+                    Entry::Occupied(entry) => {
+                        return Err(format_err!(
+                            "the module `{}` is defined in both `{}` and `{}`",
+                            entry.key(),
+                            entry.get().display(),
+                            path.display(),
+                        ));
+                    },
+                }
+            },
+            Err(diagnostics) => {
+                for diagnostic in &diagnostics {
+                    if summary.record(diagnostic, opts.max_errors) {
+                        codespan_reporting::emit(&codemap, diagnostic);
+                    }
+                }
+                failures += 1;
+            },
+        }
+    }
+
+    if let Some(note) = summary.suppressed_note(opts.max_errors) {
+        eprintln!("{}", note);
+    }
+
+    if opts.message_format == "json" {
+        writeln!(out, "{}", summary.to_json(start.elapsed()))?;
+    }
+
+    match failures {
+        0 => Ok(()),
+        _ => Err(format_err!("{} file(s) failed to check", failures)),
+    }
+}
+
+/// Print the core syntax of each definition in a file as an S-expression
+fn emit_sexp<W: Write>(out: &mut W, file: &FileMap) -> io::Result<()> {
+    let (module, _) = parse::module(file);
+    let module: core::Module = module.to_core();
+    for definition in module.definitions {
+        writeln!(out, "(define {} {})", definition.name, core::to_sexp(&definition.term))?;
+    }
+    Ok(())
+}
+
+/// Print the elaborated type and value of each definition in a checked
+/// module as S-expressions
+fn emit_core<W: Write>(out: &mut W, module: &CheckedModule) -> io::Result<()> {
+    for definition in &module.definitions {
+        let ann = core::to_sexp(&RcTerm::from(&definition.ann));
+        let term = core::to_sexp(&RcTerm::from(&definition.term));
+        writeln!(out, "(define {} {} {})", definition.name, ann, term)?;
+    }
+    Ok(())
+}
+
+/// A summary of the results of checking a set of files
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Summary {
+    files_checked: usize,
+    errors: usize,
+    warnings: usize,
+}
+
+impl Summary {
+    /// Count a diagnostic that was reported while checking, returning `false`
+    /// if it is an error past the first `max_errors`, and so should not be
+    /// emitted. A `max_errors` of `0` means that there is no limit.
+    fn record(&mut self, diagnostic: &Diagnostic, max_errors: usize) -> bool {
+        match diagnostic.severity {
+            Severity::Bug | Severity::Error => {
+                self.errors += 1;
+                max_errors == 0 || self.errors <= max_errors
+            },
+            Severity::Warning => {
+                self.warnings += 1;
+                true
+            },
+            Severity::Note | Severity::Help => true,
+        }
+    }
+
+    /// A note saying how many errors were not emitted because of `max_errors`
+    fn suppressed_note(&self, max_errors: usize) -> Option<String> {
+        if max_errors == 0 {
+            return None;
+        }
+
+        match self.errors.saturating_sub(max_errors) {
+            0 => None,
+            1 => Some(String::from("... and 1 more error")),
+            suppressed => Some(format!("... and {} more errors", suppressed)),
+        }
+    }
+
+    /// Render the summary as a single line of JSON
+    fn to_json(&self, duration: Duration) -> String {
+        let millis = u64::from(duration.subsec_nanos() / 1_000_000);
+        let duration_ms = duration.as_secs() * 1000 + millis;
+
+        format!(
+            r#"{{"files_checked":{},"errors":{},"warnings":{},"duration_ms":{}}}"#,
+            self.files_checked, self.errors, self.warnings, duration_ms,
+        )
+    }
+}
+
+/// Check each of the embedded examples, naming those that fail
+fn check_examples() -> Result<(), Error> {
+    let mut failures = 0;
+
+    for example in library::EXAMPLES {
+        match example.check() {
+            Ok(()) => println!("example `{}` ... ok", example.name),
+            Err(err) => {
+                println!("example `{}` ... FAILED: {}", example.name, err);
+                failures += 1;
+            },
+        }
+    }
+
+    match failures {
+        0 => Ok(()),
+        _ => Err(format_err!("{} example(s) failed", failures)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codespan::FileName;
+
+    use super::*;
+
+    #[test]
+    fn summary_counts_diagnostics() {
+        let src = "module test; Foo : Type; Foo = Type -> Type; bar = Type Type;";
+        let mut codemap = CodeMap::new();
+        let file = codemap.add_filemap(FileName::virtual_("test"), src.into());
+
+        let (module, _) = parse::module(&file);
+        let warnings = lint::naming(&module, NamingConvention::KebabCase);
+        let errors = ::load_file(&file).err().unwrap();
+
+        let mut summary = Summary::default();
+        summary.files_checked += 1;
+        for diagnostic in warnings.iter().map(|warning| warning.to_diagnostic()) {
+            assert!(summary.record(&diagnostic, 0));
+        }
+        for diagnostic in &errors {
+            assert!(summary.record(diagnostic, 0));
+        }
+
+        assert!(!warnings.is_empty());
+        assert!(!errors.is_empty());
+        assert_eq!(summary.warnings, warnings.len());
+        assert_eq!(summary.errors, errors.len());
+        assert_eq!(
+            summary.to_json(Duration::from_millis(42)),
+            format!(
+                r#"{{"files_checked":1,"errors":{},"warnings":{},"duration_ms":42}}"#,
+                errors.len(),
+                warnings.len(),
+            ),
+        );
+    }
+
+    #[test]
+    fn summary_caps_errors() {
+        let src = "module test; a = Type Type; b = Type Type; c = Type Type; d = Type Type;";
+        let mut codemap = CodeMap::new();
+        let file = codemap.add_filemap(FileName::virtual_("test"), src.into());
+
+        let errors = ::load_file(&file).err().unwrap();
+        assert_eq!(errors.len(), 4);
+
+        let mut summary = Summary::default();
+        let emitted = errors
+            .iter()
+            .filter(|diagnostic| summary.record(diagnostic, 3))
+            .count();
+
+        assert_eq!(emitted, 3);
+        assert_eq!(summary.errors, 4);
+        assert_eq!(summary.suppressed_note(3), Some(String::from("... and 1 more error")));
+        assert_eq!(summary.suppressed_note(1), Some(String::from("... and 3 more errors")));
+        assert_eq!(summary.suppressed_note(4), None);
+        assert_eq!(summary.suppressed_note(0), None);
+    }
 }
@@ -0,0 +1,102 @@
+use codespan::CodeMap;
+use codespan_reporting;
+use failure::Error;
+use std::io;
+use std::path::PathBuf;
+
+use semantics::CheckedModule;
+use syntax::pretty::{self, ToDoc};
+
+/// Options for the `doc` subcommand
+#[derive(Debug, StructOpt)]
+pub struct Opts {
+    /// The width to wrap the rendered types at
+    #[structopt(long = "width", default_value = "80")]
+    pub width: usize,
+
+    /// File to generate documentation for, or `-` to read from stdin
+    #[structopt(name = "FILE", parse(from_os_str))]
+    pub file: PathBuf,
+}
+
+/// Run the `doc` subcommand with the given options
+pub fn run(opts: Opts) -> Result<(), Error> {
+    let mut codemap = CodeMap::new();
+    let file = super::add_filemap(&mut codemap, &opts.file, io::stdin())?;
+
+    match ::load_file(&file) {
+        Ok(module) => {
+            print!("{}", to_markdown(&module, opts.width));
+            Ok(())
+        },
+        Err(diagnostics) => {
+            for diagnostic in &diagnostics {
+                codespan_reporting::emit(&codemap, diagnostic);
+            }
+            Err(format_err!("could not check `{}`", opts.file.display()))
+        },
+    }
+}
+
+/// Render the definitions in the module, along with their types and doc
+/// comments, as Markdown
+///
+/// All definitions are currently public, so none are omitted.
+pub fn to_markdown(module: &CheckedModule, width: usize) -> String {
+    let mut output = format!("# Module `{}`\n", module.name);
+
+    for definition in &module.definitions {
+        let ann = definition.ann.to_doc(pretty::Options::default()).group();
+
+        output.push_str(&format!("\n## `{}`\n\n", definition.name));
+        output.push_str("```pikelet\n");
+        output.push_str(&format!("{} : {}\n", definition.name, ann.pretty(width)));
+        output.push_str("```\n");
+
+        if !definition.docs.is_empty() {
+            output.push('\n');
+            for line in &definition.docs {
+                output.push_str(line);
+                output.push('\n');
+            }
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use codespan::FileName;
+
+    use super::*;
+
+    #[test]
+    fn two_definitions() {
+        let src = r"
+            module test;
+
+            ||| The polymorphic identity function
+            id : (a : Type) -> a -> a;
+            id a x = x;
+
+            ||| Always return the first argument
+            const : (a b : Type) -> a -> b -> a;
+            const a b x y = id a x;
+        ";
+
+        let mut codemap = CodeMap::new();
+        let file = codemap.add_filemap(FileName::virtual_("test"), src.into());
+        let module = ::load_file(&file).unwrap_or_else(|_| panic!("failed to check module"));
+
+        let markdown = to_markdown(&module, 80);
+
+        assert!(markdown.contains("# Module `test`"));
+        assert!(markdown.contains("## `id`"));
+        assert!(markdown.contains("id : (a : Type) ->"));
+        assert!(markdown.contains("The polymorphic identity function"));
+        assert!(markdown.contains("## `const`"));
+        assert!(markdown.contains("const : (a : Type) -> (b : Type) ->"));
+        assert!(markdown.contains("Always return the first argument"));
+    }
+}
@@ -1,9 +1,15 @@
 //! The command line interface for Pikelet
 
+use codespan::{CodeMap, FileMap, FileName};
 use failure::Error;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::Arc;
 
 pub mod check;
+pub mod doc;
 pub mod repl;
 
 // TODO: test using https://github.com/killercup/assert_cli
@@ -21,7 +27,7 @@ pub struct Opts {
     pub command: Command,
 }
 
-// TODO: actually use this!
+/// Whether to highlight the output with ANSI escape codes
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ColorArg {
     Auto,
@@ -31,12 +37,12 @@ pub enum ColorArg {
 
 impl ColorArg {
     /// Returns a boolean that specifies if the output should be colorized,
-    /// checking if we are in a terminal session if `Auto` is passed.
+    /// checking if stdout is a terminal if `Auto` is passed.
     pub fn should_colorize(self) -> bool {
         use isatty;
 
         match self {
-            ColorArg::Auto => isatty::stdin_isatty(),
+            ColorArg::Auto => isatty::stdout_isatty(),
             ColorArg::Always => true,
             ColorArg::Never => false,
         }
@@ -62,6 +68,10 @@ pub enum Command {
     #[structopt(name = "check")]
     Check(check::Opts),
 
+    /// Generate Markdown documentation for the definitions in a file
+    #[structopt(name = "doc")]
+    Doc(doc::Opts),
+
     /// A REPL for running expressions
     #[structopt(name = "repl")]
     Repl(repl::Opts),
@@ -70,6 +80,97 @@ pub enum Command {
 pub fn run(opts: Opts) -> Result<(), Error> {
     match opts.command {
         Command::Check(check_opts) => check::run(check_opts),
-        Command::Repl(repl_opts) => repl::run(repl_opts),
+        Command::Doc(doc_opts) => doc::run(doc_opts),
+        Command::Repl(repl_opts) => repl::run(repl_opts, opts.color.should_colorize()),
+    }
+}
+
+/// Add the source at the given path to the codemap. If the path is `-`, the
+/// source is read from `stdin` instead, and given the name `<stdin>`.
+pub fn add_filemap<R: Read>(
+    codemap: &mut CodeMap,
+    path: &Path,
+    mut stdin: R,
+) -> Result<Arc<FileMap>, Error> {
+    if path == Path::new("-") {
+        let mut src = String::new();
+        stdin.read_to_string(&mut src)?;
+        Ok(codemap.add_filemap(FileName::virtual_("<stdin>"), src))
+    } else {
+        let src = fs::read_to_string(path)?;
+        Ok(codemap.add_filemap(FileName::real(path), src))
+    }
+}
+
+/// Expand any directories in the given paths into the Pikelet source files
+/// that they contain, searching recursively. The files found in each
+/// directory are sorted, so that they are always loaded in the same order.
+pub fn expand_paths(paths: &[PathBuf]) -> Result<Vec<PathBuf>, Error> {
+    fn go(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+        if !path.is_dir() {
+            files.push(path.to_path_buf());
+            return Ok(());
+        }
+
+        let mut entries = fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort();
+
+        for entry in entries {
+            if entry.is_dir() {
+                go(&entry, files)?;
+            } else if entry.extension().map_or(false, |ext| ext == "pi") {
+                files.push(entry);
+            }
+        }
+
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    for path in paths {
+        go(path, &mut files)?;
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn expand_paths_directory() {
+        let dir = env::temp_dir().join(format!("pikelet-expand-paths-{}", ::std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("b.pi"), "module b;").unwrap();
+        fs::write(dir.join("a.pi"), "module a;").unwrap();
+        fs::write(dir.join("notes.txt"), "not pikelet").unwrap();
+        fs::write(dir.join("nested").join("c.pi"), "module c;").unwrap();
+
+        let files = expand_paths(&[dir.clone(), PathBuf::from("-")]);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            files.unwrap(),
+            vec![
+                dir.join("a.pi"),
+                dir.join("b.pi"),
+                dir.join("nested").join("c.pi"),
+                PathBuf::from("-"),
+            ],
+        );
+    }
+
+    #[test]
+    fn add_filemap_stdin() {
+        let stdin = "module test; id : (a : Type) -> a -> a; id a x = x;".as_bytes();
+        let mut codemap = CodeMap::new();
+        let file = add_filemap(&mut codemap, Path::new("-"), stdin).unwrap();
+
+        assert_eq!(*file.name(), FileName::virtual_("<stdin>"));
+        assert!(::load_file(&file).is_ok());
     }
 }
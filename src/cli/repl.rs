@@ -4,24 +4,80 @@ use failure::Error;
 use rustyline::error::ReadlineError;
 use rustyline::Editor;
 use codespan::{CodeMap, FileMap, FileName};
-use codespan_reporting;
-use std::path::PathBuf;
+use codespan_reporting::{self, Diagnostic};
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use std::usize;
 use term_size;
 
-use semantics;
+use semantics::{self, CheckedModule};
+use syntax::core::{Binder, Context, Name, Origin, RcTerm, RcType, RcValue, Transparency};
+use syntax::concrete::{ReplCommand, Term};
+use syntax::core::UnfoldPolicy;
 use syntax::parse;
+use syntax::pretty;
 
 /// Options for the `repl` subcommand
 #[derive(Debug, StructOpt)]
 pub struct Opts {
-    /// The prompt to display before expressions
+    /// The prompt to display before expressions. `{count}` is replaced with
+    /// the number of names bound in the session, and `{module}` with the name
+    /// of the most recently loaded module.
     #[structopt(long = "prompt", default_value = "Pikelet> ")]
     pub prompt: String,
 
+    /// The prompt to display while an incomplete expression is being continued
+    #[structopt(long = "continuation-prompt", default_value = "...> ")]
+    pub continuation_prompt: String,
+
     /// The history file to record previous commands to (blank to disable)
     #[structopt(long = "history-file", parse(from_os_str), default_value = "repl-history")]
     pub history_file: Option<PathBuf>,
 
+    /// The maximum depth of nested binders to allow in terms
+    #[structopt(long = "max-binder-depth", default_value = "65536")]
+    pub max_binder_depth: u32,
+
+    /// The depth past which terms are printed as `…`
+    #[structopt(long = "print-depth", default_value = "256")]
+    pub print_depth: u32,
+
+    /// The number of reduction steps to allow when normalizing a term before
+    /// giving up (unlimited if not given)
+    #[structopt(long = "step-limit")]
+    pub step_limit: Option<u64>,
+
+    /// The number of in-scope bindings to list when reporting a hole, most
+    /// recently bound first
+    #[structopt(long = "hole-context", default_value = "10")]
+    pub hole_context: usize,
+
+    /// Warn about the names exposed by the imports of loaded files that are
+    /// never used
+    #[structopt(long = "warn-unused")]
+    pub warn_unused: bool,
+
+    /// Which definitions to unfold when evaluating terms
+    #[structopt(long = "unfold", parse(try_from_str), default_value = "all",
+                raw(possible_values = "&[\"all\", \"prelude\", \"user\", \"none\"]"))]
+    pub unfold: UnfoldPolicy,
+
+    /// Commands to execute before starting the REPL (may be repeated)
+    #[structopt(long = "exec")]
+    pub exec: Vec<String>,
+
+    /// The width to wrap printed terms at (defaults to `$COLUMNS`, or the
+    /// width of the terminal)
+    #[structopt(long = "width")]
+    pub width: Option<usize>,
+
+    /// Start an interactive session after executing the `--exec` commands
+    #[structopt(long = "interactive")]
+    pub interactive: bool,
+
     /// Files to preload into the REPL
     #[structopt(name = "FILE", parse(from_os_str))]
     pub files: Vec<PathBuf>,
@@ -38,21 +94,74 @@ const LOGO_TEXT: &[&str] = &[
 
 const HELP_TEXT: &[&str] = &[
     "",
-    "Command       Arguments   Purpose",
+    "Command       Arguments         Purpose",
     "",
-    "<expr>                    evaluate a term",
-    ":? :h :help               display this help text",
-    ":q :quit                  quit the repl",
-    ":t :type      <expr>      infer the type of an expression",
+    "<expr>                          evaluate a term",
+    "<name> : <expr>                 claim the type of a name that is not yet defined",
+    "<name> = <expr>                 bind a name to an expression for the rest of the session",
+    ":? :h :help                     display this help text",
+    ":q :quit                        quit the repl",
+    ":t :type      <expr>            infer the type of an expression",
+    ":b :browse                      list the names that are in scope, along with their types",
+    ":all          <expr>            show the core term, type, and normal form of an expression",
+    ":bench        [<n>] <expr>      time normalizing an expression <n> times (default 1000)",
+    ":l :load      <path>            load the definitions in a file",
+    ":let          <name> = <expr>   bind a name to an expression for the rest of the session",
+    ":n :normalize <expr>            evaluate a term, without printing its type",
+    ":save         <path>            save the claims and definitions in the session",
+    ":set depth    <n>               print terms nested deeper than <n> as `…`",
+    ":set timing   <on|off>          print how long each phase of evaluating a term takes",
+    ":set unfold   <policy>          unfold `all`, `prelude`, `user`, or `none` of the definitions",
     "",
 ];
 
-/// Run the `repl` subcommand with the given options
-pub fn run(opts: Opts) -> Result<(), Error> {
-    // TODO: Load files
+/// The number of times to normalize a term in `:bench` if no count is given
+const DEFAULT_BENCH_ITERATIONS: u32 = 1000;
 
-    let mut rl = Editor::<()>::new();
+/// The width to wrap the terms written by `:save` at
+const SAVE_WIDTH: usize = 80;
+
+/// Run the `repl` subcommand with the given options, highlighting the terms
+/// that are printed if `color` is set
+pub fn run(opts: Opts, color: bool) -> Result<(), Error> {
     let mut codemap = CodeMap::new();
+    let mut session = Session::new(&opts, &mut codemap);
+    session.color = color;
+
+    for path in &opts.files {
+        let result = session.load(&mut codemap, path).map(|warnings| {
+            for warning in warnings {
+                codespan_reporting::emit(&codemap, &warning.to_diagnostic());
+            }
+            ControlFlow::Continue
+        });
+        report(&codemap, result)?;
+    }
+
+    if !opts.exec.is_empty() {
+        let stdout = io::stdout();
+        let mut failed = false;
+
+        for command in &opts.exec {
+            let filemap = codemap.add_filemap(FileName::virtual_("exec"), command.clone());
+            let result =
+                eval_print(&opts, &mut session, &mut codemap, &filemap, &mut stdout.lock());
+            match report(&codemap, result)? {
+                Some(ControlFlow::Continue) => {},
+                Some(ControlFlow::Break) => return Ok(()),
+                None => failed = true,
+            }
+        }
+
+        if !opts.interactive {
+            return match failed {
+                true => Err(format_err!("encountered errors while executing commands")),
+                false => Ok(()),
+            };
+        }
+    }
+
+    let mut rl = Editor::<()>::new();
 
     if let Some(ref history_file) = opts.history_file {
         rl.load_history(&history_file)?;
@@ -67,29 +176,50 @@ pub fn run(opts: Opts) -> Result<(), Error> {
         }
     }
 
-    // TODO: Load files
+    // The lines of an incomplete command that is being continued
+    let mut buffer = String::new();
+    let prompt_template = PromptTemplate::parse(&opts.prompt);
 
     loop {
-        match rl.readline(&opts.prompt) {
+        let prompt = match buffer.is_empty() {
+            true => prompt_template.render(&session),
+            false => opts.continuation_prompt.clone(),
+        };
+
+        match rl.readline(&prompt) {
             Ok(line) => {
                 if let Some(_) = opts.history_file {
                     rl.add_history_entry(&line);
                 }
 
-                let filename = FileName::virtual_("repl");
-                match eval_print(&codemap.add_filemap(filename, line)) {
-                    Ok(ControlFlow::Continue) => {},
-                    Ok(ControlFlow::Break) => break,
-                    Err(EvalPrintError::Parse(errs)) => for err in errs {
-                        codespan_reporting::emit(&codemap, &err.to_diagnostic());
-                    },
-                    Err(EvalPrintError::Type(err)) => {
-                        codespan_reporting::emit(&codemap, &err.to_diagnostic());
+                // Submitting a blank line gives up on continuing the command
+                let finished = !buffer.is_empty() && line.trim().is_empty();
+                if !buffer.is_empty() {
+                    buffer.push('\n');
+                }
+                buffer.push_str(&line);
+
+                let filemap = codemap.add_filemap(FileName::virtual_("repl"), buffer.clone());
+                let stdout = io::stdout();
+                let result =
+                    eval_print(&opts, &mut session, &mut codemap, &filemap, &mut stdout.lock());
+                match result {
+                    Err(EvalPrintError::Parse(ref errs)) if !finished && is_incomplete(errs) => {},
+                    result => {
+                        buffer.clear();
+                        match report(&codemap, result)? {
+                            Some(ControlFlow::Continue) | None => {},
+                            Some(ControlFlow::Break) => break,
+                        }
                     },
                 }
             },
             Err(err) => match err {
-                ReadlineError::Interrupted => println!("Interrupt"),
+                ReadlineError::Interrupted => {
+                    // Discard any incomplete command, rather than exiting
+                    buffer.clear();
+                    println!("Interrupt");
+                },
                 ReadlineError::Eof => break,
                 err => {
                     println!("readline error: {:?}", err);
@@ -108,46 +238,212 @@ pub fn run(opts: Opts) -> Result<(), Error> {
     Ok(())
 }
 
-fn eval_print(filemap: &FileMap) -> Result<ControlFlow, EvalPrintError> {
-    use std::usize;
+/// Returns `true` if the parse errors were all caused by the input ending
+/// early, in which case the user can continue the command on the next line
+fn is_incomplete(errors: &[parse::ParseError]) -> bool {
+    !errors.is_empty() && errors.iter().all(parse::ParseError::is_unexpected_eof)
+}
 
-    use syntax::concrete::ReplCommand;
-    use syntax::core::Context;
-    use syntax::pretty::{self, ToDoc};
+/// Emit any diagnostics produced by `eval_print`, returning `None` if the
+/// command failed
+fn report(
+    codemap: &CodeMap,
+    result: Result<ControlFlow, EvalPrintError>,
+) -> Result<Option<ControlFlow>, Error> {
+    match result {
+        Ok(control_flow) => Ok(Some(control_flow)),
+        Err(EvalPrintError::Parse(errs)) => {
+            for err in errs {
+                codespan_reporting::emit(codemap, &err.to_diagnostic());
+            }
+            Ok(None)
+        },
+        Err(EvalPrintError::Type(err)) => {
+            codespan_reporting::emit(codemap, &err.to_diagnostic());
+            Ok(None)
+        },
+        Err(EvalPrintError::Holes(errs)) => {
+            for err in errs {
+                codespan_reporting::emit(codemap, &err.to_diagnostic());
+            }
+            Ok(None)
+        },
+        Err(EvalPrintError::Load(diagnostics)) => {
+            for diagnostic in diagnostics {
+                codespan_reporting::emit(codemap, &diagnostic);
+            }
+            Ok(None)
+        },
+        Err(EvalPrintError::Io(err)) => Err(err.into()),
+    }
+}
+
+fn eval_print(
+    opts: &Opts,
+    session: &mut Session,
+    codemap: &mut CodeMap,
+    filemap: &FileMap,
+    out: &mut Write,
+) -> Result<ControlFlow, EvalPrintError> {
+    use syntax::pretty::ToDoc;
     use syntax::translation::ToCore;
 
-    fn term_width() -> Option<usize> {
-        term_size::dimensions().map(|(width, _)| width)
-    }
+    // Discard any holes left over from a command that failed part way through
+    session.context.take_holes();
 
+    let parse_start = Instant::now();
     let (repl_command, parse_errors) = parse::repl_command(filemap);
+    let parse_time = parse_start.elapsed();
     if !parse_errors.is_empty() {
         return Err(EvalPrintError::Parse(parse_errors));
     }
 
-    match repl_command {
+    match reparse_claim(&session.context, repl_command) {
         ReplCommand::Help => for line in HELP_TEXT {
-            println!("{}", line);
+            writeln!(out, "{}", line)?;
         },
 
         ReplCommand::Eval(parse_term) => {
             let term = parse_term.to_core();
-            let context = Context::new();
-            let (_, inferred) = semantics::infer(&context, &term)?;
-            let evaluated = semantics::normalize(&context, &term)?;
-            let doc = pretty::pretty_ann(pretty::Options::default(), &evaluated, &inferred);
+            semantics::check_binder_depth(&term, opts.max_binder_depth)?;
+            let infer_start = Instant::now();
+            let (_, inferred) = semantics::infer(&session.context, &term)?;
+            let infer_time = infer_start.elapsed();
+            unsolved_holes(&session.context)?;
+            let normalize_start = Instant::now();
+            let evaluated = semantics::normalize(&session.unfold_context(), &term)?;
+            let normalize_time = normalize_start.elapsed();
+            let options = session.pretty_options();
+            let doc = pretty::pretty_ann(options, &evaluated, &inferred);
+            let width = output_width(opts);
 
-            println!("{}", doc.pretty(term_width().unwrap_or(usize::MAX)));
+            writeln!(out, "{}", pretty::render(&doc, options, width))?;
+
+            if session.timing {
+                let timings = Timings {
+                    parse: parse_time,
+                    infer: infer_time,
+                    normalize: normalize_time,
+                };
+                // Timings go to stderr, so that they don't end up in piped output
+                eprintln!("{}", render_timings(&timings));
+                session.last_timings = Some(timings);
+            }
         },
+        ReplCommand::All(parse_term) => {
+            let term = parse_term.to_core();
+            semantics::check_binder_depth(&term, opts.max_binder_depth)?;
+            let (_, inferred) = semantics::infer(&session.context, &term)?;
+            unsolved_holes(&session.context)?;
+            let evaluated = semantics::normalize(&session.unfold_context(), &term)?;
+            let width = output_width(opts);
+
+            let options = session.pretty_options();
+
+            write!(out, "{}", render_all(&term, &inferred, &evaluated, options, width))?;
+        },
+        ReplCommand::Browse => {
+            let width = output_width(opts);
+
+            write!(out, "{}", render_browse(&session.context, session.pretty_options(), width))?;
+        },
+        ReplCommand::Bench(iterations, parse_term) => {
+            let term = parse_term.to_core();
+            semantics::check_binder_depth(&term, opts.max_binder_depth)?;
+            semantics::infer(&session.context, &term)?;
+            unsolved_holes(&session.context)?;
+
+            let iterations = iterations.unwrap_or(DEFAULT_BENCH_ITERATIONS).max(1);
+            let context = session.unfold_context();
+
+            // Warm up before we start timing
+            semantics::normalize(&context, &term)?;
+
+            let mut timings = Vec::with_capacity(iterations as usize);
+            for _ in 0..iterations {
+                let start = Instant::now();
+                semantics::normalize(&context, &term)?;
+                timings.push(start.elapsed());
+            }
+
+            writeln!(out, "{}", render_bench(&timings))?;
+        },
+        ReplCommand::Claim(name, parse_ann) => {
+            let ann = parse_ann.to_core();
+            semantics::check_binder_depth(&ann, opts.max_binder_depth)?;
+            semantics::infer(&session.context, &ann)?;
+            unsolved_holes(&session.context)?;
+            let ann = semantics::normalize(&session.context, &ann)?;
+
+            session.context = session.context.declare(Name::user(name), ann);
+        },
+        ReplCommand::Let(name, parse_term) => {
+            let name = Name::user(name);
+            let term = parse_term.to_core();
+            semantics::check_binder_depth(&term, opts.max_binder_depth)?;
+
+            let claim = match session.context.lookup_binder(&name) {
+                Some(&Binder::Claim(ref ann)) => Some(ann.clone()),
+                Some(_) | None => None,
+            };
+
+            // The elaborated term is evaluated, rather than the term itself, so
+            // that the implicit arguments that were filled in are kept
+            session.context = match claim {
+                // Fill in a claim from an earlier command, checking the term
+                // against the claimed type
+                Some(ann) => {
+                    let elab = semantics::check(&session.context, &term, &ann)?;
+                    unsolved_holes(&session.context)?;
+                    let evaluated = semantics::normalize(&session.context, &RcTerm::from(&elab))?;
+                    session
+                        .context
+                        .define(name, evaluated, Transparency::Transparent)
+                        .expect("a claim was found for the name")
+                },
+                None => {
+                    let (elab, inferred) = semantics::infer(&session.context, &term)?;
+                    unsolved_holes(&session.context)?;
+                    let evaluated = semantics::normalize(&session.context, &RcTerm::from(&elab))?;
+                    let binder = Binder::Let(evaluated, inferred, Transparency::Transparent);
+
+                    session.context.extend(name, binder)
+                },
+            };
+        },
+        ReplCommand::Load(path) => {
+            for warning in session.load(codemap, Path::new(&path))? {
+                codespan_reporting::emit(codemap, &warning.to_diagnostic());
+            }
+        },
+        ReplCommand::Normalize(parse_term) => {
+            let term = parse_term.to_core();
+            semantics::check_binder_depth(&term, opts.max_binder_depth)?;
+            semantics::infer(&session.context, &term)?;
+            unsolved_holes(&session.context)?;
+            let evaluated = semantics::normalize(&session.unfold_context(), &term)?;
+            let options = session.pretty_options();
+            let width = output_width(opts);
+
+            writeln!(out, "{}", pretty::render(&evaluated.to_doc(options), options, width))?;
+        },
+        ReplCommand::Save(path) => session.save(Path::new(&path))?,
+        ReplCommand::SetDepth(depth) => session.print_depth = depth,
+        ReplCommand::SetTiming(timing) => session.timing = timing,
+        ReplCommand::SetUnfold(unfold_policy) => session.unfold_policy = unfold_policy,
         ReplCommand::TypeOf(parse_term) => {
             let term = parse_term.to_core();
-            let context = Context::new();
-            let (_, inferred) = semantics::infer(&context, &term)?;
-            let doc = inferred.to_doc(pretty::Options::default());
+            semantics::check_binder_depth(&term, opts.max_binder_depth)?;
+            let (_, inferred) = semantics::infer(&session.context, &term)?;
+            unsolved_holes(&session.context)?;
+            let options = session.pretty_options();
+            let width = output_width(opts);
 
-            println!("{}", doc.pretty(term_width().unwrap_or(usize::MAX)));
+            writeln!(out, "{}", pretty::render(&inferred.to_doc(options), options, width))?;
         },
 
+        // Parse errors have already been reported by this point, so there is
+        // nothing left for us to do
         ReplCommand::NoOp | ReplCommand::Error(_) => {},
         ReplCommand::Quit => return Ok(ControlFlow::Break),
     }
@@ -155,6 +451,396 @@ fn eval_print(filemap: &FileMap) -> Result<ControlFlow, EvalPrintError> {
     Ok(ControlFlow::Continue)
 }
 
+/// The width to wrap printed terms at, taken from the `--width` flag, the
+/// `COLUMNS` environment variable, or the size of the terminal, in that order
+fn output_width(opts: &Opts) -> usize {
+    let columns = env::var("COLUMNS").ok();
+    let term_width = term_size::dimensions().map(|(width, _)| width);
+
+    resolve_width(opts.width, columns.as_ref().map(String::as_str), term_width)
+}
+
+/// Pick the first width that is available, ignoring a `COLUMNS` value that
+/// is not a positive number. Output is left unwrapped if no width is found.
+fn resolve_width(flag: Option<usize>, columns: Option<&str>, term_width: Option<usize>) -> usize {
+    let columns = columns.and_then(|columns| match columns.trim().parse() {
+        Ok(0) | Err(_) => None,
+        Ok(columns) => Some(columns),
+    });
+
+    flag.or(columns).or(term_width).unwrap_or(usize::MAX)
+}
+
+/// Report the holes found while checking a command, rather than evaluating
+/// it any further
+fn unsolved_holes(context: &Context) -> Result<(), EvalPrintError> {
+    let errs = semantics::unsolved_holes(context);
+    if errs.is_empty() {
+        Ok(())
+    } else {
+        Err(EvalPrintError::Holes(errs))
+    }
+}
+
+/// Bare claims (`<name> : <type>`) can't be distinguished from annotated
+/// terms by the parser, so we treat an annotated variable as a claim if the
+/// variable is not yet in scope
+fn reparse_claim(context: &Context, repl_command: ReplCommand) -> ReplCommand {
+    let is_bound = |name: &str| {
+        let name = Name::user(name);
+        context.lookup_binder(&name).is_some() || context.lookup_prim(&name).is_some()
+    };
+
+    let claim = match repl_command {
+        ReplCommand::Eval(ref term) => match **term {
+            Term::Ann(ref expr, ref ann) => match **expr {
+                Term::Var(_, ref name) if !is_bound(name.as_str()) => {
+                    Some((name.clone(), ann.clone()))
+                },
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    };
+
+    match claim {
+        Some((name, ann)) => ReplCommand::Claim(name, ann),
+        None => repl_command,
+    }
+}
+
+/// Render the core term (with debug indices), the type, and the normal form of
+/// a term, with each section labelled
+fn render_all(
+    term: &RcTerm,
+    ty: &RcType,
+    value: &RcValue,
+    options: pretty::Options,
+    width: usize,
+) -> String {
+    use syntax::pretty::ToDoc;
+
+    let term = term.to_doc(options.with_debug_indices(true)).group();
+    let ty = ty.to_doc(options).group();
+    let value = value.to_doc(options).group();
+
+    format!(
+        "core:  {}\ntype:  {}\nvalue: {}\n",
+        pretty::render(&term, options, width),
+        pretty::render(&ty, options, width),
+        pretty::render(&value, options, width),
+    )
+}
+
+/// Render the names that are in scope in a context, sorted by name, along with
+/// their types
+fn render_browse(context: &Context, options: pretty::Options, width: usize) -> String {
+    use std::collections::BTreeMap;
+
+    use syntax::pretty::ToDoc;
+
+    // Binders shadow the primitives with the same name, so primitives are
+    // only added if their names have not been bound
+    let mut entries = BTreeMap::new();
+    for (name, ann, _) in context.iter_bindings() {
+        entries.insert(name.to_string(), ann.clone());
+    }
+    for (name, prim) in context.prims.iter() {
+        entries.entry(name.to_string()).or_insert_with(|| prim.ann.clone());
+    }
+
+    if entries.is_empty() {
+        return String::from("no bindings\n");
+    }
+
+    entries
+        .iter()
+        .map(|(name, ann)| {
+            let ann = pretty::render(&ann.to_doc(options).group(), options, width);
+            format!("{} : {}\n", name, ann)
+        })
+        .collect()
+}
+
+/// Render the claims and definitions made during a session as a module, so
+/// that it can be loaded again later. Only the latest binder for each name is
+/// written, but it is placed where the name was first bound, so that claims
+/// still come before any definitions that refer to them.
+fn render_session(context: &Context, module_name: &str) -> String {
+    use std::collections::HashMap;
+
+    use syntax::pretty::ToDoc;
+
+    fn render(value: &RcValue) -> String {
+        let term = RcTerm::from(value).rename_shadowed();
+        let doc = term.to_doc(pretty::Options::default()).group();
+
+        doc.pretty(SAVE_WIDTH).to_string()
+    }
+
+    // The binders are ordered from the most recently bound
+    let binders = context
+        .binders
+        .iter()
+        .filter(|&&(_, _, origin)| origin == Origin::User)
+        .collect::<Vec<_>>();
+    let mut latest = HashMap::new();
+    for &&(ref name, ref binder, _) in &binders {
+        latest.entry(name).or_insert(binder);
+    }
+
+    let mut src = format!("module {};\n", module_name);
+    for &&(ref name, _, _) in binders.iter().rev() {
+        match latest.remove(name) {
+            Some(&Binder::Claim(ref ann)) => {
+                src.push_str(&format!("\n{} : {};\n", name, render(ann)));
+            },
+            Some(&Binder::Let(ref value, ref ann, transparency)) => {
+                let opaque = match transparency {
+                    Transparency::Transparent => "",
+                    Transparency::Opaque => "opaque ",
+                };
+                src.push_str(&format!("\n{} : {};\n", name, render(ann)));
+                src.push_str(&format!("{}{} = {};\n", opaque, name, render(value)));
+            },
+            // Only claims and definitions are bound by the commands in a
+            // session, and names that were already written are skipped
+            Some(&Binder::Lam(_)) | Some(&Binder::Pi(_)) | None => {},
+        }
+    }
+    src
+}
+
+/// Format a duration in milliseconds, to the nearest microsecond
+fn millis(duration: Duration) -> String {
+    let micros = duration.as_secs() * 1_000_000 + u64::from(duration.subsec_nanos() / 1_000);
+    format!("{}.{:03}ms", micros / 1_000, micros % 1_000)
+}
+
+/// Summarise the timings collected by `:bench`
+fn render_bench(timings: &[Duration]) -> String {
+    let min = timings.iter().min().cloned().unwrap_or_default();
+    let max = timings.iter().max().cloned().unwrap_or_default();
+    let total = timings.iter().fold(Duration::default(), |total, &time| total + time);
+    let mean = match timings.len() {
+        0 => Duration::default(),
+        len => total / len as u32,
+    };
+
+    format!(
+        "min: {}, mean: {}, max: {} ({} iterations)",
+        millis(min),
+        millis(mean),
+        millis(max),
+        timings.len(),
+    )
+}
+
+/// How long each phase of evaluating a term took, recorded when timing is
+/// turned on with `:set timing on`
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Timings {
+    parse: Duration,
+    infer: Duration,
+    normalize: Duration,
+}
+
+/// Summarise the timings of the phases of evaluating a term
+fn render_timings(timings: &Timings) -> String {
+    format!(
+        "parse: {}, infer: {}, normalize: {}",
+        millis(timings.parse),
+        millis(timings.infer),
+        millis(timings.normalize),
+    )
+}
+
+/// A part of a prompt template
+#[derive(Debug, Clone, PartialEq)]
+enum PromptPart {
+    Text(String),
+    /// `{count}`, the number of names bound in the session
+    Count,
+    /// `{module}`, the name of the most recently loaded module
+    Module,
+}
+
+/// A prompt with placeholders that are filled in from the session each time
+/// it is displayed
+#[derive(Debug, Clone, PartialEq)]
+struct PromptTemplate(Vec<PromptPart>);
+
+impl PromptTemplate {
+    /// Parse a prompt template, leaving any unrecognised placeholders as they
+    /// were written
+    fn parse(src: &str) -> PromptTemplate {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut rest = src;
+
+        while let Some(start) = rest.find('{') {
+            let placeholder = rest[start..].find('}').map(|end| &rest[start..start + end + 1]);
+            let part = match placeholder {
+                Some("{count}") => PromptPart::Count,
+                Some("{module}") => PromptPart::Module,
+                Some(_) | None => {
+                    text.push_str(&rest[..start + 1]);
+                    rest = &rest[start + 1..];
+                    continue;
+                },
+            };
+
+            text.push_str(&rest[..start]);
+            rest = &rest[start + placeholder.map_or(0, str::len)..];
+            if !text.is_empty() {
+                parts.push(PromptPart::Text(text.clone()));
+                text.clear();
+            }
+            parts.push(part);
+        }
+
+        text.push_str(rest);
+        if !text.is_empty() {
+            parts.push(PromptPart::Text(text));
+        }
+
+        PromptTemplate(parts)
+    }
+
+    /// Fill in the placeholders of the template from the session
+    fn render(&self, session: &Session) -> String {
+        let mut prompt = String::new();
+        for part in &self.0 {
+            match *part {
+                PromptPart::Text(ref text) => prompt.push_str(text),
+                PromptPart::Count => prompt.push_str(&session.binding_count().to_string()),
+                PromptPart::Module => {
+                    prompt.push_str(session.last_module.as_ref().map_or("", String::as_str))
+                },
+            }
+        }
+        prompt
+    }
+}
+
+/// The state that is carried between the commands entered into the REPL
+struct Session {
+    /// The definitions from the prelude, and the names that have been claimed
+    /// or bound by earlier commands
+    context: Context,
+    /// The depth past which terms are printed as `…`, set using `:set depth`
+    print_depth: u32,
+    /// Which definitions to unfold when evaluating, set using `:set unfold`
+    unfold_policy: UnfoldPolicy,
+    /// Whether to highlight the terms that are printed
+    color: bool,
+    /// Whether to print how long each phase of evaluating a term took, set
+    /// using `:set timing`
+    timing: bool,
+    /// The timings of the most recently evaluated term, if timing is on
+    last_timings: Option<Timings>,
+    /// The name of the most recently loaded module
+    last_module: Option<String>,
+    /// Whether to warn about unused imports when loading files
+    warn_unused: bool,
+}
+
+impl Session {
+    fn new(opts: &Opts, codemap: &mut CodeMap) -> Session {
+        let mut session = Session {
+            context: Context::default()
+                .with_step_limit(opts.step_limit)
+                .with_hole_context_limit(opts.hole_context),
+            print_depth: opts.print_depth,
+            unfold_policy: opts.unfold,
+            color: false,
+            timing: false,
+            last_timings: None,
+            last_module: None,
+            warn_unused: opts.warn_unused,
+        };
+        session.extend_with_module(::load_prelude(codemap), Origin::Prelude);
+
+        session
+    }
+
+    /// Load the definitions in a file into the session, so that they can be
+    /// referred to by later commands, returning the warnings produced while
+    /// checking it. The modules that the file imports are resolved to the
+    /// files of the same name in its directory.
+    fn load(
+        &mut self,
+        codemap: &mut CodeMap,
+        path: &Path,
+    ) -> Result<Vec<semantics::TypeError>, EvalPrintError> {
+        use std::mem;
+        use syntax::translation::ToCore;
+
+        let file = super::add_filemap(codemap, path, io::stdin()).map_err(|err| {
+            let message = format!("failed to read `{}`: {}", path.display(), err);
+            EvalPrintError::Load(vec![Diagnostic::new_error(message)])
+        })?;
+        let root = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut module =
+            ::load_file_with_imports(codemap, &file, root).map_err(EvalPrintError::Load)?;
+
+        let mut warnings = mem::replace(&mut module.warnings, Vec::new());
+        if self.warn_unused {
+            // The module has already been checked, so it parsed without errors
+            let (concrete_module, _) = parse::module(&file);
+            warnings.extend(semantics::lint::unused_imports(&concrete_module.to_core()));
+        }
+
+        self.last_module = Some(module.name.clone());
+        self.extend_with_module(module, Origin::User);
+
+        Ok(warnings)
+    }
+
+    /// Write the claims and definitions made during the session to a file, as
+    /// a module named after the file
+    fn save(&self, path: &Path) -> Result<(), EvalPrintError> {
+        let module_name = path.file_stem().and_then(|stem| stem.to_str());
+        let src = render_session(&self.context, module_name.unwrap_or("session"));
+
+        fs::write(path, src).map_err(|err| {
+            let message = format!("failed to write `{}`: {}", path.display(), err);
+            EvalPrintError::Load(vec![Diagnostic::new_error(message)])
+        })
+    }
+
+    /// Add the definitions in a checked module to the session context
+    fn extend_with_module(&mut self, module: CheckedModule, origin: Origin) {
+        for definition in module.definitions {
+            let binder = Binder::Let(definition.term, definition.ann, definition.transparency);
+            self.context = self.context
+                .extend_with_origin(Name::user(definition.name), binder, origin);
+        }
+    }
+
+    /// The number of names that have been bound in the session, not including
+    /// those from the prelude
+    fn binding_count(&self) -> usize {
+        self.context
+            .iter_bindings()
+            .filter(|&(name, _, _)| self.context.lookup_origin(name) == Some(Origin::User))
+            .count()
+    }
+
+    /// The session context, set to unfold definitions according to the
+    /// session's unfold policy
+    fn unfold_context(&self) -> Context {
+        self.context.clone().with_unfold_policy(self.unfold_policy)
+    }
+
+    fn pretty_options(&self) -> pretty::Options {
+        pretty::Options::default()
+            .with_max_depth(Some(self.print_depth))
+            .with_color(self.color)
+    }
+}
+
 #[derive(Copy, Clone)]
 enum ControlFlow {
     Break,
@@ -164,6 +850,9 @@ enum ControlFlow {
 enum EvalPrintError {
     Parse(Vec<parse::ParseError>),
     Type(semantics::TypeError),
+    Holes(Vec<semantics::TypeError>),
+    Load(Vec<Diagnostic>),
+    Io(io::Error),
 }
 
 impl From<parse::ParseError> for EvalPrintError {
@@ -184,8 +873,496 @@ impl From<semantics::TypeError> for EvalPrintError {
     }
 }
 
+impl From<io::Error> for EvalPrintError {
+    fn from(src: io::Error) -> EvalPrintError {
+        EvalPrintError::Io(src)
+    }
+}
+
 impl From<semantics::InternalError> for EvalPrintError {
     fn from(src: semantics::InternalError) -> EvalPrintError {
         EvalPrintError::Type(src.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::usize;
+
+    use syntax::translation::ToCore;
+
+    use super::*;
+
+    fn exec(commands: &[&str]) -> String {
+        exec_session(commands).1
+    }
+
+    /// Execute some commands, returning the resulting session along with the
+    /// output of the commands
+    fn exec_session(commands: &[&str]) -> (Session, String) {
+        use structopt::StructOpt;
+
+        let opts = Opts::from_iter(vec!["repl"]);
+        let mut codemap = CodeMap::new();
+        let mut session = Session::new(&opts, &mut codemap);
+        let mut out = Vec::new();
+
+        for command in commands {
+            let filemap = codemap.add_filemap(FileName::virtual_("exec"), command.to_string());
+            match eval_print(&opts, &mut session, &mut codemap, &filemap, &mut out) {
+                Ok(ControlFlow::Continue) => {},
+                Ok(ControlFlow::Break) => break,
+                Err(_) => panic!("failed to execute `{}`", command),
+            }
+        }
+
+        (session, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn incomplete_input() {
+        fn is_incomplete_src(src: &str) -> bool {
+            let mut codemap = CodeMap::new();
+            let filemap = codemap.add_filemap(FileName::virtual_("test"), src.into());
+            let (_, errors) = parse::repl_command(&filemap);
+            is_incomplete(&errors)
+        }
+
+        assert!(is_incomplete_src(r"\x =>"));
+        assert!(is_incomplete_src("(a : Type) ->"));
+        assert!(is_incomplete_src(":let id ="));
+        assert!(!is_incomplete_src(""));
+        assert!(!is_incomplete_src("Type"));
+        assert!(!is_incomplete_src("Type )"));
+        assert!(!is_incomplete_src(":type"));
+    }
+
+    #[test]
+    fn exec_let_then_type() {
+        let output = exec(&[":let x = Type", ":type x"]);
+
+        assert_eq!(output, "Type 1\n");
+    }
+
+    #[test]
+    fn exec_type_universe() {
+        assert_eq!(exec(&[":type Type"]), "Type 1\n");
+        assert_eq!(exec(&[":type Type 3"]), "Type 4\n");
+    }
+
+    #[test]
+    fn exec_type_universe_overflow() {
+        use structopt::StructOpt;
+
+        let opts = Opts::from_iter(vec!["repl"]);
+        let mut codemap = CodeMap::new();
+        let mut session = Session::new(&opts, &mut codemap);
+        let mut out = Vec::new();
+
+        let src = ":type Type 4294967295";
+        let filemap = codemap.add_filemap(FileName::virtual_("exec"), src.into());
+        match eval_print(&opts, &mut session, &mut codemap, &filemap, &mut out) {
+            Err(EvalPrintError::Type(semantics::TypeError::UniverseLevelOverflow { .. })) => {},
+            Err(_) | Ok(_) => panic!("expected the universe level to overflow"),
+        }
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn exec_let_then_eval() {
+        let output = exec(&[r":let id = \(a : Type) (x : a) => x", "id Type"]);
+
+        assert!(output.starts_with(r"\x"));
+    }
+
+    #[test]
+    fn exec_bare_let_then_eval() {
+        let output = exec(&[r"id = \(a : Type) (x : a) => x", "id Type"]);
+
+        assert!(output.starts_with(r"\x"));
+    }
+
+    #[test]
+    fn exec_let_implicit_partial_app() {
+        let (session, _) = exec_session(&[
+            r":let const = \{a : Type} {b : Type} (x : a) (y : b) => x",
+            r":let k = (const : Type -> Bool -> Type) Type",
+        ]);
+
+        match session.context.lookup_binder(&Name::user("k")) {
+            Some(&Binder::Let(ref value, _, _)) => {
+                assert!(value.free_vars().iter().all(|name| match *name {
+                    Name::User(_) => true,
+                    Name::Gen(_) => false,
+                }));
+            },
+            _ => panic!("expected `k` to be defined"),
+        }
+    }
+
+    #[test]
+    fn exec_claim_then_define() {
+        let output = exec(&["my-type : Type 1", ":type my-type", "my-type = Type", "my-type"]);
+
+        assert_eq!(output, "Type 1\nType : Type 1\n");
+    }
+
+    #[test]
+    #[should_panic]
+    fn exec_claim_then_define_mismatch() {
+        exec(&["my-type : Type", r"my-type = \(a : Type) => a"]);
+    }
+
+    #[test]
+    fn exec_normalize() {
+        let output = exec(&[r":normalize (\x : Type 1 => x) Type"]);
+
+        assert_eq!(output, "Type\n");
+    }
+
+    #[test]
+    #[should_panic]
+    fn exec_normalize_ill_typed() {
+        exec(&[":n Type Type"]);
+    }
+
+    #[test]
+    fn exec_hole() {
+        use structopt::StructOpt;
+
+        let opts = Opts::from_iter(vec!["repl"]);
+        let mut codemap = CodeMap::new();
+        let mut session = Session::new(&opts, &mut codemap);
+        let mut out = Vec::new();
+
+        let src = "(? : Type -> Type)";
+        let filemap = codemap.add_filemap(FileName::virtual_("exec"), src.into());
+        match eval_print(&opts, &mut session, &mut codemap, &filemap, &mut out) {
+            Err(EvalPrintError::Holes(ref errs)) => assert_eq!(errs.len(), 1),
+            Err(_) | Ok(_) => panic!("expected the hole to be reported"),
+        }
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn exec_hole_context() {
+        use structopt::StructOpt;
+
+        let opts = Opts::from_iter(vec!["repl", "--hole-context", "2"]);
+        let mut codemap = CodeMap::new();
+        let mut session = Session::new(&opts, &mut codemap);
+        let mut out = Vec::new();
+
+        for src in &[":let a = Type", ":let b = 1", ":let c = \"hello\""] {
+            let filemap = codemap.add_filemap(FileName::virtual_("exec"), src.to_string());
+            if eval_print(&opts, &mut session, &mut codemap, &filemap, &mut out).is_err() {
+                panic!("failed to execute `{}`", src);
+            }
+        }
+
+        // Only the most recently bound names are listed, up to the limit
+        let src = "(? : Type)";
+        let filemap = codemap.add_filemap(FileName::virtual_("exec"), src.into());
+        match eval_print(&opts, &mut session, &mut codemap, &filemap, &mut out) {
+            Err(EvalPrintError::Holes(ref errs)) => match errs.as_slice() {
+                [ref err] => {
+                    let message = err.to_diagnostic().message;
+                    assert!(message.ends_with("\n\n  in scope:\n    c : String\n    b : Int"));
+                },
+                errs => panic!("expected a single hole, found {:?}", errs),
+            },
+            Err(_) | Ok(_) => panic!("expected the hole to be reported"),
+        }
+    }
+
+    #[test]
+    fn exec_set_unfold() {
+        fn exec_with(policy: &str) -> String {
+            let set_unfold = format!(":set unfold {}", policy);
+            exec(&[":let my-type = Type", set_unfold.as_str(), r"\(a : my-type) => a"])
+        }
+
+        assert!(exec_with("all").contains(": Type =>"));
+        assert!(exec_with("user").contains(": Type =>"));
+        assert!(exec_with("prelude").contains(": my-type =>"));
+        assert!(exec_with("none").contains(": my-type =>"));
+    }
+
+    #[test]
+    fn exec_set_depth() {
+        let output = exec(&[":set depth 1", r"\(a : Type) (x : a) => x"]);
+
+        // The names of the binders in the value are freshly generated
+        assert!(output.starts_with(r"\a"));
+        assert!(output.contains(" : … => … : "));
+    }
+
+    #[test]
+    fn exec_load() {
+        use std::{env, fs};
+
+        let path = env::temp_dir().join("pikelet-repl-exec-load.pi");
+        fs::write(&path, "module load-test; my-type : Type 1; my-type = Type;").unwrap();
+
+        let load = format!(":load {}", path.display());
+        let output = exec(&[load.as_str(), ":type my-type", "my-type"]);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(output, "Type 1\nType : Type 1\n");
+    }
+
+    #[test]
+    fn exec_load_opaque() {
+        use std::{env, fs};
+
+        let path = env::temp_dir().join("pikelet-repl-exec-load-opaque.pi");
+        fs::write(&path, "module load-test; my-type : Type 1; opaque my-type = Type;").unwrap();
+
+        let load = format!(":load {}", path.display());
+        let output = exec(&[load.as_str(), r":normalize \(a : my-type) => a"]);
+        fs::remove_file(&path).unwrap();
+
+        assert!(output.contains(": my-type =>"));
+    }
+
+    /// Write a module that imports two names from another module, but only
+    /// uses one of them, returning the path of the importing module
+    fn write_unused_import(dir_name: &str) -> PathBuf {
+        use std::{env, fs};
+
+        let dir = env::temp_dir().join(dir_name);
+        fs::create_dir_all(&dir).unwrap();
+        let foo_src = "module foo; bar : Type 1; bar = Type; baz : Type 1; baz = Type;";
+        fs::write(dir.join("foo.pi"), foo_src).unwrap();
+        let main_src = "module main; import foo (bar, baz); my-type : Type 1; my-type = bar;";
+        fs::write(dir.join("main.pi"), main_src).unwrap();
+
+        dir.join("main.pi")
+    }
+
+    #[test]
+    fn load_warn_unused() {
+        use std::fs;
+        use structopt::StructOpt;
+
+        let path = write_unused_import("pikelet-repl-load-warn-unused");
+        let opts = Opts::from_iter(vec!["repl", "--warn-unused"]);
+        let mut codemap = CodeMap::new();
+        let mut session = Session::new(&opts, &mut codemap);
+
+        let result = session.load(&mut codemap, &path);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        match result {
+            Ok(warnings) => match warnings.as_slice() {
+                [semantics::TypeError::UnusedImport { ref name, .. }] => assert_eq!(name, "baz"),
+                warnings => panic!("expected `baz` to be unused, found {:?}", warnings),
+            },
+            Err(_) => panic!("failed to load `{}`", path.display()),
+        }
+    }
+
+    #[test]
+    fn load_imports() {
+        use std::fs;
+        use structopt::StructOpt;
+
+        // Unused imports are only reported with `--warn-unused`
+        let path = write_unused_import("pikelet-repl-load-imports");
+        let opts = Opts::from_iter(vec!["repl"]);
+        let mut codemap = CodeMap::new();
+        let mut session = Session::new(&opts, &mut codemap);
+
+        let result = session.load(&mut codemap, &path);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        match result {
+            Ok(warnings) => assert!(warnings.is_empty()),
+            Err(_) => panic!("failed to load `{}`", path.display()),
+        }
+        assert_eq!(session.last_module, Some(String::from("main")));
+    }
+
+    #[test]
+    fn load_missing_file() {
+        use structopt::StructOpt;
+
+        let opts = Opts::from_iter(vec!["repl"]);
+        let mut codemap = CodeMap::new();
+        let mut session = Session::new(&opts, &mut codemap);
+
+        match session.load(&mut codemap, Path::new("does-not-exist.pi")) {
+            Err(EvalPrintError::Load(diagnostics)) => assert_eq!(diagnostics.len(), 1),
+            _ => panic!("expected an error loading a missing file"),
+        }
+    }
+
+    #[test]
+    fn exec_bench() {
+        let output = exec(&[r":bench 3 (\(a : Type) (x : a) => x) Type"]);
+
+        assert!(output.starts_with("min: "));
+        assert!(output.contains(", mean: "));
+        assert!(output.contains(", max: "));
+        assert!(output.ends_with("(3 iterations)\n"));
+    }
+
+    #[test]
+    fn exec_browse() {
+        let output = exec(&[":let my-type = Type", "my-value : my-type", ":browse"]);
+
+        assert!(output.contains("my-type : Type 1\n"));
+        assert!(output.contains("my-value : Type\n"));
+        // Sorted by name
+        assert!(output.find("my-type :") < output.find("my-value :"));
+    }
+
+    #[test]
+    fn width_flag() {
+        use structopt::StructOpt;
+
+        assert_eq!(Opts::from_iter(vec!["repl"]).width, None);
+        assert_eq!(Opts::from_iter(vec!["repl", "--width", "40"]).width, Some(40));
+    }
+
+    #[test]
+    fn resolve_width_precedence() {
+        assert_eq!(resolve_width(Some(40), Some("60"), Some(80)), 40);
+        assert_eq!(resolve_width(None, Some("60"), Some(80)), 60);
+        assert_eq!(resolve_width(None, None, Some(80)), 80);
+        assert_eq!(resolve_width(None, None, None), usize::MAX);
+    }
+
+    #[test]
+    fn resolve_width_invalid_columns() {
+        assert_eq!(resolve_width(None, Some("wide"), Some(80)), 80);
+        assert_eq!(resolve_width(None, Some("0"), None), usize::MAX);
+        assert_eq!(resolve_width(None, Some(" 60\n"), None), 60);
+    }
+
+    #[test]
+    fn render_session_definitions() {
+        let (session, _) = exec_session(&[
+            r":let id = \(a : Type) (x : a) => x",
+            "endo : Type 1",
+            "endo = (a : Type) -> a -> a",
+        ]);
+        let src = render_session(&session.context, "saved");
+
+        let mut codemap = CodeMap::new();
+        let file = codemap.add_filemap(FileName::virtual_("saved"), src.clone());
+        let module = match ::load_file(&file) {
+            Ok(module) => module,
+            Err(_) => panic!("failed to load the saved session:\n{}", src),
+        };
+        let names = module.definitions.iter().map(|d| d.name.as_str()).collect::<Vec<_>>();
+
+        assert_eq!(names, ["id", "endo"]);
+    }
+
+    #[test]
+    fn render_session_claim() {
+        let (session, _) = exec_session(&["my-type : Type 1"]);
+
+        assert_eq!(
+            render_session(&session.context, "saved"),
+            "module saved;\n\nmy-type : Type 1;\n",
+        );
+    }
+
+    #[test]
+    fn render_browse_empty() {
+        let output = render_browse(&Context::new(), pretty::Options::default(), usize::MAX);
+
+        assert_eq!(output, "no bindings\n");
+    }
+
+    #[test]
+    fn render_bench_stats() {
+        let timings = [
+            Duration::from_millis(3),
+            Duration::from_millis(1),
+            Duration::from_millis(2),
+        ];
+
+        assert_eq!(
+            render_bench(&timings),
+            "min: 1.000ms, mean: 2.000ms, max: 3.000ms (3 iterations)",
+        );
+    }
+
+    #[test]
+    fn prompt_template_parse() {
+        assert_eq!(
+            PromptTemplate::parse("Pikelet> "),
+            PromptTemplate(vec![PromptPart::Text(String::from("Pikelet> "))]),
+        );
+        assert_eq!(
+            PromptTemplate::parse("{module} [{count}] {other}> "),
+            PromptTemplate(vec![
+                PromptPart::Module,
+                PromptPart::Text(String::from(" [")),
+                PromptPart::Count,
+                PromptPart::Text(String::from("] {other}> ")),
+            ]),
+        );
+    }
+
+    #[test]
+    fn prompt_template_count() {
+        let (session, _) = exec_session(&["x = Type", "y = Type 1"]);
+        let template = PromptTemplate::parse("{count}> ");
+
+        assert_eq!(template.render(&session), "2> ");
+    }
+
+    #[test]
+    fn exec_timing() {
+        let (session, output) = exec_session(&[":set timing on", "Type"]);
+
+        assert_eq!(output, "Type : Type 1\n");
+        assert!(session.last_timings.is_some());
+    }
+
+    #[test]
+    fn exec_timing_off() {
+        let (session, _) = exec_session(&[":set timing on", ":set timing off", "Type"]);
+
+        assert_eq!(session.last_timings, None);
+    }
+
+    #[test]
+    fn render_timings_phases() {
+        let timings = Timings {
+            parse: Duration::from_millis(1),
+            infer: Duration::from_millis(2),
+            normalize: Duration::new(0, 3_500_000),
+        };
+
+        assert_eq!(
+            render_timings(&timings),
+            "parse: 1.000ms, infer: 2.000ms, normalize: 3.500ms",
+        );
+    }
+
+    #[test]
+    fn render_all_lam() {
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), r"\x : Type => x".into());
+        let (term, errors) = parse::term(&filemap);
+        assert!(errors.is_empty());
+
+        let term = term.to_core();
+        let context = Context::new();
+        let (_, ty) = semantics::infer(&context, &term).unwrap();
+        let value = semantics::normalize(&context, &term).unwrap();
+
+        let output = render_all(&term, &ty, &value, pretty::Options::default(), usize::MAX);
+
+        assert!(output.contains(r"core:  \x : Type => x@0"));
+        // The names of the binders in the type and value are freshly generated
+        assert!(output.contains("type:  ("));
+        assert!(output.contains(") -> Type"));
+        assert!(output.contains(r"value: \x"));
+    }
+}
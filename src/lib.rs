@@ -3,13 +3,16 @@ extern crate codespan_reporting;
 #[macro_use]
 extern crate failure;
 extern crate lalrpop_util;
-#[macro_use]
-extern crate lazy_static;
 extern crate pretty;
 #[cfg(test)]
 #[macro_use]
 extern crate pretty_assertions;
 extern crate rpds;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
 extern crate unicode_xid;
 
 mod library;
@@ -31,10 +34,53 @@ pub mod cli;
 
 use codespan::{CodeMap, FileMap, FileName};
 use codespan_reporting::Diagnostic;
+use std::path::Path;
 
 use semantics::CheckedModule;
+use syntax::core;
 
 pub fn load_file(file: &FileMap) -> Result<CheckedModule, Vec<Diagnostic>> {
+    check_file(file, &mut |_| None)
+}
+
+/// Load a file, resolving the modules that it imports to the files of the
+/// same name in `root`, eg. `import foo;` is resolved to `root/foo.pi`
+pub fn load_file_with_imports(
+    codemap: &mut CodeMap,
+    file: &FileMap,
+    root: &Path,
+) -> Result<CheckedModule, Vec<Diagnostic>> {
+    use std::fs;
+    use syntax::translation::ToCore;
+
+    // Parse errors in the imported modules, reported before the errors in
+    // the file itself
+    let mut import_diagnostics = Vec::new();
+
+    let result = check_file(file, &mut |name: &str| -> Option<core::Module> {
+        let path = root.join(format!("{}.pi", name));
+        let src = fs::read_to_string(&path).ok()?;
+        let import_file = codemap.add_filemap(FileName::real(path), src);
+
+        let (module, errors) = syntax::parse::module(&import_file);
+        if errors.is_empty() {
+            Some(module.to_core())
+        } else {
+            import_diagnostics.extend(errors.iter().map(|err| err.to_diagnostic()));
+            None
+        }
+    });
+
+    result.map_err(|diagnostics| {
+        import_diagnostics.extend(diagnostics);
+        import_diagnostics
+    })
+}
+
+fn check_file<F>(file: &FileMap, resolve: &mut F) -> Result<CheckedModule, Vec<Diagnostic>>
+where
+    F: FnMut(&str) -> Option<core::Module>,
+{
     use syntax::translation::ToCore;
 
     let mut diagnostics = Vec::new();
@@ -42,11 +88,20 @@ pub fn load_file(file: &FileMap) -> Result<CheckedModule, Vec<Diagnostic>> {
     let (module, errors) = syntax::parse::module(&file);
     diagnostics.extend(errors.iter().map(|err| err.to_diagnostic()));
 
+    // There's nothing left to check if the module header failed to parse
+    if let syntax::concrete::Module::Error(_) = module {
+        return Err(diagnostics);
+    }
+
+    // Declarations that failed to parse are skipped, and terms that failed to
+    // parse are replaced with holes, so that we can still report the type
+    // errors in the rest of the module
     let module = module.to_core();
-    match semantics::check_module(&module) {
-        Ok(module) => Ok(module),
-        Err(err) => {
-            diagnostics.push(err.to_diagnostic());
+    match semantics::check_module_with_imports(&module, resolve) {
+        Ok(module) if diagnostics.is_empty() => Ok(module),
+        Ok(_) => Err(diagnostics),
+        Err(errors) => {
+            diagnostics.extend(errors.iter().map(|err| err.to_diagnostic()));
             Err(diagnostics)
         },
     }
@@ -1,3 +1,113 @@
 //! Builtin libraries
 
+use codespan::{CodeMap, FileName};
+
+use semantics;
+use syntax::core::Context;
+use syntax::parse;
+use syntax::translation::ToCore;
+
 pub const PRELUDE: &str = include_str!("prelude.pi");
+
+/// An example term, along with the type and normal form that we expect it to
+/// have. These are checked as part of the test suite to prevent them from
+/// bit-rotting.
+pub struct Example {
+    /// The name of the example, used when reporting failures
+    pub name: &'static str,
+    /// The source code of the term
+    pub term: &'static str,
+    /// The source code of the expected type of the term
+    pub ty: &'static str,
+    /// The source code of the expected normal form of the term
+    pub normal: &'static str,
+}
+
+pub const EXAMPLES: &[Example] = &[
+    Example {
+        name: "id",
+        term: r"\(a : Type) (x : a) => x",
+        ty: r"(a : Type) -> a -> a",
+        normal: r"\(a : Type) (x : a) => x",
+    },
+    Example {
+        name: "const",
+        term: r"\(a b : Type) (x : a) (y : b) => x",
+        ty: r"(a b : Type) -> a -> b -> a",
+        normal: r"\(a b : Type) (x : a) (y : b) => x",
+    },
+    Example {
+        name: "id-app-ty",
+        term: r"(\(a : Type 1) (x : a) => x) Type",
+        ty: r"Type -> Type",
+        normal: r"\x : Type => x",
+    },
+    Example {
+        name: "id-app-ty-ty",
+        term: r"(\(a : Type 2) (x : a) => x) (Type 1) Type",
+        ty: r"Type 1",
+        normal: r"Type",
+    },
+    Example {
+        name: "and",
+        term: r"\(p q : Type) => (c : Type) -> (p -> q -> c) -> c",
+        ty: r"Type -> Type -> Type 1",
+        normal: r"\(p q : Type) => (c : Type) -> (p -> q -> c) -> c",
+    },
+];
+
+impl Example {
+    /// Check that the example has the expected type and normal form,
+    /// returning a description of the problem if it does not
+    pub fn check(&self) -> Result<(), String> {
+        let context = Context::default();
+        let mut codemap = CodeMap::new();
+
+        let mut parse_term = |src: &str| {
+            let filemap = codemap.add_filemap(FileName::virtual_(self.name), src.into());
+            let (term, errors) = parse::term(&filemap);
+            match errors.first() {
+                None => Ok(term.to_core()),
+                Some(err) => Err(format!("parse error in `{}`: {}", src, err)),
+            }
+        };
+
+        let term = parse_term(self.term)?;
+        let expected_ty = parse_term(self.ty)?;
+        let expected_normal = parse_term(self.normal)?;
+
+        let (_, ty) = semantics::infer(&context, &term).map_err(|err| err.to_string())?;
+        let expected_ty =
+            semantics::normalize(&context, &expected_ty).map_err(|err| err.to_string())?;
+        if ty != expected_ty {
+            return Err(format!("expected type `{}`, found `{}`", expected_ty, ty));
+        }
+
+        let normal = semantics::normalize(&context, &term).map_err(|err| err.to_string())?;
+        let expected_normal =
+            semantics::normalize(&context, &expected_normal).map_err(|err| err.to_string())?;
+        if normal != expected_normal {
+            return Err(format!(
+                "expected normal form `{}`, found `{}`",
+                expected_normal, normal,
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples() {
+        let failures = EXAMPLES
+            .iter()
+            .filter_map(|example| example.check().err().map(|err| (example.name, err)))
+            .collect::<Vec<_>>();
+
+        assert!(failures.is_empty(), "examples failed: {:#?}", failures);
+    }
+}
@@ -4,7 +4,8 @@ use codespan::ByteSpan;
 use codespan_reporting::Diagnostic;
 use std::fmt;
 
-use syntax::core::{Name, RcType};
+use semantics::lint::NamingConvention;
+use syntax::core::{Level, Name, Origin, Pattern, RcType, RcValue};
 use syntax::var::Debruijn;
 
 /// An internal error. These are bugs!
@@ -18,6 +19,12 @@ pub enum InternalError {
     },
     #[fail(display = "Undefined name `{}`.", name)]
     UndefinedName { var_span: ByteSpan, name: Name },
+    #[fail(display = "Binders nested more than {} deep.", max_depth)]
+    BinderDepthExceeded { span: ByteSpan, max_depth: u32 },
+    #[fail(display = "Normalization did not finish within {} steps.", steps)]
+    NormalizationLimitExceeded { span: ByteSpan, steps: u64 },
+    #[fail(display = "No clause matched the scrutinee of a case expression.")]
+    NoMatchingClause { span: ByteSpan },
 }
 
 impl InternalError {
@@ -25,6 +32,9 @@ impl InternalError {
         match *self {
             InternalError::UnsubstitutedDebruijnIndex { span, .. } => span,
             InternalError::UndefinedName { var_span, .. } => var_span,
+            InternalError::BinderDepthExceeded { span, .. } => span,
+            InternalError::NormalizationLimitExceeded { span, .. } => span,
+            InternalError::NoMatchingClause { span } => span,
         }
     }
 
@@ -40,6 +50,17 @@ impl InternalError {
                 Diagnostic::new_bug(format!("cannot find `{}` in scope", name))
                     .with_primary_label(var_span, "not found in this scope")
             },
+            InternalError::BinderDepthExceeded { span, max_depth } => {
+                Diagnostic::new_error(format!("binders nested more than {} deep", max_depth))
+                    .with_primary_label(span, "binder depth exceeded here")
+            },
+            InternalError::NormalizationLimitExceeded { span, steps } => Diagnostic::new_error(
+                format!("normalization did not finish within {} steps", steps),
+            ).with_primary_label(span, "step limit exceeded while reducing this term"),
+            InternalError::NoMatchingClause { span } => {
+                Diagnostic::new_bug("no clause matched the scrutinee of a case expression")
+                    .with_primary_label(span, "the case expression")
+            },
         }
     }
 }
@@ -52,6 +73,23 @@ pub enum TypeError {
         arg_span: ByteSpan,
         found: RcType,
     },
+    /// A projection was applied to a term that was not a pair
+    NotAPairType {
+        span: ByteSpan,
+        found: RcType,
+    },
+    /// A field was projected from a term whose type does not have that field
+    FieldNotFound {
+        span: ByteSpan,
+        expected_label: String,
+        found: RcType,
+    },
+    /// The type of a field in a record type refers to the field itself, or
+    /// to a field that is declared after it
+    ForwardFieldReference {
+        span: ByteSpan,
+        field: String,
+    },
     FunctionParamNeedsAnnotation {
         param_span: ByteSpan,
         var_span: Option<ByteSpan>,
@@ -61,6 +99,11 @@ pub enum TypeError {
         span: ByteSpan,
         found: RcType,
         expected: RcType,
+        /// The innermost parts of the found and expected types where they
+        /// first differ, if they share some of their structure
+        difference: Option<(RcType, RcType)>,
+        /// Where the head of the expected type was defined, if it was a name
+        expected_origin: Option<Origin>,
     },
     UnexpectedFunction {
         span: ByteSpan,
@@ -68,11 +111,88 @@ pub enum TypeError {
     },
     ExpectedUniverse {
         span: ByteSpan,
-        found: RcType,
+        found: RcValue,
+        found_ty: RcType,
+    },
+    /// A type was found in a different universe to the one that was expected
+    UniverseMismatch {
+        span: ByteSpan,
+        found: Level,
+        expected: Level,
+    },
+    /// The largest universe was used in a position where it needs a type
+    UniverseLevelOverflow {
+        span: ByteSpan,
+        level: Level,
     },
     UndefinedName {
         var_span: ByteSpan,
         name: Name,
+        /// A similarly named binder that is in scope
+        suggestion: Option<String>,
+    },
+    /// A hole was found, along with the type that was expected of it and the
+    /// most recently bound names that were in scope
+    UnsolvedHole {
+        span: ByteSpan,
+        expected: RcType,
+        bindings: Vec<(Name, RcType)>,
+    },
+    /// A hole was found where its type could not be inferred
+    HoleNeedsAnnotation {
+        span: ByteSpan,
+    },
+    /// The argument to an implicit parameter could not be inferred
+    UnsolvedImplicit {
+        span: ByteSpan,
+        name: Name,
+    },
+    /// The clauses of a case expression do not cover every possible value of
+    /// the scrutinee, along with some patterns that are not covered
+    NonExhaustiveMatch {
+        span: ByteSpan,
+        missing: Vec<Pattern>,
+    },
+    /// An imported module could not be found
+    UnresolvedImport {
+        span: ByteSpan,
+        name: String,
+    },
+    /// A module imports itself, either directly or through the modules that
+    /// it imports
+    CircularImport {
+        span: ByteSpan,
+        name: String,
+    },
+    /// A definition was exposed by an import, or projected from an imported
+    /// module, but the module does not define it
+    UndefinedImportedName {
+        span: ByteSpan,
+        module: String,
+        name: String,
+    },
+    /// A definition was hidden by an import, but the module does not define it
+    HiddenNameNotFound {
+        span: ByteSpan,
+        module: String,
+        name: String,
+    },
+    /// The type annotation of a definition refers back to the definition
+    CircularAnnotation {
+        span: ByteSpan,
+        name: String,
+    },
+    /// A warning produced by the naming lint
+    NamingConvention {
+        span: ByteSpan,
+        name: String,
+        convention: NamingConvention,
+    },
+    /// A warning produced by the unused imports lint
+    UnusedImport {
+        span: ByteSpan,
+        module: String,
+        name: String,
     },
     Internal(InternalError),
 }
@@ -91,6 +211,22 @@ impl TypeError {
                 found,
             )).with_primary_label(fn_span, "the term")
                 .with_secondary_label(arg_span, "the applied argument"),
+            TypeError::NotAPairType { span, ref found } => Diagnostic::new_error(format!(
+                "projected a component from a term that was not a pair - found type `{}`",
+                found,
+            )).with_primary_label(span, "the term"),
+            TypeError::FieldNotFound {
+                span,
+                ref expected_label,
+                ref found,
+            } => Diagnostic::new_error(format!(
+                "no field `{}` was found in the type `{}`",
+                expected_label, found,
+            )).with_primary_label(span, "the field projection"),
+            TypeError::ForwardFieldReference { span, ref field } => Diagnostic::new_error(format!(
+                "the field `{}` is referred to before it is declared",
+                field,
+            )).with_primary_label(span, "the type of the field that refers to it"),
             TypeError::FunctionParamNeedsAnnotation {
                 param_span,
                 var_span: _, // TODO
@@ -109,18 +245,127 @@ impl TypeError {
                 span,
                 ref found,
                 ref expected,
+                ref difference,
+                expected_origin,
+            } => {
+                // Core values don't retain their spans, so the parts of the
+                // types that differ are described in the label instead
+                let label = match *difference {
+                    Some((ref found, ref expected)) => format!(
+                        "the term - these differ here: found `{}`, but expected `{}`",
+                        found, expected,
+                    ),
+                    None => String::from("the term"),
+                };
+
+                Diagnostic::new_error(format!(
+                    "found a term of type `{}`, but expected a term of type `{}`{}",
+                    found,
+                    expected,
+                    match expected_origin {
+                        Some(Origin::Prelude) => " (defined in the prelude)",
+                        Some(Origin::User) | None => "",
+                    },
+                )).with_primary_label(span, label)
+            },
+            TypeError::ExpectedUniverse {
+                span,
+                ref found,
+                ref found_ty,
             } => Diagnostic::new_error(format!(
-                "found a term of type `{}`, but expected a term of type `{}`",
+                "expected a type, but `{}` has type `{}`",
+                found, found_ty,
+            )).with_primary_label(span, "the value"),
+            TypeError::UniverseMismatch {
+                span,
+                ref found,
+                ref expected,
+            } => Diagnostic::new_error(format!(
+                "found a type in universe level {}, but expected one in universe level {}",
                 found, expected,
-            )).with_primary_label(span, "the term"),
-            TypeError::ExpectedUniverse { ref found, span } => {
-                Diagnostic::new_error(format!("expected type, found value `{}`", found))
-                    .with_primary_label(span, "the value")
-            },
-            TypeError::UndefinedName { ref name, var_span } => {
+            )).with_primary_label(span, "the type"),
+            TypeError::UniverseLevelOverflow { span, ref level } => Diagnostic::new_error(format!(
+                "universe level {} is the largest, so `Type {}` has no type",
+                level, level,
+            )).with_primary_label(span, "the universe"),
+            TypeError::UndefinedName {
+                ref name,
+                var_span,
+                ref suggestion,
+            } => {
+                let label = match *suggestion {
+                    Some(ref suggestion) => format!("did you mean `{}`?", suggestion),
+                    None => String::from("not found in this scope"),
+                };
+
                 Diagnostic::new_error(format!("cannot find `{}` in scope", name))
-                    .with_primary_label(var_span, "not found in this scope")
+                    .with_primary_label(var_span, label)
+            },
+            TypeError::UnsolvedHole {
+                span,
+                ref expected,
+                ref bindings,
+            } => {
+                let mut message = format!("found a hole of type `{}`", expected);
+                if !bindings.is_empty() {
+                    message.push_str("\n\n  in scope:");
+                    for &(ref name, ref ann) in bindings {
+                        message.push_str(&format!("\n    {} : {}", name, ann));
+                    }
+                }
+
+                Diagnostic::new_error(message).with_primary_label(span, "the hole")
+            },
+            TypeError::HoleNeedsAnnotation { span } => {
+                Diagnostic::new_error("type annotation needed for the hole")
+                    .with_primary_label(span, "the hole that requires an annotation")
             },
+            TypeError::UnsolvedImplicit { span, ref name } => Diagnostic::new_error(format!(
+                "could not infer the argument to the implicit parameter `{}`",
+                name,
+            )).with_primary_label(span, "a type annotation is needed here"),
+            TypeError::NonExhaustiveMatch { span, ref missing } => Diagnostic::new_error(format!(
+                "non-exhaustive patterns - `{}` not covered",
+                join_patterns(missing),
+            )).with_primary_label(span, "the case expression"),
+            TypeError::UnresolvedImport { span, ref name } => {
+                Diagnostic::new_error(format!("cannot find the module `{}`", name))
+                    .with_primary_label(span, "imported here")
+            },
+            TypeError::CircularImport { span, ref name } => {
+                Diagnostic::new_error(format!("the module `{}` is imported circularly", name))
+                    .with_primary_label(span, "imported again here")
+            },
+            TypeError::UndefinedImportedName {
+                span,
+                ref module,
+                ref name,
+            } => Diagnostic::new_error(format!("cannot find `{}` in the module `{}`", name, module))
+                .with_primary_label(span, "not found in the imported module"),
+            TypeError::HiddenNameNotFound {
+                span,
+                ref module,
+                ref name,
+            } => Diagnostic::new_warning(format!(
+                "cannot hide `{}`, because it is not in the module `{}`",
+                name, module,
+            )).with_primary_label(span, "not found in the imported module"),
+            TypeError::CircularAnnotation { span, ref name } => Diagnostic::new_error(format!(
+                "the type annotation of `{}` refers back to `{}`",
+                name, name,
+            )).with_primary_label(span, "circular type annotation"),
+            TypeError::NamingConvention {
+                span,
+                ref name,
+                convention,
+            } => Diagnostic::new_warning(format!("`{}` is not in {}", name, convention))
+                .with_primary_label(span, format!("consider renaming this to use {}", convention)),
+            TypeError::UnusedImport {
+                span,
+                ref module,
+                ref name,
+            } => Diagnostic::new_warning(format!("unused import `{}` from `{}`", name, module))
+                .with_primary_label(span, "never used in this module"),
         }
     }
 }
@@ -137,6 +382,21 @@ impl fmt::Display for TypeError {
             TypeError::NotAFunctionType { ref found, .. } => {
                 write!(f, "Applied an argument to a non-function type `{}`", found,)
             },
+            TypeError::NotAPairType { ref found, .. } => {
+                write!(f, "Projected a component from a non-pair type `{}`", found)
+            },
+            TypeError::FieldNotFound {
+                ref expected_label,
+                ref found,
+                ..
+            } => write!(
+                f,
+                "The field `{}` was not found in the type `{}`",
+                expected_label, found,
+            ),
+            TypeError::ForwardFieldReference { ref field, .. } => {
+                write!(f, "The field `{}` is referred to before it is declared", field)
+            },
             TypeError::FunctionParamNeedsAnnotation { ref name, .. } => write!(
                 f,
                 "Type annotation needed for the function parameter `{}`",
@@ -154,11 +414,79 @@ impl fmt::Display for TypeError {
             TypeError::UnexpectedFunction { ref expected, .. } => {
                 write!(f, "Found a function but expected `{}`", expected,)
             },
-            TypeError::ExpectedUniverse { ref found, .. } => {
-                write!(f, "Found `{}` but a universe was expected", found,)
+            TypeError::ExpectedUniverse {
+                ref found,
+                ref found_ty,
+                ..
+            } => write!(
+                f,
+                "Found `{}` of type `{}` but a universe was expected",
+                found, found_ty,
+            ),
+            TypeError::UniverseMismatch {
+                ref found,
+                ref expected,
+                ..
+            } => write!(
+                f,
+                "Universe mismatch: found level `{}` but `{}` was expected",
+                found, expected,
+            ),
+            TypeError::UniverseLevelOverflow { ref level, .. } => {
+                write!(f, "Universe level overflow: `Type {}` has no type", level)
             },
+            TypeError::UndefinedName {
+                ref name,
+                suggestion: Some(ref suggestion),
+                ..
+            } => write!(f, "Undefined name `{}`, did you mean `{}`?", name, suggestion),
             TypeError::UndefinedName { ref name, .. } => write!(f, "Undefined name `{}`", name),
+            TypeError::UnsolvedHole { ref expected, .. } => {
+                write!(f, "Found a hole of type `{}`", expected)
+            },
+            TypeError::HoleNeedsAnnotation { .. } => write!(f, "Type annotation needed for a hole"),
+            TypeError::UnsolvedImplicit { ref name, .. } => {
+                write!(f, "Could not infer the implicit argument `{}`", name)
+            },
+            TypeError::NonExhaustiveMatch { ref missing, .. } => {
+                write!(f, "Non-exhaustive patterns: `{}` not covered", join_patterns(missing))
+            },
+            TypeError::UnresolvedImport { ref name, .. } => {
+                write!(f, "Unresolved import of the module `{}`", name)
+            },
+            TypeError::CircularImport { ref name, .. } => {
+                write!(f, "Circular import of the module `{}`", name)
+            },
+            TypeError::UndefinedImportedName {
+                ref module,
+                ref name,
+                ..
+            } => write!(f, "The module `{}` does not define `{}`", module, name),
+            TypeError::HiddenNameNotFound {
+                ref module,
+                ref name,
+                ..
+            } => write!(f, "The module `{}` does not define the hidden name `{}`", module, name),
+            TypeError::CircularAnnotation { ref name, .. } => {
+                write!(f, "The type annotation of `{}` is circular", name)
+            },
+            TypeError::NamingConvention {
+                ref name,
+                convention,
+                ..
+            } => write!(f, "The name `{}` is not in {}", name, convention),
+            TypeError::UnusedImport {
+                ref module,
+                ref name,
+                ..
+            } => write!(f, "The name `{}` is imported from `{}`, but never used", name, module),
             TypeError::Internal(ref err) => write!(f, "Internal error - this is a bug! {}", err),
         }
     }
 }
+
+/// Join patterns into a comma separated list, for use in error messages
+fn join_patterns(patterns: &[Pattern]) -> String {
+    let patterns = patterns.iter().map(Pattern::to_string).collect::<Vec<_>>();
+    patterns.join("`, `")
+}
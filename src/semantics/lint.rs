@@ -0,0 +1,247 @@
+//! Opt-in stylistic checks that produce warnings
+
+use codespan::ByteSpan;
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use syntax::concrete::{Declaration, LamParams, Module, Pattern, Term};
+use syntax::core;
+
+use super::TypeError;
+
+/// A convention for naming identifiers
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NamingConvention {
+    /// Names like `foo-bar`
+    KebabCase,
+    /// Names like `foo_bar`
+    SnakeCase,
+    /// Names like `fooBar`
+    CamelCase,
+}
+
+impl NamingConvention {
+    /// Returns `true` if the name follows the naming convention
+    pub fn matches(self, name: &str) -> bool {
+        let has_upper = name.chars().any(char::is_uppercase);
+        let starts_upper = name.chars().next().map_or(false, char::is_uppercase);
+
+        match self {
+            NamingConvention::KebabCase => !has_upper && !name.contains('_'),
+            NamingConvention::SnakeCase => !has_upper && !name.contains('-'),
+            NamingConvention::CamelCase => {
+                !starts_upper && !name.contains('-') && !name.contains('_')
+            },
+        }
+    }
+}
+
+impl fmt::Display for NamingConvention {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NamingConvention::KebabCase => write!(f, "kebab-case"),
+            NamingConvention::SnakeCase => write!(f, "snake_case"),
+            NamingConvention::CamelCase => write!(f, "camelCase"),
+        }
+    }
+}
+
+impl FromStr for NamingConvention {
+    type Err = &'static str;
+
+    fn from_str(src: &str) -> Result<NamingConvention, &'static str> {
+        match src {
+            "kebab" | "kebab-case" => Ok(NamingConvention::KebabCase),
+            "snake" | "snake_case" => Ok(NamingConvention::SnakeCase),
+            "camel" | "camelCase" => Ok(NamingConvention::CamelCase),
+            _ => Err("no match"),
+        }
+    }
+}
+
+/// Find the names introduced in a module that do not follow the given naming
+/// convention, returning a warning for each of them
+pub fn naming(module: &Module, convention: NamingConvention) -> Vec<TypeError> {
+    let mut warnings = Vec::new();
+
+    {
+        let mut check_name = |&(span, ref name): &(ByteSpan, String)| {
+            if !convention.matches(name) {
+                warnings.push(TypeError::NamingConvention {
+                    span,
+                    name: name.clone(),
+                    convention,
+                });
+            }
+        };
+
+        if let Module::Valid {
+            ref declarations, ..
+        } = *module
+        {
+            naming_declarations(declarations, &mut check_name);
+        }
+    }
+
+    warnings
+}
+
+/// Find the names exposed by the imports of a module that none of its
+/// definitions refer to, returning a warning for each of them
+///
+/// Only imports that expose an exact list of names are checked, because the
+/// names that `(..)` brings into scope aren't known until the imported module
+/// has been resolved.
+pub fn unused_imports(module: &core::Module) -> Vec<TypeError> {
+    let mut used = HashSet::new();
+    for definition in &module.definitions {
+        used.extend(definition.term.free_vars());
+        if let Some(ref ann) = definition.ann {
+            used.extend(ann.free_vars());
+        }
+    }
+
+    let mut warnings = Vec::new();
+    for import in &module.imports {
+        if let core::Exposing::Exact(ref names) = import.exposing {
+            for &(span, _, ref rename) in names {
+                if !used.contains(&core::Name::user(rename.clone())) {
+                    warnings.push(TypeError::UnusedImport {
+                        span,
+                        module: import.name.clone(),
+                        name: rename.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    warnings
+}
+
+fn naming_declarations<F>(declarations: &[Declaration], check_name: &mut F)
+where
+    F: FnMut(&(ByteSpan, String)),
+{
+    for declaration in declarations {
+        match *declaration {
+            Declaration::Import { .. } | Declaration::Error(_) => {},
+            Declaration::Claim { ref ann, .. } => naming_term(ann, check_name),
+            Declaration::Definition {
+                ref name,
+                ref params,
+                ref body,
+                ref where_block,
+                ..
+            } => {
+                check_name(name);
+                naming_lam_params(params, check_name);
+                naming_term(body, check_name);
+                if let Some((_, ref declarations)) = *where_block {
+                    naming_declarations(declarations, check_name);
+                }
+            },
+            Declaration::Recursive {
+                ref name,
+                ref ann,
+                ref body,
+                ..
+            } => {
+                check_name(name);
+                naming_term(ann, check_name);
+                naming_term(body, check_name);
+            },
+        }
+    }
+}
+
+fn naming_lam_params<F>(params: &LamParams, check_name: &mut F)
+where
+    F: FnMut(&(ByteSpan, String)),
+{
+    for &(_, ref names, ref ann) in params {
+        names.iter().for_each(|name| check_name(name));
+        if let Some(ref ann) = *ann {
+            naming_term(ann, check_name);
+        }
+    }
+}
+
+fn naming_term<F>(term: &Term, check_name: &mut F)
+where
+    F: FnMut(&(ByteSpan, String)),
+{
+    match *term {
+        Term::Universe(_, _)
+        | Term::IntLit(_, _)
+        | Term::StringLit(_, _)
+        | Term::Hole(_)
+        | Term::Var(_, _)
+        | Term::Error(_) => {},
+        Term::Parens(_, ref term) | Term::Fst(_, ref term) | Term::Snd(_, ref term) => {
+            naming_term(term, check_name)
+        },
+        Term::Lam(_, ref params, ref body) => {
+            naming_lam_params(params, check_name);
+            naming_term(body, check_name);
+        },
+        Term::Pi(_, _, (ref names, ref ann), ref body)
+        | Term::Sigma(_, (ref names, ref ann), ref body) => {
+            names.iter().for_each(|name| check_name(name));
+            naming_term(ann, check_name);
+            naming_term(body, check_name);
+        },
+        Term::RecordType(_, ref fields) | Term::Record(_, ref fields) => {
+            for &(span, ref label, ref term) in fields {
+                check_name(&(span, label.clone()));
+                naming_term(term, check_name);
+            }
+        },
+        Term::Proj(ref record, _, _) => naming_term(record, check_name),
+        Term::Let(_, ref bindings, ref body) => {
+            for &(span, ref name, ref ann, ref value) in bindings {
+                check_name(&(span, name.clone()));
+                if let Some(ref ann) = *ann {
+                    naming_term(ann, check_name);
+                }
+                naming_term(value, check_name);
+            }
+            naming_term(body, check_name);
+        },
+        Term::If(_, ref cond, ref if_true, ref if_false) => {
+            naming_term(cond, check_name);
+            naming_term(if_true, check_name);
+            naming_term(if_false, check_name);
+        },
+        Term::Case(_, ref scrutinee, ref clauses) => {
+            naming_term(scrutinee, check_name);
+            for &(ref pattern, ref body) in clauses {
+                naming_pattern(pattern, check_name);
+                naming_term(body, check_name);
+            }
+        },
+        Term::Ann(ref lhs, ref rhs)
+        | Term::Arrow(ref lhs, ref rhs)
+        | Term::Product(ref lhs, ref rhs)
+        | Term::Pair(_, ref lhs, ref rhs)
+        | Term::App(ref lhs, ref rhs) => {
+            naming_term(lhs, check_name);
+            naming_term(rhs, check_name);
+        },
+    }
+}
+
+fn naming_pattern<F>(pattern: &Pattern, check_name: &mut F)
+where
+    F: FnMut(&(ByteSpan, String)),
+{
+    match *pattern {
+        Pattern::Wildcard(_) => {},
+        Pattern::Var(span, ref name) => check_name(&(span, name.clone())),
+        Pattern::Pair(_, ref fst, ref snd) => {
+            naming_pattern(fst, check_name);
+            naming_pattern(snd, check_name);
+        },
+    }
+}
@@ -8,11 +8,13 @@
 //!
 //! The semantics of Pikelet are described by the following judgement forms:
 //!
-//! | name           | notation         | inputs        | outputs  | implementing function  |
-//! |----------------|------------------|---------------|----------|------------------------|
-//! | normalization  | `Γ ⊢ e ⇓ v`     | `Γ`, `e`      | `v`      | `semantics::normalize` |
-//! | type checking  | `Γ ⊢ e ⇐ τ ⤳ v` | `Γ`, `e`, `τ` | `v`      | `semantics::check`     |
-//! | type inference | `Γ ⊢ e ⇒ τ ⤳ v` | `Γ`, `e`      | `τ`, `v` | `semantics::infer`     |
+//! | name           | notation         | inputs          | outputs  | implementing function   |
+//! |----------------|------------------|-----------------|----------|-------------------------|
+//! | normalization  | `Γ ⊢ e ⇓ v`     | `Γ`, `e`        | `v`      | `semantics::normalize`  |
+//! | type checking  | `Γ ⊢ e ⇐ τ ⤳ v` | `Γ`, `e`, `τ`   | `v`      | `semantics::check`      |
+//! | type inference | `Γ ⊢ e ⇒ τ ⤳ v` | `Γ`, `e`        | `τ`, `v` | `semantics::infer`      |
+//! | subtyping      | `Γ ⊢ τ₁ ≼ τ₂`   | `Γ`, `τ₁`, `τ₂` |          | `semantics::is_subtype` |
+//! | weak head norm | `Γ ⊢ e ⇓ʷ v`    | `Γ`, `e`        | `v`      | `semantics::whnf`       |
 //!
 //! The judgements rely on the syntax for terms, values, and contexts that were
 //! previously defined in `syntax::core`.  Care has been taken to design the
@@ -81,14 +83,19 @@
 //! [axiom-wikipedia]: https://en.wikipedia.org/wiki/Axiom
 
 use codespan::ByteSpan;
+use std::collections::{HashMap, HashSet};
 
 use syntax::core::{self, Binder, Context, Level, Module, Name, RcTerm, RcType, RcValue, Term};
-use syntax::core::{Value, ValueLam, ValuePi};
-use syntax::var::{Named, Var};
+use syntax::core::{Constant, Exposing, Import, Pattern, Plicity, SourceMeta, Transparency};
+use syntax::core::{TermClause, TermLet, TermRecordType, Value, ValueLam, ValuePi};
+use syntax::core::{ValueRecordType, ValueSigma};
+use syntax::lev_distance;
+use syntax::var::{Debruijn, GenId, Named, Var};
 
 #[cfg(test)]
 mod tests;
 mod errors;
+pub mod lint;
 
 pub use self::errors::{InternalError, TypeError};
 
@@ -98,51 +105,459 @@ pub struct CheckedModule {
     pub name: String,
     /// The definitions contained in the module
     pub definitions: Vec<CheckedDefinition>,
+    /// Warnings that were produced while checking the module
+    pub warnings: Vec<TypeError>,
 }
 
 /// A typechecked and elaborated definition
 pub struct CheckedDefinition {
     /// The name of the definition
     pub name: String,
+    /// The doc comments attached to the definition
+    pub docs: Vec<String>,
     /// The elaborated value
     pub term: RcValue,
     /// The type of the definition
     pub ann: RcType,
+    /// Whether the value of the definition is visible to other definitions
+    pub transparency: Transparency,
+    /// Whether the value of the definition refers to itself
+    pub recursive: bool,
+}
+
+/// The maximum depth of nested binders that is allowed in terms by default
+pub const DEFAULT_MAX_BINDER_DEPTH: u32 = 1 << 16;
+
+/// Check that the binders in a term are nested no more than `max_depth` deep
+///
+/// This guards against the debruijn indices in overly large (possibly
+/// generated or malicious) terms overflowing.
+pub fn check_binder_depth(term: &RcTerm, max_depth: u32) -> Result<(), InternalError> {
+    // The terms that are still to be checked, along with the depth of the
+    // binders that they are nested under. We use an explicit stack rather
+    // than recursion, so that checking a deeply nested term can't overflow
+    // the call stack before we get the chance to reject it.
+    let mut stack = vec![(term, Debruijn::ZERO)];
+
+    while let Some((term, depth)) = stack.pop() {
+        let succ_depth = |depth: Debruijn| match depth.succ() {
+            depth if depth.0 <= u64::from(max_depth) => Ok(depth),
+            _ => Err(InternalError::BinderDepthExceeded {
+                span: term.span(),
+                max_depth,
+            }),
+        };
+
+        match *term.inner {
+            Term::Ann(_, ref expr, ref ty) => {
+                stack.push((expr, depth));
+                stack.push((ty, depth));
+            },
+            Term::Universe(_, _) | Term::Constant(_, _) | Term::Var(_, _) | Term::Hole(_) => {},
+            Term::Lam(_, ref lam) => {
+                if let Some(ref ann) = lam.unsafe_param.inner {
+                    stack.push((ann, depth));
+                }
+                stack.push((&lam.unsafe_body, succ_depth(depth)?));
+            },
+            Term::Pi(_, ref pi) => {
+                stack.push((&pi.unsafe_param.inner, depth));
+                stack.push((&pi.unsafe_body, succ_depth(depth)?));
+            },
+            Term::App(_, _, _) => {
+                let (head, args) = app_spine(term);
+                stack.push((head, depth));
+                stack.extend(args.into_iter().map(|arg| (arg, depth)));
+            },
+            Term::Let(_, ref let_) => {
+                if let Some(ref ann) = let_.unsafe_param.inner {
+                    stack.push((ann, depth));
+                }
+                stack.push((&let_.value, depth));
+                stack.push((&let_.unsafe_body, succ_depth(depth)?));
+            },
+            Term::Sigma(_, ref sigma) => {
+                stack.push((&sigma.unsafe_param.inner, depth));
+                stack.push((&sigma.unsafe_body, succ_depth(depth)?));
+            },
+            Term::Pair(_, ref fst, ref snd) => {
+                stack.push((fst, depth));
+                stack.push((snd, depth));
+            },
+            Term::Fst(_, ref pair) | Term::Snd(_, ref pair) => stack.push((pair, depth)),
+            Term::RecordType(_, ref record_ty) => {
+                stack.push((&record_ty.unsafe_param.inner, depth));
+                stack.push((&record_ty.unsafe_body, succ_depth(depth)?));
+            },
+            Term::RecordTypeEmpty(_) | Term::RecordEmpty(_) => {},
+            Term::Record(_, _, ref value, ref rest) => {
+                stack.push((value, depth));
+                stack.push((rest, depth));
+            },
+            Term::Proj(_, ref record, _) => stack.push((record, depth)),
+            Term::If(_, ref cond, ref if_true, ref if_false) => {
+                stack.push((cond, depth));
+                stack.push((if_true, depth));
+                stack.push((if_false, depth));
+            },
+            Term::Case(_, ref scrutinee, ref clauses) => {
+                stack.push((scrutinee, depth));
+                for clause in clauses {
+                    let mut body_depth = depth;
+                    for _ in clause.unsafe_pattern.binders() {
+                        body_depth = succ_depth(body_depth)?;
+                    }
+                    stack.push((&clause.unsafe_body, body_depth));
+                }
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Split an application into its head and the arguments that are applied to
+/// it, from left to right
+///
+/// ```text
+/// e₀ e₁ ... eₙ  ↦  (e₀, [e₁, ..., eₙ])
+/// ```
+fn app_spine(term: &RcTerm) -> (&RcTerm, Vec<&RcTerm>) {
+    let mut head = term;
+    let mut args = Vec::new();
+    while let Term::App(_, ref fn_expr, ref arg_expr) = *head.inner {
+        args.push(arg_expr);
+        head = fn_expr;
+    }
+    args.reverse();
+    (head, args)
 }
 
 /// Typecheck and elaborate a module
-pub fn check_module(module: &Module) -> Result<CheckedModule, TypeError> {
-    let mut context = Context::new();
+///
+/// Checking continues past definitions that fail to check, so that as many
+/// errors as possible are reported at once. If a broken definition has a
+/// valid type claim, it is added to the context as a postulate of that type,
+/// preventing later definitions that refer to it from reporting spurious
+/// errors of their own.
+pub fn check_module(module: &Module) -> Result<CheckedModule, Vec<TypeError>> {
+    // There is no way of locating other modules here, so any imports are
+    // reported rather than being silently dropped
+    check_module_with_imports(module, &mut |_| None)
+}
+
+/// Typecheck and elaborate a module, using `resolve` to find the modules
+/// that it imports
+///
+/// The imported modules are checked before the definitions of the module.
+/// Each one is bound in the context as a record of its definitions, and the
+/// definitions that it exposes are brought into scope as well.
+pub fn check_module_with_imports<F>(
+    module: &Module,
+    resolve: &mut F,
+) -> Result<CheckedModule, Vec<TypeError>>
+where
+    F: FnMut(&str) -> Option<Module>,
+{
+    check_module_importing(module, resolve, &mut vec![module.name.clone()])
+}
+
+/// Typecheck and elaborate a module, keeping track of the modules that are in
+/// the middle of being imported so that circular imports can be reported
+fn check_module_importing<F>(
+    module: &Module,
+    resolve: &mut F,
+    importing: &mut Vec<String>,
+) -> Result<CheckedModule, Vec<TypeError>>
+where
+    F: FnMut(&str) -> Option<Module>,
+{
+    let mut context = Context::default();
     let mut definitions = Vec::with_capacity(module.definitions.len());
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+    let circular = circular_annotations(module);
+
+    for import in &module.imports {
+        match import_module(&context, import, resolve, importing, &mut warnings) {
+            Ok(import_context) => context = import_context,
+            Err(import_errors) => errors.extend(import_errors),
+        }
+    }
 
     for definition in &module.definitions {
         let name = definition.name.clone();
-        let (term, ann) = match definition.ann {
-            // We don't have a type annotation available to us! Instead we will
-            // attempt to infer it based on the body of the definition
-            None => infer(&context, &definition.term)?,
-            // We have a type annotation! Evaluate it to its normal form, then
-            // check that it matches the body of the definition
-            Some(ref ann) => {
-                let ann = normalize(&context, &ann)?;
-                let elab_term = check(&context, &definition.term, &ann)?;
-                (elab_term, ann)
+
+        // Evaluate the type annotation to its normal form, if we have one
+        let ann = match definition.ann {
+            None => None,
+            Some(ref ann) if circular.contains(&name) => {
+                errors.push(TypeError::CircularAnnotation {
+                    span: ann.span(),
+                    name,
+                });
+                continue;
             },
+            Some(ref ann) => match check_binder_depth(ann, DEFAULT_MAX_BINDER_DEPTH)
+                .and_then(|()| normalize(&context, ann))
+            {
+                Ok(ann) => Some(ann),
+                Err(err) => {
+                    errors.push(err.into());
+                    continue;
+                },
+            },
+        };
+
+        // Recursive definitions are checked with their claimed type in scope,
+        // so that they can refer to themselves. There's no termination check
+        // yet, so normalization is only bounded by the step limit!
+        let body_context = match ann {
+            Some(ref ann) if definition.recursive => {
+                context.declare(Name::user(name.clone()), ann.clone())
+            },
+            Some(_) | None => context.clone(),
         };
 
-        // Add the definition to the context
-        context = context.extend(
-            Name::user(name.clone()),
-            Binder::Let(term.clone(), ann.clone()),
+        let elab_result = check_binder_depth(&definition.term, DEFAULT_MAX_BINDER_DEPTH)
+            .map_err(TypeError::from)
+            .and_then(|()| match ann {
+                // We don't have a type annotation available to us! Instead we will
+                // attempt to infer it based on the body of the definition
+                None => infer(&body_context, &definition.term),
+                // We have a type annotation! Check that it matches the body of
+                // the definition
+                Some(ref ann) => Ok((check(&body_context, &definition.term, ann)?, ann.clone())),
+            });
+
+        errors.extend(unsolved_holes(&context));
+
+        match (elab_result, ann) {
+            (Ok((term, ann)), _) => {
+                // Add the definition to the context
+                context = context.extend(
+                    Name::user(name.clone()),
+                    Binder::Let(term.clone(), ann.clone(), definition.transparency),
+                );
+
+                definitions.push(CheckedDefinition {
+                    name,
+                    docs: definition.docs.clone(),
+                    term,
+                    ann,
+                    transparency: definition.transparency,
+                    recursive: definition.recursive,
+                })
+            },
+            (Err(err), Some(ann)) => {
+                errors.push(err);
+                // Postulate the claimed type, so that later definitions can
+                // still refer to this one
+                context = context.declare(Name::user(name), ann);
+            },
+            (Err(err), None) => errors.push(err),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(CheckedModule {
+            name: module.name.clone(),
+            definitions,
+            warnings,
+        })
+    } else {
+        errors.extend(warnings);
+        Err(errors)
+    }
+}
+
+/// Resolve and check an imported module, returning the context extended with
+/// the module and the definitions that it exposes
+///
+/// Definitions that are not exposed are bound to fresh names, so that the
+/// exposed definitions can still refer to them without them being in scope.
+/// Hiding a name that the module doesn't define is only worth a warning, so
+/// these are pushed onto `warnings` rather than failing the import.
+fn import_module<F>(
+    context: &Context,
+    import: &Import,
+    resolve: &mut F,
+    importing: &mut Vec<String>,
+    warnings: &mut Vec<TypeError>,
+) -> Result<Context, Vec<TypeError>>
+where
+    F: FnMut(&str) -> Option<Module>,
+{
+    if importing.contains(&import.name) {
+        return Err(vec![TypeError::CircularImport {
+            span: import.span,
+            name: import.name.clone(),
+        }]);
+    }
+
+    let module = match resolve(&import.name) {
+        Some(module) => module,
+        None => {
+            return Err(vec![TypeError::UnresolvedImport {
+                span: import.span,
+                name: import.name.clone(),
+            }]);
+        },
+    };
+
+    importing.push(import.name.clone());
+    let checked_module = check_module_importing(&module, resolve, importing);
+    importing.pop();
+    let mut checked_module = checked_module?;
+    warnings.append(&mut checked_module.warnings);
+
+    if let Exposing::Hiding(ref names) = import.exposing {
+        warnings.extend(
+            names
+                .iter()
+                .filter(|&&(_, ref name)| {
+                    !checked_module.definitions.iter().any(|definition| definition.name == *name)
+                })
+                .map(|&(span, ref name)| TypeError::HiddenNameNotFound {
+                    span,
+                    module: import.name.clone(),
+                    name: name.clone(),
+                }),
         );
+    }
+
+    if let Exposing::Exact(ref names) = import.exposing {
+        let errors = names
+            .iter()
+            .filter(|&&(_, ref name, _)| {
+                !checked_module.definitions.iter().any(|definition| definition.name == *name)
+            })
+            .map(|&(span, ref name, _)| TypeError::UndefinedImportedName {
+                span,
+                module: import.name.clone(),
+                name: name.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+    }
+
+    let mut context = context.clone();
+    // The variables that the definitions of the module were bound to
+    let mut renames = Vec::<(Name, RcValue)>::new();
+    // The label, bound name, value, and type of each field of the module
+    let mut fields = Vec::with_capacity(checked_module.definitions.len());
+
+    for definition in &checked_module.definitions {
+        let exposed_name = match import.exposing {
+            Exposing::Nothing => None,
+            Exposing::All => Some(&definition.name),
+            Exposing::Exact(ref names) => names
+                .iter()
+                .find(|&&(_, ref name, _)| *name == definition.name)
+                .map(|&(_, _, ref rename)| rename),
+            Exposing::Hiding(ref names) => {
+                let hidden = names.iter().any(|&(_, ref name)| *name == definition.name);
+                if hidden { None } else { Some(&definition.name) }
+            },
+        };
+        let name = match exposed_name {
+            Some(exposed_name) => Name::user(exposed_name.clone()),
+            None => Name::fresh(Some(definition.name.clone())),
+        };
+
+        let var = RcValue::from(Value::Var(Var::Free(name.clone())));
+
+        let mut term = definition.term.clone();
+        let mut ann = definition.ann.clone();
+        // Recursive definitions refer to themselves by their original name
+        if definition.recursive {
+            term.subst(&Name::user(definition.name.clone()), &var);
+        }
+        for &(ref original_name, ref var) in &renames {
+            term.subst(original_name, var);
+            ann.subst(original_name, var);
+        }
+        let field = match definition.transparency {
+            Transparency::Transparent => term.clone(),
+            Transparency::Opaque => var.clone(),
+        };
+        fields.push((definition.name.clone(), name.clone(), field, ann.clone()));
 
-        definitions.push(CheckedDefinition { name, term, ann })
+        context = context.extend(name, Binder::Let(term, ann, definition.transparency));
+        renames.push((Name::user(definition.name.clone()), var));
     }
 
-    Ok(CheckedModule {
-        name: module.name.clone(),
-        definitions,
-    })
+    let mut record = RcValue::from(Value::RecordEmpty);
+    let mut record_ty = RcValue::from(Value::RecordTypeEmpty);
+    for (label, name, field, ann) in fields.into_iter().rev() {
+        record = Value::Record(label.clone(), field, record).into();
+        let record_ty_field = ValueRecordType::bind(label, Named::new(name, ann), record_ty);
+        record_ty = Value::RecordType(record_ty_field).into();
+    }
+
+    let module_name = Name::user(import.rename.as_ref().unwrap_or(&import.name).clone());
+
+    Ok(context.import(module_name, import.name.clone(), record, record_ty))
+}
+
+/// Remove the holes that have been recorded in the context, converting them
+/// into errors
+pub fn unsolved_holes(context: &Context) -> Vec<TypeError> {
+    context
+        .take_holes()
+        .into_iter()
+        .map(|(span, expected, bindings)| TypeError::UnsolvedHole {
+            span,
+            expected,
+            bindings,
+        })
+        .collect()
+}
+
+/// Find the definitions in a module whose type annotations refer back to
+/// themselves, either directly (eg. `x : x`), or through the type annotations
+/// of the other definitions they refer to (eg. `x : y` and `y : x`)
+fn circular_annotations(module: &Module) -> HashSet<String> {
+    // The definitions referred to by the annotation of each definition
+    let dependencies = module
+        .definitions
+        .iter()
+        .map(|definition| match definition.ann {
+            None => Vec::new(),
+            Some(ref ann) => {
+                let free_vars = ann.free_vars();
+                module
+                    .definitions
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, other)| free_vars.contains(&Name::user(other.name.as_str())))
+                    .map(|(index, _)| index)
+                    .collect()
+            },
+        })
+        .collect::<Vec<Vec<usize>>>();
+
+    let mut circular = HashSet::new();
+    for (index, definition) in module.definitions.iter().enumerate() {
+        // Search for a path of annotations leading back to this definition
+        let mut visited = vec![false; dependencies.len()];
+        let mut stack = dependencies[index].clone();
+        while let Some(next) = stack.pop() {
+            if next == index {
+                circular.insert(definition.name.clone());
+                break;
+            }
+            if !visited[next] {
+                visited[next] = true;
+                stack.extend(&dependencies[next]);
+            }
+        }
+    }
+
+    circular
 }
 
 /// Evaluate a term in a context
@@ -157,18 +572,31 @@ pub fn check_module(module: &Module) -> Result<CheckedModule, TypeError> {
 /// Here we diverge from the LambdaPi paper by requiring a context to be
 /// supplied. This allows us to resolve previously defined terms during
 /// normalization.
+///
+/// If the context has a step limit, normalization gives up with
+/// `InternalError::NormalizationLimitExceeded` once that many reduction steps
+/// have been taken.
 pub fn normalize(context: &Context, term: &RcTerm) -> Result<RcValue, InternalError> {
+    normalize_steps(context, term, &mut Steps::new(context))
+}
+
+/// Normalize a term, counting the reduction steps that are taken
+fn normalize_steps(
+    context: &Context,
+    term: &RcTerm,
+    steps: &mut Steps,
+) -> Result<RcValue, InternalError> {
     match *term.inner {
         //  1.  Γ ⊢ e ⇓ v
         // ─────────────────────── (EVAL/ANN)
         //      Γ ⊢ e:ρ ⇓ v
         Term::Ann(_, ref expr, _) => {
-            normalize(context, expr) // 1.
+            normalize_steps(context, expr, steps) // 1.
         },
 
         // ─────────────────── (EVAL/TYPE)
         //  Γ ⊢ Type ⇓ Type
-        Term::Universe(_, level) => Ok(Value::Universe(level).into()),
+        Term::Universe(_, ref level) => Ok(Value::Universe(level.clone()).into()),
 
         Term::Var(_, ref var) => match *var {
             Var::Free(ref name) => match context.lookup_binder(name) {
@@ -184,18 +612,57 @@ pub fn normalize(context: &Context, term: &RcTerm) -> Result<RcValue, InternalEr
                 //  2.  Πx:τ ∈ Γ
                 // ───────────────────── (EVAL/VAR-PI)
                 //      Γ ⊢ x ⇓ x
-                Some(&Binder::Lam(_)) | Some(&Binder::Pi(_)) => Ok(Value::Var(var.clone()).into()),
+                //
+                // Opaque definitions hide their values, so we treat them as
+                // if they were neutral variables:
+                //
+                //  3.  opaque x:τ = v ∈ Γ
+                // ───────────────────── (EVAL/VAR-OPAQUE)
+                //      Γ ⊢ x ⇓ x
+                //
+                // Claims have no value yet, so they are also neutral:
+                //
+                //  4.  x:τ ∈ Γ
+                // ───────────────────── (EVAL/VAR-CLAIM)
+                //      Γ ⊢ x ⇓ x
+                Some(&Binder::Lam(_))
+                | Some(&Binder::Pi(_))
+                | Some(&Binder::Let(_, _, Transparency::Opaque))
+                | Some(&Binder::Claim(_)) => {
+                    Ok(Value::Var(var.clone()).into())
+                },
+                // Definitions that are excluded by the unfold policy are also
+                // left folded, so that they can be displayed symbolically:
+                //
+                //  4.  let x:τ = v ∈ Γ    x is not unfolded in Γ
+                // ───────────────────── (EVAL/VAR-FOLDED)
+                //      Γ ⊢ x ⇓ x
+                Some(&Binder::Let(_, _, Transparency::Transparent)) if !context.unfolds(name) => {
+                    Ok(Value::Var(var.clone()).into())
+                },
                 // We have a value in scope, let's use that!
                 //
                 //  1.  let x:τ = v ∈ Γ
                 // ───────────────────── (EVAL/VAR-LET)
                 //      Γ ⊢ x ⇓ v
-                Some(&Binder::Let(_, ref value)) => Ok(value.clone()),
+                Some(&Binder::Let(ref value, _, Transparency::Transparent)) => Ok(value.clone()),
 
-                None => Err(InternalError::UndefinedName {
-                    var_span: term.span(),
-                    name: name.clone(),
-                }),
+                None => match context.lookup_prim(name) {
+                    // Primitives are neutral until they are fully applied,
+                    // unless they take no arguments:
+                    //
+                    //  1.  prim x:τ ∈ Γ
+                    // ───────────────────── (EVAL/VAR-PRIM)
+                    //      Γ ⊢ x ⇓ x
+                    Some(_) => {
+                        let value = Value::Var(var.clone()).into();
+                        Ok(reduce_prim(context, &value).unwrap_or(value))
+                    },
+                    None => Err(InternalError::UndefinedName {
+                        var_span: term.span(),
+                        name: name.clone(),
+                    }),
+                },
             },
 
             // We should always be substituting bound variables with fresh
@@ -217,126 +684,1871 @@ pub fn normalize(context: &Context, term: &RcTerm) -> Result<RcValue, InternalEr
         // ──────────────────────────────── (EVAL/LAM-ANN)
         //      Γ ⊢ λx:ρ.e ⇓ λx:τ.v
         Term::Lam(_, ref lam) => {
+            let lam_plicity = lam.plicity;
+            let (param, body) = lam.clone().unbind();
+
+            let ann = match param.inner {
+                None => None,
+                Some(ann) => Some(normalize_steps(context, &ann, steps)?), // 2.
+            };
+            let body_context = context.extend(param.name.clone(), Binder::Lam(ann.clone()));
+            let body = normalize_steps(&body_context, &body, steps)?; // 1,3.
+
+            let lam = ValueLam::bind(Named::new(param.name.clone(), ann), body);
+            Ok(Value::Lam(lam.with_plicity(lam_plicity)).into())
+        },
+
+        //  1.  Γ ⊢ ρ₁ ⇓ τ₁
+        //  2.  Γ,Πx:τ ⊢ ρ₂ ⇓ τ₂
+        // ─────────────────────────────────── (EVAL/PI-ANN)
+        //      Γ ⊢ Πx:ρ₁.ρ₂ ⇓ Πx:τ₁.τ₂
+        Term::Pi(_, ref pi) => {
+            let pi_plicity = pi.plicity;
+            let (param, body) = pi.clone().unbind();
+
+            let ann = normalize_steps(context, &param.inner, steps)?; // 1.
+            let body_context = context.extend(param.name.clone(), Binder::Pi(ann.clone()));
+            let body = normalize_steps(&body_context, &body, steps)?; // 2.
+
+            let pi = ValuePi::bind(Named::new(param.name.clone(), ann), body);
+            Ok(Value::Pi(pi.with_plicity(pi_plicity)).into())
+        },
+
+        // Perform [β-reduction](https://en.wikipedia.org/wiki/Lambda_calculus#β-reduction),
+        // ie. apply functions to their arguments
+        //
+        //  1.  Γ ⊢ e₁ ⇓ λx.v₁
+        //  2.  Γ ⊢ e₂ ⇓ v₂
+        //  3.  Γ ⊢ v₁[x↦v₂] ↝ v₃
+        // ───────────────────────────── (EVAL/APP)
+        //      Γ ⊢ e₁ e₂ ⇓ v₃
+        //
+        // The arguments of the application spine are processed in a loop to
+        // avoid overflowing the stack on long spines.
+        Term::App(_, _, _) => {
+            let (head, args) = app_spine(term);
+            let mut fn_expr = normalize_steps(context, head, steps)?; // 1.
+
+            for arg in args {
+                let arg = normalize_steps(context, arg, steps)?; // 2.
+                fn_expr = apply_steps(context, term.span(), &fn_expr, &arg, steps)?; // 3.
+            }
+
+            Ok(fn_expr)
+        },
+
+        // Let bindings are substituted into their bodies, so that they never
+        // appear in normal forms
+        //
+        //  1.  Γ ⊢ e₂[x↦e₁] ⇓ v
+        // ───────────────────────────────── (EVAL/LET)
+        //      Γ ⊢ let x = e₁ in e₂ ⇓ v
+        Term::Let(_, ref let_) => {
+            steps.take(term.span())?;
+            normalize_steps(context, &let_.instantiate(&let_.value), steps) // 1.
+        },
+
+        // The first component of a pair is bound in the type of the second
+        // component in the same way as the parameter of a pi type
+        //
+        //  1.  Γ ⊢ ρ₁ ⇓ τ₁
+        //  2.  Γ,Πx:τ₁ ⊢ ρ₂ ⇓ τ₂
+        // ─────────────────────────────────── (EVAL/SIGMA)
+        //      Γ ⊢ Σx:ρ₁.ρ₂ ⇓ Σx:τ₁.τ₂
+        Term::Sigma(_, ref sigma) => {
+            let (param, body) = sigma.clone().unbind();
+
+            let ann = normalize_steps(context, &param.inner, steps)?; // 1.
+            let body_context = context.extend(param.name.clone(), Binder::Pi(ann.clone()));
+            let body = normalize_steps(&body_context, &body, steps)?; // 2.
+
+            Ok(Value::Sigma(ValueSigma::bind(Named::new(param.name.clone(), ann), body)).into())
+        },
+
+        //  1.  Γ ⊢ e₁ ⇓ v₁
+        //  2.  Γ ⊢ e₂ ⇓ v₂
+        // ─────────────────────────────────── (EVAL/PAIR)
+        //      Γ ⊢ (e₁, e₂) ⇓ (v₁, v₂)
+        Term::Pair(_, ref fst, ref snd) => {
+            let fst = normalize_steps(context, fst, steps)?; // 1.
+            let snd = normalize_steps(context, snd, steps)?; // 2.
+
+            Ok(Value::Pair(fst, snd).into())
+        },
+
+        //  1.  Γ ⊢ e ⇓ (v₁, v₂)
+        // ─────────────────────────────────── (EVAL/FST)
+        //      Γ ⊢ fst e ⇓ v₁
+        Term::Fst(_, ref pair) => {
+            let pair = normalize_steps(context, pair, steps)?; // 1.
+            Ok(project_fst(&pair))
+        },
+
+        //  1.  Γ ⊢ e ⇓ (v₁, v₂)
+        // ─────────────────────────────────── (EVAL/SND)
+        //      Γ ⊢ snd e ⇓ v₂
+        Term::Snd(_, ref pair) => {
+            let pair = normalize_steps(context, pair, steps)?; // 1.
+            Ok(project_snd(&pair))
+        },
+
+        // Each field is bound in the types of the remaining fields in the
+        // same way as the parameter of a pi type
+        //
+        //  1.  Γ ⊢ ρ₁ ⇓ τ₁
+        //  2.  Γ,Πl:τ₁ ⊢ ρ₂ ⇓ τ₂
+        // ─────────────────────────────────────────────── (EVAL/RECORD-TYPE)
+        //      Γ ⊢ Record { l:ρ₁; ρ₂ } ⇓ Record { l:τ₁; τ₂ }
+        Term::RecordType(_, ref record_ty) => {
+            let (label, param, body) = record_ty.clone().unbind();
+
+            let ann = normalize_steps(context, &param.inner, steps)?; // 1.
+            let body_context = context.extend(param.name.clone(), Binder::Pi(ann.clone()));
+            let body = normalize_steps(&body_context, &body, steps)?; // 2.
+
+            let param = Named::new(param.name.clone(), ann);
+            Ok(Value::RecordType(ValueRecordType::bind(label, param, body)).into())
+        },
+
+        // ─────────────────────────────────── (EVAL/RECORD-TYPE-EMPTY)
+        //  Γ ⊢ Record {} ⇓ Record {}
+        Term::RecordTypeEmpty(_) => Ok(Value::RecordTypeEmpty.into()),
+
+        //  1.  Γ ⊢ e₁ ⇓ v₁
+        //  2.  Γ ⊢ e₂ ⇓ v₂
+        // ─────────────────────────────────────────────── (EVAL/RECORD)
+        //      Γ ⊢ record { l = e₁; e₂ } ⇓ record { l = v₁; v₂ }
+        Term::Record(_, ref label, ref value, ref rest) => {
+            let value = normalize_steps(context, value, steps)?; // 1.
+            let rest = normalize_steps(context, rest, steps)?; // 2.
+
+            Ok(Value::Record(label.clone(), value, rest).into())
+        },
+
+        // ─────────────────────────────────── (EVAL/RECORD-EMPTY)
+        //  Γ ⊢ record {} ⇓ record {}
+        Term::RecordEmpty(_) => Ok(Value::RecordEmpty.into()),
+
+        //  1.  Γ ⊢ e ⇓ record { ...; l = v; ... }
+        // ─────────────────────────────────── (EVAL/PROJ)
+        //      Γ ⊢ e.l ⇓ v
+        Term::Proj(_, ref record, ref label) => {
+            let record = normalize_steps(context, record, steps)?; // 1.
+            Ok(project(&record, label))
+        },
+
+        // ─────────────────── (EVAL/CONST)
+        //  Γ ⊢ c ⇓ c
+        Term::Constant(_, ref constant) => Ok(Value::Constant(constant.clone()).into()),
+
+        // Holes have no value, so they are left stuck as fresh variables
+        //
+        // ─────────────────── (EVAL/HOLE)
+        //  Γ ⊢ ? ⇓ ?
+        Term::Hole(_) => Ok(hole_value()),
+
+        //  1.  Γ ⊢ e₁ ⇓ true
+        //  2.  Γ ⊢ e₂ ⇓ v₂
+        // ─────────────────────────────────────── (EVAL/IF-TRUE)
+        //      Γ ⊢ if e₁ then e₂ else e₃ ⇓ v₂
+        //
+        //  1.  Γ ⊢ e₁ ⇓ false
+        //  3.  Γ ⊢ e₃ ⇓ v₃
+        // ─────────────────────────────────────── (EVAL/IF-FALSE)
+        //      Γ ⊢ if e₁ then e₂ else e₃ ⇓ v₃
+        //
+        // Only the selected branch is evaluated, so that recursive definitions
+        // can stop at a base case. Both branches are evaluated if the
+        // condition is neutral.
+        Term::If(_, ref cond, ref if_true, ref if_false) => {
+            let cond = normalize_steps(context, cond, steps)?; // 1.
+            match *cond.inner {
+                Value::Constant(Constant::Bool(true)) => {
+                    normalize_steps(context, if_true, steps) // 2.
+                },
+                Value::Constant(Constant::Bool(false)) => {
+                    normalize_steps(context, if_false, steps) // 3.
+                },
+                _ => {
+                    let if_true = normalize_steps(context, if_true, steps)?;
+                    let if_false = normalize_steps(context, if_false, steps)?;
+
+                    Ok(Value::If(cond, if_true, if_false).into())
+                },
+            }
+        },
+
+        // The first clause whose pattern matches the scrutinee is selected,
+        // and the values bound by the pattern are substituted into its body
+        //
+        //  1.  Γ ⊢ e ⇓ v
+        //  2.  pᵢ is the first pattern that matches v, binding x₁ ... xₙ to v₁ ... vₙ
+        //  3.  Γ ⊢ eᵢ[x₁↦v₁, ..., xₙ↦vₙ] ⇓ v'
+        // ─────────────────────────────────────────────── (EVAL/CASE)
+        //      Γ ⊢ case e of { pᵢ => eᵢ; ... } ⇓ v'
+        Term::Case(_, ref scrutinee, ref clauses) => {
+            let scrutinee = normalize_steps(context, scrutinee, steps)?; // 1.
+            let patterns = clauses.iter().map(|clause| &clause.unsafe_pattern).collect::<Vec<_>>();
+
+            let mut eval = |index: usize, bindings: Vec<RcValue>| -> Result<_, InternalError> {
+                steps.take(term.span())?;
+                let bindings = bindings.iter().map(RcTerm::from).collect::<Vec<_>>();
+                normalize_steps(context, &clauses[index].instantiate(&bindings), steps) // 3.
+            };
+            select_clause(term.span(), &scrutinee, &patterns, 0, &mut Vec::new(), &mut eval) // 2.
+        },
+    }
+}
+
+/// Reduce a term to weak head normal form
+///
+/// Unlike `normalize`, this only reduces a term until its head is a binder, a
+/// constructor, or a neutral term. The bodies of binders and the arguments of
+/// neutral terms are left unevaluated, so the value that is returned may
+/// still contain redexes below its head.
+///
+/// ```text
+/// Γ ⊢ e ⇓ʷ v
+/// ```
+pub fn whnf(context: &Context, term: &RcTerm) -> Result<RcValue, InternalError> {
+    let mut steps = Steps::new(context);
+    let term = whnf_steps(context, term, &mut steps)?;
+    suspend_steps(context, &term, &mut steps)
+}
+
+/// Convert a term to a value without reducing it
+///
+/// Let bindings, case expressions and holes have no corresponding values, so
+/// these are normalized instead. Annotations are dropped, as in `normalize`.
+fn suspend_steps(
+    context: &Context,
+    term: &RcTerm,
+    steps: &mut Steps,
+) -> Result<RcValue, InternalError> {
+    match *term.inner {
+        Term::Ann(_, ref expr, _) => suspend_steps(context, expr, steps),
+        Term::Universe(_, ref level) => Ok(Value::Universe(level.clone()).into()),
+        Term::Var(_, ref var) => Ok(Value::Var(var.clone()).into()),
+        Term::Lam(_, ref lam) => {
+            let lam_plicity = lam.plicity;
             let (param, body) = lam.clone().unbind();
 
-            let ann = match param.inner {
-                None => None,
-                Some(ann) => Some(normalize(context, &ann)?), // 2.
-            };
-            let body_context = context.extend(param.name.clone(), Binder::Lam(ann.clone()));
-            let body = normalize(&body_context, &body)?; // 1,3.
+            let ann = match param.inner {
+                None => None,
+                Some(ann) => Some(suspend_steps(context, &ann, steps)?),
+            };
+            let body_context = context.extend(param.name.clone(), Binder::Lam(ann.clone()));
+            let body = suspend_steps(&body_context, &body, steps)?;
+
+            let lam = ValueLam::bind(Named::new(param.name.clone(), ann), body);
+            Ok(Value::Lam(lam.with_plicity(lam_plicity)).into())
+        },
+        Term::Pi(_, ref pi) => {
+            let pi_plicity = pi.plicity;
+            let (param, body) = pi.clone().unbind();
+
+            let ann = suspend_steps(context, &param.inner, steps)?;
+            let body_context = context.extend(param.name.clone(), Binder::Pi(ann.clone()));
+            let body = suspend_steps(&body_context, &body, steps)?;
+
+            let pi = ValuePi::bind(Named::new(param.name.clone(), ann), body);
+            Ok(Value::Pi(pi.with_plicity(pi_plicity)).into())
+        },
+        Term::App(_, _, _) => {
+            let (head, args) = app_spine(term);
+            let mut fn_expr = suspend_steps(context, head, steps)?;
+
+            for arg in args {
+                fn_expr = Value::App(fn_expr, suspend_steps(context, arg, steps)?).into();
+            }
+
+            Ok(fn_expr)
+        },
+        Term::Sigma(_, ref sigma) => {
+            let (param, body) = sigma.clone().unbind();
+
+            let ann = suspend_steps(context, &param.inner, steps)?;
+            let body_context = context.extend(param.name.clone(), Binder::Pi(ann.clone()));
+            let body = suspend_steps(&body_context, &body, steps)?;
+
+            Ok(Value::Sigma(ValueSigma::bind(Named::new(param.name.clone(), ann), body)).into())
+        },
+        Term::Pair(_, ref fst, ref snd) => {
+            let fst = suspend_steps(context, fst, steps)?;
+            let snd = suspend_steps(context, snd, steps)?;
+
+            Ok(Value::Pair(fst, snd).into())
+        },
+        Term::Fst(_, ref pair) => Ok(Value::Fst(suspend_steps(context, pair, steps)?).into()),
+        Term::Snd(_, ref pair) => Ok(Value::Snd(suspend_steps(context, pair, steps)?).into()),
+        Term::RecordType(_, ref record_ty) => {
+            let (label, param, body) = record_ty.clone().unbind();
+
+            let ann = suspend_steps(context, &param.inner, steps)?;
+            let body_context = context.extend(param.name.clone(), Binder::Pi(ann.clone()));
+            let body = suspend_steps(&body_context, &body, steps)?;
+
+            let param = Named::new(param.name.clone(), ann);
+            Ok(Value::RecordType(ValueRecordType::bind(label, param, body)).into())
+        },
+        Term::RecordTypeEmpty(_) => Ok(Value::RecordTypeEmpty.into()),
+        Term::Record(_, ref label, ref value, ref rest) => {
+            let value = suspend_steps(context, value, steps)?;
+            let rest = suspend_steps(context, rest, steps)?;
+
+            Ok(Value::Record(label.clone(), value, rest).into())
+        },
+        Term::RecordEmpty(_) => Ok(Value::RecordEmpty.into()),
+        Term::Proj(_, ref record, ref label) => {
+            let record = suspend_steps(context, record, steps)?;
+            Ok(Value::Proj(record, label.clone()).into())
+        },
+        Term::Constant(_, ref constant) => Ok(Value::Constant(constant.clone()).into()),
+        Term::If(_, ref cond, ref if_true, ref if_false) => {
+            let cond = suspend_steps(context, cond, steps)?;
+            let if_true = suspend_steps(context, if_true, steps)?;
+            let if_false = suspend_steps(context, if_false, steps)?;
+
+            Ok(Value::If(cond, if_true, if_false).into())
+        },
+        Term::Let(_, _) | Term::Case(_, _, _) | Term::Hole(_) => {
+            normalize_steps(context, term, steps)
+        },
+    }
+}
+
+/// Reduce a term to weak head normal form, counting the reduction steps
+/// that are taken
+fn whnf_steps(
+    context: &Context,
+    term: &RcTerm,
+    steps: &mut Steps,
+) -> Result<RcTerm, InternalError> {
+    match *term.inner {
+        //  1.  Γ ⊢ e ⇓ʷ e'
+        // ─────────────────────── (WHNF/ANN)
+        //      Γ ⊢ e:ρ ⇓ʷ e'
+        Term::Ann(_, ref expr, _) => whnf_steps(context, expr, steps), // 1.
+
+        Term::Var(_, Var::Free(ref name)) => match context.lookup_binder(name) {
+            //  1.  let x:τ = v ∈ Γ
+            // ───────────────────── (WHNF/VAR-LET)
+            //      Γ ⊢ x ⇓ʷ v
+            Some(&Binder::Let(ref value, _, Transparency::Transparent))
+                if context.unfolds(name) =>
+            {
+                Ok(RcTerm::from(value))
+            },
+            // Every other binder leaves the variable neutral
+            Some(_) => Ok(term.clone()),
+            // Primitives that take no arguments reduce immediately, and
+            // undefined names are reported by `normalize`
+            None => Ok(RcTerm::from(&normalize_steps(context, term, steps)?)),
+        },
+        Term::Var(_, Var::Bound(_)) => {
+            Ok(RcTerm::from(&normalize_steps(context, term, steps)?))
+        },
+
+        //  1.  Γ ⊢ e₁ ⇓ʷ λx.e
+        //  2.  Γ ⊢ e[x↦e₂] ⇓ʷ e'
+        // ───────────────────────────── (WHNF/APP)
+        //      Γ ⊢ e₁ e₂ ⇓ʷ e'
+        Term::App(_, _, _) => {
+            let (head, args) = app_spine(term);
+            let mut fn_expr = whnf_steps(context, head, steps)?; // 1.
+
+            for (index, arg) in args.iter().enumerate() {
+                // Implicit lambdas are left stuck, as in `normalize`
+                let body = match *fn_expr.inner {
+                    Term::Lam(_, ref lam) if lam.plicity == Plicity::Explicit => {
+                        steps.take(term.span())?;
+                        lam.instantiate(arg)
+                    },
+                    _ => return whnf_stuck_app(context, &fn_expr, &args[index..], steps),
+                };
+                fn_expr = whnf_steps(context, &body, steps)?; // 2.
+            }
+
+            Ok(fn_expr)
+        },
+
+        //  1.  Γ ⊢ e₂[x↦e₁] ⇓ʷ e'
+        // ───────────────────────────────── (WHNF/LET)
+        //      Γ ⊢ let x = e₁ in e₂ ⇓ʷ e'
+        Term::Let(_, ref let_) => {
+            steps.take(term.span())?;
+            whnf_steps(context, &let_.instantiate(&let_.value), steps) // 1.
+        },
+
+        //  1.  Γ ⊢ e ⇓ʷ (e₁, e₂)
+        //  2.  Γ ⊢ e₁ ⇓ʷ e'
+        // ─────────────────────────── (WHNF/FST)
+        //      Γ ⊢ fst e ⇓ʷ e'
+        Term::Fst(_, ref pair) => {
+            let pair = whnf_steps(context, pair, steps)?; // 1.
+            match *pair.inner {
+                Term::Pair(_, ref fst, _) => whnf_steps(context, fst, steps), // 2.
+                _ => Ok(Term::Fst(SourceMeta::default(), pair.clone()).into()),
+            }
+        },
+
+        //  1.  Γ ⊢ e ⇓ʷ (e₁, e₂)
+        //  2.  Γ ⊢ e₂ ⇓ʷ e'
+        // ─────────────────────────── (WHNF/SND)
+        //      Γ ⊢ snd e ⇓ʷ e'
+        Term::Snd(_, ref pair) => {
+            let pair = whnf_steps(context, pair, steps)?; // 1.
+            match *pair.inner {
+                Term::Pair(_, _, ref snd) => whnf_steps(context, snd, steps), // 2.
+                _ => Ok(Term::Snd(SourceMeta::default(), pair.clone()).into()),
+            }
+        },
+
+        //  1.  Γ ⊢ e ⇓ʷ record { ...; l = e₁; ... }
+        //  2.  Γ ⊢ e₁ ⇓ʷ e'
+        // ─────────────────────────────────────────── (WHNF/PROJ)
+        //      Γ ⊢ e.l ⇓ʷ e'
+        Term::Proj(_, ref record, ref label) => {
+            let record = whnf_steps(context, record, steps)?; // 1.
+
+            let mut current = record.clone();
+            loop {
+                let next = match *current.inner {
+                    Term::Record(_, ref current_label, ref value, ref rest) => {
+                        if current_label == label {
+                            return whnf_steps(context, value, steps); // 2.
+                        }
+                        whnf_steps(context, rest, steps)?
+                    },
+                    _ => break,
+                };
+                current = next;
+            }
+
+            Ok(Term::Proj(SourceMeta::default(), record, label.clone()).into())
+        },
+
+        //  1.  Γ ⊢ e₁ ⇓ʷ true
+        //  2.  Γ ⊢ e₂ ⇓ʷ e'
+        // ─────────────────────────────────────── (WHNF/IF-TRUE)
+        //      Γ ⊢ if e₁ then e₂ else e₃ ⇓ʷ e'
+        //
+        //  1.  Γ ⊢ e₁ ⇓ʷ false
+        //  3.  Γ ⊢ e₃ ⇓ʷ e'
+        // ─────────────────────────────────────── (WHNF/IF-FALSE)
+        //      Γ ⊢ if e₁ then e₂ else e₃ ⇓ʷ e'
+        Term::If(_, ref cond, ref if_true, ref if_false) => {
+            let cond = whnf_steps(context, cond, steps)?; // 1.
+            match *cond.inner {
+                Term::Constant(_, Constant::Bool(true)) => {
+                    whnf_steps(context, if_true, steps) // 2.
+                },
+                Term::Constant(_, Constant::Bool(false)) => {
+                    whnf_steps(context, if_false, steps) // 3.
+                },
+                _ => {
+                    let meta = SourceMeta::default();
+                    Ok(Term::If(meta, cond.clone(), if_true.clone(), if_false.clone()).into())
+                },
+            }
+        },
+
+        // Clauses are selected by matching their patterns against the whole
+        // scrutinee, so we fall back to `normalize`
+        Term::Case(_, _, _) => Ok(RcTerm::from(&normalize_steps(context, term, steps)?)),
+
+        // Binders and constructors are already in weak head normal form
+        Term::Universe(_, _)
+        | Term::Lam(_, _)
+        | Term::Pi(_, _)
+        | Term::Sigma(_, _)
+        | Term::Pair(_, _, _)
+        | Term::RecordType(_, _)
+        | Term::RecordTypeEmpty(_)
+        | Term::Record(_, _, _, _)
+        | Term::RecordEmpty(_)
+        | Term::Constant(_, _)
+        | Term::Hole(_) => Ok(term.clone()),
+    }
+}
+
+/// Rebuild an application whose head can't be reduced any further. Saturated
+/// primitives are still reduced, by falling back to `normalize`.
+fn whnf_stuck_app(
+    context: &Context,
+    head: &RcTerm,
+    args: &[&RcTerm],
+    steps: &mut Steps,
+) -> Result<RcTerm, InternalError> {
+    let app = args.iter().fold(head.clone(), |fn_expr, &arg| {
+        Term::App(SourceMeta::default(), fn_expr, arg.clone()).into()
+    });
+
+    match *head.inner {
+        Term::Var(_, Var::Free(ref name)) if context.lookup_prim(name).is_some() => {
+            Ok(RcTerm::from(&normalize_steps(context, &app, steps)?))
+        },
+        _ => Ok(app),
+    }
+}
+
+/// Reduce the head of a value to weak head normal form
+///
+/// The values returned by `whnf` may contain redexes below their heads, so
+/// these are reduced as they are reached by the conversion check.
+fn whnf_value_steps(
+    context: &Context,
+    value: &RcValue,
+    steps: &mut Steps,
+) -> Result<RcValue, InternalError> {
+    match *value.inner {
+        Value::Var(Var::Free(ref name)) => match context.lookup_binder(name) {
+            Some(&Binder::Let(ref unfolded, _, Transparency::Transparent))
+                if context.unfolds(name) =>
+            {
+                Ok(unfolded.clone())
+            },
+            _ => Ok(value.clone()),
+        },
+        Value::App(ref fn_expr, ref arg) => {
+            let fn_expr = whnf_value_steps(context, fn_expr, steps)?;
+            match *fn_expr.inner {
+                Value::Lam(ref lam) if lam.plicity == Plicity::Explicit => {
+                    steps.take(ByteSpan::none())?;
+                    whnf_value_steps(context, &lam.instantiate(arg), steps)
+                },
+                _ => {
+                    let value = Value::App(fn_expr.clone(), arg.clone()).into();
+                    Ok(reduce_prim(context, &value).unwrap_or(value))
+                },
+            }
+        },
+        Value::Fst(ref pair) => {
+            let pair = whnf_value_steps(context, pair, steps)?;
+            match *pair.inner {
+                Value::Pair(ref fst, _) => whnf_value_steps(context, fst, steps),
+                _ => Ok(Value::Fst(pair.clone()).into()),
+            }
+        },
+        Value::Snd(ref pair) => {
+            let pair = whnf_value_steps(context, pair, steps)?;
+            match *pair.inner {
+                Value::Pair(_, ref snd) => whnf_value_steps(context, snd, steps),
+                _ => Ok(Value::Snd(pair.clone()).into()),
+            }
+        },
+        Value::Proj(ref record, ref label) => {
+            let record = whnf_value_steps(context, record, steps)?;
+
+            let mut current = record.clone();
+            loop {
+                let next = match *current.inner {
+                    Value::Record(ref current_label, ref value, ref rest) => {
+                        if current_label == label {
+                            return whnf_value_steps(context, value, steps);
+                        }
+                        whnf_value_steps(context, rest, steps)?
+                    },
+                    _ => break,
+                };
+                current = next;
+            }
+
+            Ok(Value::Proj(record, label.clone()).into())
+        },
+        Value::If(ref cond, ref if_true, ref if_false) => {
+            let cond = whnf_value_steps(context, cond, steps)?;
+            match *cond.inner {
+                Value::Constant(Constant::Bool(true)) => whnf_value_steps(context, if_true, steps),
+                Value::Constant(Constant::Bool(false)) => {
+                    whnf_value_steps(context, if_false, steps)
+                },
+                _ => Ok(Value::If(cond.clone(), if_true.clone(), if_false.clone()).into()),
+            }
+        },
+        _ => Ok(value.clone()),
+    }
+}
+
+/// Counts the beta reductions and let substitutions taken while reducing a
+/// term, so that we can give up on terms that might never finish normalizing
+struct Steps {
+    taken: u64,
+    limit: Option<u64>,
+}
+
+impl Steps {
+    fn new(context: &Context) -> Steps {
+        Steps {
+            taken: 0,
+            limit: context.step_limit,
+        }
+    }
+
+    /// Record a reduction step, returning an error if it would exceed the
+    /// step limit
+    fn take(&mut self, span: ByteSpan) -> Result<(), InternalError> {
+        match self.limit {
+            Some(limit) if self.taken >= limit => {
+                Err(InternalError::NormalizationLimitExceeded { span, steps: limit })
+            },
+            Some(_) | None => {
+                self.taken += 1;
+                Ok(())
+            },
+        }
+    }
+}
+
+/// Select the branch of a conditional, leaving the conditional stuck if the
+/// condition is neutral
+fn select_branch(cond: &RcValue, if_true: &RcValue, if_false: &RcValue) -> RcValue {
+    match *cond.inner {
+        Value::Constant(Constant::Bool(true)) => if_true.clone(),
+        Value::Constant(Constant::Bool(false)) => if_false.clone(),
+        _ => Value::If(cond.clone(), if_true.clone(), if_false.clone()).into(),
+    }
+}
+
+/// The result of matching a value against a pattern
+enum Match {
+    /// The value matches the pattern
+    Yes,
+    /// The value does not match the pattern
+    No,
+    /// A boolean literal in the pattern was matched against the given
+    /// neutral value
+    Stuck(RcValue),
+}
+
+/// Match a value against a pattern, pushing the values that the pattern
+/// binds onto `bindings`, from left to right
+///
+/// Neutral values that were assumed to be `true` or `false` while selecting
+/// a clause are looked up in `assumptions`.
+fn match_pattern(
+    pattern: &Pattern,
+    value: &RcValue,
+    assumptions: &[(RcValue, bool)],
+    bindings: &mut Vec<RcValue>,
+) -> Match {
+    match *pattern {
+        Pattern::Wildcard(_) => Match::Yes,
+        Pattern::Binder(_, _) => {
+            bindings.push(value.clone());
+            Match::Yes
+        },
+        Pattern::Constant(_, ref constant) => match *value.inner {
+            Value::Constant(ref found) if found == constant => Match::Yes,
+            Value::Constant(_) => Match::No,
+            _ => match assumptions.iter().find(|&&(ref assumed, _)| assumed == value) {
+                Some(&(_, found)) if Constant::Bool(found) == *constant => Match::Yes,
+                Some(_) => Match::No,
+                None => Match::Stuck(value.clone()),
+            },
+        },
+        // Neutral pairs are matched by projecting out their components, so
+        // pair patterns never get stuck by themselves
+        Pattern::Pair(_, ref fst, ref snd) => {
+            match match_pattern(fst, &project_fst(value), assumptions, bindings) {
+                Match::Yes => match_pattern(snd, &project_snd(value), assumptions, bindings),
+                result => result,
+            }
+        },
+    }
+}
+
+/// Select the first clause, starting at `start`, whose pattern matches the
+/// scrutinee, and evaluate it with the values that its pattern binds
+///
+/// If a boolean literal is matched against a neutral part of the scrutinee,
+/// the remaining clauses are selected once assuming that it is `true`, and
+/// once assuming that it is `false`, leaving a stuck conditional behind.
+fn select_clause<F>(
+    span: ByteSpan,
+    scrutinee: &RcValue,
+    patterns: &[&Pattern],
+    start: usize,
+    assumptions: &mut Vec<(RcValue, bool)>,
+    eval: &mut F,
+) -> Result<RcValue, InternalError>
+where
+    F: FnMut(usize, Vec<RcValue>) -> Result<RcValue, InternalError>,
+{
+    for (index, pattern) in patterns.iter().enumerate().skip(start) {
+        let mut bindings = Vec::new();
+        match match_pattern(pattern, scrutinee, assumptions, &mut bindings) {
+            Match::Yes => return eval(index, bindings),
+            Match::No => {},
+            Match::Stuck(cond) => {
+                assumptions.push((cond.clone(), true));
+                let if_true = select_clause(span, scrutinee, patterns, index, assumptions, eval);
+                assumptions.pop();
+
+                assumptions.push((cond.clone(), false));
+                let if_false = select_clause(span, scrutinee, patterns, index, assumptions, eval);
+                assumptions.pop();
+
+                return Ok(Value::If(cond, if_true?, if_false?).into());
+            },
+        }
+    }
+
+    Err(InternalError::NoMatchingClause { span })
+}
+
+/// Apply an elaborated function to an implicit argument, reducing the
+/// application if the function is a lambda
+fn apply(context: &Context, fn_expr: &RcValue, arg: &RcValue) -> Result<RcValue, InternalError> {
+    match *fn_expr.inner {
+        Value::Lam(ref lam) => reduce(context, &lam.instantiate(arg)),
+        _ => Ok(Value::App(fn_expr.clone(), arg.clone()).into()),
+    }
+}
+
+/// Apply a value to an argument, reducing the application if the function is
+/// an explicit lambda
+///
+/// Applications are always explicit at the moment, so an implicit lambda is
+/// left stuck rather than being given an argument meant for one of its
+/// explicit parameters. The implicit arguments are filled in during
+/// elaboration.
+fn apply_steps(
+    context: &Context,
+    span: ByteSpan,
+    fn_expr: &RcValue,
+    arg: &RcValue,
+    steps: &mut Steps,
+) -> Result<RcValue, InternalError> {
+    match *fn_expr.inner {
+        Value::Lam(ref lam) if lam.plicity == Plicity::Explicit => {
+            steps.take(span)?;
+            reduce_steps(context, span, &lam.instantiate(arg), steps)
+        },
+        _ => {
+            let value = Value::App(fn_expr.clone(), arg.clone()).into();
+            Ok(reduce_prim(context, &value).unwrap_or(value))
+        },
+    }
+}
+
+/// Reduce the redexes in a value that were exposed by substituting into it
+///
+/// Substituting a value for a variable can turn a neutral term into one that
+/// can be reduced further, eg. substituting `true` for `b` in the stuck
+/// conditional `if b then Type else Type 1`.
+///
+/// ```text
+/// Γ ⊢ v ↝ v'
+/// ```
+fn reduce(context: &Context, value: &RcValue) -> Result<RcValue, InternalError> {
+    reduce_steps(context, ByteSpan::none(), value, &mut Steps::new(context))
+}
+
+/// Reduce the redexes in a value, counting the reduction steps that are taken
+fn reduce_steps(
+    context: &Context,
+    span: ByteSpan,
+    value: &RcValue,
+    steps: &mut Steps,
+) -> Result<RcValue, InternalError> {
+    match *value.inner {
+        Value::Universe(_)
+        | Value::Constant(_)
+        | Value::Var(Var::Bound(_))
+        | Value::RecordTypeEmpty
+        | Value::RecordEmpty => Ok(value.clone()),
+
+        // Recursive definitions refer to themselves in their own values, so
+        // these are unfolded in the same way as in EVAL/VAR-LET
+        Value::Var(Var::Free(ref name)) => match context.lookup_binder(name) {
+            Some(&Binder::Let(ref unfolded, _, Transparency::Transparent))
+                if context.unfolds(name) =>
+            {
+                Ok(unfolded.clone())
+            },
+            _ => Ok(value.clone()),
+        },
+
+        Value::Lam(ref lam) => {
+            let plicity = lam.plicity;
+            let (param, body) = lam.clone().unbind();
+
+            let ann = match param.inner {
+                None => None,
+                Some(ref ann) => Some(reduce_steps(context, span, ann, steps)?),
+            };
+            let body = reduce_steps(context, span, &body, steps)?;
+
+            let lam = ValueLam::bind(Named::new(param.name, ann), body);
+            Ok(Value::Lam(lam.with_plicity(plicity)).into())
+        },
+        Value::Pi(ref pi) => {
+            let plicity = pi.plicity;
+            let (param, body) = pi.clone().unbind();
+
+            let ann = reduce_steps(context, span, &param.inner, steps)?;
+            let body = reduce_steps(context, span, &body, steps)?;
+
+            let pi = ValuePi::bind(Named::new(param.name, ann), body);
+            Ok(Value::Pi(pi.with_plicity(plicity)).into())
+        },
+        Value::App(ref fn_expr, ref arg) => {
+            let fn_expr = reduce_steps(context, span, fn_expr, steps)?;
+            let arg = reduce_steps(context, span, arg, steps)?;
+
+            match *fn_expr.inner {
+                Value::Lam(ref lam) if lam.plicity == Plicity::Explicit => {
+                    steps.take(span)?;
+                    reduce_steps(context, span, &lam.instantiate(&arg), steps)
+                },
+                // Primitives may have been given the last of their arguments
+                _ => {
+                    let value = Value::App(fn_expr.clone(), arg).into();
+                    Ok(reduce_prim(context, &value).unwrap_or(value))
+                },
+            }
+        },
+        Value::Sigma(ref sigma) => {
+            let (param, body) = sigma.clone().unbind();
+
+            let ann = reduce_steps(context, span, &param.inner, steps)?;
+            let body = reduce_steps(context, span, &body, steps)?;
+
+            Ok(Value::Sigma(ValueSigma::bind(Named::new(param.name, ann), body)).into())
+        },
+        Value::Pair(ref fst, ref snd) => {
+            let fst = reduce_steps(context, span, fst, steps)?;
+            let snd = reduce_steps(context, span, snd, steps)?;
+
+            Ok(Value::Pair(fst, snd).into())
+        },
+        Value::Fst(ref pair) => Ok(project_fst(&reduce_steps(context, span, pair, steps)?)),
+        Value::Snd(ref pair) => Ok(project_snd(&reduce_steps(context, span, pair, steps)?)),
+        Value::RecordType(ref record_ty) => {
+            let (label, param, body) = record_ty.clone().unbind();
+
+            let ann = reduce_steps(context, span, &param.inner, steps)?;
+            let body = reduce_steps(context, span, &body, steps)?;
+
+            let param = Named::new(param.name, ann);
+            Ok(Value::RecordType(ValueRecordType::bind(label, param, body)).into())
+        },
+        Value::Record(ref label, ref value, ref rest) => {
+            let value = reduce_steps(context, span, value, steps)?;
+            let rest = reduce_steps(context, span, rest, steps)?;
+
+            Ok(Value::Record(label.clone(), value, rest).into())
+        },
+        Value::Proj(ref record, ref label) => {
+            let record = reduce_steps(context, span, record, steps)?;
+            Ok(project(&record, label))
+        },
+        // Only the selected branch is reduced, as in EVAL/IF
+        Value::If(ref cond, ref if_true, ref if_false) => {
+            let cond = reduce_steps(context, span, cond, steps)?;
+            match *cond.inner {
+                Value::Constant(Constant::Bool(true)) => {
+                    reduce_steps(context, span, if_true, steps)
+                },
+                Value::Constant(Constant::Bool(false)) => {
+                    reduce_steps(context, span, if_false, steps)
+                },
+                _ => {
+                    let if_true = reduce_steps(context, span, if_true, steps)?;
+                    let if_false = reduce_steps(context, span, if_false, steps)?;
+
+                    Ok(Value::If(cond, if_true, if_false).into())
+                },
+            }
+        },
+    }
+}
+
+/// The value of a hole, which is left stuck as a fresh variable
+fn hole_value() -> RcValue {
+    Value::Var(Var::Free(Name::fresh(Some("?")))).into()
+}
+
+/// Project the first component out of a pair, leaving the projection stuck
+/// if the pair is neutral
+fn project_fst(pair: &RcValue) -> RcValue {
+    match *pair.inner {
+        Value::Pair(ref fst, _) => fst.clone(),
+        _ => Value::Fst(pair.clone()).into(),
+    }
+}
+
+/// Project the second component out of a pair, leaving the projection stuck
+/// if the pair is neutral
+fn project_snd(pair: &RcValue) -> RcValue {
+    match *pair.inner {
+        Value::Pair(_, ref snd) => snd.clone(),
+        _ => Value::Snd(pair.clone()).into(),
+    }
+}
+
+/// Project the field with the given label out of a record, leaving the
+/// projection stuck if the record is neutral
+fn project(record: &RcValue, label: &str) -> RcValue {
+    let mut current = record;
+    while let Value::Record(ref current_label, ref value, ref rest) = *current.inner {
+        if current_label == label {
+            return value.clone();
+        }
+        current = rest;
+    }
+
+    Value::Proj(record.clone(), String::from(label)).into()
+}
+
+/// Reduce a neutral value if it is a fully applied primitive function,
+/// returning `None` if it is not, or if the primitive could not be reduced
+///
+/// ```text
+///  1.  prim f:τ ∈ Γ    f takes n arguments
+///  2.  f v₁ ... vₙ ↝ v
+/// ─────────────────────────── (EVAL/APP-PRIM)
+///      Γ ⊢ f v₁ ... vₙ ⇓ v
+/// ```
+fn reduce_prim(context: &Context, value: &RcValue) -> Option<RcValue> {
+    let mut args = Vec::new();
+    let mut head = value;
+    while let Value::App(ref fn_expr, ref arg) = *head.inner {
+        // No primitive could be saturated by a spine this long
+        if args.len() == context.prims.max_arity() {
+            return None;
+        }
+        args.push(arg.clone());
+        head = fn_expr;
+    }
+
+    match *head.inner {
+        Value::Var(Var::Free(ref name)) => {
+            let prim = context.lookup_prim(name)?;
+            if prim.arity != args.len() {
+                return None;
+            }
+            args.reverse();
+            (prim.fun)(&args)
+        },
+        _ => None,
+    }
+}
+
+/// Check the bound value of a let binding, returning the context that its
+/// body should be checked in, along with the unbound body
+///
+/// ```text
+///  1.  Γ ⊢ ρ ⇒ Typeᵢ ⤳ _
+///  2.  ρ ⇓ τ
+///  3.  Γ ⊢ e₁ ⇐ τ ⤳ _
+///  4.  Γ ⊢ e₁ ⇓ v
+/// ─────────────────────────────────────────── (LET-ANN)
+///      Γ ⊢ let x:ρ = e₁ ⤳ Γ,let x:τ = v
+///
+///  1.  Γ ⊢ e₁ ⇒ τ ⤳ _
+///  2.  Γ ⊢ e₁ ⇓ v
+/// ─────────────────────────────────────────── (LET)
+///      Γ ⊢ let x = e₁ ⤳ Γ,let x:τ = v
+/// ```
+fn check_let(context: &Context, let_: &TermLet) -> Result<(Context, RcTerm), TypeError> {
+    let (param, value, body) = let_.clone().unbind();
+
+    let ann = match param.inner {
+        Some(ann) => {
+            infer_universe(context, &ann)?; // 1.
+            let simp_ann = normalize(context, &ann)?; // 2.
+            check(context, &value, &simp_ann)?; // 3.
+            simp_ann
+        },
+        None => infer(context, &value)?.1, // 1.
+    };
+    let simp_value = normalize(context, &value)?; // 2,4.
+    let binder = Binder::Let(simp_value, ann, Transparency::Transparent);
+
+    Ok((context.extend(param.name, binder), body))
+}
+
+/// Check the scrutinee and the patterns of a case expression, returning the
+/// elaborated scrutinee along with the unbound bodies of the clauses and the
+/// contexts that they should be checked in
+///
+/// The variables bound by each pattern are added to the context as
+/// definitions of the parts of the scrutinee that they match.
+///
+/// ```text
+///  1.  Γ ⊢ e ⇒ τ ⤳ v
+///  2.  Γ ⊢ pᵢ ⇐ τ ⤳ Δᵢ    for each clause
+///  3.  p₁ ... pₙ are exhaustive for τ
+/// ─────────────────────────────────────────────────── (CASE)
+///      Γ ⊢ case e of { pᵢ => eᵢ; ... } ⤳ v, Γ,Δᵢ ⊢ eᵢ
+/// ```
+fn check_case(
+    context: &Context,
+    span: ByteSpan,
+    scrutinee: &RcTerm,
+    clauses: &[TermClause],
+) -> Result<(RcValue, Vec<(Pattern, Context, RcTerm)>), TypeError> {
+    let (elab_scrutinee, scrutinee_ty) = infer(context, scrutinee)?; // 1.
+
+    let mut checked_clauses = Vec::with_capacity(clauses.len());
+    for clause in clauses {
+        let (pattern, body) = clause.clone().unbind();
+
+        let mut binders = Vec::new();
+        check_pattern(context, &pattern, &scrutinee_ty, &elab_scrutinee, &mut binders)?; // 2.
+
+        let mut body_context = context.clone();
+        for (name, ty, value) in binders {
+            let binder = Binder::Let(value, ty, Transparency::Transparent);
+            body_context = body_context.extend(name, binder);
+        }
+        checked_clauses.push((pattern, body_context, body));
+    }
+
+    let rows = checked_clauses
+        .iter()
+        .map(|&(ref pattern, _, _)| vec![pattern.clone()])
+        .collect::<Vec<_>>();
+    let missing = missing_patterns(&rows, &[scrutinee_ty]); // 3.
+    if !missing.is_empty() {
+        return Err(TypeError::NonExhaustiveMatch {
+            span,
+            missing: missing.into_iter().map(|mut row| row.remove(0)).collect(),
+        });
+    }
+
+    Ok((elab_scrutinee, checked_clauses))
+}
+
+/// Select the elaborated body of the clause whose pattern matches the
+/// elaborated scrutinee of a case expression
+///
+/// The variables bound by the patterns were already replaced by the parts of
+/// the scrutinee that they match during elaboration, so the bodies can be
+/// used as they are.
+fn select_elab_clause(
+    span: ByteSpan,
+    scrutinee: &RcValue,
+    clauses: &[(Pattern, Context, RcTerm)],
+    elab_bodies: &[RcValue],
+) -> Result<RcValue, InternalError> {
+    let patterns = clauses.iter().map(|&(ref pattern, _, _)| pattern).collect::<Vec<_>>();
+    let mut eval = |index: usize, _| Ok(elab_bodies[index].clone());
+    select_clause(span, scrutinee, &patterns, 0, &mut Vec::new(), &mut eval)
+}
+
+/// Check that a pattern matches values of the expected type, pushing the
+/// variables that it binds onto `binders`, along with their types and the
+/// parts of `value` that they match
+///
+/// Returns the value that the pattern stands for, which is substituted into
+/// the type of the second component of a pair pattern.
+///
+/// ```text
+/// ─────────────────────── (PATTERN/WILDCARD)
+///  Γ ⊢ _ ⇐ τ ⤳ ε
+///
+/// ─────────────────────── (PATTERN/BINDER)
+///  Γ ⊢ x ⇐ τ ⤳ x:τ
+///
+///  1.  Γ ⊢ c ⇐ τ ⤳ _
+/// ─────────────────────── (PATTERN/CONST)
+///  Γ ⊢ c ⇐ τ ⤳ ε
+///
+///  1.  Γ ⊢ p₁ ⇐ τ₁ ⤳ Δ₁
+///  2.  Γ ⊢ p₂ ⇐ τ₂[x↦p₁] ⤳ Δ₂
+/// ───────────────────────────────────── (PATTERN/PAIR)
+///      Γ ⊢ (p₁, p₂) ⇐ Σx:τ₁.τ₂ ⤳ Δ₁,Δ₂
+/// ```
+fn check_pattern(
+    context: &Context,
+    pattern: &Pattern,
+    expected: &RcType,
+    value: &RcValue,
+    binders: &mut Vec<(Name, RcType, RcValue)>,
+) -> Result<RcValue, TypeError> {
+    match (pattern, &*expected.inner) {
+        (&Pattern::Wildcard(_), _) => Ok(value.clone()),
+        (&Pattern::Binder(_, ref binder), _) => {
+            binders.push((binder.name.clone(), expected.clone(), value.clone()));
+            Ok(value.clone())
+        },
+        (&Pattern::Constant(meta, ref constant), _) => {
+            let term = Term::Constant(meta, constant.clone()).into();
+            Ok(check(context, &term, expected)?) // 1.
+        },
+        (&Pattern::Pair(_, ref fst_pattern, ref snd_pattern), &Value::Sigma(ref sigma)) => {
+            let (fst_ty, fst) = (&sigma.unsafe_param.inner, project_fst(value));
+            let fst_value = check_pattern(context, fst_pattern, fst_ty, &fst, binders)?; // 1.
+            let (snd_ty, snd) = (sigma.instantiate(&fst_value), project_snd(value));
+            let snd_value = check_pattern(context, snd_pattern, &snd_ty, &snd, binders)?; // 2.
+
+            Ok(Value::Pair(fst_value, snd_value).into())
+        },
+        (&Pattern::Pair(meta, _, _), _) => Err(TypeError::NotAPairType {
+            span: meta.span,
+            found: expected.clone(),
+        }),
+    }
+}
+
+/// Find patterns that are not covered by the rows of a pattern matrix, where
+/// the columns match values of the given types
+///
+/// Booleans are split into `true` and `false`, and pairs are expanded into
+/// their components. Values of other types are only covered by wildcards and
+/// binders. This is a simplified version of the algorithm described in
+/// [Warnings for pattern matching].
+///
+/// [Warnings for pattern matching]: http://moscova.inria.fr/~maranget/papers/warn/index.html
+fn missing_patterns(rows: &[Vec<Pattern>], tys: &[RcType]) -> Vec<Vec<Pattern>> {
+    let (ty, rest_tys) = match tys.split_first() {
+        Some(split) => split,
+        // An empty row covers everything
+        None if rows.is_empty() => return vec![Vec::new()],
+        None => return Vec::new(),
+    };
+    let meta = SourceMeta::default();
+
+    match *ty.inner {
+        Value::Constant(Constant::BoolType) => {
+            let mut missing = Vec::new();
+            for &value in &[true, false] {
+                let constant = Constant::Bool(value);
+                let rows = rows
+                    .iter()
+                    .filter(|row| match row[0] {
+                        Pattern::Constant(_, ref found) => *found == constant,
+                        _ => true,
+                    })
+                    .map(|row| row[1..].to_vec())
+                    .collect::<Vec<_>>();
+
+                for mut row in missing_patterns(&rows, rest_tys) {
+                    row.insert(0, Pattern::Constant(meta, constant.clone()));
+                    missing.push(row);
+                }
+            }
+            missing
+        },
+        Value::Sigma(ref sigma) => {
+            let (param, body) = sigma.clone().unbind();
+            let rows = rows
+                .iter()
+                .map(|row| {
+                    let mut expanded = match row[0] {
+                        Pattern::Pair(_, ref fst, ref snd) => {
+                            vec![(**fst).clone(), (**snd).clone()]
+                        },
+                        _ => vec![Pattern::Wildcard(meta), Pattern::Wildcard(meta)],
+                    };
+                    expanded.extend_from_slice(&row[1..]);
+                    expanded
+                })
+                .collect::<Vec<_>>();
+            let mut tys = vec![param.inner, body];
+            tys.extend_from_slice(rest_tys);
+
+            missing_patterns(&rows, &tys)
+                .into_iter()
+                .map(|mut row| {
+                    let rest = row.split_off(2);
+                    let snd = row.pop().unwrap();
+                    let fst = row.pop().unwrap();
+                    let mut row = vec![Pattern::Pair(meta, Box::new(fst), Box::new(snd))];
+                    row.extend(rest);
+                    row
+                })
+                .collect()
+        },
+        _ => {
+            let rows = rows
+                .iter()
+                .filter(|row| match row[0] {
+                    Pattern::Wildcard(_) | Pattern::Binder(_, _) => true,
+                    Pattern::Constant(_, _) | Pattern::Pair(_, _, _) => false,
+                })
+                .map(|row| row[1..].to_vec())
+                .collect::<Vec<_>>();
+
+            missing_patterns(&rows, rest_tys)
+                .into_iter()
+                .map(|mut row| {
+                    row.insert(0, Pattern::Wildcard(meta));
+                    row
+                })
+                .collect()
+        },
+    }
+}
+
+/// Type checking of terms
+///
+/// Under the assumptions in the context, check that the given term has
+/// the expected type and return its elaborated form.
+///
+/// ```text
+/// Γ ⊢ e ⇐ τ ⤳ v
+/// ```
+pub fn check(context: &Context, term: &RcTerm, expected: &RcType) -> Result<RcValue, TypeError> {
+    match (&*term.inner, &*expected.inner) {
+        // Holes are recorded in the context along with the type that was
+        // expected of them, and are reported once checking has finished
+        //
+        // ─────────────────── (CHECK/HOLE)
+        //  Γ ⊢ ? ⇐ τ ⤳ ?
+        (&Term::Hole(meta), _) => {
+            context.push_hole(meta.span, expected.clone());
+            return Ok(hole_value());
+        },
+
+        // We infer the type of the argument (`τ₁`) of the lambda from the
+        // supplied pi type, then 'push' it into the elaborated term, along with
+        // the elaborated body (`v`).
+        //
+        //  1.  Γ,Πx:τ₁ ⊢ e ⇐ τ₂ ⤳ v
+        // ────────────────────────────────────── (CHECK/LAM)
+        //      Γ ⊢ λx.e ⇐ Πx:τ₁.τ₂ ⤳ λx:τ₁.v
+        (&Term::Lam(_, ref lam), &Value::Pi(ref pi)) if lam.plicity == pi.plicity => {
+            let plicity = lam.plicity;
+            let (lam_param, lam_body, pi_param, pi_body) = core::unbind2(lam.clone(), pi.clone());
+
+            if lam_param.inner.is_none() {
+                let body_context =
+                    context.extend(pi_param.name, Binder::Pi(pi_param.inner.clone()));
+                let elab_lam_body = check(&body_context, &lam_body, &pi_body)?; // 1.
+
+                let elab_term = Value::Lam(ValueLam::bind(
+                    Named::new(lam_param.name, Some(pi_param.inner)),
+                    elab_lam_body,
+                ).with_plicity(plicity)).into();
+
+                return Ok(elab_term);
+            }
+
+            // TODO: We might want to optimise for this case, rather than
+            // falling through to `infer` and reunbinding at INFER/LAM
+        },
+
+        // Implicit parameters are introduced automatically when checking a
+        // term against an implicit function type, so they don't need to be
+        // written out
+        //
+        //  1.  Γ,Πx:τ₁ ⊢ e ⇐ τ₂ ⤳ v
+        // ────────────────────────────────────── (CHECK/LAM-IMPLICIT)
+        //      Γ ⊢ e ⇐ Π{x:τ₁}.τ₂ ⤳ λ{x:τ₁}.v
+        (_, &Value::Pi(ref pi)) if pi.plicity == Plicity::Implicit => {
+            let (param, body_ty) = pi.clone().unbind();
+
+            let body_context = context.extend(param.name.clone(), Binder::Pi(param.inner.clone()));
+            let elab_body = check(&body_context, term, &body_ty)?; // 1.
+
+            let elab_param = Named::new(param.name, Some(param.inner));
+            let elab_lam = ValueLam::bind(elab_param, elab_body).with_plicity(Plicity::Implicit);
+
+            return Ok(Value::Lam(elab_lam).into());
+        },
+        (&Term::Lam(_, _), _) => {
+            return Err(TypeError::UnexpectedFunction {
+                span: term.span(),
+                expected: expected.clone(),
+            });
+        },
+
+        // The type of the second component may depend on the value of the
+        // first component, so we substitute it into the sigma type before
+        // checking the second component.
+        //
+        //  1.  Γ ⊢ e₁ ⇐ τ₁ ⤳ v₁
+        //  2.  Γ ⊢ τ₂[x↦v₁] ↝ τ₂'
+        //  3.  Γ ⊢ e₂ ⇐ τ₂' ⤳ v₂
+        // ────────────────────────────────────────── (CHECK/PAIR)
+        //      Γ ⊢ (e₁, e₂) ⇐ Σx:τ₁.τ₂ ⤳ (v₁, v₂)
+        (&Term::Pair(_, ref fst, ref snd), &Value::Sigma(ref sigma)) => {
+            let elab_fst = check(context, fst, &sigma.unsafe_param.inner)?; // 1.
+            let snd_ty = reduce(context, &sigma.instantiate(&elab_fst))?; // 2.
+            let elab_snd = check(context, snd, &snd_ty)?; // 3.
+
+            return Ok(Value::Pair(elab_fst, elab_snd).into());
+        },
+
+        // Records are checked in the order that their fields were declared,
+        // substituting the value of each field into the types of the fields
+        // that follow it.
+        //
+        //  1.  Γ ⊢ e₁ ⇐ τ₁ ⤳ v₁
+        //  2.  Γ ⊢ τ₂[l↦v₁] ↝ τ₂'
+        //  3.  Γ ⊢ e₂ ⇐ τ₂' ⤳ v₂
+        // ───────────────────────────────────────────────────────────── (CHECK/RECORD)
+        //      Γ ⊢ record { l = e₁; e₂ } ⇐ Record { l:τ₁; τ₂ } ⤳ record { l = v₁; v₂ }
+        (&Term::Record(_, ref label, ref value, ref rest), &Value::RecordType(ref record_ty))
+            if *label == record_ty.label =>
+        {
+            let elab_value = check(context, value, &record_ty.unsafe_param.inner)?; // 1.
+            let rest_ty = reduce(context, &record_ty.instantiate(&elab_value))?; // 2.
+            let elab_rest = check(context, rest, &rest_ty)?; // 3.
+
+            return Ok(Value::Record(label.clone(), elab_value, elab_rest).into());
+        },
+
+        // Push the expected type into the body of let bindings, so that
+        // lambdas in the body can be checked
+        //
+        //  1.  Γ ⊢ let x:ρ = e₁ ⤳ Γ'
+        //  2.  Γ' ⊢ e₂ ⇐ τ ⤳ v
+        // ───────────────────────────────────── (CHECK/LET)
+        //      Γ ⊢ let x:ρ = e₁ in e₂ ⇐ τ ⤳ v
+        (&Term::Let(_, ref let_), _) => {
+            let (body_context, body) = check_let(context, let_)?; // 1.
+            return check(&body_context, &body, expected); // 2.
+        },
+
+        // Push the expected type into both branches of conditionals
+        //
+        //  1.  Γ ⊢ e₁ ⇐ Bool ⤳ v₁
+        //  2.  Γ ⊢ e₂ ⇐ τ ⤳ v₂
+        //  3.  Γ ⊢ e₃ ⇐ τ ⤳ v₃
+        // ───────────────────────────────────────────────────────────── (CHECK/IF)
+        //      Γ ⊢ if e₁ then e₂ else e₃ ⇐ τ ⤳ if v₁ then v₂ else v₃
+        (&Term::If(_, ref cond, ref if_true, ref if_false), _) => {
+            let bool_ty = Value::Constant(Constant::BoolType).into();
+            let elab_cond = check(context, cond, &bool_ty)?; // 1.
+            let elab_if_true = check(context, if_true, expected)?; // 2.
+            let elab_if_false = check(context, if_false, expected)?; // 3.
+
+            return Ok(select_branch(&elab_cond, &elab_if_true, &elab_if_false));
+        },
+
+        // Push the expected type into the bodies of each clause
+        //
+        //  1.  Γ ⊢ case e of { pᵢ => eᵢ; ... } ⤳ v, Γ,Δᵢ ⊢ eᵢ
+        //  2.  Γ,Δᵢ ⊢ eᵢ ⇐ τ ⤳ vᵢ    for each clause
+        // ─────────────────────────────────────────────────────────── (CHECK/CASE)
+        //      Γ ⊢ case e of { pᵢ => eᵢ; ... } ⇐ τ ⤳ case v of { pᵢ => vᵢ; ... }
+        (&Term::Case(_, ref scrutinee, ref clauses), _) => {
+            let (elab_scrutinee, clauses) = check_case(context, term.span(), scrutinee, clauses)?; // 1.
+
+            let mut elab_bodies = Vec::with_capacity(clauses.len());
+            for &(_, ref body_context, ref body) in &clauses {
+                elab_bodies.push(check(body_context, body, expected)?); // 2.
+            }
+
+            return Ok(select_elab_clause(term.span(), &elab_scrutinee, &clauses, &elab_bodies)?);
+        },
+        _ => {},
+    }
+
+    // Flip the direction of the type checker, checking that the inferred type
+    // is a subtype of the expected type. Any implicit arguments are filled in
+    // by matching the rest of the inferred type against the expected type.
+    //
+    //  1.  Γ ⊢ e ⇒ τ₁ ⤳ v
+    //  2.  τ₁ ≼ τ₂
+    // ─────────────────────── (CHECK/INFER)
+    //      Γ ⊢ e ⇐ τ₂ ⤳ v
+    //
+    //  1.  Γ ⊢ e ⇒ Π{x:τ₁}.τ₂ ⤳ v
+    //  3.  τ₂[x↦v₁] ≼ τ₃
+    // ─────────────────────────────── (CHECK/INFER-IMPLICIT)
+    //      Γ ⊢ e ⇐ τ₃ ⤳ v v₁
+
+    let (mut elab_term, mut inferred_ty) = infer(context, term)?; // 1.
+
+    // Implicit lambdas that were annotated end up here, in which case the
+    // implicit parameters are expected, and should be left alone
+    let expects_implicit = match *expected.inner {
+        Value::Pi(ref pi) => pi.plicity == Plicity::Implicit,
+        _ => false,
+    };
+
+    loop {
+        let next_ty = match *inferred_ty.inner {
+            Value::Pi(ref pi) if pi.plicity == Plicity::Implicit && !expects_implicit => {
+                let arg = match solve_implicit(pi, expected) {
+                    Some(arg) => arg,
+                    None => {
+                        return Err(TypeError::UnsolvedImplicit {
+                            span: term.span(),
+                            name: pi.unsafe_param.name.clone(),
+                        });
+                    },
+                };
+                elab_term = apply(context, &elab_term, &arg)?;
+                pi.instantiate(&arg) // 3.
+            },
+            _ => break,
+        };
+        inferred_ty = next_ty;
+    }
+
+    // Report universes that are too large separately from other mismatches,
+    // so that the levels are easier to spot
+    if let (&Value::Universe(ref found), &Value::Universe(ref expected)) =
+        (&*inferred_ty.inner, &*expected.inner)
+    {
+        return match unify_levels(context, found, expected) {
+            true => Ok(elab_term),
+            false => Err(TypeError::UniverseMismatch {
+                span: term.span(),
+                found: found.subst(&context.levels.borrow()),
+                expected: expected.subst(&context.levels.borrow()),
+            }),
+        };
+    }
+
+    match is_subtype(context, &inferred_ty, expected)? {
+        true => Ok(elab_term), // 2.
+        false => Err(TypeError::Mismatch {
+            span: term.span(),
+            difference: first_difference(&inferred_ty, expected),
+            found: inferred_ty,
+            expected: expected.clone(),
+            expected_origin: head_name(expected).and_then(|name| context.lookup_origin(name)),
+        }),
+    }
+}
+
+/// Subtyping of types
+///
+/// Universes are cumulative, so a type in a smaller universe can be used
+/// where a type in a larger universe is expected. Every other type is only a
+/// subtype of the types that it is convertible with.
+///
+/// ```text
+/// Γ ⊢ τ₁ ≼ τ₂
+/// ```
+pub fn is_subtype(context: &Context, ty1: &RcType, ty2: &RcType) -> Result<bool, InternalError> {
+    let mut steps = Steps::new(context);
+    let ty1 = whnf_value_steps(context, ty1, &mut steps)?;
+    let ty2 = whnf_value_steps(context, ty2, &mut steps)?;
 
-            Ok(Value::Lam(ValueLam::bind(Named::new(param.name.clone(), ann), body)).into())
-        },
+    match (&*ty1.inner, &*ty2.inner) {
+        //      i ≤ j
+        // ───────────────────── (ST/TYPE)
+        //  Γ ⊢ Typeᵢ ≼ Typeⱼ
+        (&Value::Universe(ref level1), &Value::Universe(ref level2)) => Ok(level1.is_le(level2)),
 
-        //  1.  Γ ⊢ ρ₁ ⇓ τ₁
-        //  2.  Γ,Πx:τ ⊢ ρ₂ ⇓ τ₂
-        // ─────────────────────────────────── (EVAL/PI-ANN)
-        //      Γ ⊢ Πx:ρ₁.ρ₂ ⇓ Πx:τ₁.τ₂
-        Term::Pi(_, ref pi) => {
-            let (param, body) = pi.clone().unbind();
+        //      Γ ⊢ τ₁ ≡ τ₂
+        // ───────────────── (ST/CONV)
+        //      Γ ⊢ τ₁ ≼ τ₂
+        (_, _) => is_convertible_steps(context, &ty1, &ty2, &mut steps),
+    }
+}
 
-            let ann = normalize(context, &param.inner)?; // 1.
-            let body_context = context.extend(param.name.clone(), Binder::Pi(ann.clone()));
-            let body = normalize(&body_context, &body)?; // 2.
+/// Check that two values are equal, up to [alpha equivalence], reduction,
+/// and the eta expansion of records
+///
+/// The values are compared spine-by-spine, reducing each part to weak head
+/// normal form only when it is reached, so the parts below the first
+/// difference are never reduced.
+///
+/// ```text
+/// Γ ⊢ v₁ ≡ v₂
+/// ```
+///
+/// [alpha equivalence]: https://en.wikipedia.org/wiki/Lambda_calculus#Alpha_equivalence
+fn is_convertible_steps(
+    context: &Context,
+    value1: &RcValue,
+    value2: &RcValue,
+    steps: &mut Steps,
+) -> Result<bool, InternalError> {
+    let value1 = whnf_value_steps(context, value1, steps)?;
+    let value2 = whnf_value_steps(context, value2, steps)?;
+
+    // The bodies of binders are opened with the same fresh variable, as in
+    // `first_difference`
+    match (&*value1.inner, &*value2.inner) {
+        (&Value::Lam(ref lam1), &Value::Lam(ref lam2)) if lam1.plicity == lam2.plicity => {
+            let (param1, body1) = lam1.clone().unbind();
+            let body2 = lam2.instantiate(&Value::Var(Var::Free(param1.name)).into());
 
-            Ok(Value::Pi(ValuePi::bind(Named::new(param.name.clone(), ann), body)).into())
+            let anns_convertible = match (&param1.inner, &lam2.unsafe_param.inner) {
+                (&Some(ref ann1), &Some(ref ann2)) => {
+                    is_convertible_steps(context, ann1, ann2, steps)?
+                },
+                (&None, &None) => true,
+                (_, _) => false,
+            };
+            Ok(anns_convertible && is_convertible_steps(context, &body1, &body2, steps)?)
         },
+        (&Value::Pi(ref pi1), &Value::Pi(ref pi2)) if pi1.plicity == pi2.plicity => {
+            let (param1, body1) = pi1.clone().unbind();
+            let body2 = pi2.instantiate(&Value::Var(Var::Free(param1.name)).into());
 
-        // Perform [β-reduction](https://en.wikipedia.org/wiki/Lambda_calculus#β-reduction),
-        // ie. apply functions to their arguments
-        //
-        //  1.  Γ ⊢ e₁ ⇓ λx.v₁
-        //  2.  Γ ⊢ v₁ ⇓ v₂
-        // ───────────────────────────── (EVAL/APP)
-        //      Γ ⊢ e₁ e₂ ⇓ v₂[x↦e₂]
-        Term::App(_, ref fn_expr, ref arg) => {
-            let fn_expr = normalize(context, fn_expr)?; // 1.
-            let arg = normalize(context, arg)?; // 2.
+            Ok(is_convertible_steps(context, &param1.inner, &pi2.unsafe_param.inner, steps)?
+                && is_convertible_steps(context, &body1, &body2, steps)?)
+        },
+        (&Value::Sigma(ref sigma1), &Value::Sigma(ref sigma2)) => {
+            let (param1, body1) = sigma1.clone().unbind();
+            let body2 = sigma2.instantiate(&Value::Var(Var::Free(param1.name)).into());
 
-            match *fn_expr.inner {
-                Value::Lam(ref lam) => {
-                    // FIXME: do a local unbind here
-                    let (param, mut body) = lam.clone().unbind();
-                    body.subst(&param.name, &arg);
-                    Ok(body)
-                },
-                _ => Ok(Value::App(fn_expr.clone(), arg).into()),
-            }
+            Ok(is_convertible_steps(context, &param1.inner, &sigma2.unsafe_param.inner, steps)?
+                && is_convertible_steps(context, &body1, &body2, steps)?)
+        },
+        (&Value::RecordType(ref record1), &Value::RecordType(ref record2))
+            if record1.label == record2.label =>
+        {
+            let (_, param1, body1) = record1.clone().unbind();
+            let body2 = record2.instantiate(&Value::Var(Var::Free(param1.name)).into());
+
+            Ok(is_convertible_steps(context, &param1.inner, &record2.unsafe_param.inner, steps)?
+                && is_convertible_steps(context, &body1, &body2, steps)?)
+        },
+        (&Value::App(ref fn1, ref arg1), &Value::App(ref fn2, ref arg2))
+        | (&Value::Pair(ref fn1, ref arg1), &Value::Pair(ref fn2, ref arg2)) => {
+            Ok(is_convertible_steps(context, fn1, fn2, steps)?
+                && is_convertible_steps(context, arg1, arg2, steps)?)
+        },
+        (
+            &Value::Record(ref label1, ref field1, ref rest1),
+            &Value::Record(ref label2, ref field2, ref rest2),
+        ) if label1 == label2 =>
+        {
+            Ok(is_convertible_steps(context, field1, field2, steps)?
+                && is_convertible_steps(context, rest1, rest2, steps)?)
+        },
+        // Records are compared with neutral terms field-by-field, projecting
+        // each field out of the neutral term. Both sides have the same record
+        // type, so once every field has been compared the rest of the neutral
+        // term is equal to the empty record.
+        (&Value::Record(ref label, ref field, ref rest), _) if is_neutral(&value2) => {
+            let proj = Value::Proj(value2.clone(), label.clone()).into();
+            Ok(is_convertible_steps(context, field, &proj, steps)?
+                && is_convertible_steps(context, rest, &value2, steps)?)
         },
+        (_, &Value::Record(ref label, ref field, ref rest)) if is_neutral(&value1) => {
+            let proj = Value::Proj(value1.clone(), label.clone()).into();
+            Ok(is_convertible_steps(context, &proj, field, steps)?
+                && is_convertible_steps(context, &value1, rest, steps)?)
+        },
+        (&Value::RecordEmpty, _) if is_neutral(&value2) => Ok(true),
+        (_, &Value::RecordEmpty) if is_neutral(&value1) => Ok(true),
+        (&Value::Fst(ref pair1), &Value::Fst(ref pair2))
+        | (&Value::Snd(ref pair1), &Value::Snd(ref pair2)) => {
+            is_convertible_steps(context, pair1, pair2, steps)
+        },
+        (&Value::Proj(ref record1, ref label1), &Value::Proj(ref record2, ref label2))
+            if label1 == label2 =>
+        {
+            is_convertible_steps(context, record1, record2, steps)
+        },
+        (
+            &Value::If(ref cond1, ref if_true1, ref if_false1),
+            &Value::If(ref cond2, ref if_true2, ref if_false2),
+        ) => {
+            Ok(is_convertible_steps(context, cond1, cond2, steps)?
+                && is_convertible_steps(context, if_true1, if_true2, steps)?
+                && is_convertible_steps(context, if_false1, if_false2, steps)?)
+        },
+        (_, _) => Ok(value1 == value2),
     }
 }
 
-/// Type checking of terms
+/// Level unification
 ///
-/// Under the assumptions in the context, check that the given term has
-/// the expected type and return its elaborated form.
+/// Solves the level variables in `found` and `expected` so that `found` is
+/// no larger than `expected`, recording the solutions in the context.
+/// Returns `false` if no solution could be found.
 ///
-/// ```text
-/// Γ ⊢ e ⇐ τ ⤳ v
-/// ```
-pub fn check(context: &Context, term: &RcTerm, expected: &RcType) -> Result<RcValue, TypeError> {
-    match (&*term.inner, &*expected.inner) {
-        // We infer the type of the argument (`τ₁`) of the lambda from the
-        // supplied pi type, then 'push' it into the elaborated term, along with
-        // the elaborated body (`v`).
-        //
-        //  1.  Γ,Πx:τ₁ ⊢ e ⇐ τ₂ ⤳ v
-        // ────────────────────────────────────── (CHECK/LAM)
-        //      Γ ⊢ λx.e ⇐ Πx:τ₁.τ₂ ⤳ λx:τ₁.v
-        (&Term::Lam(_, ref lam), &Value::Pi(ref pi)) => {
-            let (lam_param, lam_body, pi_param, pi_body) = core::unbind2(lam.clone(), pi.clone());
-
-            if lam_param.inner.is_none() {
-                let body_context =
-                    context.extend(pi_param.name, Binder::Pi(pi_param.inner.clone()));
-                let elab_lam_body = check(&body_context, &lam_body, &pi_body)?; // 1.
+/// An unsolved level variable is simply set to the level that it is compared
+/// with, so the solutions are not always the most general ones. This is
+/// enough to instantiate each use of a level-polymorphic definition, like
+/// `id : (A : Type^l) -> A -> A`, at the level of its arguments.
+pub fn unify_levels(context: &Context, found: &Level, expected: &Level) -> bool {
+    let found = found.subst(&context.levels.borrow());
+    let expected = expected.subst(&context.levels.borrow());
 
-                let elab_term = Value::Lam(ValueLam::bind(
-                    Named::new(lam_param.name, Some(pi_param.inner)),
-                    elab_lam_body,
-                )).into();
+    if found.is_le(&expected) {
+        return true;
+    }
 
-                return Ok(elab_term);
+    match (&found, &expected) {
+        (&Level::Var(id), level) | (level, &Level::Var(id)) => {
+            if level.vars().contains(&id) {
+                return false;
             }
+            context.levels.borrow_mut().insert(id, level.clone());
+            true
+        },
+        (&Level::Succ(ref level1), &Level::Succ(ref level2)) => {
+            unify_levels(context, level1, level2)
+        },
+        (&Level::Succ(ref level1), &Level::Const(level2)) if level2 > 0 => {
+            unify_levels(context, level1, &Level::Const(level2 - 1))
+        },
+        (&Level::Const(level1), &Level::Succ(ref level2)) if level1 > 0 => {
+            unify_levels(context, &Level::Const(level1 - 1), level2)
+        },
+        (&Level::Max(ref level1, ref level2), _) => {
+            unify_levels(context, level1, &expected) && unify_levels(context, level2, &expected)
+        },
+        (_, _) => false,
+    }
+}
 
-            // TODO: We might want to optimise for this case, rather than
-            // falling through to `infer` and reunbinding at INFER/LAM
+/// Replace the level variables in a type that have been solved by level
+/// unification
+fn solve_levels(context: &Context, ty: &RcType) -> RcType {
+    ty.subst_levels(&context.levels.borrow())
+}
+
+/// Replace the level variables in the type of a definition with fresh ones,
+/// so that each use of the definition can be at a different level. The
+/// same replacements are made in the definition's value.
+fn instantiate_levels(context: &Context, value: &RcValue, ty: &RcType) -> (RcValue, RcType) {
+    let ty = solve_levels(context, ty);
+    let mappings = ty.level_vars()
+        .into_iter()
+        .map(|id| (id, Level::fresh()))
+        .collect::<HashMap<GenId, Level>>();
+
+    match mappings.is_empty() {
+        true => (value.clone(), ty),
+        false => (value.subst_levels(&mappings), ty.subst_levels(&mappings)),
+    }
+}
+
+/// Find the innermost pair of corresponding parts where two types first
+/// differ, descending into the parts that have the same structure in both
+/// types. Returns `None` if the types differ at their outermost level.
+fn first_difference(found: &RcType, expected: &RcType) -> Option<(RcType, RcType)> {
+    // The bodies of binders are opened with the same fresh variable, so that
+    // the parts that are found can be printed
+    let parts = match (&*found.inner, &*expected.inner) {
+        (&Value::Pi(ref pi1), &Value::Pi(ref pi2)) if pi1.plicity == pi2.plicity => {
+            let (param1, body1) = pi1.clone().unbind();
+            let body2 = pi2.instantiate(&Value::Var(Var::Free(param1.name)).into());
+            vec![(param1.inner, pi2.unsafe_param.inner.clone()), (body1, body2)]
         },
-        (&Term::Lam(_, _), _) => {
-            return Err(TypeError::UnexpectedFunction {
-                span: term.span(),
-                expected: expected.clone(),
-            });
+        (&Value::Sigma(ref sigma1), &Value::Sigma(ref sigma2)) => {
+            let (param1, body1) = sigma1.clone().unbind();
+            let body2 = sigma2.instantiate(&Value::Var(Var::Free(param1.name)).into());
+            vec![(param1.inner, sigma2.unsafe_param.inner.clone()), (body1, body2)]
         },
-        _ => {},
+        (&Value::RecordType(ref record1), &Value::RecordType(ref record2))
+            if record1.label == record2.label =>
+        {
+            let (_, param1, body1) = record1.clone().unbind();
+            let body2 = record2.instantiate(&Value::Var(Var::Free(param1.name)).into());
+            vec![(param1.inner, record2.unsafe_param.inner.clone()), (body1, body2)]
+        },
+        (&Value::App(ref fn1, ref arg1), &Value::App(ref fn2, ref arg2)) => {
+            vec![(fn1.clone(), fn2.clone()), (arg1.clone(), arg2.clone())]
+        },
+        (_, _) => return None,
+    };
+
+    parts
+        .into_iter()
+        .find(|&(ref part1, ref part2)| part1 != part2)
+        .map(|(part1, part2)| first_difference(&part1, &part2).unwrap_or((part1, part2)))
+}
+
+/// Check that the type of the first field of a record type only refers to
+/// the fields that were declared before it
+///
+/// The labels of the earlier fields are bound in the type of the field, so a
+/// free variable named after this field, or after one of the fields that
+/// follow it, refers to a field that has not been declared yet. Names that
+/// are in scope outside of the record type are left alone.
+fn check_field_references(context: &Context, record_ty: &TermRecordType) -> Result<(), TypeError> {
+    let free_vars = record_ty.unsafe_param.inner.free_vars();
+
+    let mut current = Some(record_ty);
+    while let Some(field) = current {
+        let name = Name::user(field.label.as_str());
+        if free_vars.contains(&name)
+            && context.lookup_binder(&name).is_none()
+            && context.lookup_prim(&name).is_none()
+        {
+            return Err(TypeError::ForwardFieldReference {
+                span: record_ty.unsafe_param.inner.span(),
+                field: field.label.clone(),
+            });
+        }
+
+        current = match *field.unsafe_body.inner {
+            Term::RecordType(_, ref next) => Some(next),
+            _ => None,
+        };
     }
 
-    // Flip the direction of the type checker, comparing the type of the
-    // expected term for [alpha equivalence] with the inferred term.
-    //
-    //  1.  Γ ⊢ e₂ ⇒ τ ⤳ v
-    //  2.  e₁ ≡ e₂
-    // ─────────────────────── (CHECK/INFER)
-    //      Γ ⊢ e₁ ⇐ τ ⤳ v
-    //
-    // NOTE: We could change 2. to check for subtyping instead of alpha
-    // equivalence. This could be useful for implementing a cumulative
-    // universe hierarchy.
-    //
-    // [alpha equivalence]: https://en.wikipedia.org/wiki/Lambda_calculus#Alpha_equivalence
+    Ok(())
+}
 
-    let (elab_term, inferred_ty) = infer(context, term)?; // 1.
+/// Check if a value is neutral, ie. if it is stuck on a variable
+fn is_neutral(value: &RcValue) -> bool {
+    match *value.inner {
+        Value::Var(_)
+        | Value::App(_, _)
+        | Value::Fst(_)
+        | Value::Snd(_)
+        | Value::Proj(_, _)
+        | Value::If(_, _, _) => true,
+        _ => false,
+    }
+}
 
-    // Because we have invested lots of effort into setting up our
-    // locally nameless representation alpha equivalence is easy-peasy!
-    match &inferred_ty == expected {
-        true => Ok(elab_term),
-        false => Err(TypeError::Mismatch {
-            span: term.span(),
-            found: inferred_ty,
-            expected: expected.clone(),
-        }),
+/// Return the name at the head of a neutral value, if there is one
+fn head_name(value: &RcValue) -> Option<&Name> {
+    match *value.inner {
+        Value::Var(Var::Free(ref name)) => Some(name),
+        Value::App(ref fn_expr, _) => head_name(fn_expr),
+        _ => None,
+    }
+}
+
+/// Find the argument to supply to an implicit parameter, by matching the
+/// type that the parameter is bound in against the expected type
+///
+/// This is a simple first-order matching, so it only succeeds if the
+/// parameter appears somewhere in the type that it can be read back from.
+/// It will eventually be replaced by unification with metavariables.
+fn solve_implicit(pi: &ValuePi, expected: &RcType) -> Option<RcValue> {
+    let (param, body_ty) = pi.clone().unbind();
+    let mut solution = None;
+
+    if match_value(&param.name, &body_ty, expected, &mut Vec::new(), &mut solution) {
+        solution
+    } else {
+        None
+    }
+}
+
+/// Check that `pattern` is equal to `target`, recording the value found in
+/// place of the variable `name` in `solution`. The names bound by the
+/// binders that have been entered are kept in `scope`, so that they don't
+/// escape in the solution.
+fn match_value(
+    name: &Name,
+    pattern: &RcValue,
+    target: &RcValue,
+    scope: &mut Vec<Name>,
+    solution: &mut Option<RcValue>,
+) -> bool {
+    // Match the bodies of two binders, instantiating them with the same
+    // fresh variable
+    fn match_scoped<F>(
+        name: &Name,
+        instantiate: F,
+        scope: &mut Vec<Name>,
+        solution: &mut Option<RcValue>,
+    ) -> bool
+    where
+        F: FnOnce(&RcValue) -> (RcValue, RcValue),
+    {
+        let fv = Name::fresh(None::<&str>);
+        let (pattern, target) = instantiate(&Value::Var(Var::Free(fv.clone())).into());
+
+        scope.push(fv);
+        let matched = match_value(name, &pattern, &target, scope, solution);
+        scope.pop();
+        matched
+    }
+
+    match (&*pattern.inner, &*target.inner) {
+        (&Value::Var(Var::Free(ref var_name)), _) if var_name == name => {
+            let free_vars = target.free_vars();
+            if scope.iter().any(|bound| free_vars.contains(bound)) {
+                return false;
+            }
+            if let Some(ref solution) = *solution {
+                return solution == target;
+            }
+            *solution = Some(target.clone());
+            true
+        },
+        (&Value::Lam(ref lam1), &Value::Lam(ref lam2)) => {
+            let params_match = match (&lam1.unsafe_param.inner, &lam2.unsafe_param.inner) {
+                (&Some(ref ann1), &Some(ref ann2)) => {
+                    match_value(name, ann1, ann2, scope, solution)
+                },
+                (&None, &None) => true,
+                (_, _) => false,
+            };
+
+            lam1.plicity == lam2.plicity
+                && params_match
+                && match_scoped(
+                    name,
+                    |x| (lam1.instantiate(x), lam2.instantiate(x)),
+                    scope,
+                    solution,
+                )
+        },
+        (&Value::Pi(ref pi1), &Value::Pi(ref pi2)) => {
+            pi1.plicity == pi2.plicity
+                && match_value(
+                    name,
+                    &pi1.unsafe_param.inner,
+                    &pi2.unsafe_param.inner,
+                    scope,
+                    solution,
+                )
+                && match_scoped(name, |x| (pi1.instantiate(x), pi2.instantiate(x)), scope, solution)
+        },
+        (&Value::Sigma(ref sigma1), &Value::Sigma(ref sigma2)) => {
+            match_value(
+                name,
+                &sigma1.unsafe_param.inner,
+                &sigma2.unsafe_param.inner,
+                scope,
+                solution,
+            ) && match_scoped(
+                name,
+                |x| (sigma1.instantiate(x), sigma2.instantiate(x)),
+                scope,
+                solution,
+            )
+        },
+        (&Value::RecordType(ref record_ty1), &Value::RecordType(ref record_ty2)) => {
+            record_ty1.label == record_ty2.label
+                && match_value(
+                    name,
+                    &record_ty1.unsafe_param.inner,
+                    &record_ty2.unsafe_param.inner,
+                    scope,
+                    solution,
+                )
+                && match_scoped(
+                    name,
+                    |x| (record_ty1.instantiate(x), record_ty2.instantiate(x)),
+                    scope,
+                    solution,
+                )
+        },
+        (&Value::App(ref left1, ref right1), &Value::App(ref left2, ref right2))
+        | (&Value::Pair(ref left1, ref right1), &Value::Pair(ref left2, ref right2)) => {
+            match_value(name, left1, left2, scope, solution)
+                && match_value(name, right1, right2, scope, solution)
+        },
+        (&Value::Fst(ref pair1), &Value::Fst(ref pair2))
+        | (&Value::Snd(ref pair1), &Value::Snd(ref pair2)) => {
+            match_value(name, pair1, pair2, scope, solution)
+        },
+        (
+            &Value::Record(ref label1, ref value1, ref rest1),
+            &Value::Record(ref label2, ref value2, ref rest2),
+        ) => {
+            label1 == label2
+                && match_value(name, value1, value2, scope, solution)
+                && match_value(name, rest1, rest2, scope, solution)
+        },
+        (&Value::Proj(ref record1, ref label1), &Value::Proj(ref record2, ref label2)) => {
+            label1 == label2 && match_value(name, record1, record2, scope, solution)
+        },
+        (
+            &Value::If(ref cond1, ref if_true1, ref if_false1),
+            &Value::If(ref cond2, ref if_true2, ref if_false2),
+        ) => {
+            match_value(name, cond1, cond2, scope, solution)
+                && match_value(name, if_true1, if_true2, scope, solution)
+                && match_value(name, if_false1, if_false2, scope, solution)
+        },
+        (_, _) => pattern == target,
     }
 }
 
@@ -349,25 +2561,6 @@ pub fn check(context: &Context, term: &RcTerm, expected: &RcType) -> Result<RcVa
 /// Γ ⊢ e ⇒ τ ⤳ v
 /// ```
 pub fn infer(context: &Context, term: &RcTerm) -> Result<(RcValue, RcType), TypeError> {
-    use std::cmp;
-
-    /// Ensures that the given term is a universe, returning the level of that
-    /// universe and its elaborated form.
-    ///
-    /// ```text
-    /// Γ ⊢ ρ ⇒ Typeᵢ ⤳ τ
-    /// ```
-    fn infer_universe(context: &Context, term: &RcTerm) -> Result<(RcValue, Level), TypeError> {
-        let (elab, ty) = infer(context, term)?;
-        match *ty.inner {
-            Value::Universe(level) => Ok((elab, level)),
-            _ => Err(TypeError::ExpectedUniverse {
-                span: term.span(),
-                found: ty,
-            }),
-        }
-    }
-
     match *term.inner {
         //  1.  Γ ⊢ ρ ⇒ Typeᵢ ⤳ τ
         //  2.  ρ ⇓ τ
@@ -381,12 +2574,21 @@ pub fn infer(context: &Context, term: &RcTerm) -> Result<(RcValue, RcType), Type
             Ok((elab_expr, simp_ty))
         },
 
+        // The largest universe has no type, because it can't be a member of
+        // itself without making the type system inconsistent
+        //
         // ───────────────────────────────── (INFER/TYPE)
         //  Γ ⊢ Typeᵢ ⇒ Typeᵢ₊₁ ⤳ Typeᵢ
-        Term::Universe(_, level) => Ok((
-            Value::Universe(level).into(),
-            Value::Universe(level.succ()).into(),
-        )),
+        Term::Universe(_, ref level) => match level.checked_succ() {
+            Some(ty_level) => Ok((
+                Value::Universe(level.clone()).into(),
+                Value::Universe(ty_level).into(),
+            )),
+            None => Err(TypeError::UniverseLevelOverflow {
+                span: term.span(),
+                level: level.clone(),
+            }),
+        },
 
         Term::Var(_, ref var) => match *var {
             Var::Free(ref name) => match context.lookup_binder(name) {
@@ -397,23 +2599,49 @@ pub fn infer(context: &Context, term: &RcTerm) -> Result<(RcValue, RcType), Type
                 //  2.  Πx:τ ∈ Γ
                 // ─────────────────────── (INFER/VAR-PI)
                 //      Γ ⊢ x ⇒ τ ⤳ x
+                //
                 Some(&Binder::Lam(Some(ref ty))) | Some(&Binder::Pi(ref ty)) => {
                     Ok((Value::Var(var.clone()).into(), ty.clone()))
                 },
+                // Claims and definitions are polymorphic over the level
+                // variables in their types, so these are instantiated with
+                // fresh level variables
+                //
+                //  1.  x:τ ∈ Γ
+                // ─────────────────────── (INFER/VAR-CLAIM)
+                //      Γ ⊢ x ⇒ τ ⤳ x
+                Some(&Binder::Claim(ref ty)) => {
+                    Ok(instantiate_levels(context, &RcValue::from(Value::Var(var.clone())), ty))
+                },
                 //  1.  let x:τ = v ∈ Γ
                 // ─────────────────────── (INFER/VAR-LET)
                 //      Γ ⊢ x ⇒ τ ⤳ v
-                Some(&Binder::Let(ref ty, ref value)) => Ok((ty.clone(), value.clone())),
+                Some(&Binder::Let(ref value, ref ty, Transparency::Transparent)) => {
+                    Ok(instantiate_levels(context, value, ty))
+                },
+                //  1.  opaque x:τ = v ∈ Γ
+                // ─────────────────────── (INFER/VAR-OPAQUE)
+                //      Γ ⊢ x ⇒ τ ⤳ x
+                Some(&Binder::Let(_, ref ty, Transparency::Opaque)) => {
+                    Ok(instantiate_levels(context, &RcValue::from(Value::Var(var.clone())), ty))
+                },
 
                 Some(&Binder::Lam(None)) => Err(TypeError::FunctionParamNeedsAnnotation {
                     param_span: ByteSpan::none(), // TODO: binder.span(),
                     var_span: Some(term.span()),
                     name: name.clone(),
                 }),
-                None => Err(TypeError::UndefinedName {
-                    var_span: term.span(),
-                    name: name.clone(),
-                }),
+                None => match context.lookup_prim(name) {
+                    //  1.  prim x:τ ∈ Γ
+                    // ─────────────────────── (INFER/VAR-PRIM)
+                    //      Γ ⊢ x ⇒ τ ⤳ x
+                    Some(prim) => Ok((normalize(context, term)?, prim.ann.clone())),
+                    None => Err(TypeError::UndefinedName {
+                        var_span: term.span(),
+                        name: name.clone(),
+                        suggestion: suggest_name(context, name),
+                    }),
+                },
             },
 
             // We should always be substituting bound variables with fresh
@@ -432,6 +2660,7 @@ pub fn infer(context: &Context, term: &RcTerm) -> Result<(RcValue, RcType), Type
         // ───────────────────────────────────────── (INFER/LAM)
         //      Γ ⊢ λx:ρ.e ⇒ Πx:τ₁.τ₂ ⤳ λx:τ.v
         Term::Lam(_, ref lam) => {
+            let plicity = lam.plicity;
             let (param, body) = lam.clone().unbind();
 
             match param.inner {
@@ -443,8 +2672,9 @@ pub fn infer(context: &Context, term: &RcTerm) -> Result<(RcValue, RcType), Type
                     let (elab_body, body_ty) = infer(&body_context, &body)?; // 3.
 
                     let elab_param = Named::new(param.name.clone(), Some(elab_ann));
-                    let elab_lam = ValueLam::bind(elab_param, elab_body);
-                    let pi_ty = ValuePi::bind(Named::new(param.name.clone(), simp_ann), body_ty);
+                    let elab_lam = ValueLam::bind(elab_param, elab_body).with_plicity(plicity);
+                    let pi_ty = ValuePi::bind(Named::new(param.name.clone(), simp_ann), body_ty)
+                        .with_plicity(plicity);
 
                     Ok((Value::Lam(elab_lam).into(), Value::Pi(pi_ty).into()))
                 },
@@ -463,6 +2693,7 @@ pub fn infer(context: &Context, term: &RcTerm) -> Result<(RcValue, RcType), Type
         // ────────────────────────────────────────── (INFER/PI)
         //      Γ ⊢ Πx:ρ₁.ρ₂ ⇒ Typeₖ ⤳ Πx:τ₁.τ₂
         Term::Pi(_, ref pi) => {
+            let plicity = pi.plicity;
             let (param, body) = pi.clone().unbind();
 
             let (elab_ann, level_ann) = infer_universe(context, &param.inner)?; // 1.
@@ -471,36 +2702,359 @@ pub fn infer(context: &Context, term: &RcTerm) -> Result<(RcValue, RcType), Type
             let (elab_body, level_body) = infer_universe(&body_context, &body)?; // 3.
 
             let elab_param = Named::new(param.name.clone(), elab_ann);
-            let elab_pi = ValuePi::bind(elab_param, elab_body);
-            let level = cmp::max(level_ann, level_body); // 4.
+            let elab_pi = ValuePi::bind(elab_param, elab_body).with_plicity(plicity);
+            let level = level_ann.max(&level_body); // 4.
 
             Ok((Value::Pi(elab_pi).into(), Value::Universe(level).into()))
         },
 
         //  1.  Γ ⊢ e₁ ⇒ Πx:τ₁.τ₂ ⤳ v₁
         //  2.  Γ ⊢ e₂ ⇐ τ₁ ⤳ v₂
-        //  3.  τ₂ ⇓ τ₃
+        //  3.  Γ ⊢ τ₂[x↦v₂] ↝ τ₃
         // ────────────────────────────────────── (INFER/APP)
-        //      Γ ⊢ e₁ e₂ ⇒ τ₃[x↦e₂] ⤳ v₁ v₂
-        Term::App(_, ref fn_expr, ref arg_expr) => {
-            let (elab_fn_expr, fn_type) = infer(context, fn_expr)?; // 1.
+        //      Γ ⊢ e₁ e₂ ⇒ τ₃ ⤳ v₁ v₂
+        //
+        // The arguments of the application spine are checked in a loop to
+        // avoid overflowing the stack on long spines. An argument is only
+        // substituted if the result type depends on it, so inferring the type
+        // of an application doesn't evaluate arguments that it doesn't need.
+        Term::App(_, _, _) => {
+            let (head, args) = app_spine(term);
+            let (mut elab_fn_expr, mut fn_type) = infer(context, head)?; // 1.
+            let mut fn_span = head.span();
+
+            for arg_expr in args {
+                let (elab_arg_expr, pi_body) = match *fn_type.inner {
+                    // Implicit arguments can only be inferred in checking mode
+                    // for now
+                    Value::Pi(ref pi) if pi.plicity == Plicity::Implicit => {
+                        return Err(TypeError::UnsolvedImplicit {
+                            span: fn_span,
+                            name: pi.unsafe_param.name.clone(),
+                        });
+                    },
+                    Value::Pi(ref pi) => {
+                        let elab_arg_expr = check(context, arg_expr, &pi.unsafe_param.inner)?; // 2.
+                        let pi_body = if pi.is_dependent() {
+                            reduce(context, &pi.instantiate(&elab_arg_expr))? // 3.
+                        } else {
+                            pi.unsafe_body.clone()
+                        };
+
+                        (elab_arg_expr, pi_body)
+                    },
+                    _ => {
+                        return Err(TypeError::NotAFunctionType {
+                            fn_span,
+                            arg_span: arg_expr.span(),
+                            found: fn_type.clone(),
+                        });
+                    },
+                };
+
+                elab_fn_expr = Value::App(elab_fn_expr, elab_arg_expr).into();
+                fn_type = pi_body;
+                fn_span = fn_span.to(arg_expr.span());
+            }
+
+            // Checking the arguments may have solved some of the level
+            // variables in the result type
+            Ok((elab_fn_expr, solve_levels(context, &fn_type)))
+        },
+
+        //  1.  Γ ⊢ let x:ρ = e₁ ⤳ Γ'
+        //  2.  Γ' ⊢ e₂ ⇒ τ ⤳ v
+        // ───────────────────────────────────── (INFER/LET)
+        //      Γ ⊢ let x:ρ = e₁ in e₂ ⇒ τ ⤳ v
+        Term::Let(_, ref let_) => {
+            let (body_context, body) = check_let(context, let_)?; // 1.
+            infer(&body_context, &body) // 2.
+        },
+
+        //  1.  Γ ⊢ ρ₁ ⇒ Typeᵢ ⤳ τ₁
+        //  2.  ρ₁ ⇓ τ₁'
+        //  3.  Γ,Πx:τ₁' ⊢ ρ₂ ⇐ Typeⱼ ⤳ τ₂
+        //  4.  k = max(i, j)
+        // ────────────────────────────────────────── (INFER/SIGMA)
+        //      Γ ⊢ Σx:ρ₁.ρ₂ ⇒ Typeₖ ⤳ Σx:τ₁.τ₂
+        Term::Sigma(_, ref sigma) => {
+            let (param, body) = sigma.clone().unbind();
 
-            match *fn_type.inner {
-                Value::Pi(ref pi) => {
-                    let (pi_param, mut pi_body) = pi.clone().unbind();
+            let (elab_ann, level_ann) = infer_universe(context, &param.inner)?; // 1.
+            let simp_ann = normalize(context, &param.inner)?; // 2.
+            let body_context = context.extend(param.name.clone(), Binder::Pi(simp_ann));
+            let (elab_body, level_body) = infer_universe(&body_context, &body)?; // 3.
+
+            let elab_param = Named::new(param.name.clone(), elab_ann);
+            let elab_sigma = ValueSigma::bind(elab_param, elab_body);
+            let level = level_ann.max(&level_body); // 4.
+
+            Ok((Value::Sigma(elab_sigma).into(), Value::Universe(level).into()))
+        },
+
+        // Without an annotation we can only infer a non-dependent pair type
+        //
+        //  1.  Γ ⊢ e₁ ⇒ τ₁ ⤳ v₁
+        //  2.  Γ ⊢ e₂ ⇒ τ₂ ⤳ v₂
+        // ────────────────────────────────────────── (INFER/PAIR)
+        //      Γ ⊢ (e₁, e₂) ⇒ Σx:τ₁.τ₂ ⤳ (v₁, v₂)
+        Term::Pair(_, ref fst, ref snd) => {
+            let (elab_fst, fst_ty) = infer(context, fst)?; // 1.
+            let (elab_snd, snd_ty) = infer(context, snd)?; // 2.
+
+            let param = Named::new(Name::fresh(None::<&str>), fst_ty);
+            let sigma_ty = ValueSigma::bind(param, snd_ty);
+
+            Ok((Value::Pair(elab_fst, elab_snd).into(), Value::Sigma(sigma_ty).into()))
+        },
+
+        //  1.  Γ ⊢ e ⇒ Σx:τ₁.τ₂ ⤳ v
+        // ────────────────────────────────────────── (INFER/FST)
+        //      Γ ⊢ fst e ⇒ τ₁ ⤳ fst v
+        Term::Fst(_, ref pair) => {
+            let (elab_pair, pair_ty) = infer(context, pair)?; // 1.
+            match *pair_ty.inner {
+                Value::Sigma(ref sigma) => {
+                    Ok((project_fst(&elab_pair), sigma.unsafe_param.inner.clone()))
+                },
+                _ => Err(TypeError::NotAPairType {
+                    span: pair.span(),
+                    found: pair_ty.clone(),
+                }),
+            }
+        },
 
-                    let elab_arg_expr = check(context, arg_expr, &pi_param.inner)?; // 2.
-                    let simp_arg_expr = normalize(context, &arg_expr)?; // 3.
-                    pi_body.subst(&pi_param.name, &simp_arg_expr);
+        //  1.  Γ ⊢ e ⇒ Σx:τ₁.τ₂ ⤳ v
+        //  2.  Γ ⊢ e ⇓ v'
+        // ────────────────────────────────────────── (INFER/SND)
+        //      Γ ⊢ snd e ⇒ τ₂[x↦fst v'] ⤳ snd v
+        Term::Snd(_, ref pair) => {
+            let (elab_pair, pair_ty) = infer(context, pair)?; // 1.
+            match *pair_ty.inner {
+                Value::Sigma(ref sigma) => {
+                    let simp_pair = normalize(context, pair)?; // 2.
+                    let snd_ty = sigma.instantiate(&project_fst(&simp_pair));
 
-                    Ok((Value::App(elab_fn_expr, elab_arg_expr).into(), pi_body))
+                    Ok((project_snd(&elab_pair), snd_ty))
                 },
-                _ => Err(TypeError::NotAFunctionType {
-                    fn_span: fn_expr.span(),
-                    arg_span: arg_expr.span(),
-                    found: fn_type.clone(),
+                _ => Err(TypeError::NotAPairType {
+                    span: pair.span(),
+                    found: pair_ty.clone(),
                 }),
             }
         },
+
+        //  1.  Γ ⊢ ρ₁ ⇒ Typeᵢ ⤳ τ₁
+        //  2.  ρ₁ ⇓ τ₁'
+        //  3.  Γ,Πl:τ₁' ⊢ ρ₂ ⇒ Typeⱼ ⤳ τ₂
+        //  4.  k = max(i, j)
+        // ──────────────────────────────────────────────────── (INFER/RECORD-TYPE)
+        //      Γ ⊢ Record { l:ρ₁; ρ₂ } ⇒ Typeₖ ⤳ Record { l:τ₁; τ₂ }
+        Term::RecordType(_, ref record_ty) => {
+            check_field_references(context, record_ty)?;
+            let (label, param, body) = record_ty.clone().unbind();
+
+            let (elab_ann, level_ann) = infer_universe(context, &param.inner)?; // 1.
+            let simp_ann = normalize(context, &param.inner)?; // 2.
+            let body_context = context.extend(param.name.clone(), Binder::Pi(simp_ann));
+            let (elab_body, level_body) = infer_universe(&body_context, &body)?; // 3.
+
+            let elab_param = Named::new(param.name.clone(), elab_ann);
+            let elab_record_ty = ValueRecordType::bind(label, elab_param, elab_body);
+            let level = level_ann.max(&level_body); // 4.
+
+            Ok((Value::RecordType(elab_record_ty).into(), Value::Universe(level).into()))
+        },
+
+        // ──────────────────────────────────────────────────── (INFER/RECORD-TYPE-EMPTY)
+        //  Γ ⊢ Record {} ⇒ Type ⤳ Record {}
+        Term::RecordTypeEmpty(_) => Ok((
+            Value::RecordTypeEmpty.into(),
+            Value::Universe(Level::ZERO).into(),
+        )),
+
+        // Without an annotation we can only infer a non-dependent record type
+        //
+        //  1.  Γ ⊢ e₁ ⇒ τ₁ ⤳ v₁
+        //  2.  Γ ⊢ e₂ ⇒ τ₂ ⤳ v₂
+        // ──────────────────────────────────────────────────── (INFER/RECORD)
+        //      Γ ⊢ record { l = e₁; e₂ } ⇒ Record { l:τ₁; τ₂ } ⤳ record { l = v₁; v₂ }
+        Term::Record(_, ref label, ref value, ref rest) => {
+            let (elab_value, value_ty) = infer(context, value)?; // 1.
+            let (elab_rest, rest_ty) = infer(context, rest)?; // 2.
+
+            let param = Named::new(Name::fresh(Some(label.as_str())), value_ty);
+            let record_ty = ValueRecordType::bind(label.clone(), param, rest_ty);
+
+            Ok((
+                Value::Record(label.clone(), elab_value, elab_rest).into(),
+                Value::RecordType(record_ty).into(),
+            ))
+        },
+
+        // ──────────────────────────────────────────────────── (INFER/RECORD-EMPTY)
+        //  Γ ⊢ record {} ⇒ Record {} ⤳ record {}
+        Term::RecordEmpty(_) => Ok((Value::RecordEmpty.into(), Value::RecordTypeEmpty.into())),
+
+        // The types of the fields that precede the projected field are
+        // instantiated with the projections of those fields from the record
+        //
+        //  1.  Γ ⊢ e ⇒ Record { l₁:τ₁; ...; l:τ; ... } ⤳ v
+        //  2.  Γ ⊢ e ⇓ v'
+        // ──────────────────────────────────────────────────── (INFER/PROJ)
+        //      Γ ⊢ e.l ⇒ τ[l₁↦v'.l₁, ...] ⤳ v.l
+        Term::Proj(_, ref record, ref label) => {
+            let (elab_record, record_ty) = infer(context, record)?; // 1.
+            let simp_record = normalize(context, record)?; // 2.
+
+            let mut current_ty = record_ty.clone();
+            loop {
+                let next_ty = match *current_ty.inner {
+                    Value::RecordType(ref field_ty) if field_ty.label == *label => {
+                        let field_ty = field_ty.unsafe_param.inner.clone();
+                        return Ok((project(&elab_record, label), field_ty));
+                    },
+                    Value::RecordType(ref field_ty) => {
+                        field_ty.instantiate(&project(&simp_record, &field_ty.label))
+                    },
+                    _ => {
+                        // Qualified names are projections from imported
+                        // modules, so we report them as missing definitions
+                        let module = match *record.inner {
+                            Term::Var(_, Var::Free(ref name)) => context.lookup_module(name),
+                            _ => None,
+                        };
+
+                        return Err(match module {
+                            Some(module) => TypeError::UndefinedImportedName {
+                                span: term.span(),
+                                module: module.to_owned(),
+                                name: label.clone(),
+                            },
+                            None => TypeError::FieldNotFound {
+                                span: term.span(),
+                                expected_label: label.clone(),
+                                found: record_ty.clone(),
+                            },
+                        });
+                    },
+                };
+                current_ty = next_ty;
+            }
+        },
+
+        // ─────────────────────────────── (INFER/CONST-BOOL-TYPE)
+        //  Γ ⊢ Bool ⇒ Type ⤳ Bool
+        //
+        // ─────────────────────────────── (INFER/CONST-BOOL)
+        //  Γ ⊢ true ⇒ Bool ⤳ true
+        //  Γ ⊢ false ⇒ Bool ⤳ false
+        //
+        // ─────────────────────────────── (INFER/CONST-INT-TYPE)
+        //  Γ ⊢ Int ⇒ Type ⤳ Int
+        //
+        // ─────────────────────────────── (INFER/CONST-INT)
+        //  Γ ⊢ n ⇒ Int ⤳ n
+        //
+        // ─────────────────────────────── (INFER/CONST-STRING-TYPE)
+        //  Γ ⊢ String ⇒ Type ⤳ String
+        //
+        // ─────────────────────────────── (INFER/CONST-STRING)
+        //  Γ ⊢ "s" ⇒ String ⤳ "s"
+        Term::Constant(_, ref constant) => {
+            let ty = match *constant {
+                Constant::BoolType | Constant::IntType | Constant::StringType => {
+                    Value::Universe(Level::ZERO)
+                },
+                Constant::Bool(_) => Value::Constant(Constant::BoolType),
+                Constant::Int(_) => Value::Constant(Constant::IntType),
+                Constant::String(_) => Value::Constant(Constant::StringType),
+            };
+
+            Ok((Value::Constant(constant.clone()).into(), ty.into()))
+        },
+
+        // The type of the first branch is used as the expected type of the
+        // second branch
+        //
+        //  1.  Γ ⊢ e₁ ⇐ Bool ⤳ v₁
+        //  2.  Γ ⊢ e₂ ⇒ τ ⤳ v₂
+        //  3.  Γ ⊢ e₃ ⇐ τ ⤳ v₃
+        // ───────────────────────────────────────────────────────────── (INFER/IF)
+        //      Γ ⊢ if e₁ then e₂ else e₃ ⇒ τ ⤳ if v₁ then v₂ else v₃
+        Term::If(_, ref cond, ref if_true, ref if_false) => {
+            let bool_ty = Value::Constant(Constant::BoolType).into();
+            let elab_cond = check(context, cond, &bool_ty)?; // 1.
+            let (elab_if_true, ty) = infer(context, if_true)?; // 2.
+            let elab_if_false = check(context, if_false, &ty)?; // 3.
+
+            Ok((select_branch(&elab_cond, &elab_if_true, &elab_if_false), ty))
+        },
+
+        // The type of the first clause is used as the expected type of the
+        // clauses that follow it
+        //
+        //  1.  Γ ⊢ case e of { pᵢ => eᵢ; ... } ⤳ v, Γ,Δᵢ ⊢ eᵢ
+        //  2.  Γ,Δ₁ ⊢ e₁ ⇒ τ ⤳ v₁
+        //  3.  Γ,Δᵢ ⊢ eᵢ ⇐ τ ⤳ vᵢ    for each remaining clause
+        // ─────────────────────────────────────────────────────────── (INFER/CASE)
+        //      Γ ⊢ case e of { pᵢ => eᵢ; ... } ⇒ τ ⤳ case v of { pᵢ => vᵢ; ... }
+        Term::Case(_, ref scrutinee, ref clauses) => {
+            let (elab_scrutinee, clauses) = check_case(context, term.span(), scrutinee, clauses)?; // 1.
+
+            // Exhaustive case expressions always have at least one clause
+            let (_, ref first_context, ref first_body) = clauses[0];
+            let (elab_first_body, ty) = infer(first_context, first_body)?; // 2.
+            let mut elab_bodies = vec![elab_first_body];
+            for &(_, ref body_context, ref body) in &clauses[1..] {
+                elab_bodies.push(check(body_context, body, &ty)?); // 3.
+            }
+
+            let elab_term =
+                select_elab_clause(term.span(), &elab_scrutinee, &clauses, &elab_bodies)?;
+            Ok((elab_term, ty))
+        },
+
+        // There is nothing to infer the type of a hole from
+        Term::Hole(meta) => Err(TypeError::HoleNeedsAnnotation { span: meta.span }),
+    }
+}
+
+/// Find a name in scope that is similar to an undefined name, to suggest in
+/// its place
+///
+/// Longer names are allowed more edits, up to a maximum of two, so that short
+/// names aren't matched with unrelated names of a similar length.
+fn suggest_name(context: &Context, name: &Name) -> Option<String> {
+    use std::cmp;
+
+    let lookup = match *name {
+        Name::User(ref name) => name,
+        Name::Gen(_) => return None,
+    };
+    let candidates = context.names().filter_map(|name| match *name {
+        Name::User(ref name) => Some(name.as_str()),
+        Name::Gen(_) => None,
+    });
+    let max_dist = cmp::min(2, cmp::max(1, lookup.chars().count() / 3));
+
+    lev_distance::find_best_match(lookup, candidates, max_dist).map(String::from)
+}
+
+/// Ensures that the given term is a universe, returning the level of that
+/// universe and its elaborated form.
+///
+/// ```text
+/// Γ ⊢ ρ ⇒ Typeᵢ ⤳ τ
+/// ```
+fn infer_universe(context: &Context, term: &RcTerm) -> Result<(RcValue, Level), TypeError> {
+    let (elab, ty) = infer(context, term)?;
+    match *ty.inner {
+        Value::Universe(ref level) => Ok((elab, level.clone())),
+        _ => Err(TypeError::ExpectedUniverse {
+            span: term.span(),
+            found: elab,
+            found_ty: ty,
+        }),
     }
 }
@@ -15,6 +15,8 @@ fn parse(src: &str) -> RcTerm {
 }
 
 mod normalize {
+    use syntax::core::{Origin, PrimFn, PrimRegistry, UnfoldPolicy};
+
     use super::*;
 
     #[test]
@@ -42,6 +44,174 @@ mod normalize {
         );
     }
 
+    #[test]
+    fn var_transparent() {
+        let x = Name::user("x");
+        let context = Context::new().extend(
+            x.clone(),
+            Binder::Let(
+                Value::Universe(Level::ZERO).into(),
+                Value::Universe(Level::ZERO.succ()).into(),
+                Transparency::Transparent,
+            ),
+        );
+
+        assert_eq!(
+            normalize(&context, &parse(r"x")).unwrap(),
+            Value::Universe(Level::ZERO).into(),
+        );
+    }
+
+    #[test]
+    fn var_opaque() {
+        let x = Name::user("x");
+        let context = Context::new().extend(
+            x.clone(),
+            Binder::Let(
+                Value::Universe(Level::ZERO).into(),
+                Value::Universe(Level::ZERO.succ()).into(),
+                Transparency::Opaque,
+            ),
+        );
+
+        assert_eq!(
+            normalize(&context, &parse(r"x")).unwrap(),
+            Value::Var(Var::Free(x)).into(),
+        );
+    }
+
+    /// A context with a primitive that computes the successor of a universe,
+    /// and a variable, `a`, that it can't be reduced for
+    fn prim_context() -> Context {
+        let ann = normalize(&Context::new(), &parse(r"(Type 1) -> Type 2")).unwrap();
+        let mut prims = PrimRegistry::new();
+        prims.register(PrimFn::new("universe-succ", 1, ann, |args| {
+            match *args[0].inner {
+                Value::Universe(ref level) => Some(Value::Universe(level.succ()).into()),
+                _ => None,
+            }
+        }));
+
+        Context::new()
+            .with_prims(prims)
+            .declare(Name::user("a"), Value::Universe(Level::Const(1)).into())
+    }
+
+    #[test]
+    fn prim_fold() {
+        let context = prim_context();
+        let term = parse(r"universe-succ Type");
+
+        assert_eq!(
+            infer(&context, &term).unwrap().1,
+            Value::Universe(Level::Const(2)).into(),
+        );
+        assert_eq!(
+            normalize(&context, &term).unwrap(),
+            Value::Universe(Level::Const(1)).into(),
+        );
+    }
+
+    #[test]
+    fn prim_neutral() {
+        let context = prim_context();
+        let normalize_str = |src| normalize(&context, &parse(src)).unwrap().to_string();
+
+        assert_eq!(normalize_str(r"universe-succ a"), "universe-succ a");
+        assert_eq!(normalize_str(r"universe-succ"), "universe-succ");
+    }
+
+    #[test]
+    fn prim_shadowed() {
+        let context = prim_context().declare(
+            Name::user("universe-succ"),
+            Value::Universe(Level::Const(1)).into(),
+        );
+
+        assert!(infer(&context, &parse(r"universe-succ Type")).is_err());
+    }
+
+    fn unfold_policy_context(unfold_policy: UnfoldPolicy) -> Context {
+        let level0 = Level::ZERO;
+        let level1 = level0.succ();
+        let level2 = level1.succ();
+
+        Context::new()
+            .with_unfold_policy(unfold_policy)
+            .extend(Name::user("f"), Binder::Lam(None))
+            .extend_with_origin(
+                Name::user("p"),
+                Binder::Let(
+                    Value::Universe(level0).into(),
+                    Value::Universe(level1.clone()).into(),
+                    Transparency::Transparent,
+                ),
+                Origin::Prelude,
+            )
+            .extend(
+                Name::user("u"),
+                Binder::Let(
+                    Value::Universe(level1).into(),
+                    Value::Universe(level2).into(),
+                    Transparency::Transparent,
+                ),
+            )
+    }
+
+    #[test]
+    fn step_limit() {
+        let term = parse(r"(\a : Type 1 => a) ((\b : Type 1 => b) Type)");
+        let normalize_with = |step_limit| {
+            let context = Context::new().with_step_limit(step_limit);
+            normalize(&context, &term)
+        };
+
+        assert_eq!(normalize_with(None), Ok(Value::Universe(Level::ZERO).into()));
+        assert_eq!(normalize_with(Some(2)), Ok(Value::Universe(Level::ZERO).into()));
+        match normalize_with(Some(1)) {
+            Err(InternalError::NormalizationLimitExceeded { steps, .. }) => assert_eq!(steps, 1),
+            result => panic!("expected the step limit to be exceeded, found {:?}", result),
+        }
+    }
+
+    #[test]
+    fn definitions_are_normalized_once() {
+        let x = Name::user("x");
+        let value = normalize(&Context::new(), &parse(r"(\a : Type 2 => a) (Type 1)")).unwrap();
+        let ty = RcValue::from(Value::Universe(Level::Const(2)));
+        let context = Context::new()
+            .extend(x.clone(), Binder::Let(value, ty.clone(), Transparency::Transparent))
+            .with_step_limit(Some(0));
+
+        // The body of `x` was reduced when it was defined, so unfolding it
+        // again doesn't take any steps
+        for _ in 0..2 {
+            assert_eq!(
+                normalize(&context, &parse(r"x")),
+                Ok(Value::Universe(Level::Const(1)).into()),
+            );
+        }
+
+        // Redefining `x` shadows the old normal form
+        let value = RcValue::from(Value::Universe(Level::ZERO));
+        let context = context.extend(x, Binder::Let(value, ty, Transparency::Transparent));
+        assert_eq!(normalize(&context, &parse(r"x")), Ok(Value::Universe(Level::ZERO).into()));
+    }
+
+    #[test]
+    fn unfold_policy() {
+        let term = parse(r"f p u");
+        let normalize_with = |unfold_policy| {
+            let context = unfold_policy_context(unfold_policy);
+            normalize(&context, &term).unwrap().to_string()
+        };
+
+        assert_eq!(normalize_with(UnfoldPolicy::All), "f Type (Type 1)");
+        assert_eq!(normalize_with(UnfoldPolicy::Prelude), "f Type u");
+        assert_eq!(normalize_with(UnfoldPolicy::User), "f p (Type 1)");
+        assert_eq!(normalize_with(UnfoldPolicy::None), "f p u");
+    }
+
     #[test]
     fn lam() {
         let context = Context::new();
@@ -206,379 +376,2338 @@ mod normalize {
             normalize(&context, &parse(expected_expr)).unwrap(),
         );
     }
-}
-
-mod infer {
-    use super::*;
 
     #[test]
-    fn free() {
+    fn let_() {
         let context = Context::new();
 
-        let given_expr = r"x";
-        let x = Name::user("x");
+        let given_expr = r"let x = Type in x";
+        let expected_expr = r"Type";
 
         assert_eq!(
-            infer(&context, &parse(given_expr)),
-            Err(TypeError::UndefinedName {
-                var_span: ByteSpan::new(ByteIndex(1), ByteIndex(2)),
-                name: x,
-            }),
+            normalize(&context, &parse(given_expr)).unwrap(),
+            normalize(&context, &parse(expected_expr)).unwrap(),
         );
     }
 
     #[test]
-    fn ty() {
+    fn let_shadowed() {
         let context = Context::new();
 
-        let expected_ty = r"Type 1";
-        let given_expr = r"Type";
+        let given_expr = r"let x = Type; x = x -> x in x";
+        let expected_expr = r"Type -> Type";
 
         assert_eq!(
-            infer(&context, &parse(given_expr)).unwrap().1,
-            normalize(&context, &parse(expected_ty)).unwrap(),
+            normalize(&context, &parse(given_expr)).unwrap(),
+            normalize(&context, &parse(expected_expr)).unwrap(),
         );
     }
 
     #[test]
-    fn ty_levels() {
+    fn let_refers_to_earlier_binding() {
         let context = Context::new();
 
-        let expected_ty = r"Type 1";
-        let given_expr = r"Type 0 : Type 1 : Type 2 : Type 3"; //... Type ∞       ...+:｡(ﾉ･ω･)ﾉﾞ
+        let given_expr = r"let a = Type; id = \(x : a) => x in id";
+        let expected_expr = r"\(x : Type) => x";
 
         assert_eq!(
-            infer(&context, &parse(given_expr)).unwrap().1,
-            normalize(&context, &parse(expected_ty)).unwrap(),
+            normalize(&context, &parse(given_expr)).unwrap(),
+            normalize(&context, &parse(expected_expr)).unwrap(),
         );
     }
 
     #[test]
-    fn ann_ty_id() {
+    fn fst_pair() {
         let context = Context::new();
 
-        let expected_ty = r"Type -> Type";
-        let given_expr = r"(\a => a) : Type -> Type";
+        let given_expr = r"fst (Type, Type 1)";
+        let expected_expr = r"Type";
 
         assert_eq!(
-            infer(&context, &parse(given_expr)).unwrap().1,
-            normalize(&context, &parse(expected_ty)).unwrap(),
+            normalize(&context, &parse(given_expr)).unwrap(),
+            normalize(&context, &parse(expected_expr)).unwrap(),
         );
     }
 
     #[test]
-    fn ann_arrow_ty_id() {
+    fn snd_pair() {
         let context = Context::new();
 
-        let expected_ty = r"(Type -> Type) -> (Type -> Type)";
-        let given_expr = r"(\a => a) : (Type -> Type) -> (Type -> Type)";
+        let given_expr = r"snd (Type, Type 1)";
+        let expected_expr = r"Type 1";
 
         assert_eq!(
-            infer(&context, &parse(given_expr)).unwrap().1,
-            normalize(&context, &parse(expected_ty)).unwrap(),
+            normalize(&context, &parse(given_expr)).unwrap(),
+            normalize(&context, &parse(expected_expr)).unwrap(),
         );
     }
 
     #[test]
-    fn ann_id_as_ty() {
+    fn fst_app() {
         let context = Context::new();
 
-        let given_expr = r"(\a => a) : Type";
+        let given_expr = r"(\p : (a : Type 1) * Type 2 => fst p) (Type, Type 1)";
+        let expected_expr = r"Type";
 
-        match infer(&context, &parse(given_expr)) {
-            Err(TypeError::UnexpectedFunction { .. }) => {},
-            other => panic!("unexpected result: {:#?}", other),
-        }
+        assert_eq!(
+            normalize(&context, &parse(given_expr)).unwrap(),
+            normalize(&context, &parse(expected_expr)).unwrap(),
+        );
     }
 
     #[test]
-    fn app() {
+    fn snd_app() {
         let context = Context::new();
 
-        let expected_ty = r"Type 1";
-        let given_expr = r"(\a : Type 1 => a) Type";
+        let given_expr = r"(\p : (a : Type 1) * Type 2 => snd p) (Type, Type 1)";
+        let expected_expr = r"Type 1";
 
         assert_eq!(
-            infer(&context, &parse(given_expr)).unwrap().1,
-            normalize(&context, &parse(expected_ty)).unwrap(),
+            normalize(&context, &parse(given_expr)).unwrap(),
+            normalize(&context, &parse(expected_expr)).unwrap(),
         );
     }
 
     #[test]
-    fn app_ty() {
-        let context = Context::new();
-
-        let given_expr = r"Type Type";
+    fn fst_neutral() {
+        let x = Name::user("x");
+        let ty = Value::Universe(Level::ZERO).into();
+        let context = Context::new().extend(x.clone(), Binder::Pi(ty));
 
         assert_eq!(
-            infer(&context, &parse(given_expr)),
-            Err(TypeError::NotAFunctionType {
-                fn_span: ByteSpan::new(ByteIndex(1), ByteIndex(5)),
-                arg_span: ByteSpan::new(ByteIndex(6), ByteIndex(10)),
-                found: Value::Universe(Level::ZERO.succ()).into(),
-            }),
-        )
+            normalize(&context, &parse(r"fst x")).unwrap(),
+            Value::Fst(Value::Var(Var::Free(x)).into()).into(),
+        );
     }
 
     #[test]
-    fn lam() {
+    fn proj_record() {
         let context = Context::new();
 
-        let expected_ty = r"(a : Type) -> Type";
-        let given_expr = r"\a : Type => a";
+        let given_expr = r"(record { a = Type; b = Type 1 }).b";
+        let expected_expr = r"Type 1";
 
         assert_eq!(
-            infer(&context, &parse(given_expr)).unwrap().1,
-            normalize(&context, &parse(expected_ty)).unwrap(),
+            normalize(&context, &parse(given_expr)).unwrap(),
+            normalize(&context, &parse(expected_expr)).unwrap(),
         );
     }
 
     #[test]
-    fn pi() {
+    fn proj_app() {
         let context = Context::new();
 
-        let expected_ty = r"Type 1";
-        let given_expr = r"(a : Type) -> a";
+        let given_expr = r"(\r : Record { x : Type 1 } => r.x) (record { x = Type })";
+        let expected_expr = r"Type";
 
         assert_eq!(
-            infer(&context, &parse(given_expr)).unwrap().1,
-            normalize(&context, &parse(expected_ty)).unwrap(),
+            normalize(&context, &parse(given_expr)).unwrap(),
+            normalize(&context, &parse(expected_expr)).unwrap(),
         );
     }
 
     #[test]
-    fn id() {
-        let context = Context::new();
+    fn if_true() {
+        let context = Context::default();
 
-        let expected_ty = r"(a : Type) -> a -> a";
-        let given_expr = r"\(a : Type) (x : a) => x";
+        let given_expr = r"if true then Type else Type 1";
+        let expected_expr = r"Type";
 
         assert_eq!(
-            infer(&context, &parse(given_expr)).unwrap().1,
-            normalize(&context, &parse(expected_ty)).unwrap(),
+            normalize(&context, &parse(given_expr)).unwrap(),
+            normalize(&context, &parse(expected_expr)).unwrap(),
         );
     }
 
     #[test]
-    fn id_ann() {
-        let context = Context::new();
+    fn if_false() {
+        let context = Context::default();
 
-        let expected_ty = r"(a : Type) -> a -> a";
-        let given_expr = r"(\a (x : a) => x) : (A : Type) -> A -> A";
+        let given_expr = r"if false then Type else Type 1";
+        let expected_expr = r"Type 1";
 
         assert_eq!(
-            infer(&context, &parse(given_expr)).unwrap().1,
-            normalize(&context, &parse(expected_ty)).unwrap(),
+            normalize(&context, &parse(given_expr)).unwrap(),
+            normalize(&context, &parse(expected_expr)).unwrap(),
         );
     }
 
-    // Passing `Type` to the polymorphic identity function should yeild the type
-    // identity function
     #[test]
-    fn id_app_ty() {
-        let context = Context::new();
+    fn if_app() {
+        let context = Context::default();
 
-        let expected_expr = r"Type -> Type";
-        let given_expr = r"(\(a : Type 1) (x : a) => x) Type";
+        let given_expr = r"(\b : Bool => if b then Type else Type 1) true";
+        let expected_expr = r"Type";
 
         assert_eq!(
-            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(given_expr)).unwrap(),
             normalize(&context, &parse(expected_expr)).unwrap(),
         );
     }
 
-    // Passing `Type` to the `Type` identity function should yeild `Type`
     #[test]
-    fn id_app_ty_ty() {
-        let context = Context::new();
+    fn if_selected_branch_only() {
+        // Evaluating the other branch would take a reduction step
+        let context = Context::default().with_step_limit(Some(0));
 
-        let expected_expr = r"Type 1";
-        let given_expr = r"(\(a : Type 2) (x : a) => x) (Type 1) Type";
+        let given_expr = r"if true then Type else (\a : Type 1 => a) Type";
+        let expected_expr = r"Type";
 
         assert_eq!(
-            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(given_expr)).unwrap(),
             normalize(&context, &parse(expected_expr)).unwrap(),
         );
     }
 
     #[test]
-    fn id_app_ty_arr_ty() {
-        let context = Context::new();
+    fn case_bool() {
+        let context = Context::default();
 
-        let expected_ty = r"Type 1";
-        let given_expr = r"(\(a : Type 2) (x : a) => x) (Type 1) (Type -> Type)";
+        let given_expr = r"case false of { true => Type; false => Type 1 }";
+        let expected_expr = r"Type 1";
 
         assert_eq!(
-            infer(&context, &parse(given_expr)).unwrap().1,
-            normalize(&context, &parse(expected_ty)).unwrap(),
+            normalize(&context, &parse(given_expr)).unwrap(),
+            normalize(&context, &parse(expected_expr)).unwrap(),
         );
     }
 
     #[test]
-    fn id_app_arr_pi_ty() {
-        let context = Context::new();
+    fn case_pair() {
+        let context = Context::default();
 
-        let expected_ty = r"Type -> Type";
-        let given_expr = r"(\(a : Type 1) (x : a) => x) (Type -> Type) (\x => x)";
+        let given_expr = r"case (Type, Type 1) of { (x, y) => (y, x) }";
+        let expected_expr = r"(Type 1, Type)";
 
         assert_eq!(
-            infer(&context, &parse(given_expr)).unwrap().1,
-            normalize(&context, &parse(expected_ty)).unwrap(),
+            normalize(&context, &parse(given_expr)).unwrap(),
+            normalize(&context, &parse(expected_expr)).unwrap(),
         );
     }
 
     #[test]
-    fn apply() {
-        let context = Context::new();
-
-        let expected_ty = r"(a b : Type) -> (a -> b) -> a -> b";
-        let given_expr = r"\(a b : Type) (f : a -> b) (x : a) => f x";
+    fn int_add() {
+        let context = Context::default();
 
         assert_eq!(
-            infer(&context, &parse(given_expr)).unwrap().1,
-            normalize(&context, &parse(expected_ty)).unwrap(),
+            normalize(&context, &parse(r"add 2 3")).unwrap(),
+            Value::Constant(Constant::Int(5)).into(),
         );
     }
 
     #[test]
-    fn const_() {
-        let context = Context::new();
+    fn int_mul_add() {
+        let context = Context::default();
 
-        let expected_ty = r"(a b : Type) -> a -> b -> a";
-        let given_expr = r"\(a b : Type) (x : a) (y : b) => x";
+        assert_eq!(
+            normalize(&context, &parse(r"mul (add 1 2) 4")).unwrap(),
+            Value::Constant(Constant::Int(12)).into(),
+        );
+    }
+
+    #[test]
+    fn int_add_app() {
+        let context = Context::default();
 
         assert_eq!(
-            infer(&context, &parse(given_expr)).unwrap().1,
-            normalize(&context, &parse(expected_ty)).unwrap(),
+            normalize(&context, &parse(r"(\x : Int => add x 1) 2")).unwrap(),
+            Value::Constant(Constant::Int(3)).into(),
         );
     }
 
     #[test]
-    fn const_flipped() {
+    fn int_add_neutral() {
+        let x = Name::user("x");
+        let int_ty = Value::Constant(Constant::IntType).into();
+        let context = Context::default().declare(x.clone(), int_ty);
+
+        match *normalize(&context, &parse(r"add x 3")).unwrap().inner {
+            Value::App(_, ref arg) => assert_eq!(*arg, Value::Constant(Constant::Int(3)).into()),
+            ref value => panic!("expected a stuck application, found {}", value),
+        }
+    }
+}
+
+mod whnf {
+    use super::*;
+
+    /// Convert a term to a value without reducing it
+    fn suspend(context: &Context, src: &str) -> RcValue {
+        suspend_steps(context, &parse(src), &mut Steps::new(context)).unwrap()
+    }
+
+    #[test]
+    fn lam() {
         let context = Context::new();
 
-        let expected_ty = r"(a b : Type) -> a -> b -> b";
-        let given_expr = r"\(a b : Type) (x : a) (y : b) => y";
+        let given_expr = r"\a : Type 1 => (\b : Type 1 => b) a";
 
-        assert_eq!(
-            infer(&context, &parse(given_expr)).unwrap().1,
-            normalize(&context, &parse(expected_ty)).unwrap(),
-        );
+        let value = whnf(&context, &parse(given_expr)).unwrap();
+        assert_eq!(value, suspend(&context, given_expr));
+        assert_ne!(value, normalize(&context, &parse(given_expr)).unwrap());
     }
 
     #[test]
-    fn flip() {
+    fn app_stops_at_lam() {
         let context = Context::new();
 
-        let expected_ty = r"(a b c : Type) -> (a -> b -> c) -> (b -> a -> c)";
-        let given_expr = r"\(a b c : Type) (f : a -> b -> c) (y : b) (x : a) => f x y";
+        let given_expr = r"(\a : Type 1 => \x : a => (\y : a => y) x) Type";
+        let expected_expr = r"\x : Type => (\y : Type => y) x";
 
         assert_eq!(
-            infer(&context, &parse(given_expr)).unwrap().1,
-            normalize(&context, &parse(expected_ty)).unwrap(),
+            whnf(&context, &parse(given_expr)).unwrap(),
+            suspend(&context, expected_expr),
         );
     }
 
     #[test]
-    fn compose() {
-        let context = Context::new();
+    fn app_neutral() {
+        let context = Context::new().extend(Name::user("f"), Binder::Lam(None));
 
-        let expected_ty = r"(a b c : Type) -> (b -> c) -> (a -> b) -> (a -> c)";
-        let given_expr = r"\(a b c : Type) (f : b -> c) (g : a -> b) (x : a) => f (g x)";
+        let given_expr = r"f ((\a : Type 1 => a) Type)";
 
         assert_eq!(
-            infer(&context, &parse(given_expr)).unwrap().1,
-            normalize(&context, &parse(expected_ty)).unwrap(),
+            whnf(&context, &parse(given_expr)).unwrap(),
+            suspend(&context, given_expr),
         );
     }
 
-    mod church_encodings {
-        use super::*;
+    #[test]
+    fn let_var() {
+        let context = Context::new().extend(
+            Name::user("x"),
+            Binder::Let(
+                Value::Universe(Level::ZERO).into(),
+                Value::Universe(Level::Const(1)).into(),
+                Transparency::Transparent,
+            ),
+        );
 
-        #[test]
-        fn and() {
-            let context = Context::new();
+        assert_eq!(whnf(&context, &parse(r"x")).unwrap(), suspend(&context, r"Type"));
+    }
+
+    #[test]
+    fn self_application_step_limit() {
+        let context = Context::new().with_step_limit(Some(100));
+
+        let given_expr = r"(\x : Type => x x) (\x : Type => x x)";
+
+        match whnf(&context, &parse(given_expr)) {
+            Err(InternalError::NormalizationLimitExceeded { steps, .. }) => assert_eq!(steps, 100),
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(value) => panic!("expected the step limit to be exceeded, found {}", value),
+        }
+    }
+
+    #[test]
+    fn if_true() {
+        let context = Context::default();
+
+        let given_expr = r"if true then (\a : Type 1 => a) Type else Type 1";
+
+        assert_eq!(whnf(&context, &parse(given_expr)).unwrap(), suspend(&context, r"Type"));
+    }
+}
+
+mod infer {
+    use super::*;
+
+    /// Wrap a term in a number of applications of the identity function,
+    /// each of which takes a reduction step to normalize away
+    fn nested_ids(depth: usize) -> String {
+        (0..depth).fold(String::from("Type"), |term, _| format!(r"(\a : Type 1 => a) ({})", term))
+    }
+
+    #[test]
+    fn app_does_not_normalize_big_args() {
+        let context = Context::new().with_step_limit(Some(10));
+        let term = parse(&nested_ids(100));
+
+        match normalize(&context, &term) {
+            Err(InternalError::NormalizationLimitExceeded { .. }) => {},
+            result => panic!("expected the step limit to be exceeded, found {:?}", result),
+        }
+        // The type of the application doesn't depend on its argument, so the
+        // argument should not be normalized during inference
+        let (_, ty) = infer(&context, &term).unwrap();
+
+        assert_eq!(ty, Value::Universe(Level::ZERO.succ()).into());
+    }
+
+    #[test]
+    fn long_app_spine() {
+        let context = Context::new();
+        let id_ty = parse(r"(a : Type) -> a -> a");
+        let id_ty_ty = infer(&context, &id_ty).unwrap().1;
+        let id_ty = normalize(&context, &id_ty).unwrap();
+        let context = context
+            .extend(
+                Name::user("T"),
+                Binder::Let(id_ty.clone(), id_ty_ty, Transparency::Transparent),
+            )
+            .declare(Name::user("id"), id_ty.clone());
+
+        // `id T id : T`, so we can keep on applying `T id` forever!
+        let given_expr = format!("id{}", " T id".repeat(2000));
+        let given_expr = parse(&given_expr);
+
+        assert_eq!(infer(&context, &given_expr).unwrap().1, id_ty);
+        assert!(normalize(&context, &given_expr).is_ok());
+    }
+
+    #[test]
+    fn long_app_spine_not_a_function() {
+        let context = Context::new();
+
+        let given_expr = format!("Type{}", " Type".repeat(5000));
+
+        match infer(&context, &parse(&given_expr)) {
+            Err(TypeError::NotAFunctionType { fn_span, .. }) => {
+                assert_eq!(fn_span, ByteSpan::new(ByteIndex(1), ByteIndex(5)))
+            },
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok((_, ty)) => panic!("expected an error, found type {}", ty),
+        }
+    }
+
+    #[test]
+    fn free() {
+        let context = Context::new();
+
+        let given_expr = r"x";
+        let x = Name::user("x");
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)),
+            Err(TypeError::UndefinedName {
+                var_span: ByteSpan::new(ByteIndex(1), ByteIndex(2)),
+                name: x,
+                suggestion: None,
+            }),
+        );
+    }
+
+    #[test]
+    fn free_suggestion() {
+        let ty = Value::Universe(Level::ZERO).into();
+        let context = Context::new().extend(Name::user("elems"), Binder::Pi(ty));
+
+        match infer(&context, &parse(r"elem")) {
+            Err(TypeError::UndefinedName { ref suggestion, .. }) => {
+                assert_eq!(*suggestion, Some(String::from("elems")));
+            },
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok((_, ty)) => panic!("expected an error, found type {}", ty),
+        }
+
+        match infer(&context, &parse(r"shape")) {
+            Err(TypeError::UndefinedName { ref suggestion, .. }) => assert_eq!(*suggestion, None),
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok((_, ty)) => panic!("expected an error, found type {}", ty),
+        }
+    }
+
+    #[test]
+    fn ty() {
+        let context = Context::new();
+
+        let expected_ty = r"Type 1";
+        let given_expr = r"Type";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn var_opaque() {
+        let x = Name::user("x");
+        let context = Context::new().extend(
+            x.clone(),
+            Binder::Let(
+                Value::Universe(Level::ZERO).into(),
+                Value::Universe(Level::ZERO.succ()).into(),
+                Transparency::Opaque,
+            ),
+        );
+
+        assert_eq!(
+            infer(&context, &parse(r"x")).unwrap(),
+            (
+                Value::Var(Var::Free(x)).into(),
+                Value::Universe(Level::ZERO.succ()).into(),
+            ),
+        );
+    }
+
+    #[test]
+    fn ty_levels() {
+        let context = Context::new();
+
+        let expected_ty = r"Type 1";
+        let given_expr = r"Type 0 : Type 1 : Type 2 : Type 3"; //... Type ∞       ...+:｡(ﾉ･ω･)ﾉﾞ
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn ann_not_a_type() {
+        let context = Context::new();
+
+        let given_expr = r"Type : (\a : Type => a)";
+        let err = infer(&context, &parse(given_expr)).unwrap_err();
+
+        match err {
+            TypeError::ExpectedUniverse { ref found_ty, .. } => assert_eq!(
+                *found_ty,
+                normalize(&context, &parse(r"(a : Type) -> Type")).unwrap(),
+            ),
+            ref err => panic!("expected a universe error, found {}", err),
+        }
+        // The name of the parameter in the type is freshly generated
+        let message = err.to_diagnostic().message;
+        assert!(message.starts_with("expected a type, but `\\"));
+        assert!(message.contains("has type `(a"));
+        assert!(message.ends_with(": Type) -> Type`"));
+    }
+
+    #[test]
+    fn ann_ty_id() {
+        let context = Context::new();
+
+        let expected_ty = r"Type -> Type";
+        let given_expr = r"(\a => a) : Type -> Type";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn ann_arrow_ty_id() {
+        let context = Context::new();
+
+        let expected_ty = r"(Type -> Type) -> (Type -> Type)";
+        let given_expr = r"(\a => a) : (Type -> Type) -> (Type -> Type)";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn ann_dependent_ty_id() {
+        let context = Context::new();
+
+        let expected_ty = r"(A : Type) -> Type";
+        let given_expr = r"(\x => x) : (A : Type) -> Type";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn ann_id_as_ty() {
+        let context = Context::new();
+
+        let given_expr = r"(\a => a) : Type";
+
+        match infer(&context, &parse(given_expr)) {
+            Err(TypeError::UnexpectedFunction { .. }) => {},
+            other => panic!("unexpected result: {:#?}", other),
+        }
+    }
+
+    #[test]
+    fn app() {
+        let context = Context::new();
+
+        let expected_ty = r"Type 1";
+        let given_expr = r"(\a : Type 1 => a) Type";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn app_ty() {
+        let context = Context::new();
+
+        let given_expr = r"Type Type";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)),
+            Err(TypeError::NotAFunctionType {
+                fn_span: ByteSpan::new(ByteIndex(1), ByteIndex(5)),
+                arg_span: ByteSpan::new(ByteIndex(6), ByteIndex(10)),
+                found: Value::Universe(Level::ZERO.succ()).into(),
+            }),
+        )
+    }
+
+    #[test]
+    fn lam() {
+        let context = Context::new();
+
+        let expected_ty = r"(a : Type) -> Type";
+        let given_expr = r"\a : Type => a";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn lam_param_needs_annotation() {
+        let context = Context::new();
+
+        let given_expr = r"\x => x";
+
+        match infer(&context, &parse(given_expr)) {
+            Err(TypeError::FunctionParamNeedsAnnotation { ref name, .. }) => {
+                assert_eq!(name.name(), Some("x"));
+            },
+            other => panic!("unexpected result: {:#?}", other),
+        }
+    }
+
+    #[test]
+    fn pi() {
+        let context = Context::new();
+
+        let expected_ty = r"Type 1";
+        let given_expr = r"(a : Type) -> a";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn id() {
+        let context = Context::new();
+
+        let expected_ty = r"(a : Type) -> a -> a";
+        let given_expr = r"\(a : Type) (x : a) => x";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn id_ann() {
+        let context = Context::new();
+
+        let expected_ty = r"(a : Type) -> a -> a";
+        let given_expr = r"(\a (x : a) => x) : (A : Type) -> A -> A";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    // Passing `Type` to the polymorphic identity function should yeild the type
+    // identity function
+    #[test]
+    fn id_app_ty() {
+        let context = Context::new();
+
+        let expected_expr = r"Type -> Type";
+        let given_expr = r"(\(a : Type 1) (x : a) => x) Type";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_expr)).unwrap(),
+        );
+    }
+
+    // Passing `Type` to the `Type` identity function should yeild `Type`
+    #[test]
+    fn id_app_ty_ty() {
+        let context = Context::new();
+
+        let expected_expr = r"Type 1";
+        let given_expr = r"(\(a : Type 2) (x : a) => x) (Type 1) Type";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_expr)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn id_app_ty_arr_ty() {
+        let context = Context::new();
+
+        let expected_ty = r"Type 1";
+        let given_expr = r"(\(a : Type 2) (x : a) => x) (Type 1) (Type -> Type)";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn id_app_arr_pi_ty() {
+        let context = Context::new();
+
+        let expected_ty = r"Type -> Type";
+        let given_expr = r"(\(a : Type 1) (x : a) => x) (Type -> Type) (\x => x)";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn apply() {
+        let context = Context::new();
+
+        let expected_ty = r"(a b : Type) -> (a -> b) -> a -> b";
+        let given_expr = r"\(a b : Type) (f : a -> b) (x : a) => f x";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn const_() {
+        let context = Context::new();
+
+        let expected_ty = r"(a b : Type) -> a -> b -> a";
+        let given_expr = r"\(a b : Type) (x : a) (y : b) => x";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn const_flipped() {
+        let context = Context::new();
+
+        let expected_ty = r"(a b : Type) -> a -> b -> b";
+        let given_expr = r"\(a b : Type) (x : a) (y : b) => y";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn flip() {
+        let context = Context::new();
+
+        let expected_ty = r"(a b c : Type) -> (a -> b -> c) -> (b -> a -> c)";
+        let given_expr = r"\(a b c : Type) (f : a -> b -> c) (y : b) (x : a) => f x y";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn compose() {
+        let context = Context::new();
+
+        let expected_ty = r"(a b c : Type) -> (b -> c) -> (a -> b) -> (a -> c)";
+        let given_expr = r"\(a b c : Type) (f : b -> c) (g : a -> b) (x : a) => f (g x)";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn let_() {
+        let context = Context::new();
+
+        let expected_ty = r"Type 1";
+        let given_expr = r"let x = Type in x";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn let_shadowed() {
+        let context = Context::new();
+
+        let expected_ty = r"Type 1";
+        let given_expr = r"let x = Type 1; x : x = Type -> Type in x";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn let_refers_to_earlier_binding() {
+        let context = Context::new();
+
+        let expected_ty = r"Type -> Type";
+        let given_expr = r"
+            let id : (a : Type) -> a -> a = \a x => x;
+                id-ty = id Type
+            in
+                id-ty
+        ";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn let_ann_mismatch() {
+        let context = Context::new();
+
+        let given_expr = r"let x : Type = Type in x";
+
+        assert!(infer(&context, &parse(given_expr)).is_err());
+    }
+
+    #[test]
+    fn pair_ann() {
+        let context = Context::new();
+
+        let expected_ty = r"(_ : Type 1) * Type 1";
+        let given_expr = r"(Type, Type) : (_ : Type 1) * Type 1";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn pair_ann_dependent() {
+        let context = Context::new();
+
+        let expected_ty = r"(a : Type 1) * a";
+        let given_expr = r"(Type, Type -> Type) : (a : Type 1) * a";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn pair_ann_dependent_mismatch() {
+        let context = Context::new();
+
+        let given_expr = r"(Type, Type 1) : (a : Type 1) * a";
+
+        assert!(infer(&context, &parse(given_expr)).is_err());
+    }
+
+    #[test]
+    fn pair() {
+        let context = Context::new();
+
+        let expected_ty = r"(_ : Type 1) * Type 2";
+        let given_expr = r"(Type, Type 1)";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn snd_dependent() {
+        let context = Context::new();
+
+        let expected_ty = r"Type";
+        let given_expr = r"let p : (a : Type 1) * a = (Type, Type -> Type) in snd p";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn record_ty() {
+        let context = Context::new();
+
+        let expected_ty = r"Type 1";
+        let given_expr = r"Record { t : Type; x : t }";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn record_dependent() {
+        let context = Context::new();
+
+        let expected_ty = r"Record { t : Type 1; x : t }";
+        let given_expr = r"record { t = Type; x = Type -> Type } : Record { t : Type 1; x : t }";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn record_dependent_mismatch() {
+        let context = Context::new();
+
+        let given_expr = r"record { t = Type; x = Type 1 } : Record { t : Type 1; x : t }";
+
+        assert!(infer(&context, &parse(given_expr)).is_err());
+    }
+
+    #[test]
+    fn record_ty_self_reference() {
+        let context = Context::new();
+
+        match infer(&context, &parse(r"Record { t : t }")) {
+            Err(TypeError::ForwardFieldReference { ref field, .. }) => assert_eq!(field, "t"),
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok((_, ty)) => panic!("expected an error, found type {}", ty),
+        }
+    }
+
+    #[test]
+    fn record_ty_forward_reference() {
+        let context = Context::new();
+
+        match infer(&context, &parse(r"Record { x : t; t : Type }")) {
+            Err(TypeError::ForwardFieldReference { ref field, .. }) => assert_eq!(field, "t"),
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok((_, ty)) => panic!("expected an error, found type {}", ty),
+        }
+    }
+
+    #[test]
+    fn record_ty_outer_reference() {
+        let context = Context::new();
+
+        let expected_ty = r"Type -> Type 1";
+        let given_expr = r"\t : Type => Record { x : t; t : Type }";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn proj_dependent() {
+        let context = Context::new();
+
+        let expected_ty = r"Type";
+        let given_expr = r"
+            let r : Record { t : Type 1; x : t } = record { t = Type; x = Type -> Type }
+            in r.x
+        ";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn proj_field_not_found() {
+        let context = Context::new();
+
+        match infer(&context, &parse(r"(record { a = Type }).b")) {
+            Err(TypeError::FieldNotFound { ref expected_label, .. }) => {
+                assert_eq!(expected_label, "b")
+            },
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok((_, ty)) => panic!("expected an error, found type {}", ty),
+        }
+    }
+
+    #[test]
+    fn if_() {
+        let context = Context::default();
+
+        let expected_ty = r"Bool -> Bool";
+        let given_expr = r"\b : Bool => if b then false else true";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn case_bool() {
+        let context = Context::default();
+
+        let expected_ty = r"Bool -> Bool";
+        let given_expr = r"\b : Bool => case b of { true => false; _ => true }";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn case_bool_app() {
+        let context = Context::default();
+
+        // The case expression is stuck on `b` until the lambda is applied
+        let given_expr = r"(\b : Bool => case b of { true => false; _ => true }) true";
+        let expected_expr = r"false";
+
+        let (elab_term, _) = infer(&context, &parse(given_expr)).unwrap();
+        assert_eq!(
+            normalize(&context, &RcTerm::from(&elab_term)).unwrap(),
+            normalize(&context, &parse(expected_expr)).unwrap(),
+        );
+        assert_eq!(
+            normalize(&context, &parse(given_expr)).unwrap(),
+            normalize(&context, &parse(expected_expr)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn case_pair() {
+        let context = Context::default();
+
+        let expected_ty = r"((_ : Bool) * Int) -> Int";
+        let given_expr = r"\p : (_ : Bool) * Int => case p of { (b, n) => if b then n else 0 }";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn case_non_exhaustive() {
+        let context = Context::default();
+
+        let given_expr = r"\p : (_ : Bool) * Bool => case p of { (true, _) => 1; (_, true) => 2 }";
+
+        match infer(&context, &parse(given_expr)) {
+            Err(TypeError::NonExhaustiveMatch { ref missing, .. }) => {
+                assert_eq!(missing.len(), 1);
+                assert_eq!(missing[0].to_string(), "(false, false)");
+            },
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok((_, ty)) => panic!("expected an error, found type {}", ty),
+        }
+    }
+
+    #[test]
+    fn int_literal() {
+        let context = Context::default();
+
+        assert_eq!(
+            infer(&context, &parse(r"42")).unwrap().1,
+            Value::Constant(Constant::IntType).into(),
+        );
+    }
+
+    #[test]
+    fn string_literal() {
+        let context = Context::default();
+
+        assert_eq!(
+            infer(&context, &parse(r#""hello\n""#)).unwrap().1,
+            normalize(&context, &parse(r"String")).unwrap(),
+        );
+    }
+
+    #[test]
+    fn int_add() {
+        let context = Context::default();
+
+        let expected_ty = r"Int -> Int";
+        let given_expr = r"\x : Int => add x 1";
+
+        assert_eq!(
+            infer(&context, &parse(given_expr)).unwrap().1,
+            normalize(&context, &parse(expected_ty)).unwrap(),
+        );
+    }
+
+    #[test]
+    fn if_branch_mismatch() {
+        let context = Context::default();
+
+        match infer(&context, &parse(r"if true then Type else true")) {
+            Err(TypeError::Mismatch { .. }) => {},
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok((_, ty)) => panic!("expected an error, found type {}", ty),
+        }
+    }
+
+    #[test]
+    fn fst_not_a_pair() {
+        let context = Context::new();
+
+        match infer(&context, &parse(r"fst Type")) {
+            Err(TypeError::NotAPairType { .. }) => {},
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok((_, ty)) => panic!("expected an error, found type {}", ty),
+        }
+    }
+
+    #[test]
+    fn hole() {
+        let context = Context::new();
+
+        match infer(&context, &parse(r"?")) {
+            Err(TypeError::HoleNeedsAnnotation { .. }) => {},
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok((_, ty)) => panic!("expected an error, found type {}", ty),
+        }
+    }
+
+    #[test]
+    fn hole_ann() {
+        let context = Context::new();
+
+        let expected_ty = normalize(&context, &parse(r"Type -> Type")).unwrap();
+
+        let (_, ty) = infer(&context, &parse(r"(? : Type -> Type)")).unwrap();
+        assert_eq!(ty, expected_ty);
+
+        match unsolved_holes(&context).as_slice() {
+            [TypeError::UnsolvedHole { ref expected, .. }] => {
+                assert_eq!(*expected, expected_ty);
+                match *expected.inner {
+                    Value::Pi(_) => {},
+                    ref found => panic!("expected a function type, found {}", found),
+                }
+            },
+            errors => panic!("expected a single hole, found {:?}", errors),
+        }
+        assert!(context.take_holes().is_empty());
+    }
+
+    #[test]
+    fn hole_context() {
+        let context = Context::new().with_hole_context_limit(2);
+        let ty = RcValue::from(Value::Universe(Level::ZERO));
+        let context = context
+            .declare(Name::user("a"), ty.clone())
+            .declare(Name::user("b"), ty.clone())
+            .declare(Name::user("c"), ty.clone());
+
+        check(&context, &parse(r"?"), &ty).unwrap();
+
+        // Only the most recently bound names are listed, up to the limit
+        let errors = unsolved_holes(&context);
+        match errors.as_slice() {
+            [TypeError::UnsolvedHole { ref bindings, .. }] => {
+                let names = bindings.iter().map(|&(ref name, _)| name.clone());
+                assert_eq!(names.collect::<Vec<_>>(), [Name::user("c"), Name::user("b")]);
+            },
+            errors => panic!("expected a single hole, found {:?}", errors),
+        }
+
+        let message = errors[0].to_diagnostic().message;
+        assert!(message.ends_with("\n\n  in scope:\n    c : Type\n    b : Type"));
+    }
+
+    mod church_encodings {
+        use super::*;
+
+        #[test]
+        fn and() {
+            let context = Context::new();
 
             let expected_ty = r"Type -> Type -> Type 1";
             let given_expr = r"\(p q : Type) => (c : Type) -> (p -> q -> c) -> c";
 
-            assert_eq!(
-                infer(&context, &parse(given_expr)).unwrap().1,
-                normalize(&context, &parse(expected_ty)).unwrap(),
-            );
+            assert_eq!(
+                infer(&context, &parse(given_expr)).unwrap().1,
+                normalize(&context, &parse(expected_ty)).unwrap(),
+            );
+        }
+
+        #[test]
+        fn and_intro() {
+            let context = Context::new();
+
+            let expected_ty = r"
+                (p q : Type) -> p -> q ->
+                    ((c : Type) -> (p -> q -> c) -> c)
+            ";
+            let given_expr = r"
+                \(p q : Type) (x : p) (y : q) =>
+                    \c : Type => \f : (p -> q -> c) => f x y
+            ";
+
+            assert_eq!(
+                infer(&context, &parse(given_expr)).unwrap().1,
+                normalize(&context, &parse(expected_ty)).unwrap(),
+            );
+        }
+
+        #[test]
+        fn and_proj_left() {
+            let context = Context::new();
+
+            let expected_ty = r"
+                (p q : Type) ->
+                    ((c : Type) -> (p -> q -> c) -> c) -> p
+            ";
+            let given_expr = r"
+                \(p q : Type) (pq : (c : Type) -> (p -> q -> c) -> c) =>
+                    pq p (\x y => x)
+            ";
+
+            assert_eq!(
+                infer(&context, &parse(given_expr)).unwrap().1,
+                normalize(&context, &parse(expected_ty)).unwrap(),
+            );
+        }
+
+        #[test]
+        fn and_proj_right() {
+            let context = Context::new();
+
+            let expected_ty = r"
+                (p q : Type) -> ((c : Type) -> (p -> q -> c) -> c) -> q
+            ";
+            let given_expr = r"
+                \(p q : Type) (pq : (c : Type) -> (p -> q -> c) -> c) =>
+                    pq q (\x y => y)
+            ";
+
+            assert_eq!(
+                infer(&context, &parse(given_expr)).unwrap().1,
+                normalize(&context, &parse(expected_ty)).unwrap(),
+            );
+        }
+    }
+
+    /// A context where `id` is polymorphic over the level of the type that
+    /// it is applied to, ie. `id : (A : Type^l) -> A -> A`
+    fn level_poly_id_context() -> Context {
+        let a = Name::user("A");
+        let a_var = RcValue::from(Value::Var(Var::Free(a.clone())));
+        let id_ty = ValuePi::bind(
+            Named::new(a.clone(), Value::Universe(Level::fresh()).into()),
+            Value::Pi(ValuePi::bind(Named::new(Name::user("x"), a_var.clone()), a_var)).into(),
+        );
+
+        Context::default().declare(Name::user("id"), Value::Pi(id_ty).into())
+    }
+
+    #[test]
+    fn level_poly_id() {
+        let context = level_poly_id_context();
+        let infer_ty = |src| infer(&context, &parse(src)).unwrap().1;
+
+        // Each use of `id` gets its own level variable, otherwise solving
+        // the first use at level 1 would prevent the second use at level 2
+        assert_eq!(infer_ty(r#"id String "hello""#), Value::Constant(Constant::StringType).into());
+        assert_eq!(infer_ty(r"id Type String"), Value::Universe(Level::ZERO).into());
+        assert_eq!(infer_ty(r"id (Type 1) Type"), Value::Universe(Level::Const(1)).into());
+    }
+
+    #[test]
+    fn level_mono_id() {
+        let context = Context::default();
+        let id_ty = normalize(&context, &parse(r"(A : Type) -> A -> A")).unwrap();
+        let context = context.declare(Name::user("id"), id_ty);
+
+        match infer(&context, &parse(r"id Type String")) {
+            Err(TypeError::UniverseMismatch {
+                found, expected, ..
+            }) => {
+                assert_eq!(found, Level::Const(1));
+                assert_eq!(expected, Level::ZERO);
+            },
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok((_, ty)) => panic!("expected an error, found type {}", ty),
+        }
+    }
+
+    #[test]
+    fn level_solved_in_result_type() {
+        let context = Context::new();
+        let level = Level::fresh();
+        let ty = ValuePi::bind(
+            Named::new(Name::user("A"), Value::Universe(level.clone()).into()),
+            Value::Universe(level).into(),
+        );
+        let context = context.declare(Name::user("Lift"), Value::Pi(ty).into());
+
+        assert_eq!(
+            infer(&context, &parse(r"Lift (Type 1)")).unwrap().1,
+            Value::Universe(Level::Const(2)).into(),
+        );
+    }
+}
+
+mod check {
+    use syntax::core::Origin;
+
+    use super::*;
+
+    #[test]
+    fn universe() {
+        let context = Context::new();
+
+        let expected = normalize(&context, &parse(r"Type 1")).unwrap();
+
+        assert!(check(&context, &parse(r"Type"), &expected).is_ok());
+    }
+
+    #[test]
+    fn universe_cumulative() {
+        let context = Context::new();
+
+        let expected = normalize(&context, &parse(r"Type 2")).unwrap();
+
+        assert!(check(&context, &parse(r"Type"), &expected).is_ok());
+        assert!(check(&context, &parse(r"Type -> Type"), &expected).is_ok());
+    }
+
+    #[test]
+    fn universe_too_large() {
+        let context = Context::new();
+
+        let expected = normalize(&context, &parse(r"Type")).unwrap();
+
+        match check(&context, &parse(r"Type 1"), &expected) {
+            Err(TypeError::UniverseMismatch {
+                found, expected, ..
+            }) => {
+                assert_eq!(found, Level::Const(2));
+                assert_eq!(expected, Level::ZERO);
+            },
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("expected `Type 1` to fail to check against `Type`"),
+        }
+    }
+
+    #[test]
+    fn mismatch_prelude_origin() {
+        let context = Context::new().extend_with_origin(
+            Name::user("Int"),
+            Binder::Lam(Some(Value::Universe(Level::ZERO).into())),
+            Origin::Prelude,
+        );
+
+        let expected = normalize(&context, &parse(r"Int")).unwrap();
+        let err = check(&context, &parse(r"Type"), &expected).unwrap_err();
+
+        match err {
+            TypeError::Mismatch {
+                expected_origin, ..
+            } => assert_eq!(expected_origin, Some(Origin::Prelude)),
+            ref err => panic!("expected a mismatch, found {}", err),
+        }
+        assert!(
+            err.to_diagnostic()
+                .message
+                .ends_with("(defined in the prelude)")
+        );
+    }
+
+    #[test]
+    fn lam_instantiated_pi() {
+        let a = Name::user("A");
+        let context = Context::new().declare(a.clone(), Value::Universe(Level::ZERO).into());
+        let id_ty = normalize(&context, &parse(r"(A : Type) -> A -> A")).unwrap();
+        let expected = match *id_ty.inner {
+            Value::Pi(ref pi) => pi.instantiate(&Value::Var(Var::Free(a)).into()),
+            _ => panic!("expected a function type, found {}", id_ty),
+        };
+
+        assert!(check(&context, &parse(r"\x => x"), &expected).is_ok());
+        match check(&context, &parse(r"\x => Type"), &expected) {
+            Err(TypeError::Mismatch { .. }) => {},
+            Err(err) => panic!("expected a mismatch, found {}", err),
+            Ok(_) => panic!("expected `\\x => Type` to fail to check against `A -> A`"),
+        }
+    }
+
+    #[test]
+    fn mismatch_pi_codomain() {
+        let context = Context::default();
+        let found = normalize(&context, &parse(r"Int -> Bool -> Bool")).unwrap();
+        let context = context.declare(Name::user("f"), found);
+
+        let expected = normalize(&context, &parse(r"Int -> Bool -> Int")).unwrap();
+        let err = check(&context, &parse(r"f"), &expected).unwrap_err();
+
+        match err {
+            TypeError::Mismatch {
+                difference: Some((ref found, ref expected)),
+                ..
+            } => {
+                assert_eq!(*found, Value::Constant(Constant::BoolType).into());
+                assert_eq!(*expected, Value::Constant(Constant::IntType).into());
+            },
+            ref err => panic!("expected a mismatch in the codomain, found {}", err),
+        }
+        assert_eq!(
+            err.to_diagnostic().labels[0].message,
+            Some(String::from("the term - these differ here: found `Bool`, but expected `Int`")),
+        );
+    }
+
+    /// A context containing an identity function, whose type parameter is
+    /// implicit
+    fn id_context() -> Context {
+        let context = Context::default();
+
+        let id_ty = normalize(&context, &parse(r"{a : Type} -> a -> a")).unwrap();
+        let id = check(&context, &parse(r"\x => x"), &id_ty).unwrap();
+
+        context.extend(Name::user("id"), Binder::Let(id, id_ty, Transparency::Transparent))
+    }
+
+    #[test]
+    fn implicit_lam_inserted() {
+        let context = id_context();
+
+        let id = normalize(&context, &parse(r"id")).unwrap();
+        let expected = normalize(&context, &parse(r"\{a : Type} (x : a) => x")).unwrap();
+
+        assert_eq!(id, expected);
+    }
+
+    #[test]
+    fn implicit_arg_inferred() {
+        let context = id_context();
+
+        let expected_ty = normalize(&context, &parse(r"Bool -> Bool")).unwrap();
+        let expected = normalize(&context, &parse(r"\x : Bool => x")).unwrap();
+
+        assert_eq!(check(&context, &parse(r"id"), &expected_ty), Ok(expected));
+    }
+
+    #[test]
+    fn implicit_arg_inferred_app() {
+        let context = id_context();
+
+        let term = parse(r"(id : Bool -> Bool) true");
+        let expected_ty = normalize(&context, &parse(r"Bool")).unwrap();
+        let expected = normalize(&context, &parse(r"true")).unwrap();
+
+        let (elab, ty) = infer(&context, &term).unwrap();
+        assert_eq!(ty, expected_ty);
+        assert_eq!(normalize(&context, &RcTerm::from(&elab)).unwrap(), expected);
+    }
+
+    #[test]
+    fn implicit_lam_stuck() {
+        let context = id_context();
+
+        // Without elaboration the argument can't be given to the implicit
+        // parameter, so the application is left as it is
+        let id = normalize(&context, &parse(r"id")).unwrap();
+        let arg = normalize(&context, &parse(r"true")).unwrap();
+        let expected = RcValue::from(Value::App(id, arg));
+
+        assert_eq!(normalize(&context, &parse(r"id true")).unwrap(), expected);
+    }
+
+    #[test]
+    fn implicit_arg_unsolved() {
+        let context = id_context();
+
+        let expected_ty = normalize(&context, &parse(r"Type")).unwrap();
+
+        match check(&context, &parse(r"id"), &expected_ty) {
+            Err(TypeError::UnsolvedImplicit { .. }) => {},
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(term) => panic!("expected an error, found {}", term),
+        }
+    }
+
+    #[test]
+    fn implicit_arg_in_infer_mode() {
+        let context = id_context();
+
+        match infer(&context, &parse(r"id true")) {
+            Err(TypeError::UnsolvedImplicit { .. }) => {},
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok((_, ty)) => panic!("expected an error, found type {}", ty),
+        }
+    }
+
+    #[test]
+    fn implicit_lam_inserted_pair() {
+        let context = id_context();
+
+        let expected_ty = r"(f : {a : Type} -> a -> a) * Type";
+        let expected_ty = normalize(&context, &parse(expected_ty)).unwrap();
+        let expected = normalize(&context, &parse(r"(\{a : Type} (x : a) => x, Bool)")).unwrap();
+
+        assert_eq!(check(&context, &parse(r"(\x => x, Bool)"), &expected_ty), Ok(expected));
+    }
+
+    #[test]
+    fn implicit_arg_inferred_pair() {
+        let context = id_context();
+
+        // The type of the second component depends on the elaborated first
+        // component, which has had its implicit argument filled in
+        let expected_ty = r"(f : Bool -> Bool) * (if f true then String else Int)";
+        let expected_ty = normalize(&context, &parse(expected_ty)).unwrap();
+        let expected = normalize(&context, &parse(r#"(\x : Bool => x, "hello")"#)).unwrap();
+
+        assert_eq!(check(&context, &parse(r#"(id, "hello")"#), &expected_ty), Ok(expected));
+    }
+
+    /// A context containing a record `r`, a type family `F` over records,
+    /// and a variable `a : F r`
+    fn record_family_context() -> Context {
+        let context = Context::new();
+
+        let record_ty = normalize(&context, &parse(r"Record { x : Type; y : Type }")).unwrap();
+        let family_ty = r"Record { x : Type; y : Type } -> Type";
+        let family_ty = normalize(&context, &parse(family_ty)).unwrap();
+        let context = context
+            .extend(Name::user("r"), Binder::Pi(record_ty))
+            .extend(Name::user("F"), Binder::Pi(family_ty));
+
+        let a_ty = normalize(&context, &parse(r"F r")).unwrap();
+        context.extend(Name::user("a"), Binder::Pi(a_ty))
+    }
+
+    #[test]
+    fn record_eta() {
+        let context = record_family_context();
+
+        let expected_ty = r"F (record { x = r.x; y = r.y })";
+        let expected_ty = normalize(&context, &parse(expected_ty)).unwrap();
+        let expected = normalize(&context, &parse(r"a")).unwrap();
+
+        assert_eq!(check(&context, &parse(r"a"), &expected_ty), Ok(expected));
+    }
+
+    #[test]
+    fn record_eta_mismatch() {
+        let context = record_family_context();
+
+        let expected_ty = r"F (record { x = r.y; y = r.x })";
+        let expected_ty = normalize(&context, &parse(expected_ty)).unwrap();
+
+        match check(&context, &parse(r"a"), &expected_ty) {
+            Err(TypeError::Mismatch { .. }) => {},
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(term) => panic!("expected an error, found {}", term),
+        }
+    }
+}
+
+mod is_subtype {
+    use super::*;
+
+    #[test]
+    fn universe() {
+        let type0: RcType = Value::Universe(Level::ZERO).into();
+        let type2: RcType = Value::Universe(Level::Const(2)).into();
+
+        let context = Context::new();
+
+        assert_eq!(is_subtype(&context, &type0, &type0), Ok(true));
+        assert_eq!(is_subtype(&context, &type0, &type2), Ok(true));
+        assert_eq!(is_subtype(&context, &type2, &type0), Ok(false));
+    }
+
+    #[test]
+    fn exact_elsewhere() {
+        let context = Context::new();
+        let ty1 = normalize(&context, &parse(r"Type -> Type")).unwrap();
+        let ty2 = normalize(&context, &parse(r"Type -> Type 1")).unwrap();
+
+        assert_eq!(is_subtype(&context, &ty1, &ty1), Ok(true));
+        assert_eq!(is_subtype(&context, &ty1, &ty2), Ok(false));
+    }
+
+    #[test]
+    fn reduces_under_binders() {
+        let context = Context::new();
+        let ty1 = whnf(&context, &parse(r"Type -> (\a : Type 1 => a) Type")).unwrap();
+        let ty2 = normalize(&context, &parse(r"Type -> Type")).unwrap();
+
+        assert_ne!(ty1, ty2);
+        assert_eq!(is_subtype(&context, &ty1, &ty2), Ok(true));
+    }
+}
+
+mod unify_levels {
+    use super::*;
+
+    #[test]
+    fn var() {
+        let context = Context::new();
+        let level = Level::fresh();
+
+        assert!(unify_levels(&context, &Level::Const(1), &level));
+        assert_eq!(level.subst(&context.levels.borrow()), Level::Const(1));
+        assert!(unify_levels(&context, &Level::ZERO, &level));
+        assert!(!unify_levels(&context, &Level::Const(2), &level));
+    }
+
+    #[test]
+    fn succ() {
+        let context = Context::new();
+        let level = Level::fresh();
+
+        assert!(unify_levels(&context, &level.succ(), &Level::Const(3)));
+        assert_eq!(level.subst(&context.levels.borrow()), Level::Const(2));
+    }
+
+    #[test]
+    fn max() {
+        let context = Context::new();
+        let level1 = Level::fresh();
+        let level2 = Level::fresh();
+
+        assert!(unify_levels(&context, &level1.max(&level2), &Level::Const(1)));
+        assert_eq!(level1.subst(&context.levels.borrow()), Level::Const(1));
+        assert_eq!(level2.subst(&context.levels.borrow()), Level::Const(1));
+    }
+
+    #[test]
+    fn occurs() {
+        let context = Context::new();
+        let level = Level::fresh();
+
+        assert!(!unify_levels(&context, &level.succ(), &level));
+        assert!(unify_levels(&context, &level, &level.succ()));
+    }
+}
+
+mod check_binder_depth {
+    use syntax::core::TermLam;
+
+    use super::*;
+
+    fn nested_lams(depth: usize) -> RcTerm {
+        let mut src = String::new();
+        for _ in 0..depth {
+            src.push_str(r"\x : Type => ");
+        }
+        src.push_str("x");
+
+        parse(&src)
+    }
+
+    #[test]
+    fn within_limit() {
+        assert_eq!(check_binder_depth(&nested_lams(10), 10), Ok(()));
+    }
+
+    #[test]
+    fn exceeds_limit() {
+        match check_binder_depth(&nested_lams(11), 10) {
+            Err(InternalError::BinderDepthExceeded { max_depth, .. }) => assert_eq!(max_depth, 10),
+            result => panic!("expected the binder depth to be exceeded, found {:?}", result),
+        }
+    }
+
+    /// Build nested lambdas directly, because parsing or binding a term this
+    /// deep would recurse once for every binder
+    fn deeply_nested_lams(depth: u32) -> RcTerm {
+        let x = Name::user("x");
+        let mut term = RcTerm::from(Term::Var(
+            SourceMeta::default(),
+            Var::Bound(Named::new(x.clone(), Debruijn::ZERO)),
+        ));
+        for _ in 0..depth {
+            let lam = TermLam {
+                plicity: Plicity::Explicit,
+                unsafe_param: Named::new(x.clone(), None),
+                unsafe_body: term,
+            };
+            term = Term::Lam(SourceMeta::default(), lam).into();
+        }
+        term
+    }
+
+    #[test]
+    fn near_default_limit() {
+        use std::thread;
+
+        // Dropping the terms is still recursive, so we need a bigger stack
+        // than the test threads are given by default
+        let test = thread::Builder::new().stack_size(256 * 1024 * 1024).spawn(|| {
+            let within = deeply_nested_lams(DEFAULT_MAX_BINDER_DEPTH);
+            let exceeds = deeply_nested_lams(DEFAULT_MAX_BINDER_DEPTH + 1);
+
+            assert_eq!(check_binder_depth(&within, DEFAULT_MAX_BINDER_DEPTH), Ok(()));
+            match check_binder_depth(&exceeds, DEFAULT_MAX_BINDER_DEPTH) {
+                Err(InternalError::BinderDepthExceeded { max_depth, .. }) => {
+                    assert_eq!(max_depth, DEFAULT_MAX_BINDER_DEPTH)
+                },
+                result => panic!("expected the binder depth to be exceeded, found {:?}", result),
+            }
+        });
+
+        test.unwrap().join().unwrap();
+    }
+
+    #[test]
+    fn debruijn_overflow() {
+        use std::u64;
+
+        use syntax::var::Debruijn;
+
+        assert_eq!(Debruijn(u64::MAX - 1).checked_succ(), Some(Debruijn(u64::MAX)));
+        assert_eq!(Debruijn(u64::MAX).checked_succ(), None);
+    }
+}
+
+mod check_module {
+    use library;
+    use super::*;
+
+    #[test]
+    fn check_prelude() {
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), library::PRELUDE.into());
+
+        let (concrete_module, errors) = parse::module(&filemap);
+        assert!(errors.is_empty());
+
+        let module = concrete_module.to_core();
+        check_module(&module).unwrap();
+    }
+
+    #[test]
+    fn reproducible_after_reset() {
+        use syntax::var::GenId;
+
+        fn check_prelude_to_string() -> String {
+            GenId::reset(0);
+
+            let mut codemap = CodeMap::new();
+            let filemap = codemap.add_filemap(FileName::virtual_("test"), library::PRELUDE.into());
+            let (concrete_module, _) = parse::module(&filemap);
+            let module = check_module(&concrete_module.to_core()).unwrap();
+
+            let definitions = module.definitions.iter().map(|definition| {
+                format!("{} : {} = {}\n", definition.name, definition.ann, definition.term)
+            });
+            definitions.collect()
+        }
+
+        // Generated names are printed with their ids, so this would differ if
+        // the ids were not reset between the runs
+        assert_eq!(check_prelude_to_string(), check_prelude_to_string());
+    }
+
+    fn check_module_src(src: &str) -> Result<CheckedModule, Vec<TypeError>> {
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), src.into());
+
+        let (concrete_module, errors) = parse::module(&filemap);
+        assert!(errors.is_empty());
+
+        check_module(&concrete_module.to_core())
+    }
+
+    #[test]
+    fn empty_module() {
+        let module = check_module_src("module test;").unwrap();
+
+        assert_eq!(module.name, "test");
+        assert!(module.definitions.is_empty());
+    }
+
+    #[test]
+    fn unresolved_imports() {
+        let src = r"
+            module test;
+
+            import foo;
+            import bar as baz (..);
+        ";
+
+        match check_module_src(src) {
+            Err(errors) => match errors.as_slice() {
+                [
+                    TypeError::UnresolvedImport { name: ref first, .. },
+                    TypeError::UnresolvedImport { name: ref second, .. },
+                ] => {
+                    assert_eq!(first, "foo");
+                    assert_eq!(second, "bar");
+                },
+                errors => panic!("expected unresolved imports, found {:?}", errors),
+            },
+            Ok(_) => panic!("expected the imports to fail to resolve"),
         }
+    }
 
-        #[test]
-        fn and_intro() {
-            let context = Context::new();
+    #[test]
+    fn where_block() {
+        let src = r"
+            module test;
+
+            foo : Type 1;
+            foo = id (Type 1) Type where {
+                id : (a : Type 2) -> a -> a;
+                id a x = x;
+            };
+        ";
 
-            let expected_ty = r"
-                (p q : Type) -> p -> q ->
-                    ((c : Type) -> (p -> q -> c) -> c)
-            ";
-            let given_expr = r"
-                \(p q : Type) (x : p) (y : q) =>
-                    \c : Type => \f : (p -> q -> c) => f x y
-            ";
+        check_module_src(src).unwrap();
+    }
 
-            assert_eq!(
-                infer(&context, &parse(given_expr)).unwrap().1,
-                normalize(&context, &parse(expected_ty)).unwrap(),
-            );
+    #[test]
+    fn where_block_not_visible_to_siblings() {
+        let src = r"
+            module test;
+
+            foo = helper where { helper = Type; };
+            bar = helper;
+        ";
+
+        match check_module_src(src) {
+            Err(errors) => match errors.as_slice() {
+                [TypeError::UndefinedName { ref name, .. }] => {
+                    assert_eq!(*name, Name::user("helper"))
+                },
+                errors => panic!("expected `helper` to be undefined, found {:?}", errors),
+            },
+            Ok(_) => panic!("expected `helper` to be out of scope"),
         }
+    }
 
-        #[test]
-        fn and_proj_left() {
-            let context = Context::new();
+    /// Extend the default context with the definitions of a checked module
+    fn module_context(module: CheckedModule) -> Context {
+        module.definitions.into_iter().fold(Context::default(), |context, definition| {
+            let binder = Binder::Let(definition.term, definition.ann, definition.transparency);
+            context.extend(Name::user(definition.name), binder)
+        })
+    }
 
-            let expected_ty = r"
-                (p q : Type) ->
-                    ((c : Type) -> (p -> q -> c) -> c) -> p
-            ";
-            let given_expr = r"
-                \(p q : Type) (pq : (c : Type) -> (p -> q -> c) -> c) =>
-                    pq p (\x y => x)
-            ";
+    #[test]
+    fn recursive_definition() {
+        let src = r"
+            module test;
 
-            assert_eq!(
-                infer(&context, &parse(given_expr)).unwrap().1,
-                normalize(&context, &parse(expected_ty)).unwrap(),
-            );
+            rec flip : Bool -> Bool = \b => if b then flip false else true;
+        ";
+
+        let context = module_context(check_module_src(src).unwrap());
+
+        assert_eq!(
+            normalize(&context, &parse(r"flip true")).unwrap(),
+            normalize(&context, &parse(r"true")).unwrap(),
+        );
+        assert_eq!(
+            whnf(&context, &parse(r"flip true")).unwrap(),
+            normalize(&context, &parse(r"true")).unwrap(),
+        );
+    }
+
+    #[test]
+    fn recursive_definition_step_limit() {
+        let src = r"
+            module test;
+
+            rec loop : Bool -> Bool = \b => loop b;
+        ";
+
+        let context = module_context(check_module_src(src).unwrap()).with_step_limit(Some(100));
+
+        match whnf(&context, &parse(r"loop true")) {
+            Err(InternalError::NormalizationLimitExceeded { .. }) => {},
+            result => panic!("expected the step limit to be exceeded, found {:?}", result),
+        }
+        match normalize(&context, &parse(r"loop true")) {
+            Err(InternalError::NormalizationLimitExceeded { .. }) => {},
+            result => panic!("expected the step limit to be exceeded, found {:?}", result),
         }
+    }
 
-        #[test]
-        fn and_proj_right() {
-            let context = Context::new();
+    #[test]
+    fn recursive_definition_not_in_scope_without_rec() {
+        let src = r"
+            module test;
 
-            let expected_ty = r"
-                (p q : Type) -> ((c : Type) -> (p -> q -> c) -> c) -> q
-            ";
-            let given_expr = r"
-                \(p q : Type) (pq : (c : Type) -> (p -> q -> c) -> c) =>
-                    pq q (\x y => y)
-            ";
+            loop : Bool -> Bool;
+            loop b = loop b;
+        ";
 
-            assert_eq!(
-                infer(&context, &parse(given_expr)).unwrap().1,
-                normalize(&context, &parse(expected_ty)).unwrap(),
-            );
+        match check_module_src(src) {
+            Err(errors) => match errors.as_slice() {
+                [TypeError::UndefinedName { ref name, .. }] => {
+                    assert_eq!(*name, Name::user("loop"))
+                },
+                errors => panic!("expected `loop` to be undefined, found {:?}", errors),
+            },
+            Ok(_) => panic!("expected `loop` to be out of scope in its own body"),
+        }
+    }
+
+    fn parse_module(codemap: &mut CodeMap, src: &str) -> Module {
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), src.into());
+
+        let (concrete_module, errors) = parse::module(&filemap);
+        assert!(errors.is_empty());
+
+        concrete_module.to_core()
+    }
+
+    #[test]
+    fn recover_from_declaration_error() {
+        let src = r"
+            module test;
+
+            a : Type 1;
+            a = x;
+
+            b = ;
+
+            c : Type 1;
+            c = (record);
+
+            d : Type 1;
+            d = y;
+        ";
+
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), src.into());
+        let (module, parse_errors) = parse::module(&filemap);
+        assert_eq!(parse_errors.len(), 2);
+
+        // The terms that failed to parse are checked as holes
+        match check_module(&module.to_core()) {
+            Err(errors) => match errors.as_slice() {
+                [
+                    TypeError::UndefinedName { name: ref first, .. },
+                    TypeError::HoleNeedsAnnotation { .. },
+                    TypeError::UnsolvedHole { .. },
+                    TypeError::UndefinedName { name: ref fourth, .. },
+                ] => {
+                    assert_eq!(*first, Name::user("x"));
+                    assert_eq!(*fourth, Name::user("y"));
+                },
+                errors => panic!("unexpected errors: {:?}", errors),
+            },
+            Ok(_) => panic!("expected `x` and `y` to be out of scope"),
+        }
+    }
+
+    #[test]
+    fn import_exposing_exact() {
+        let foo_src = r"
+            module foo;
+
+            a : Type 1;
+            a = Type;
+
+            b : Type 2;
+            b = Type 1;
+        ";
+        let src = r"
+            module test;
+
+            import foo as bar (a);
+
+            c : Type 1;
+            c = a;
+
+            d : Type 2;
+            d = bar.b;
+
+            e = b;
+        ";
+
+        let mut codemap = CodeMap::new();
+        let module = parse_module(&mut codemap, src);
+        let mut resolve = |name: &str| match name {
+            "foo" => Some(parse_module(&mut codemap, foo_src)),
+            _ => None,
+        };
+
+        match check_module_with_imports(&module, &mut resolve) {
+            Err(errors) => match errors.as_slice() {
+                [TypeError::UndefinedName { ref name, .. }] => assert_eq!(*name, Name::user("b")),
+                errors => panic!("expected `b` to be undefined, found {:?}", errors),
+            },
+            Ok(_) => panic!("expected `b` to be out of scope"),
+        }
+    }
+
+    #[test]
+    fn import_hiding() {
+        let foo_src = r"
+            module foo;
+
+            a : Type 1;
+            a = Type;
+
+            b : Type 2;
+            b = Type 1;
+
+            bar : Type 1;
+            bar = Type;
+        ";
+        let src = r"
+            module test;
+
+            import foo hiding (bar);
+
+            c : Type 1;
+            c = a;
+
+            d : Type 2;
+            d = b;
+
+            e = bar;
+        ";
+
+        let mut codemap = CodeMap::new();
+        let module = parse_module(&mut codemap, src);
+        let mut resolve = |name: &str| match name {
+            "foo" => Some(parse_module(&mut codemap, foo_src)),
+            _ => None,
+        };
+
+        match check_module_with_imports(&module, &mut resolve) {
+            Err(errors) => match errors.as_slice() {
+                [TypeError::UndefinedName { ref name, .. }] => assert_eq!(*name, Name::user("bar")),
+                errors => panic!("expected `bar` to be undefined, found {:?}", errors),
+            },
+            Ok(_) => panic!("expected `bar` to be out of scope"),
+        }
+    }
+
+    #[test]
+    fn import_hiding_unknown_name() {
+        let foo_src = r"
+            module foo;
+
+            a : Type 1;
+            a = Type;
+        ";
+        let src = r"
+            module test;
+
+            import foo hiding (nope);
+
+            b : Type 1;
+            b = a;
+        ";
+
+        let mut codemap = CodeMap::new();
+        let module = parse_module(&mut codemap, src);
+        let mut resolve = |name: &str| match name {
+            "foo" => Some(parse_module(&mut codemap, foo_src)),
+            _ => None,
+        };
+
+        let module = check_module_with_imports(&module, &mut resolve).unwrap();
+        match module.warnings.as_slice() {
+            [TypeError::HiddenNameNotFound { ref module, ref name, .. }] => {
+                assert_eq!(module, "foo");
+                assert_eq!(name, "nope");
+            },
+            warnings => panic!("expected `nope` to be reported, found {:?}", warnings),
+        }
+    }
+
+    #[test]
+    fn import_qualified() {
+        let foo_src = r"
+            module foo;
+
+            bar : Type 1;
+            bar = Type;
+        ";
+        let src = r"
+            module test;
+
+            import foo as f;
+
+            x : Type 1;
+            x = f.bar;
+
+            y = bar;
+
+            z = f.baz;
+        ";
+
+        let mut codemap = CodeMap::new();
+        let module = parse_module(&mut codemap, src);
+        let mut resolve = |name: &str| match name {
+            "foo" => Some(parse_module(&mut codemap, foo_src)),
+            _ => None,
+        };
+
+        match check_module_with_imports(&module, &mut resolve) {
+            Err(errors) => match errors.as_slice() {
+                [
+                    TypeError::UndefinedName { name: ref unqualified, .. },
+                    TypeError::UndefinedImportedName {
+                        ref module,
+                        name: ref qualified,
+                        ..
+                    },
+                ] => {
+                    assert_eq!(*unqualified, Name::user("bar"));
+                    assert_eq!(module, "foo");
+                    assert_eq!(qualified, "baz");
+                },
+                errors => panic!("expected `bar` and `f.baz` to be undefined, found {:?}", errors),
+            },
+            Ok(_) => panic!("expected `bar` and `f.baz` to be out of scope"),
+        }
+    }
+
+    #[test]
+    fn import_circular() {
+        let foo_src = r"
+            module foo;
+
+            import test;
+        ";
+        let src = r"
+            module test;
+
+            import foo;
+        ";
+
+        let mut codemap = CodeMap::new();
+        let module = parse_module(&mut codemap, src);
+        let mut resolve = |name: &str| match name {
+            "foo" => Some(parse_module(&mut codemap, foo_src)),
+            "test" => Some(parse_module(&mut codemap, src)),
+            _ => None,
+        };
+
+        match check_module_with_imports(&module, &mut resolve) {
+            Err(errors) => match errors.as_slice() {
+                [TypeError::CircularImport { ref name, .. }] => assert_eq!(name, "test"),
+                errors => panic!("expected a circular import, found {:?}", errors),
+            },
+            Ok(_) => panic!("expected the import to be circular"),
+        }
+    }
+
+    #[test]
+    fn circular_annotation() {
+        let src = r"
+            module test;
+
+            x : x;
+            x = Type;
+        ";
+
+        match check_module_src(src) {
+            Err(errors) => match errors.as_slice() {
+                [TypeError::CircularAnnotation { ref name, .. }] => assert_eq!(name, "x"),
+                errors => panic!("expected a circular annotation, found {:?}", errors),
+            },
+            Ok(_) => panic!("expected `x` to fail to check"),
+        }
+    }
+
+    #[test]
+    fn circular_annotation_transitive() {
+        let src = r"
+            module test;
+
+            x : y;
+            x = Type;
+
+            y : x;
+            y = Type;
+        ";
+
+        match check_module_src(src) {
+            Err(errors) => {
+                let names = errors
+                    .iter()
+                    .map(|err| match *err {
+                        TypeError::CircularAnnotation { ref name, .. } => name.as_str(),
+                        ref err => panic!("expected a circular annotation, found {}", err),
+                    })
+                    .collect::<Vec<_>>();
+
+                assert_eq!(names, ["x", "y"]);
+            },
+            Ok(_) => panic!("expected `x` and `y` to fail to check"),
+        }
+    }
+
+    #[test]
+    fn broken_definition_with_claim() {
+        let src = r"
+            module test;
+
+            a : Type;
+            a = Type;
+
+            b : Type;
+            b = a;
+        ";
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), src.into());
+
+        let (concrete_module, errors) = parse::module(&filemap);
+        assert!(errors.is_empty());
+
+        let module = concrete_module.to_core();
+        match check_module(&module) {
+            Err(errors) => match errors.as_slice() {
+                [TypeError::UniverseMismatch { .. }] => {},
+                errors => panic!("expected a single mismatch, found {:?}", errors),
+            },
+            Ok(_) => panic!("expected `a` to fail to check"),
+        }
+    }
+
+    #[test]
+    fn hole() {
+        let src = r"
+            module test;
+
+            a : Type;
+            a = ?;
+        ";
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), src.into());
+
+        let (concrete_module, errors) = parse::module(&filemap);
+        assert!(errors.is_empty());
+
+        let module = concrete_module.to_core();
+        match check_module(&module) {
+            Err(errors) => match errors.as_slice() {
+                [TypeError::UnsolvedHole { .. }] => {},
+                errors => panic!("expected a single hole, found {:?}", errors),
+            },
+            Ok(_) => panic!("expected the hole to be reported"),
         }
     }
 }
 
-mod check_module {
-    use library;
+mod lint {
+    use semantics::lint::{self, NamingConvention};
+
     use super::*;
 
+    fn naming_warnings(src: &str, convention: NamingConvention) -> Vec<String> {
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), src.into());
+
+        let (concrete_module, errors) = parse::module(&filemap);
+        assert!(errors.is_empty());
+
+        lint::naming(&concrete_module, convention)
+            .into_iter()
+            .map(|warning| match warning {
+                TypeError::NamingConvention { name, .. } => name,
+                warning => panic!("unexpected warning: {}", warning),
+            })
+            .collect()
+    }
+
+    const SRC: &str = r"
+        module test;
+
+        kebab-id (a : Type) (kebab-x : a) = kebab-x;
+        snake_id (a : Type) (snake_x : a) = snake_x;
+        camelId (a : Type) (camelX : a) = camelX;
+    ";
+
     #[test]
-    fn check_prelude() {
+    fn naming_kebab_case() {
+        assert_eq!(
+            naming_warnings(SRC, NamingConvention::KebabCase),
+            vec!["snake_id", "snake_x", "camelId", "camelX"],
+        );
+    }
+
+    #[test]
+    fn naming_snake_case() {
+        assert_eq!(
+            naming_warnings(SRC, NamingConvention::SnakeCase),
+            vec!["kebab-id", "kebab-x", "camelId", "camelX"],
+        );
+    }
+
+    #[test]
+    fn naming_camel_case() {
+        assert_eq!(
+            naming_warnings(SRC, NamingConvention::CamelCase),
+            vec!["kebab-id", "kebab-x", "snake_id", "snake_x"],
+        );
+    }
+
+    fn unused_import_warnings(src: &str) -> Vec<String> {
         let mut codemap = CodeMap::new();
-        let filemap = codemap.add_filemap(FileName::virtual_("test"), library::PRELUDE.into());
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), src.into());
 
         let (concrete_module, errors) = parse::module(&filemap);
         assert!(errors.is_empty());
 
-        let module = concrete_module.to_core();
-        check_module(&module).unwrap();
+        lint::unused_imports(&concrete_module.to_core())
+            .into_iter()
+            .map(|warning| match warning {
+                TypeError::UnusedImport { name, .. } => name,
+                warning => panic!("unexpected warning: {}", warning),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unused_import() {
+        let src = r"
+            module test;
+
+            import foo (bar);
+        ";
+
+        assert_eq!(unused_import_warnings(src), vec!["bar"]);
+    }
+
+    #[test]
+    fn used_import() {
+        let src = r"
+            module test;
+
+            import foo (bar);
+
+            baz = bar;
+        ";
+
+        assert!(unused_import_warnings(src).is_empty());
     }
 }
@@ -1,9 +1,12 @@
 //! The concrete syntax of the language
 
-use codespan::{ByteIndex, ByteSpan};
+use codespan::{ByteIndex, ByteSpan, CodeMap, FileName};
 use std::fmt;
+use std::str::FromStr;
 use std::usize;
 
+use syntax::core::{Plicity, UnfoldPolicy};
+use syntax::parse::{self, FromStrError};
 use syntax::pretty::{self, ToDoc};
 
 /// Commands entered in the REPL
@@ -15,6 +18,36 @@ pub enum ReplCommand {
     /// <term>
     /// ```
     Eval(Box<Term>),
+    /// Print the core representation, the type, and the normal form of a term
+    ///
+    /// ```text
+    /// :all <term>
+    /// ```
+    All(Box<Term>),
+    /// Time how long it takes to normalize a term, repeating the given number
+    /// of times
+    ///
+    /// ```text
+    /// :bench <term>
+    /// :bench <n> <term>
+    /// ```
+    Bench(Option<u32>, Box<Term>),
+    /// List the names that are in scope, along with their types
+    ///
+    /// ```text
+    /// :b
+    /// :browse
+    /// ```
+    Browse,
+    /// Claim the type of a name that has not been defined yet
+    ///
+    /// This is parsed as an annotated variable, and is only treated as a
+    /// claim if the variable is not already in scope.
+    ///
+    /// ```text
+    /// <name> : <term>
+    /// ```
+    Claim(String, Box<Term>),
     /// Print some help about using the REPL
     ///
     /// ```text
@@ -23,6 +56,30 @@ pub enum ReplCommand {
     /// :help
     /// ```
     Help,
+    /// Bind a name to a term for the rest of the session
+    ///
+    /// ```text
+    /// :let <name> = <term>
+    /// <name> = <term>
+    /// ```
+    ///
+    /// If the name was claimed by an earlier command, then the term is
+    /// checked against the claimed type.
+    Let(String, Box<Term>),
+    /// Load the definitions in a file into the session
+    ///
+    /// ```text
+    /// :l <path>
+    /// :load <path>
+    /// ```
+    Load(String),
+    /// Print the normal form of a term, without its type
+    ///
+    /// ```text
+    /// :n <term>
+    /// :normalize <term>
+    /// ```
+    Normalize(Box<Term>),
     ///  No command
     NoOp,
     /// Quit the REPL
@@ -32,6 +89,35 @@ pub enum ReplCommand {
     /// :quit
     /// ```
     Quit,
+    /// Write the claims and definitions made during the session to a file,
+    /// as a module that can be loaded again later
+    ///
+    /// ```text
+    /// :save <path>
+    /// ```
+    Save(String),
+    /// Set the depth past which terms are printed as an ellipsis
+    ///
+    /// ```text
+    /// :set depth <n>
+    /// ```
+    SetDepth(u32),
+    /// Set whether to print how long each phase of evaluating a term took
+    ///
+    /// ```text
+    /// :set timing on
+    /// :set timing off
+    /// ```
+    SetTiming(bool),
+    /// Set which definitions are unfolded when evaluating terms
+    ///
+    /// ```text
+    /// :set unfold all
+    /// :set unfold prelude
+    /// :set unfold user
+    /// :set unfold none
+    /// ```
+    SetUnfold(UnfoldPolicy),
     /// Print the type of the term
     ///
     /// ```text
@@ -58,6 +144,9 @@ pub enum Module {
     Valid {
         name: (ByteSpan, String),
         declarations: Vec<Declaration>,
+        /// Line comments, which are printed before the declaration that
+        /// follows them
+        comments: Vec<(ByteSpan, String)>,
     },
     /// Modules commands that could not be parsed correctly
     ///
@@ -73,6 +162,22 @@ impl fmt::Display for Module {
     }
 }
 
+impl FromStr for Module {
+    type Err = FromStrError;
+
+    fn from_str(src: &str) -> Result<Module, FromStrError> {
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("unnamed"), src.into());
+
+        let (module, errors) = parse::module(&filemap);
+        if errors.is_empty() {
+            Ok(module)
+        } else {
+            Err(FromStrError::new(&filemap, errors))
+        }
+    }
+}
+
 /// Top level declarations
 #[derive(Debug, Clone, PartialEq)]
 pub enum Declaration {
@@ -92,19 +197,43 @@ pub enum Declaration {
     /// Claims that a term abides by the given type
     ///
     /// ```text
+    /// ||| some documentation
     /// foo : some-type
     /// ```
-    Claim { name: (ByteSpan, String), ann: Term },
+    Claim {
+        docs: Vec<String>,
+        name: (ByteSpan, String),
+        ann: Term,
+    },
     /// Declares the body of a term
     ///
     /// ```text
     /// foo = some-body
     /// foo x (y : some-type) = some-body
+    /// foo = some-body where { helper = some-other-body; }
+    /// opaque foo = some-body
     /// ```
     Definition {
+        docs: Vec<String>,
         name: (ByteSpan, String),
         params: LamParams,
         body: Term,
+        /// Local declarations that are only in scope in the body, along with
+        /// the span of the `where` block that they were declared in
+        where_block: Option<(ByteSpan, Vec<Declaration>)>,
+        opaque: bool,
+    },
+    /// Declares a definition that may refer to itself in its body, at the
+    /// type that it was annotated with
+    ///
+    /// ```text
+    /// rec foo : some-type = some-body
+    /// ```
+    Recursive {
+        docs: Vec<String>,
+        name: (ByteSpan, String),
+        ann: Term,
+        body: Term,
     },
     /// Declarations that could not be correctly parsed
     ///
@@ -117,8 +246,19 @@ impl Declaration {
     pub fn span(&self) -> ByteSpan {
         match *self {
             Declaration::Import { span, .. } => span,
-            Declaration::Claim { ref name, ref ann } => name.0.to(ann.span()),
+            Declaration::Claim {
+                ref name, ref ann, ..
+            } => name.0.to(ann.span()),
             Declaration::Definition {
+                ref name,
+                ref body,
+                ref where_block,
+                ..
+            } => match *where_block {
+                Some((where_span, _)) => name.0.to(where_span),
+                None => name.0.to(body.span()),
+            },
+            Declaration::Recursive {
                 ref name, ref body, ..
             } => name.0.to(body.span()),
             Declaration::Error(span) => span,
@@ -152,6 +292,12 @@ pub enum Exposing {
         ByteSpan,
         Vec<((ByteSpan, String), Option<(ByteSpan, String)>)>,
     ),
+    /// Import every definition in the module except for the given ones
+    ///
+    /// ```text
+    /// hiding (foo, bar)
+    /// ```
+    Hiding(ByteSpan, Vec<(ByteSpan, String)>),
     /// Exposing declarations that could not be correctly parsed
     ///
     /// This is used for error recovery
@@ -187,6 +333,24 @@ pub enum Term {
     /// Type
     /// ```
     Universe(ByteSpan, Option<u32>),
+    /// Integer literals
+    ///
+    /// ```text
+    /// 42
+    /// ```
+    IntLit(ByteSpan, i64),
+    /// String literals
+    ///
+    /// ```text
+    /// "hello\n"
+    /// ```
+    StringLit(ByteSpan, String),
+    /// Holes, standing in for terms that have not been written yet
+    ///
+    /// ```text
+    /// ?
+    /// ```
+    Hole(ByteSpan),
     /// Variables
     ///
     /// ```text
@@ -201,6 +365,8 @@ pub enum Term {
     /// \x : t1 => t2
     /// \(x : t1) y (z : t2) => t3
     /// \(x y : t1) => t3
+    /// \{x} y => t2
+    /// \{x : t1} y => t2
     /// ```
     Lam(ByteIndex, LamParams, Box<Term>),
     /// Dependent function types
@@ -208,8 +374,9 @@ pub enum Term {
     /// ```text
     /// (x : t1) -> t2
     /// (x y : t1) -> t2
+    /// {x : t1} -> t2
     /// ```
-    Pi(ByteIndex, PiParams, Box<Term>),
+    Pi(ByteIndex, Plicity, PiParams, Box<Term>),
     /// Non-Dependent function types
     ///
     /// ```text
@@ -222,6 +389,75 @@ pub enum Term {
     /// e1 e2
     /// ```
     App(Box<Term>, Box<Term>),
+    /// Dependent pair types
+    ///
+    /// ```text
+    /// (x : t1) * t2
+    /// (x y : t1) * t2
+    /// ```
+    Sigma(ByteIndex, PiParams, Box<Term>),
+    /// Non-Dependent pair types
+    ///
+    /// ```text
+    /// t1 * t2
+    /// ```
+    Product(Box<Term>, Box<Term>),
+    /// Dependent pairs
+    ///
+    /// ```text
+    /// (e1, e2)
+    /// ```
+    Pair(ByteSpan, Box<Term>, Box<Term>),
+    /// The first component of a pair
+    ///
+    /// ```text
+    /// fst e
+    /// ```
+    Fst(ByteIndex, Box<Term>),
+    /// The second component of a pair
+    ///
+    /// ```text
+    /// snd e
+    /// ```
+    Snd(ByteIndex, Box<Term>),
+    /// Record types, where the types of later fields may refer to the
+    /// values of earlier fields
+    ///
+    /// ```text
+    /// Record { x : t1; y : t2 }
+    /// ```
+    RecordType(ByteSpan, RecordTypeFields),
+    /// Records
+    ///
+    /// ```text
+    /// record { x = e1; y = e2 }
+    /// ```
+    Record(ByteSpan, RecordFields),
+    /// Field projection
+    ///
+    /// ```text
+    /// e.x
+    /// ```
+    Proj(Box<Term>, ByteSpan, String),
+    /// Let bindings, which are checked in sequence before the body
+    ///
+    /// ```text
+    /// let x = t1 in t3
+    /// let x = t1; y : t2 = t3 in t4
+    /// ```
+    Let(ByteIndex, LetBindings, Box<Term>),
+    /// Conditional expressions
+    ///
+    /// ```text
+    /// if e1 then e2 else e3
+    /// ```
+    If(ByteIndex, Box<Term>, Box<Term>, Box<Term>),
+    /// Case expressions
+    ///
+    /// ```text
+    /// case e of { p1 => e1; p2 => e2 }
+    /// ```
+    Case(ByteSpan, Box<Term>, CaseClauses),
     /// Terms that could not be correctly parsed
     ///
     /// This is used for error recovery
@@ -233,15 +469,37 @@ impl Term {
     pub fn span(&self) -> ByteSpan {
         match *self {
             Term::Parens(span, _)
+            | Term::Pair(span, _, _)
+            | Term::RecordType(span, _)
+            | Term::Record(span, _)
             | Term::Universe(span, _)
+            | Term::IntLit(span, _)
+            | Term::StringLit(span, _)
+            | Term::Hole(span)
             | Term::Var(span, _)
+            | Term::Case(span, _, _)
             | Term::Error(span) => span,
-            Term::Lam(start, _, ref body) | Term::Pi(start, _, ref body) => {
-                ByteSpan::new(start, body.span().end())
-            },
+            Term::Lam(start, _, ref body)
+            | Term::Pi(start, _, _, ref body)
+            | Term::Sigma(start, _, ref body)
+            | Term::Fst(start, ref body)
+            | Term::Snd(start, ref body)
+            | Term::Let(start, _, ref body)
+            | Term::If(start, _, _, ref body) => ByteSpan::new(start, body.span().end()),
             Term::Ann(ref term, ref ty) => term.span().to(ty.span()),
-            Term::Arrow(ref ann, ref body) => ann.span().to(body.span()),
-            Term::App(ref fn_term, ref arg) => fn_term.span().to(arg.span()),
+            Term::Arrow(ref ann, ref body) | Term::Product(ref ann, ref body) => {
+                ann.span().to(body.span())
+            },
+            Term::Proj(ref record, label_span, _) => record.span().to(label_span),
+            Term::App(ref fn_term, ref arg) => {
+                // Walk down the application spine in a loop, rather than
+                // recursively, to avoid overflowing the stack on long spines
+                let mut head = fn_term;
+                while let Term::App(ref fn_term, _) = **head {
+                    head = fn_term;
+                }
+                head.span().to(arg.span())
+            },
         }
     }
 }
@@ -254,8 +512,70 @@ impl fmt::Display for Term {
     }
 }
 
+impl FromStr for Term {
+    type Err = FromStrError;
+
+    fn from_str(src: &str) -> Result<Term, FromStrError> {
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("unnamed"), src.into());
+
+        let (term, errors) = parse::term(&filemap);
+        if errors.is_empty() {
+            Ok(term)
+        } else {
+            Err(FromStrError::new(&filemap, errors))
+        }
+    }
+}
+
+/// Patterns
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    /// Patterns that match anything, without binding it
+    ///
+    /// ```text
+    /// _
+    /// ```
+    Wildcard(ByteSpan),
+    /// Patterns that bind a variable. The names of the boolean literals match
+    /// those literals instead.
+    ///
+    /// ```text
+    /// x
+    /// true
+    /// ```
+    Var(ByteSpan, String),
+    /// Pair patterns
+    ///
+    /// ```text
+    /// (p1, p2)
+    /// ```
+    Pair(ByteSpan, Box<Pattern>, Box<Pattern>),
+}
+
+impl Pattern {
+    /// Return the span of source code that this pattern originated from
+    pub fn span(&self) -> ByteSpan {
+        match *self {
+            Pattern::Wildcard(span) | Pattern::Var(span, _) | Pattern::Pair(span, _, _) => span,
+        }
+    }
+}
+
 /// The parameters to a lambda abstraction
-pub type LamParams = Vec<(Vec<(ByteSpan, String)>, Option<Box<Term>>)>;
+pub type LamParams = Vec<(Plicity, Vec<(ByteSpan, String)>, Option<Box<Term>>)>;
 
 /// The parameters to a dependent function type
 pub type PiParams = (Vec<(ByteSpan, String)>, Box<Term>);
+
+/// The bindings in a let expression, with their optional type annotations
+pub type LetBindings = Vec<(ByteSpan, String, Option<Box<Term>>, Box<Term>)>;
+
+/// The labels and types of the fields in a record type
+pub type RecordTypeFields = Vec<(ByteSpan, String, Box<Term>)>;
+
+/// The labels and values of the fields in a record
+pub type RecordFields = Vec<(ByteSpan, String, Box<Term>)>;
+
+/// The patterns and bodies of the clauses in a case expression
+pub type CaseClauses = Vec<(Pattern, Box<Term>)>;
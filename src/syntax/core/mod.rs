@@ -1,18 +1,30 @@
 //! The core syntax of the language
 
 use codespan::ByteSpan;
+#[cfg(feature = "serde")]
+use codespan::{ByteIndex, RawIndex};
 use rpds::List;
+use std::cell::RefCell;
+use std::cmp;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fmt;
+use std::mem;
 use std::rc::Rc;
+use std::str::FromStr;
 use std::usize;
 
+use syntax::parse;
 use syntax::pretty::{self, ToDoc};
 use syntax::var::{Debruijn, GenId, Named, Var};
 
 // YUCK!
 mod nameplate_ickiness;
+mod prim;
+mod sexp;
 
 pub use self::nameplate_ickiness::unbind2;
+pub use self::prim::{PrimFn, PrimImpl, PrimRegistry};
+pub use self::sexp::to_sexp;
 
 #[cfg(test)]
 mod tests;
@@ -37,8 +49,31 @@ impl PartialEq for SourceMeta {
     }
 }
 
+/// Spans are serialized as a pair of their start and end indices
+#[cfg(feature = "serde")]
+impl ::serde::Serialize for SourceMeta {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let span = (self.span.start().0, self.span.end().0);
+        ::serde::Serialize::serialize(&span, serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> ::serde::Deserialize<'de> for SourceMeta {
+    fn deserialize<D>(deserializer: D) -> Result<SourceMeta, D::Error>
+    where
+        D: ::serde::Deserializer<'de>,
+    {
+        let (start, end): (RawIndex, RawIndex) = ::serde::Deserialize::deserialize(deserializer)?;
+        let span = ByteSpan::new(ByteIndex(start), ByteIndex(end));
+
+        Ok(SourceMeta { span })
+    }
+}
+
 /// The name of a free variable
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Name {
     /// Names originating from user input
     User(String),
@@ -78,27 +113,170 @@ impl fmt::Display for Name {
 }
 
 /// A universe level
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Level(pub u32);
+///
+/// Level variables allow a definition to be used at more than one universe
+/// level. The variables in the type of a definition are replaced with fresh
+/// ones at each of its uses, which are then solved by level unification.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Level {
+    /// A constant level
+    Const(u32),
+    /// A level variable
+    Var(GenId),
+    /// The level after another level
+    Succ(Box<Level>),
+    /// The larger of two levels
+    Max(Box<Level>, Box<Level>),
+}
 
 impl Level {
-    pub const ZERO: Level = Level(0);
+    pub const ZERO: Level = Level::Const(0);
+
+    /// Create a fresh level variable
+    pub fn fresh() -> Level {
+        Level::Var(GenId::fresh())
+    }
+
+    /// The next level up. Constant levels saturate at the largest
+    /// representable level.
+    pub fn succ(&self) -> Level {
+        match *self {
+            Level::Const(level) => Level::Const(level.saturating_add(1)),
+            _ => Level::Succ(Box::new(self.clone())),
+        }
+    }
+
+    /// The next level up, or `None` if this is the largest representable
+    /// constant level
+    pub fn checked_succ(&self) -> Option<Level> {
+        match *self {
+            Level::Const(level) => level.checked_add(1).map(Level::Const),
+            _ => Some(Level::Succ(Box::new(self.clone()))),
+        }
+    }
 
-    pub fn succ(self) -> Level {
-        Level(self.0 + 1)
+    /// The larger of two levels
+    pub fn max(&self, other: &Level) -> Level {
+        match (self, other) {
+            (&Level::Const(level1), &Level::Const(level2)) => {
+                Level::Const(cmp::max(level1, level2))
+            },
+            (_, _) if self == other => self.clone(),
+            (_, _) => Level::Max(Box::new(self.clone()), Box::new(other.clone())),
+        }
+    }
+
+    /// Evaluate the level to a constant, if it doesn't depend on any level
+    /// variables
+    pub fn to_const(&self) -> Option<u32> {
+        match *self {
+            Level::Const(level) => Some(level),
+            Level::Var(_) => None,
+            Level::Succ(ref level) => level.to_const().map(|level| level.saturating_add(1)),
+            Level::Max(ref level1, ref level2) => {
+                Some(cmp::max(level1.to_const()?, level2.to_const()?))
+            },
+        }
+    }
+
+    /// Returns `true` if this level is no larger than the other level,
+    /// whatever the level variables are later solved to
+    pub fn is_le(&self, other: &Level) -> bool {
+        if self == other {
+            return true;
+        }
+        if let (Some(level1), Some(level2)) = (self.to_const(), other.to_const()) {
+            return level1 <= level2;
+        }
+
+        match (self, other) {
+            (&Level::Succ(ref level1), &Level::Succ(ref level2)) => level1.is_le(level2),
+            (&Level::Max(ref level1, ref level2), _) => level1.is_le(other) && level2.is_le(other),
+            (_, &Level::Max(ref level1, ref level2)) => self.is_le(level1) || self.is_le(level2),
+            (_, &Level::Succ(ref level2)) => self.is_le(level2),
+            (&Level::Const(0), _) => true,
+            (_, _) => false,
+        }
+    }
+
+    /// The level variables that this level depends on, in the order that
+    /// they were generated
+    pub fn vars(&self) -> BTreeSet<GenId> {
+        let mut vars = BTreeSet::new();
+        self.visit_vars(&mut |id| {
+            vars.insert(id);
+        });
+        vars
+    }
+
+    fn visit_vars<F: FnMut(GenId)>(&self, on_var: &mut F) {
+        match *self {
+            Level::Const(_) => {},
+            Level::Var(id) => on_var(id),
+            Level::Succ(ref level) => level.visit_vars(on_var),
+            Level::Max(ref level1, ref level2) => {
+                level1.visit_vars(on_var);
+                level2.visit_vars(on_var);
+            },
+        }
+    }
+
+    /// Replace the level variables that have mappings, including those that
+    /// are mentioned by the levels they are mapped to
+    pub fn subst(&self, mappings: &HashMap<GenId, Level>) -> Level {
+        match *self {
+            Level::Const(_) => self.clone(),
+            Level::Var(id) => match mappings.get(&id) {
+                Some(level) => level.subst(mappings),
+                None => self.clone(),
+            },
+            Level::Succ(ref level) => level.subst(mappings).succ(),
+            Level::Max(ref level1, ref level2) => {
+                level1.subst(mappings).max(&level2.subst(mappings))
+            },
+        }
     }
 }
 
 impl fmt::Display for Level {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.0)
+        match *self {
+            Level::Const(level) => write!(f, "{}", level),
+            Level::Var(id) => write!(f, "{}", id),
+            Level::Succ(ref level) => write!(f, "{}+1", level),
+            Level::Max(ref level1, ref level2) => write!(f, "max({}, {})", level1, level2),
+        }
     }
 }
 
+/// Whether a definition may be unfolded to its value during normalization
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Transparency {
+    /// The definition is replaced by its value during normalization
+    Transparent,
+    /// The definition is left as a neutral variable, hiding its value. Two
+    /// opaque definitions are only equal if they have the same name.
+    Opaque,
+}
+
+/// Whether an argument is supplied explicitly at application sites, or is
+/// filled in by the type checker
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Plicity {
+    /// The argument must be supplied, eg. `(x : t) -> body`
+    Explicit,
+    /// The argument is inferred from the surrounding terms, eg. `{x : t} -> body`
+    Implicit,
+}
+
 /// A module definition
 pub struct Module {
     /// The name of the module
     pub name: String,
+    /// The modules that this module imports
+    pub imports: Vec<Import>,
     /// The definitions contained in the module
     pub definitions: Vec<Definition>,
 }
@@ -111,14 +289,47 @@ impl fmt::Display for Module {
     }
 }
 
+/// An import of another module
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import {
+    /// The span of the import declaration
+    pub span: ByteSpan,
+    /// The name of the imported module
+    pub name: String,
+    /// The name to bind the imported module to, if it is different from the
+    /// name of the module
+    pub rename: Option<String>,
+    /// The definitions that the import brings into scope
+    pub exposing: Exposing,
+}
+
+/// The definitions that an import brings into scope
+#[derive(Debug, Clone, PartialEq)]
+pub enum Exposing {
+    /// No definitions - they can still be projected from the module
+    Nothing,
+    /// Every definition in the module, under its own name
+    All,
+    /// The given definitions, along with the names to bind them to
+    Exact(Vec<(ByteSpan, String, String)>),
+    /// Every definition in the module except for the given ones
+    Hiding(Vec<(ByteSpan, String)>),
+}
+
 /// Top level definitions
 pub struct Definition {
     /// The name of the declaration
     pub name: String,
+    /// The doc comments attached to the declaration
+    pub docs: Vec<String>,
     /// The body of the definition
     pub term: RcTerm,
     /// An optional type annotation to aid in type inference
     pub ann: Option<RcTerm>,
+    /// Whether the definition may be unfolded during normalization
+    pub transparency: Transparency,
+    /// Whether the definition may refer to itself in its body
+    pub recursive: bool,
 }
 
 impl fmt::Display for Definition {
@@ -136,10 +347,32 @@ impl fmt::Display for Definition {
 ///       | Typeᵢ       2. universes
 ///       | x           3. variables
 ///       | λx:ρ₁.ρ₂    4. lambda abstractions
+///       | λ{x:ρ₁}.ρ₂  4. implicit lambda abstractions
 ///       | Πx:ρ₁.ρ₂    5. dependent function types
+///       | Π{x:ρ₁}.ρ₂  5. implicit dependent function types
 ///       | ρ₁ ρ₂       6. term application
+///       | let x:ρ₁ = e₁ in e₂
+///                     7. let bindings
+///       | Σx:ρ₁.ρ₂    8. dependent pair types
+///       | (e₁, e₂)    9. pairs
+///       | fst e       10. first projections
+///       | snd e       11. second projections
+///       | Record { l:ρ₁; ρ₂ }
+///                     12. record types
+///       | Record {}   13. empty record types
+///       | record { l = e₁; e₂ }
+///                     14. records
+///       | record {}   15. empty records
+///       | e.l         16. field projections
+///       | c           17. constants
+///       | if e₁ then e₂ else e₃
+///                     18. conditionals
+///       | ?           19. holes
+///       | case e of { p₁ => e₁; ... }
+///                     20. case expressions
 /// ```
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Term {
     /// A term annotated with a type
     Ann(SourceMeta, RcTerm, RcTerm), // 1.
@@ -153,6 +386,35 @@ pub enum Term {
     Pi(SourceMeta, TermPi), // 5.
     /// Term application
     App(SourceMeta, RcTerm, RcTerm), // 6.
+    /// Let bindings
+    Let(SourceMeta, TermLet), // 7.
+    /// Dependent pair types
+    Sigma(SourceMeta, TermSigma), // 8.
+    /// Pairs
+    Pair(SourceMeta, RcTerm, RcTerm), // 9.
+    /// The first component of a pair
+    Fst(SourceMeta, RcTerm), // 10.
+    /// The second component of a pair
+    Snd(SourceMeta, RcTerm), // 11.
+    /// Record types, extended with a field. The label of the field is bound
+    /// in the types of the remaining fields.
+    RecordType(SourceMeta, TermRecordType), // 12.
+    /// The empty record type
+    RecordTypeEmpty(SourceMeta), // 13.
+    /// Records, extended with a field
+    Record(SourceMeta, String, RcTerm, RcTerm), // 14.
+    /// The empty record
+    RecordEmpty(SourceMeta), // 15.
+    /// Field projection
+    Proj(SourceMeta, RcTerm, String), // 16.
+    /// Constants
+    Constant(SourceMeta, Constant), // 17.
+    /// Conditionals
+    If(SourceMeta, RcTerm, RcTerm, RcTerm), // 18.
+    /// A placeholder for a term that has not been written yet
+    Hole(SourceMeta), // 19.
+    /// Case expressions, selecting the first clause whose pattern matches
+    Case(SourceMeta, RcTerm, Vec<TermClause>), // 20.
 }
 
 impl fmt::Display for Term {
@@ -165,28 +427,171 @@ impl fmt::Display for Term {
 
 // TODO: Reduce boilderplate with a name binding abstraction
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TermLam {
+    pub plicity: Plicity,
     pub unsafe_param: Named<Name, Option<RcTerm>>,
     pub unsafe_body: RcTerm,
 }
 
 // TODO: Reduce boilderplate with a name binding abstraction
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TermPi {
+    pub plicity: Plicity,
+    pub unsafe_param: Named<Name, RcTerm>,
+    pub unsafe_body: RcTerm,
+}
+
+// TODO: Reduce boilderplate with a name binding abstraction
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TermLet {
+    pub unsafe_param: Named<Name, Option<RcTerm>>,
+    /// The term bound to the parameter. The binding is not recursive, so the
+    /// parameter is not in scope here.
+    pub value: RcTerm,
+    pub unsafe_body: RcTerm,
+}
+
+// TODO: Reduce boilderplate with a name binding abstraction
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TermSigma {
+    pub unsafe_param: Named<Name, RcTerm>,
+    pub unsafe_body: RcTerm,
+}
+
+// TODO: Reduce boilderplate with a name binding abstraction
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TermClause {
+    /// The pattern of the clause. The variables that it binds are bound in
+    /// the body, with the rightmost variable being the innermost binder.
+    pub unsafe_pattern: Pattern,
+    pub unsafe_body: RcTerm,
+}
+
+// TODO: Reduce boilderplate with a name binding abstraction
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TermRecordType {
+    /// The label of the field
+    pub label: String,
     pub unsafe_param: Named<Name, RcTerm>,
+    /// The types of the remaining fields
     pub unsafe_body: RcTerm,
 }
 
+/// Patterns, used to destructure the scrutinee of a case expression
+///
+/// ```text
+/// p ::= _             1. wildcard patterns
+///     | x             2. binder patterns
+///     | c             3. constant patterns
+///     | (p₁, p₂)      4. pair patterns
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Pattern {
+    /// Matches anything, without binding it
+    Wildcard(SourceMeta), // 1.
+    /// Matches anything, binding it to a variable
+    Binder(SourceMeta, Named<Name, ()>), // 2.
+    /// Matches a constant. Only boolean literals are supported for now.
+    Constant(SourceMeta, Constant), // 3.
+    /// Matches the components of a pair
+    Pair(SourceMeta, Box<Pattern>, Box<Pattern>), // 4.
+}
+
+impl Pattern {
+    pub fn span(&self) -> ByteSpan {
+        match *self {
+            Pattern::Wildcard(meta)
+            | Pattern::Binder(meta, _)
+            | Pattern::Constant(meta, _)
+            | Pattern::Pair(meta, _, _) => meta.span,
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.to_doc(pretty::Options::default().with_debug_indices(f.alternate()))
+            .group()
+            .render_fmt(f.width().unwrap_or(usize::MAX), f)
+    }
+}
+
+/// Built-in constants
+///
+/// ```text
+/// c ::= Bool          1. the type of booleans
+///     | true          2. boolean literals
+///     | false
+///     | Int           3. the type of integers
+///     | n             4. integer literals
+///     | String        5. the type of strings
+///     | "s"           6. string literals
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Constant {
+    /// The type of booleans
+    BoolType, // 1.
+    /// Boolean literals
+    Bool(bool), // 2.
+    /// The type of 64-bit signed integers
+    IntType, // 3.
+    /// Integer literals
+    Int(i64), // 4.
+    /// The type of strings
+    StringType, // 5.
+    /// String literals
+    String(String), // 6.
+}
+
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Constant::BoolType => write!(f, "Bool"),
+            Constant::Bool(true) => write!(f, "true"),
+            Constant::Bool(false) => write!(f, "false"),
+            Constant::IntType => write!(f, "Int"),
+            Constant::Int(value) => write!(f, "{}", value),
+            Constant::StringType => write!(f, "String"),
+            Constant::String(ref value) => write!(f, "\"{}\"", parse::escape_string(value)),
+        }
+    }
+}
+
 /// Normal forms
 ///
 /// ```text
 /// v,τ ::= Typeᵢ       1. universes
 ///       | x           2. variables
 ///       | λx:τ₁.τ₂    3. lambda abstractions
+///       | λ{x:τ₁}.τ₂  3. implicit lambda abstractions
 ///       | Πx:τ₁.τ₂    4. dependent function types
+///       | Π{x:τ₁}.τ₂  4. implicit dependent function types
 ///       | τ₁ τ₂       5. term application
+///       | Σx:τ₁.τ₂    6. dependent pair types
+///       | (v₁, v₂)    7. pairs
+///       | fst τ       8. stuck first projections
+///       | snd τ       9. stuck second projections
+///       | Record { l:τ₁; τ₂ }
+///                     10. record types
+///       | Record {}   11. empty record types
+///       | record { l = v₁; v₂ }
+///                     12. records
+///       | record {}   13. empty records
+///       | τ.l         14. stuck field projections
+///       | c           15. constants
+///       | if τ₁ then v₂ else v₃
+///                     16. stuck conditionals
 /// ```
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Value {
     /// Universes
     Universe(Level), // 1.
@@ -198,6 +603,28 @@ pub enum Value {
     Pi(ValuePi), // 4.
     /// Term application
     App(RcValue, RcValue), // 5.
+    /// A sigma type
+    Sigma(ValueSigma), // 6.
+    /// Pairs
+    Pair(RcValue, RcValue), // 7.
+    /// The first component of a neutral pair
+    Fst(RcValue), // 8.
+    /// The second component of a neutral pair
+    Snd(RcValue), // 9.
+    /// Record types, extended with a field
+    RecordType(ValueRecordType), // 10.
+    /// The empty record type
+    RecordTypeEmpty, // 11.
+    /// Records, extended with a field
+    Record(String, RcValue, RcValue), // 12.
+    /// The empty record
+    RecordEmpty, // 13.
+    /// A projection from a neutral record
+    Proj(RcValue, String), // 14.
+    /// Constants
+    Constant(Constant), // 15.
+    /// A conditional on a neutral boolean
+    If(RcValue, RcValue, RcValue), // 16.
 }
 
 impl fmt::Display for Value {
@@ -210,27 +637,59 @@ impl fmt::Display for Value {
 
 // TODO: Reduce boilderplate with a name binding abstraction
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ValueLam {
+    pub plicity: Plicity,
     pub unsafe_param: Named<Name, Option<RcValue>>,
     pub unsafe_body: RcValue,
 }
 
 // TODO: Reduce boilderplate with a name binding abstraction
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ValuePi {
+    pub plicity: Plicity,
     pub unsafe_param: Named<Name, RcValue>,
     pub unsafe_body: RcValue,
 }
 
+// TODO: Reduce boilderplate with a name binding abstraction
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ValueSigma {
+    pub unsafe_param: Named<Name, RcValue>,
+    pub unsafe_body: RcValue,
+}
+
+// TODO: Reduce boilderplate with a name binding abstraction
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ValueRecordType {
+    /// The label of the field
+    pub label: String,
+    pub unsafe_param: Named<Name, RcValue>,
+    /// The types of the remaining fields
+    pub unsafe_body: RcValue,
+}
+
 // Wrapper types
 
 macro_rules! make_wrapper {
     ($name:ident, $wrapper:ident, $inner:ty) => {
-        #[derive(Clone, PartialEq)]
+        #[derive(Clone)]
         pub struct $name {
             pub inner: $wrapper<$inner>,
         }
 
+        impl PartialEq for $name {
+            fn eq(&self, other: &$name) -> bool {
+                // Shared pointers are trivially equal, so we can avoid
+                // traversing the entire tree in that case. This is common
+                // when comparing a type against itself during checking.
+                $wrapper::ptr_eq(&self.inner, &other.inner) || self.inner == other.inner
+            }
+        }
+
         impl From<$inner> for $name {
             fn from(src: $inner) -> $name {
                 $name {
@@ -250,6 +709,25 @@ macro_rules! make_wrapper {
                 $crate::std::fmt::Display::fmt(&self.inner, f)
             }
         }
+
+        // Sharing between pointers is not preserved, so a deserialized tree
+        // may take up more memory than the one that was serialized
+        #[cfg(feature = "serde")]
+        impl ::serde::Serialize for $name {
+            fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                ::serde::Serialize::serialize(&*self.inner, serializer)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> ::serde::Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<$name, D::Error>
+            where
+                D: ::serde::Deserializer<'de>,
+            {
+                <$inner as ::serde::Deserialize>::deserialize(deserializer).map($name::from)
+            }
+        }
     };
 }
 
@@ -270,18 +748,121 @@ impl RcTerm {
             | Term::Var(meta, _)
             | Term::Lam(meta, _)
             | Term::Pi(meta, _)
-            | Term::App(meta, _, _) => meta.span,
+            | Term::App(meta, _, _)
+            | Term::Let(meta, _)
+            | Term::Sigma(meta, _)
+            | Term::Pair(meta, _, _)
+            | Term::Fst(meta, _)
+            | Term::Snd(meta, _)
+            | Term::RecordType(meta, _)
+            | Term::RecordTypeEmpty(meta)
+            | Term::Record(meta, _, _, _)
+            | Term::RecordEmpty(meta)
+            | Term::Proj(meta, _, _)
+            | Term::Constant(meta, _)
+            | Term::If(meta, _, _, _)
+            | Term::Hole(meta)
+            | Term::Case(meta, _, _) => meta.span,
         }
     }
 }
 
+/// Values are a subset of terms, so they can always be converted back into
+/// terms. The converted terms have no source locations.
+impl<'a> From<&'a Value> for Term {
+    fn from(src: &'a Value) -> Term {
+        let meta = SourceMeta::default();
+
+        match *src {
+            Value::Universe(ref level) => Term::Universe(meta, level.clone()),
+            Value::Var(ref var) => Term::Var(meta, var.clone()),
+            Value::Lam(ref lam) => Term::Lam(
+                meta,
+                TermLam {
+                    plicity: lam.plicity,
+                    unsafe_param: Named::new(
+                        lam.unsafe_param.name.clone(),
+                        lam.unsafe_param.inner.as_ref().map(RcTerm::from),
+                    ),
+                    unsafe_body: RcTerm::from(&lam.unsafe_body),
+                },
+            ),
+            Value::Pi(ref pi) => Term::Pi(
+                meta,
+                TermPi {
+                    plicity: pi.plicity,
+                    unsafe_param: Named::new(
+                        pi.unsafe_param.name.clone(),
+                        RcTerm::from(&pi.unsafe_param.inner),
+                    ),
+                    unsafe_body: RcTerm::from(&pi.unsafe_body),
+                },
+            ),
+            Value::App(ref fn_expr, ref arg) => {
+                Term::App(meta, RcTerm::from(fn_expr), RcTerm::from(arg))
+            },
+            Value::Sigma(ref sigma) => Term::Sigma(
+                meta,
+                TermSigma {
+                    unsafe_param: Named::new(
+                        sigma.unsafe_param.name.clone(),
+                        RcTerm::from(&sigma.unsafe_param.inner),
+                    ),
+                    unsafe_body: RcTerm::from(&sigma.unsafe_body),
+                },
+            ),
+            Value::Pair(ref fst, ref snd) => Term::Pair(meta, RcTerm::from(fst), RcTerm::from(snd)),
+            Value::Fst(ref pair) => Term::Fst(meta, RcTerm::from(pair)),
+            Value::Snd(ref pair) => Term::Snd(meta, RcTerm::from(pair)),
+            Value::RecordType(ref record_ty) => Term::RecordType(
+                meta,
+                TermRecordType {
+                    label: record_ty.label.clone(),
+                    unsafe_param: Named::new(
+                        record_ty.unsafe_param.name.clone(),
+                        RcTerm::from(&record_ty.unsafe_param.inner),
+                    ),
+                    unsafe_body: RcTerm::from(&record_ty.unsafe_body),
+                },
+            ),
+            Value::RecordTypeEmpty => Term::RecordTypeEmpty(meta),
+            Value::Record(ref label, ref value, ref rest) => {
+                Term::Record(meta, label.clone(), RcTerm::from(value), RcTerm::from(rest))
+            },
+            Value::RecordEmpty => Term::RecordEmpty(meta),
+            Value::Proj(ref record, ref label) => {
+                Term::Proj(meta, RcTerm::from(record), label.clone())
+            },
+            Value::Constant(ref constant) => Term::Constant(meta, constant.clone()),
+            Value::If(ref cond, ref if_true, ref if_false) => Term::If(
+                meta,
+                RcTerm::from(cond),
+                RcTerm::from(if_true),
+                RcTerm::from(if_false),
+            ),
+        }
+    }
+}
+
+impl<'a> From<&'a RcValue> for RcTerm {
+    fn from(src: &'a RcValue) -> RcTerm {
+        RcTerm::from(Term::from(&*src.inner))
+    }
+}
+
 /// A binder that introduces a variable into the context
 ///
 /// ```text
 /// b ::= λx:τ           1. lambda abstraction
 ///     | Πx:τ           2. dependent function
 ///     | let x:τ = v    3. let binding
+///     | x:τ            4. claim
 /// ```
+///
+/// Let bindings also record whether they are allowed to be unfolded during
+/// normalization. Their values are stored in normal form, so unfolding a
+/// definition never has to evaluate its body again - shadowing the binding
+/// is all it takes to replace it.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Binder {
     /// A type introduced after entering a lambda abstraction
@@ -289,7 +870,9 @@ pub enum Binder {
     /// A type introduced after entering a pi type
     Pi(RcType), // 2.
     /// A value and type binding that was introduced by passing over a let binding
-    Let(RcValue, RcType), // 3.
+    Let(RcValue, RcType, Transparency), // 3.
+    /// A type that was claimed for a name, before its value has been defined
+    Claim(RcType), // 4.
 }
 
 impl Binder {
@@ -299,6 +882,86 @@ impl Binder {
     }
 }
 
+/// Where a binder in the context originated from
+///
+/// Built-in bindings have no meaningful source location, so this is used to
+/// describe them in error messages instead.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Origin {
+    /// A binder that was introduced by the prelude
+    Prelude,
+    /// A binder that was introduced by user input
+    User,
+}
+
+/// Which transparent definitions should be unfolded during normalization,
+/// based on where they originated from
+///
+/// Definitions that are not unfolded are left as neutral variables, allowing
+/// parts of a term to be kept symbolic when displaying it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UnfoldPolicy {
+    /// Unfold all definitions
+    All,
+    /// Only unfold definitions from the prelude
+    Prelude,
+    /// Only unfold definitions from user input
+    User,
+    /// Don't unfold any definitions
+    None,
+}
+
+impl UnfoldPolicy {
+    /// Returns `true` if definitions with the given origin should be unfolded
+    pub fn unfolds(self, origin: Origin) -> bool {
+        match (self, origin) {
+            (UnfoldPolicy::All, _)
+            | (UnfoldPolicy::Prelude, Origin::Prelude)
+            | (UnfoldPolicy::User, Origin::User) => true,
+            (UnfoldPolicy::Prelude, Origin::User)
+            | (UnfoldPolicy::User, Origin::Prelude)
+            | (UnfoldPolicy::None, _) => false,
+        }
+    }
+}
+
+impl fmt::Display for UnfoldPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UnfoldPolicy::All => write!(f, "all"),
+            UnfoldPolicy::Prelude => write!(f, "prelude"),
+            UnfoldPolicy::User => write!(f, "user"),
+            UnfoldPolicy::None => write!(f, "none"),
+        }
+    }
+}
+
+impl FromStr for UnfoldPolicy {
+    type Err = &'static str;
+
+    fn from_str(src: &str) -> Result<UnfoldPolicy, &'static str> {
+        match src {
+            "all" => Ok(UnfoldPolicy::All),
+            "prelude" => Ok(UnfoldPolicy::Prelude),
+            "user" => Ok(UnfoldPolicy::User),
+            "none" => Ok(UnfoldPolicy::None),
+            _ => Err("no match"),
+        }
+    }
+}
+
+/// An error produced when defining a name in the context
+#[derive(Debug, Fail, Clone, PartialEq)]
+pub enum ContextError {
+    #[fail(display = "Defined `{}` without declaring its type first.", name)]
+    Undeclared { name: Name },
+    #[fail(display = "The name `{}` has already been defined.", name)]
+    AlreadyDefined { name: Name },
+}
+
+/// The default number of in-scope bindings to record along with each hole
+pub const DEFAULT_HOLE_CONTEXT_LIMIT: usize = 10;
+
 /// A list of binders that have been accumulated during typechecking
 ///
 /// ```text
@@ -307,7 +970,30 @@ impl Binder {
 /// ```
 #[derive(Clone, PartialEq)]
 pub struct Context {
-    pub binders: List<(Name, Binder)>,
+    pub binders: List<(Name, Binder, Origin)>,
+    /// Which transparent definitions to unfold during normalization
+    pub unfold_policy: UnfoldPolicy,
+    /// The number of reduction steps that normalization may take before
+    /// giving up, or `None` if there is no limit
+    pub step_limit: Option<u64>,
+    /// The primitive functions that are in scope, unless they are shadowed by
+    /// one of the binders
+    pub prims: Rc<PrimRegistry>,
+    /// The holes that were found while checking terms, along with their
+    /// expected types and the bindings that were in scope. These are shared
+    /// with the contexts that are derived from this one.
+    pub holes: Rc<RefCell<Vec<(ByteSpan, RcType, Vec<(Name, RcType)>)>>>,
+    /// The number of in-scope bindings to record along with each hole
+    pub hole_context_limit: usize,
+    /// The solutions to level variables that were found while checking
+    /// terms. These are shared with the contexts that are derived from this
+    /// one.
+    pub levels: Rc<RefCell<HashMap<GenId, Level>>>,
+    /// The names that imported modules are bound to, along with the names of
+    /// the modules and the number of binders in the context when they were
+    /// imported. The modules themselves are bound as records of their
+    /// definitions, so qualified names like `f.bar` are field projections.
+    pub modules: List<(Name, String, usize)>,
 }
 
 impl Context {
@@ -315,21 +1001,230 @@ impl Context {
     pub fn new() -> Context {
         Context {
             binders: List::new(),
+            unfold_policy: UnfoldPolicy::All,
+            step_limit: None,
+            prims: Rc::new(PrimRegistry::new()),
+            holes: Rc::new(RefCell::new(Vec::new())),
+            hole_context_limit: DEFAULT_HOLE_CONTEXT_LIMIT,
+            levels: Rc::new(RefCell::new(HashMap::new())),
+            modules: List::new(),
         }
     }
 
-    /// Extend the context with a binder
+    /// Set the primitive functions that are in scope
+    pub fn with_prims(self, prims: PrimRegistry) -> Context {
+        Context {
+            prims: Rc::new(prims),
+            ..self
+        }
+    }
+
+    /// Set which transparent definitions to unfold during normalization
+    pub fn with_unfold_policy(self, unfold_policy: UnfoldPolicy) -> Context {
+        Context {
+            unfold_policy,
+            ..self
+        }
+    }
+
+    /// Set the number of reduction steps that normalization may take before
+    /// giving up, or `None` to allow normalization to run forever
+    pub fn with_step_limit(self, step_limit: Option<u64>) -> Context {
+        Context { step_limit, ..self }
+    }
+
+    /// Set the number of in-scope bindings to record along with each hole
+    pub fn with_hole_context_limit(self, hole_context_limit: usize) -> Context {
+        Context {
+            hole_context_limit,
+            ..self
+        }
+    }
+
+    /// Extend the context with a binder that originated from user input
     pub fn extend(&self, name: Name, binder: Binder) -> Context {
+        self.extend_with_origin(name, binder, Origin::User)
+    }
+
+    /// Extend the context with a binder, recording where it originated from
+    pub fn extend_with_origin(&self, name: Name, binder: Binder, origin: Origin) -> Context {
         Context {
-            binders: self.binders.push_front((name, binder)),
+            binders: self.binders.push_front((name, binder, origin)),
+            unfold_policy: self.unfold_policy,
+            step_limit: self.step_limit,
+            prims: self.prims.clone(),
+            holes: self.holes.clone(),
+            hole_context_limit: self.hole_context_limit,
+            levels: self.levels.clone(),
+            modules: self.modules.clone(),
         }
     }
 
+    /// Extend the context with an imported module, bound to `name` as a record
+    /// of its definitions
+    pub fn import(
+        &self,
+        name: Name,
+        module: String,
+        record: RcValue,
+        record_ty: RcType,
+    ) -> Context {
+        let binder = Binder::Let(record, record_ty, Transparency::Transparent);
+        let context = self.extend(name.clone(), binder);
+        let modules = context.modules.push_front((name, module, context.binders.len()));
+
+        Context { modules, ..context }
+    }
+
     pub fn lookup_binder(&self, name: &Name) -> Option<&Binder> {
         self.binders
             .iter()
-            .find(|&&(ref n, _)| n == name)
-            .map(|&(_, ref b)| b)
+            .find(|&&(ref n, _, _)| n == name)
+            .map(|&(_, ref b, _)| b)
+    }
+
+    /// Look up the primitive function with the given name, if it is not
+    /// shadowed by a binder
+    pub fn lookup_prim(&self, name: &Name) -> Option<&PrimFn> {
+        match self.lookup_binder(name) {
+            Some(_) => None,
+            None => self.prims.lookup(name),
+        }
+    }
+
+    /// The number of binders in the context, including those that have been
+    /// shadowed by later binders
+    pub fn len(&self) -> usize {
+        self.binders.len()
+    }
+
+    /// Returns `true` if nothing has been bound in the context
+    pub fn is_empty(&self) -> bool {
+        self.binders.is_empty()
+    }
+
+    /// Look up the type of a name that is bound in the context, along with its
+    /// value if it is bound by a definition
+    pub fn lookup(&self, name: &Name) -> Option<(&RcType, Option<&RcValue>)> {
+        match *self.lookup_binder(name)? {
+            Binder::Lam(Some(ref ann)) | Binder::Pi(ref ann) | Binder::Claim(ref ann) => {
+                Some((ann, None))
+            },
+            Binder::Let(ref value, ref ann, _) => Some((ann, Some(value))),
+            Binder::Lam(None) => None,
+        }
+    }
+
+    /// The names that are bound in the context, starting with the most
+    /// recently bound, along with their types and the values of definitions.
+    /// Shadowed binders are skipped, as are lambda parameters that have no
+    /// type annotation.
+    pub fn iter_bindings<'a>(
+        &'a self,
+    ) -> impl Iterator<Item = (&'a Name, &'a RcType, Option<&'a RcValue>)> + 'a {
+        let mut seen = HashSet::new();
+
+        self.binders
+            .iter()
+            .filter(move |&&(ref name, _, _)| seen.insert(name))
+            .filter_map(|&(ref name, ref binder, _)| match *binder {
+                Binder::Lam(Some(ref ann)) | Binder::Pi(ref ann) | Binder::Claim(ref ann) => {
+                    Some((name, ann, None))
+                },
+                Binder::Let(ref value, ref ann, _) => Some((name, ann, Some(value))),
+                Binder::Lam(None) => None,
+            })
+    }
+
+    /// The names that are in scope, including those of the primitives,
+    /// starting with the most recently bound
+    pub fn names<'a>(&'a self) -> impl Iterator<Item = &'a Name> + 'a {
+        let binder_names = self.binders.iter().map(|&(ref name, _, _)| name);
+        let prim_names = self.prims.iter().map(|(name, _)| name);
+
+        binder_names.chain(prim_names)
+    }
+
+    /// Look up the name of the imported module that the given name is bound
+    /// to, if it has not been shadowed by a later binder
+    pub fn lookup_module(&self, name: &Name) -> Option<&str> {
+        let position = self.binders.iter().position(|&(ref n, _, _)| n == name)?;
+        let binders_len = self.binders.len() - position;
+
+        self.modules
+            .iter()
+            .find(|&&(ref n, _, len)| n == name && len == binders_len)
+            .map(|&(_, ref module, _)| module.as_str())
+    }
+
+    /// Look up where the binder for the given name originated from
+    pub fn lookup_origin(&self, name: &Name) -> Option<Origin> {
+        self.binders
+            .iter()
+            .find(|&&(ref n, _, _)| n == name)
+            .map(|&(_, _, origin)| origin)
+    }
+
+    /// Claim that a name has the given type, before giving it a value with
+    /// `define`
+    pub fn declare(&self, name: Name, ann: RcType) -> Context {
+        self.extend(name, Binder::Claim(ann))
+    }
+
+    /// Give a value to a name that was previously claimed with `declare`
+    ///
+    /// The definition shadows the claim, and keeps the claim's origin.
+    pub fn define(
+        &self,
+        name: Name,
+        value: RcValue,
+        transparency: Transparency,
+    ) -> Result<Context, ContextError> {
+        let found = self.binders
+            .iter()
+            .find(|&&(ref n, _, _)| *n == name)
+            .map(|&(_, ref binder, origin)| (binder.clone(), origin));
+
+        let (ann, origin) = match found {
+            Some((Binder::Claim(ann), origin)) => (ann, origin),
+            Some((Binder::Let(..), _)) => return Err(ContextError::AlreadyDefined { name }),
+            Some((Binder::Lam(_), _)) | Some((Binder::Pi(_), _)) | None => {
+                return Err(ContextError::Undeclared { name });
+            },
+        };
+
+        let binder = Binder::Let(value, ann, transparency);
+        Ok(self.extend_with_origin(name, binder, origin))
+    }
+
+    /// Record a hole that was found while checking a term, along with the
+    /// most recently bound names that are in scope at the hole
+    pub fn push_hole(&self, span: ByteSpan, expected: RcType) {
+        let bindings = self.iter_bindings()
+            .take(self.hole_context_limit)
+            .map(|(name, ann, _)| (name.clone(), ann.clone()))
+            .collect();
+
+        self.holes.borrow_mut().push((span, expected, bindings));
+    }
+
+    /// Remove and return the holes that have been recorded so far
+    pub fn take_holes(&self) -> Vec<(ByteSpan, RcType, Vec<(Name, RcType)>)> {
+        mem::replace(&mut *self.holes.borrow_mut(), Vec::new())
+    }
+
+    /// Returns `true` if the definition bound to the given name should be
+    /// unfolded, according to the unfold policy
+    pub fn unfolds(&self, name: &Name) -> bool {
+        self.lookup_origin(name)
+            .map_or(true, |origin| self.unfold_policy.unfolds(origin))
+    }
+}
+
+/// The initial context, with the built-in primitives in scope
+impl Default for Context {
+    fn default() -> Context {
+        Context::new().with_prims(PrimRegistry::builtins())
     }
 }
 
@@ -343,7 +1238,7 @@ impl fmt::Display for Context {
 
 impl fmt::Debug for Context {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        struct FmtBinders<'a>(&'a List<(Name, Binder)>);
+        struct FmtBinders<'a>(&'a List<(Name, Binder, Origin)>);
 
         impl<'a> fmt::Debug for FmtBinders<'a> {
             fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -353,6 +1248,7 @@ impl fmt::Debug for Context {
 
         f.debug_struct("Context")
             .field("binders", &FmtBinders(&self.binders))
+            .field("prims", &self.prims)
             .finish()
     }
 }
@@ -25,7 +25,7 @@
 //! - The Penn Locally Nameless Metatheory Library
 //!     - [Github](https://github.com/plclub/metalib)
 
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 use super::*;
 
@@ -34,11 +34,17 @@ impl TermLam {
         body.close(&param.name);
 
         TermLam {
+            plicity: Plicity::Explicit,
             unsafe_param: param,
             unsafe_body: body,
         }
     }
 
+    /// Set whether the parameter is supplied explicitly or implicitly
+    pub fn with_plicity(self, plicity: Plicity) -> TermLam {
+        TermLam { plicity, ..self }
+    }
+
     pub fn unbind(mut self) -> (Named<Name, Option<RcTerm>>, RcTerm) {
         let fv = Name::fresh(self.unsafe_param.name.name());
         self.unsafe_param.name = fv.clone();
@@ -48,6 +54,31 @@ impl TermLam {
                 .open(&Term::Var(SourceMeta::default(), Var::Free(fv)).into()),
         )
     }
+
+    /// Replace the bound parameter in the body of the lambda with the given
+    /// term, without having to generate a fresh name in the process
+    pub fn instantiate(&self, x: &RcTerm) -> RcTerm {
+        self.unsafe_body.open(x)
+    }
+
+    /// Rebuild the lambda by applying a fallible function to the parameter's
+    /// annotation and to the body. The body is not unbound first, so the
+    /// function sees the parameter as a bound variable.
+    pub fn try_map<E, F>(&self, mut f: F) -> Result<TermLam, E>
+    where
+        F: FnMut(&RcTerm) -> Result<RcTerm, E>,
+    {
+        let param = self.unsafe_param.clone().try_map(|ann| match ann {
+            None => Ok(None),
+            Some(ann) => Ok(Some(f(&ann)?)),
+        })?;
+
+        Ok(TermLam {
+            plicity: self.plicity,
+            unsafe_param: param,
+            unsafe_body: f(&self.unsafe_body)?,
+        })
+    }
 }
 
 impl TermPi {
@@ -55,11 +86,17 @@ impl TermPi {
         body.close(&param.name);
 
         TermPi {
+            plicity: Plicity::Explicit,
             unsafe_param: param,
             unsafe_body: body,
         }
     }
 
+    /// Set whether the parameter is supplied explicitly or implicitly
+    pub fn with_plicity(self, plicity: Plicity) -> TermPi {
+        TermPi { plicity, ..self }
+    }
+
     pub fn unbind(mut self) -> (Named<Name, RcTerm>, RcTerm) {
         let fv = Name::fresh(self.unsafe_param.name.name());
         self.unsafe_param.name = fv.clone();
@@ -69,6 +106,256 @@ impl TermPi {
                 .open(&Term::Var(SourceMeta::default(), Var::Free(fv)).into()),
         )
     }
+
+    /// Replace the bound parameter in the body of the pi type with the given
+    /// term, without having to generate a fresh name in the process
+    pub fn instantiate(&self, x: &RcTerm) -> RcTerm {
+        self.unsafe_body.open(x)
+    }
+
+    /// Rebuild the pi type by applying a fallible function to the parameter's
+    /// annotation and to the body. The body is not unbound first, so the
+    /// function sees the parameter as a bound variable.
+    pub fn try_map<E, F>(&self, mut f: F) -> Result<TermPi, E>
+    where
+        F: FnMut(&RcTerm) -> Result<RcTerm, E>,
+    {
+        let param = self.unsafe_param.clone().try_map(|ann| f(&ann))?;
+
+        Ok(TermPi {
+            plicity: self.plicity,
+            unsafe_param: param,
+            unsafe_body: f(&self.unsafe_body)?,
+        })
+    }
+}
+
+impl TermSigma {
+    pub fn bind(param: Named<Name, RcTerm>, mut body: RcTerm) -> TermSigma {
+        body.close(&param.name);
+
+        TermSigma {
+            unsafe_param: param,
+            unsafe_body: body,
+        }
+    }
+
+    pub fn unbind(mut self) -> (Named<Name, RcTerm>, RcTerm) {
+        let fv = Name::fresh(self.unsafe_param.name.name());
+        self.unsafe_param.name = fv.clone();
+        (
+            self.unsafe_param,
+            self.unsafe_body
+                .open(&Term::Var(SourceMeta::default(), Var::Free(fv)).into()),
+        )
+    }
+
+    /// Replace the bound parameter in the body of the sigma type with the given
+    /// term, without having to generate a fresh name in the process
+    pub fn instantiate(&self, x: &RcTerm) -> RcTerm {
+        self.unsafe_body.open(x)
+    }
+
+    /// Rebuild the sigma type by applying a fallible function to the parameter's
+    /// annotation and to the body. The body is not unbound first, so the
+    /// function sees the parameter as a bound variable.
+    pub fn try_map<E, F>(&self, mut f: F) -> Result<TermSigma, E>
+    where
+        F: FnMut(&RcTerm) -> Result<RcTerm, E>,
+    {
+        let param = self.unsafe_param.clone().try_map(|ann| f(&ann))?;
+
+        Ok(TermSigma {
+            unsafe_param: param,
+            unsafe_body: f(&self.unsafe_body)?,
+        })
+    }
+}
+
+impl TermRecordType {
+    pub fn bind(label: String, param: Named<Name, RcTerm>, mut body: RcTerm) -> TermRecordType {
+        body.close(&param.name);
+
+        TermRecordType {
+            label,
+            unsafe_param: param,
+            unsafe_body: body,
+        }
+    }
+
+    pub fn unbind(mut self) -> (String, Named<Name, RcTerm>, RcTerm) {
+        let fv = Name::fresh(self.unsafe_param.name.name());
+        self.unsafe_param.name = fv.clone();
+        (
+            self.label,
+            self.unsafe_param,
+            self.unsafe_body
+                .open(&Term::Var(SourceMeta::default(), Var::Free(fv)).into()),
+        )
+    }
+
+    /// Replace the bound field in the types of the remaining fields with the
+    /// given term, without having to generate a fresh name in the process
+    pub fn instantiate(&self, x: &RcTerm) -> RcTerm {
+        self.unsafe_body.open(x)
+    }
+
+    /// Rebuild the record type by applying a fallible function to the type of
+    /// the field and to the types of the remaining fields. The remaining
+    /// fields are not unbound first, so the function sees the field as a
+    /// bound variable.
+    pub fn try_map<E, F>(&self, mut f: F) -> Result<TermRecordType, E>
+    where
+        F: FnMut(&RcTerm) -> Result<RcTerm, E>,
+    {
+        let param = self.unsafe_param.clone().try_map(|ann| f(&ann))?;
+
+        Ok(TermRecordType {
+            label: self.label.clone(),
+            unsafe_param: param,
+            unsafe_body: f(&self.unsafe_body)?,
+        })
+    }
+}
+
+impl Pattern {
+    /// The variables bound by the pattern, from left to right
+    pub fn binders(&self) -> Vec<Name> {
+        fn go(pattern: &Pattern, binders: &mut Vec<Name>) {
+            match *pattern {
+                Pattern::Wildcard(_) | Pattern::Constant(_, _) => {},
+                Pattern::Binder(_, ref binder) => binders.push(binder.name.clone()),
+                Pattern::Pair(_, ref fst, ref snd) => {
+                    go(fst, binders);
+                    go(snd, binders);
+                },
+            }
+        }
+
+        let mut binders = Vec::new();
+        go(self, &mut binders);
+        binders
+    }
+
+    /// Rename the variables bound by the pattern, from left to right
+    fn rename_binders<F: FnMut(&Name) -> Name>(&self, rename: &mut F) -> Pattern {
+        match *self {
+            Pattern::Wildcard(_) | Pattern::Constant(_, _) => self.clone(),
+            Pattern::Binder(meta, ref binder) => {
+                Pattern::Binder(meta, Named::new(rename(&binder.name), ()))
+            },
+            Pattern::Pair(meta, ref fst, ref snd) => {
+                let fst = fst.rename_binders(rename);
+                let snd = snd.rename_binders(rename);
+
+                Pattern::Pair(meta, Box::new(fst), Box::new(snd))
+            },
+        }
+    }
+}
+
+/// Move a debruijn index under the variables bound by a pattern
+fn shift_level(level: Debruijn, pattern: &Pattern) -> Debruijn {
+    pattern.binders().iter().fold(level, |level, _| level.succ())
+}
+
+impl TermClause {
+    pub fn bind(pattern: Pattern, mut body: RcTerm) -> TermClause {
+        for (index, name) in pattern.binders().iter().rev().enumerate() {
+            body.close_at(Debruijn(index as u64), name);
+        }
+
+        TermClause {
+            unsafe_pattern: pattern,
+            unsafe_body: body,
+        }
+    }
+
+    pub fn unbind(self) -> (Pattern, RcTerm) {
+        let pattern = self.unsafe_pattern
+            .rename_binders(&mut |name| Name::fresh(name.name()));
+        let vars = pattern
+            .binders()
+            .into_iter()
+            .map(|name| Term::Var(SourceMeta::default(), Var::Free(name)).into())
+            .collect::<Vec<_>>();
+        let body = self.instantiate(&vars);
+
+        (pattern, body)
+    }
+
+    /// Replace the variables bound by the pattern in the body of the clause
+    /// with the given terms, from left to right, without having to generate
+    /// fresh names in the process
+    pub fn instantiate(&self, xs: &[RcTerm]) -> RcTerm {
+        let mut body = self.unsafe_body.clone();
+        for (index, x) in xs.iter().rev().enumerate() {
+            body = body.open_at(Debruijn(index as u64), x);
+        }
+        body
+    }
+
+    /// Rebuild the clause by applying a fallible function to the body. The
+    /// body is not unbound first, so the function sees the variables bound by
+    /// the pattern as bound variables.
+    pub fn try_map<E, F>(&self, mut f: F) -> Result<TermClause, E>
+    where
+        F: FnMut(&RcTerm) -> Result<RcTerm, E>,
+    {
+        Ok(TermClause {
+            unsafe_pattern: self.unsafe_pattern.clone(),
+            unsafe_body: f(&self.unsafe_body)?,
+        })
+    }
+}
+
+impl TermLet {
+    pub fn bind(param: Named<Name, Option<RcTerm>>, value: RcTerm, mut body: RcTerm) -> TermLet {
+        body.close(&param.name);
+
+        TermLet {
+            unsafe_param: param,
+            value,
+            unsafe_body: body,
+        }
+    }
+
+    pub fn unbind(mut self) -> (Named<Name, Option<RcTerm>>, RcTerm, RcTerm) {
+        let fv = Name::fresh(self.unsafe_param.name.name());
+        self.unsafe_param.name = fv.clone();
+        (
+            self.unsafe_param,
+            self.value,
+            self.unsafe_body
+                .open(&Term::Var(SourceMeta::default(), Var::Free(fv)).into()),
+        )
+    }
+
+    /// Replace the bound parameter in the body of the let binding with the
+    /// given term, without having to generate a fresh name in the process
+    pub fn instantiate(&self, x: &RcTerm) -> RcTerm {
+        self.unsafe_body.open(x)
+    }
+
+    /// Rebuild the let binding by applying a fallible function to the
+    /// parameter's annotation, to the bound value, and to the body. The body
+    /// is not unbound first, so the function sees the parameter as a bound
+    /// variable.
+    pub fn try_map<E, F>(&self, mut f: F) -> Result<TermLet, E>
+    where
+        F: FnMut(&RcTerm) -> Result<RcTerm, E>,
+    {
+        let param = self.unsafe_param.clone().try_map(|ann| match ann {
+            None => Ok(None),
+            Some(ann) => Ok(Some(f(&ann)?)),
+        })?;
+
+        Ok(TermLet {
+            unsafe_param: param,
+            value: f(&self.value)?,
+            unsafe_body: f(&self.unsafe_body)?,
+        })
+    }
 }
 
 impl ValueLam {
@@ -76,11 +363,17 @@ impl ValueLam {
         body.close(&param.name);
 
         ValueLam {
+            plicity: Plicity::Explicit,
             unsafe_param: param,
             unsafe_body: body,
         }
     }
 
+    /// Set whether the parameter is supplied explicitly or implicitly
+    pub fn with_plicity(self, plicity: Plicity) -> ValueLam {
+        ValueLam { plicity, ..self }
+    }
+
     pub fn unbind(mut self) -> (Named<Name, Option<RcValue>>, RcValue) {
         let fv = Name::fresh(self.unsafe_param.name.name());
         self.unsafe_param.name = fv.clone();
@@ -89,6 +382,12 @@ impl ValueLam {
             self.unsafe_body.open(&Value::Var(Var::Free(fv)).into()),
         )
     }
+
+    /// Replace the bound parameter in the body of the lambda with the given
+    /// value, without having to generate a fresh name in the process
+    pub fn instantiate(&self, x: &RcValue) -> RcValue {
+        self.unsafe_body.open(x)
+    }
 }
 
 impl ValuePi {
@@ -96,6 +395,45 @@ impl ValuePi {
         body.close(&param.name);
 
         ValuePi {
+            plicity: Plicity::Explicit,
+            unsafe_param: param,
+            unsafe_body: body,
+        }
+    }
+
+    /// Set whether the parameter is supplied explicitly or implicitly
+    pub fn with_plicity(self, plicity: Plicity) -> ValuePi {
+        ValuePi { plicity, ..self }
+    }
+
+    pub fn unbind(mut self) -> (Named<Name, RcValue>, RcValue) {
+        let fv = Name::fresh(self.unsafe_param.name.name());
+        self.unsafe_param.name = fv.clone();
+        (
+            self.unsafe_param,
+            self.unsafe_body.open(&Value::Var(Var::Free(fv)).into()),
+        )
+    }
+
+    /// Replace the bound parameter in the body of the pi type with the given
+    /// value, without having to generate a fresh name in the process
+    pub fn instantiate(&self, x: &RcValue) -> RcValue {
+        self.unsafe_body.open(x)
+    }
+
+    /// Returns `true` if the parameter is referred to in the body, meaning
+    /// that the type of the body depends on the argument it is applied to
+    pub fn is_dependent(&self) -> bool {
+        let (param, body) = self.clone().unbind();
+        body.free_vars().contains(&param.name)
+    }
+}
+
+impl ValueSigma {
+    pub fn bind(param: Named<Name, RcValue>, mut body: RcValue) -> ValueSigma {
+        body.close(&param.name);
+
+        ValueSigma {
             unsafe_param: param,
             unsafe_body: body,
         }
@@ -109,6 +447,40 @@ impl ValuePi {
             self.unsafe_body.open(&Value::Var(Var::Free(fv)).into()),
         )
     }
+
+    /// Replace the bound parameter in the body of the sigma type with the given
+    /// value, without having to generate a fresh name in the process
+    pub fn instantiate(&self, x: &RcValue) -> RcValue {
+        self.unsafe_body.open(x)
+    }
+}
+
+impl ValueRecordType {
+    pub fn bind(label: String, param: Named<Name, RcValue>, mut body: RcValue) -> ValueRecordType {
+        body.close(&param.name);
+
+        ValueRecordType {
+            label,
+            unsafe_param: param,
+            unsafe_body: body,
+        }
+    }
+
+    pub fn unbind(mut self) -> (String, Named<Name, RcValue>, RcValue) {
+        let fv = Name::fresh(self.unsafe_param.name.name());
+        self.unsafe_param.name = fv.clone();
+        (
+            self.label,
+            self.unsafe_param,
+            self.unsafe_body.open(&Value::Var(Var::Free(fv)).into()),
+        )
+    }
+
+    /// Replace the bound field in the types of the remaining fields with the
+    /// given value, without having to generate a fresh name in the process
+    pub fn instantiate(&self, x: &RcValue) -> RcValue {
+        self.unsafe_body.open(x)
+    }
 }
 
 // TODO: Would be nice for this to be more polymorphic
@@ -145,7 +517,7 @@ impl RcTerm {
                 ty.close_at(level, name);
                 return;
             },
-            Term::Universe(_, _) => return,
+            Term::Universe(_, _) | Term::Constant(_, _) | Term::Hole(_) => return,
             Term::Var(meta, Var::Free(ref n)) if n == name => {
                 Term::Var(meta, Var::Bound(Named::new(n.clone(), level))).into()
             },
@@ -168,6 +540,58 @@ impl RcTerm {
                 arg_expr.close_at(level, name);
                 return;
             },
+            Term::Let(_, ref mut let_) => {
+                let_.unsafe_param
+                    .inner
+                    .as_mut()
+                    .map(|param| param.close_at(level, name));
+                let_.value.close_at(level, name);
+                let_.unsafe_body.close_at(level.succ(), name);
+                return;
+            },
+            Term::Sigma(_, ref mut sigma) => {
+                sigma.unsafe_param.inner.close_at(level, name);
+                sigma.unsafe_body.close_at(level.succ(), name);
+                return;
+            },
+            Term::Pair(_, ref mut fst, ref mut snd) => {
+                fst.close_at(level, name);
+                snd.close_at(level, name);
+                return;
+            },
+            Term::Fst(_, ref mut pair) | Term::Snd(_, ref mut pair) => {
+                pair.close_at(level, name);
+                return;
+            },
+            Term::RecordType(_, ref mut record_ty) => {
+                record_ty.unsafe_param.inner.close_at(level, name);
+                record_ty.unsafe_body.close_at(level.succ(), name);
+                return;
+            },
+            Term::RecordTypeEmpty(_) | Term::RecordEmpty(_) => return,
+            Term::Record(_, _, ref mut value, ref mut rest) => {
+                value.close_at(level, name);
+                rest.close_at(level, name);
+                return;
+            },
+            Term::Proj(_, ref mut record, _) => {
+                record.close_at(level, name);
+                return;
+            },
+            Term::If(_, ref mut cond, ref mut if_true, ref mut if_false) => {
+                cond.close_at(level, name);
+                if_true.close_at(level, name);
+                if_false.close_at(level, name);
+                return;
+            },
+            Term::Case(_, ref mut scrutinee, ref mut clauses) => {
+                scrutinee.close_at(level, name);
+                for clause in clauses.iter_mut() {
+                    let level = shift_level(level, &clause.unsafe_pattern);
+                    clause.unsafe_body.close_at(level, name);
+                }
+                return;
+            },
         };
     }
 
@@ -181,9 +605,9 @@ impl RcTerm {
                 let expr = expr.open_at(level, x);
                 let ty = ty.open_at(level, x);
 
-                Term::App(meta, expr.clone(), ty.clone()).into()
+                Term::Ann(meta, expr, ty).into()
             },
-            Term::Universe(_, _) => self.clone(),
+            Term::Universe(_, _) | Term::Constant(_, _) | Term::Hole(_) => self.clone(),
             Term::Var(_, Var::Bound(Named { inner: index, .. })) if index == level => x.clone(),
             Term::Var(_, Var::Bound(_)) | Term::Var(_, Var::Free(_)) => self.clone(),
             Term::Lam(meta, ref lam) => {
@@ -193,6 +617,7 @@ impl RcTerm {
                     .map(|param_ty| param_ty.open_at(level, x));
                 let body = lam.unsafe_body.open_at(level.succ(), x);
                 let lam = TermLam {
+                    plicity: lam.plicity,
                     unsafe_param: Named::new(lam.unsafe_param.name.clone(), param_ty),
 
                     unsafe_body: body,
@@ -204,6 +629,7 @@ impl RcTerm {
                 let param_ty = pi.unsafe_param.inner.open_at(level, x);
                 let body = pi.unsafe_body.open_at(level.succ(), x);
                 let pi = TermPi {
+                    plicity: pi.plicity,
                     unsafe_param: Named::new(pi.unsafe_param.name.clone(), param_ty),
                     unsafe_body: body,
                 };
@@ -216,6 +642,82 @@ impl RcTerm {
 
                 Term::App(meta, fn_expr, arg).into()
             },
+            Term::Let(meta, ref let_) => {
+                let param_ty = let_.unsafe_param
+                    .inner
+                    .as_ref()
+                    .map(|param_ty| param_ty.open_at(level, x));
+                let value = let_.value.open_at(level, x);
+                let body = let_.unsafe_body.open_at(level.succ(), x);
+                let let_ = TermLet {
+                    unsafe_param: Named::new(let_.unsafe_param.name.clone(), param_ty),
+                    value,
+                    unsafe_body: body,
+                };
+
+                Term::Let(meta, let_).into()
+            },
+            Term::Sigma(meta, ref sigma) => {
+                let param_ty = sigma.unsafe_param.inner.open_at(level, x);
+                let body = sigma.unsafe_body.open_at(level.succ(), x);
+                let sigma = TermSigma {
+                    unsafe_param: Named::new(sigma.unsafe_param.name.clone(), param_ty),
+                    unsafe_body: body,
+                };
+
+                Term::Sigma(meta, sigma).into()
+            },
+            Term::Pair(meta, ref fst, ref snd) => {
+                let fst = fst.open_at(level, x);
+                let snd = snd.open_at(level, x);
+
+                Term::Pair(meta, fst, snd).into()
+            },
+            Term::Fst(meta, ref pair) => Term::Fst(meta, pair.open_at(level, x)).into(),
+            Term::Snd(meta, ref pair) => Term::Snd(meta, pair.open_at(level, x)).into(),
+            Term::RecordType(meta, ref record_ty) => {
+                let param_ty = record_ty.unsafe_param.inner.open_at(level, x);
+                let body = record_ty.unsafe_body.open_at(level.succ(), x);
+                let record_ty = TermRecordType {
+                    label: record_ty.label.clone(),
+                    unsafe_param: Named::new(record_ty.unsafe_param.name.clone(), param_ty),
+                    unsafe_body: body,
+                };
+
+                Term::RecordType(meta, record_ty).into()
+            },
+            Term::RecordTypeEmpty(_) | Term::RecordEmpty(_) => self.clone(),
+            Term::Record(meta, ref label, ref value, ref rest) => {
+                let value = value.open_at(level, x);
+                let rest = rest.open_at(level, x);
+
+                Term::Record(meta, label.clone(), value, rest).into()
+            },
+            Term::Proj(meta, ref record, ref label) => {
+                Term::Proj(meta, record.open_at(level, x), label.clone()).into()
+            },
+            Term::If(meta, ref cond, ref if_true, ref if_false) => {
+                let cond = cond.open_at(level, x);
+                let if_true = if_true.open_at(level, x);
+                let if_false = if_false.open_at(level, x);
+
+                Term::If(meta, cond, if_true, if_false).into()
+            },
+            Term::Case(meta, ref scrutinee, ref clauses) => {
+                let scrutinee = scrutinee.open_at(level, x);
+                let clauses = clauses
+                    .iter()
+                    .map(|clause| {
+                        let level = shift_level(level, &clause.unsafe_pattern);
+                        TermClause {
+                            unsafe_pattern: clause.unsafe_pattern.clone(),
+                            unsafe_body: clause.unsafe_body.open_at(level, x),
+                        }
+                    })
+                    .collect();
+
+                Term::Case(meta, scrutinee, clauses).into()
+            },
         }
     }
 
@@ -226,7 +728,7 @@ impl RcTerm {
                 ty.subst(name, x);
                 return;
             },
-            Term::Universe(_, _) => return,
+            Term::Universe(_, _) | Term::Constant(_, _) | Term::Hole(_) => return,
             Term::Var(_, Var::Free(ref n)) if n == name => x.clone(),
             Term::Var(_, Var::Free(_)) | Term::Var(_, Var::Bound(_)) => return,
             Term::Lam(_, ref mut lam) => {
@@ -247,6 +749,57 @@ impl RcTerm {
                 arg_expr.subst(name, x);
                 return;
             },
+            Term::Let(_, ref mut let_) => {
+                let_.unsafe_param
+                    .inner
+                    .as_mut()
+                    .map(|param| param.subst(name, x));
+                let_.value.subst(name, x);
+                let_.unsafe_body.subst(name, x);
+                return;
+            },
+            Term::Sigma(_, ref mut sigma) => {
+                sigma.unsafe_param.inner.subst(name, x);
+                sigma.unsafe_body.subst(name, x);
+                return;
+            },
+            Term::Pair(_, ref mut fst, ref mut snd) => {
+                fst.subst(name, x);
+                snd.subst(name, x);
+                return;
+            },
+            Term::Fst(_, ref mut pair) | Term::Snd(_, ref mut pair) => {
+                pair.subst(name, x);
+                return;
+            },
+            Term::RecordType(_, ref mut record_ty) => {
+                record_ty.unsafe_param.inner.subst(name, x);
+                record_ty.unsafe_body.subst(name, x);
+                return;
+            },
+            Term::RecordTypeEmpty(_) | Term::RecordEmpty(_) => return,
+            Term::Record(_, _, ref mut value, ref mut rest) => {
+                value.subst(name, x);
+                rest.subst(name, x);
+                return;
+            },
+            Term::Proj(_, ref mut record, _) => {
+                record.subst(name, x);
+                return;
+            },
+            Term::If(_, ref mut cond, ref mut if_true, ref mut if_false) => {
+                cond.subst(name, x);
+                if_true.subst(name, x);
+                if_false.subst(name, x);
+                return;
+            },
+            Term::Case(_, ref mut scrutinee, ref mut clauses) => {
+                scrutinee.subst(name, x);
+                for clause in clauses.iter_mut() {
+                    clause.unsafe_body.subst(name, x);
+                }
+                return;
+            },
         };
     }
 
@@ -256,7 +809,7 @@ impl RcTerm {
                 expr.visit_vars(on_var);
                 ty.visit_vars(on_var);
             },
-            Term::Universe(_, _) => {},
+            Term::Universe(_, _) | Term::Constant(_, _) | Term::Hole(_) => {},
             Term::Var(_, ref var) => on_var(var),
             Term::Lam(_, ref lam) => {
                 if let Some(ref param) = lam.unsafe_param.inner {
@@ -272,9 +825,132 @@ impl RcTerm {
                 fn_expr.visit_vars(on_var);
                 arg_expr.visit_vars(on_var);
             },
+            Term::Let(_, ref let_) => {
+                if let Some(ref param) = let_.unsafe_param.inner {
+                    param.visit_vars(on_var);
+                }
+                let_.value.visit_vars(on_var);
+                let_.unsafe_body.visit_vars(on_var);
+            },
+            Term::Sigma(_, ref sigma) => {
+                sigma.unsafe_param.inner.visit_vars(on_var);
+                sigma.unsafe_body.visit_vars(on_var);
+            },
+            Term::Pair(_, ref fst, ref snd) => {
+                fst.visit_vars(on_var);
+                snd.visit_vars(on_var);
+            },
+            Term::Fst(_, ref pair) | Term::Snd(_, ref pair) => pair.visit_vars(on_var),
+            Term::RecordType(_, ref record_ty) => {
+                record_ty.unsafe_param.inner.visit_vars(on_var);
+                record_ty.unsafe_body.visit_vars(on_var);
+            },
+            Term::RecordTypeEmpty(_) | Term::RecordEmpty(_) => {},
+            Term::Record(_, _, ref value, ref rest) => {
+                value.visit_vars(on_var);
+                rest.visit_vars(on_var);
+            },
+            Term::Proj(_, ref record, _) => record.visit_vars(on_var),
+            Term::If(_, ref cond, ref if_true, ref if_false) => {
+                cond.visit_vars(on_var);
+                if_true.visit_vars(on_var);
+                if_false.visit_vars(on_var);
+            },
+            Term::Case(_, ref scrutinee, ref clauses) => {
+                scrutinee.visit_vars(on_var);
+                for clause in clauses {
+                    clause.unsafe_body.visit_vars(on_var);
+                }
+            },
         };
     }
 
+    /// Rebuild the term, replacing each variable with the result of applying
+    /// a fallible function to it. Binders are rebuilt in place rather than
+    /// being unbound, so the Debruijn indices of bound variables are preserved.
+    pub fn traverse_vars<E, F>(&self, on_var: &mut F) -> Result<RcTerm, E>
+    where
+        F: FnMut(&Var<Name, Debruijn>) -> Result<Var<Name, Debruijn>, E>,
+    {
+        match *self.inner {
+            Term::Ann(meta, ref expr, ref ty) => {
+                let expr = expr.traverse_vars(on_var)?;
+                let ty = ty.traverse_vars(on_var)?;
+
+                Ok(Term::Ann(meta, expr, ty).into())
+            },
+            Term::Universe(_, _) | Term::Constant(_, _) | Term::Hole(_) => Ok(self.clone()),
+            Term::Var(meta, ref var) => Ok(Term::Var(meta, on_var(var)?).into()),
+            Term::Lam(meta, ref lam) => {
+                let lam = lam.try_map(|term| term.traverse_vars(&mut *on_var))?;
+
+                Ok(Term::Lam(meta, lam).into())
+            },
+            Term::Pi(meta, ref pi) => {
+                let pi = pi.try_map(|term| term.traverse_vars(&mut *on_var))?;
+
+                Ok(Term::Pi(meta, pi).into())
+            },
+            Term::App(meta, ref fn_expr, ref arg_expr) => {
+                let fn_expr = fn_expr.traverse_vars(on_var)?;
+                let arg_expr = arg_expr.traverse_vars(on_var)?;
+
+                Ok(Term::App(meta, fn_expr, arg_expr).into())
+            },
+            Term::Let(meta, ref let_) => {
+                let let_ = let_.try_map(|term| term.traverse_vars(&mut *on_var))?;
+
+                Ok(Term::Let(meta, let_).into())
+            },
+            Term::Sigma(meta, ref sigma) => {
+                let sigma = sigma.try_map(|term| term.traverse_vars(&mut *on_var))?;
+
+                Ok(Term::Sigma(meta, sigma).into())
+            },
+            Term::Pair(meta, ref fst, ref snd) => {
+                let fst = fst.traverse_vars(on_var)?;
+                let snd = snd.traverse_vars(on_var)?;
+
+                Ok(Term::Pair(meta, fst, snd).into())
+            },
+            Term::Fst(meta, ref pair) => Ok(Term::Fst(meta, pair.traverse_vars(on_var)?).into()),
+            Term::Snd(meta, ref pair) => Ok(Term::Snd(meta, pair.traverse_vars(on_var)?).into()),
+            Term::RecordType(meta, ref record_ty) => {
+                let record_ty = record_ty.try_map(|term| term.traverse_vars(&mut *on_var))?;
+
+                Ok(Term::RecordType(meta, record_ty).into())
+            },
+            Term::RecordTypeEmpty(_) | Term::RecordEmpty(_) => Ok(self.clone()),
+            Term::Record(meta, ref label, ref value, ref rest) => {
+                let value = value.traverse_vars(on_var)?;
+                let rest = rest.traverse_vars(on_var)?;
+
+                Ok(Term::Record(meta, label.clone(), value, rest).into())
+            },
+            Term::Proj(meta, ref record, ref label) => {
+                let record = record.traverse_vars(on_var)?;
+
+                Ok(Term::Proj(meta, record, label.clone()).into())
+            },
+            Term::If(meta, ref cond, ref if_true, ref if_false) => {
+                let cond = cond.traverse_vars(on_var)?;
+                let if_true = if_true.traverse_vars(on_var)?;
+                let if_false = if_false.traverse_vars(on_var)?;
+
+                Ok(Term::If(meta, cond, if_true, if_false).into())
+            },
+            Term::Case(meta, ref scrutinee, ref clauses) => {
+                let scrutinee = scrutinee.traverse_vars(on_var)?;
+                let mut new_clauses = Vec::with_capacity(clauses.len());
+                for clause in clauses {
+                    new_clauses.push(clause.try_map(|term| term.traverse_vars(&mut *on_var))?);
+                }
+
+                Ok(Term::Case(meta, scrutinee, new_clauses).into())
+            },
+        }
+    }
+
     pub fn free_vars(&self) -> HashSet<Name> {
         let mut free_vars = HashSet::new();
         self.visit_vars(&mut |var| match *var {
@@ -285,6 +961,220 @@ impl RcTerm {
         });
         free_vars
     }
+
+    /// Return a copy of the term where binders that would shadow a name that
+    /// is already in scope are renamed, by appending a number to the name,
+    /// eg. `\x => \x => x` becomes `\x => \x1 => x1`. This makes the printed
+    /// output of the term unambiguous.
+    pub fn rename_shadowed(&self) -> RcTerm {
+        let free_names: HashSet<String> = self.free_vars()
+            .iter()
+            .filter_map(|name| name.name().map(String::from))
+            .collect();
+
+        self.rename_shadowed_at(&free_names, &mut Vec::new())
+    }
+
+    fn rename_shadowed_at(&self, free_names: &HashSet<String>, scope: &mut Vec<Name>) -> RcTerm {
+        match *self.inner {
+            Term::Ann(meta, ref expr, ref ty) => {
+                let expr = expr.rename_shadowed_at(free_names, scope);
+                let ty = ty.rename_shadowed_at(free_names, scope);
+
+                Term::Ann(meta, expr, ty).into()
+            },
+            Term::Universe(_, _)
+            | Term::Constant(_, _)
+            | Term::Hole(_)
+            | Term::Var(_, Var::Free(_)) => {
+                self.clone()
+            },
+            Term::Var(meta, Var::Bound(Named { inner: index, .. })) => {
+                match scope.len().checked_sub(index.0 as usize + 1) {
+                    Some(level) => {
+                        let name = scope[level].clone();
+                        Term::Var(meta, Var::Bound(Named::new(name, index))).into()
+                    },
+                    None => self.clone(),
+                }
+            },
+            Term::Lam(meta, ref lam) => {
+                let param_ty = lam.unsafe_param
+                    .inner
+                    .as_ref()
+                    .map(|param_ty| param_ty.rename_shadowed_at(free_names, scope));
+                let name = unshadowed_name(&lam.unsafe_param.name, free_names, scope);
+
+                scope.push(name.clone());
+                let body = lam.unsafe_body.rename_shadowed_at(free_names, scope);
+                scope.pop();
+
+                Term::Lam(
+                    meta,
+                    TermLam {
+                        plicity: lam.plicity,
+                        unsafe_param: Named::new(name, param_ty),
+                        unsafe_body: body,
+                    },
+                ).into()
+            },
+            Term::Pi(meta, ref pi) => {
+                let param_ty = pi.unsafe_param.inner.rename_shadowed_at(free_names, scope);
+                let name = unshadowed_name(&pi.unsafe_param.name, free_names, scope);
+
+                scope.push(name.clone());
+                let body = pi.unsafe_body.rename_shadowed_at(free_names, scope);
+                scope.pop();
+
+                Term::Pi(
+                    meta,
+                    TermPi {
+                        plicity: pi.plicity,
+                        unsafe_param: Named::new(name, param_ty),
+                        unsafe_body: body,
+                    },
+                ).into()
+            },
+            Term::App(meta, ref fn_expr, ref arg_expr) => {
+                let fn_expr = fn_expr.rename_shadowed_at(free_names, scope);
+                let arg_expr = arg_expr.rename_shadowed_at(free_names, scope);
+
+                Term::App(meta, fn_expr, arg_expr).into()
+            },
+            Term::Let(meta, ref let_) => {
+                let param_ty = let_.unsafe_param
+                    .inner
+                    .as_ref()
+                    .map(|param_ty| param_ty.rename_shadowed_at(free_names, scope));
+                let value = let_.value.rename_shadowed_at(free_names, scope);
+                let name = unshadowed_name(&let_.unsafe_param.name, free_names, scope);
+
+                scope.push(name.clone());
+                let body = let_.unsafe_body.rename_shadowed_at(free_names, scope);
+                scope.pop();
+
+                Term::Let(
+                    meta,
+                    TermLet {
+                        unsafe_param: Named::new(name, param_ty),
+                        value,
+                        unsafe_body: body,
+                    },
+                ).into()
+            },
+            Term::Sigma(meta, ref sigma) => {
+                let param_ty = sigma.unsafe_param.inner.rename_shadowed_at(free_names, scope);
+                let name = unshadowed_name(&sigma.unsafe_param.name, free_names, scope);
+
+                scope.push(name.clone());
+                let body = sigma.unsafe_body.rename_shadowed_at(free_names, scope);
+                scope.pop();
+
+                Term::Sigma(
+                    meta,
+                    TermSigma {
+                        unsafe_param: Named::new(name, param_ty),
+                        unsafe_body: body,
+                    },
+                ).into()
+            },
+            Term::Pair(meta, ref fst, ref snd) => {
+                let fst = fst.rename_shadowed_at(free_names, scope);
+                let snd = snd.rename_shadowed_at(free_names, scope);
+
+                Term::Pair(meta, fst, snd).into()
+            },
+            Term::Fst(meta, ref pair) => {
+                Term::Fst(meta, pair.rename_shadowed_at(free_names, scope)).into()
+            },
+            Term::Snd(meta, ref pair) => {
+                Term::Snd(meta, pair.rename_shadowed_at(free_names, scope)).into()
+            },
+            Term::RecordType(meta, ref record_ty) => {
+                let param_ty = record_ty
+                    .unsafe_param
+                    .inner
+                    .rename_shadowed_at(free_names, scope);
+                // Fields are referred to by their labels, so renaming them
+                // would change the meaning of the type
+                let name = record_ty.unsafe_param.name.clone();
+
+                scope.push(name.clone());
+                let body = record_ty.unsafe_body.rename_shadowed_at(free_names, scope);
+                scope.pop();
+
+                Term::RecordType(
+                    meta,
+                    TermRecordType {
+                        label: record_ty.label.clone(),
+                        unsafe_param: Named::new(name, param_ty),
+                        unsafe_body: body,
+                    },
+                ).into()
+            },
+            Term::RecordTypeEmpty(_) | Term::RecordEmpty(_) => self.clone(),
+            Term::Record(meta, ref label, ref value, ref rest) => {
+                let value = value.rename_shadowed_at(free_names, scope);
+                let rest = rest.rename_shadowed_at(free_names, scope);
+
+                Term::Record(meta, label.clone(), value, rest).into()
+            },
+            Term::Proj(meta, ref record, ref label) => {
+                let record = record.rename_shadowed_at(free_names, scope);
+
+                Term::Proj(meta, record, label.clone()).into()
+            },
+            Term::If(meta, ref cond, ref if_true, ref if_false) => {
+                let cond = cond.rename_shadowed_at(free_names, scope);
+                let if_true = if_true.rename_shadowed_at(free_names, scope);
+                let if_false = if_false.rename_shadowed_at(free_names, scope);
+
+                Term::If(meta, cond, if_true, if_false).into()
+            },
+            Term::Case(meta, ref scrutinee, ref clauses) => {
+                let scrutinee = scrutinee.rename_shadowed_at(free_names, scope);
+                let mut new_clauses = Vec::with_capacity(clauses.len());
+                for clause in clauses {
+                    let depth = scope.len();
+                    let pattern = clause.unsafe_pattern.rename_binders(&mut |name| {
+                        let name = unshadowed_name(name, free_names, scope);
+                        scope.push(name.clone());
+                        name
+                    });
+                    let body = clause.unsafe_body.rename_shadowed_at(free_names, scope);
+                    scope.truncate(depth);
+
+                    new_clauses.push(TermClause {
+                        unsafe_pattern: pattern,
+                        unsafe_body: body,
+                    });
+                }
+
+                Term::Case(meta, scrutinee, new_clauses).into()
+            },
+        }
+    }
+}
+
+/// Pick a name for a binder that does not clash with the free variables of
+/// the term, or with the names of the enclosing binders. Generated names
+/// without a user-facing name, like the parameters of arrows, are named `x`
+/// so that the output can be parsed again.
+fn unshadowed_name(name: &Name, free_names: &HashSet<String>, scope: &[Name]) -> Name {
+    let base = name.name().unwrap_or("x");
+    let is_used = |candidate: &str| {
+        free_names.contains(candidate) || scope.iter().any(|n| n.name() == Some(candidate))
+    };
+
+    if !is_used(base) {
+        return Name::user(base);
+    }
+
+    (1..)
+        .map(|suffix| format!("{}{}", base, suffix))
+        .find(|candidate| !is_used(candidate.as_str()))
+        .map(Name::user)
+        .unwrap()
 }
 
 impl RcValue {
@@ -294,7 +1184,7 @@ impl RcValue {
 
     pub fn close_at(&mut self, level: Debruijn, name: &Name) {
         *self = match *Rc::make_mut(&mut self.inner) {
-            Value::Universe(_) => return,
+            Value::Universe(_) | Value::Constant(_) => return,
             Value::Var(Var::Free(ref n)) if n == name => {
                 Value::Var(Var::Bound(Named::new(n.clone(), level))).into()
             },
@@ -317,6 +1207,41 @@ impl RcValue {
                 arg_expr.close_at(level, name);
                 return;
             },
+            Value::Sigma(ref mut sigma) => {
+                sigma.unsafe_param.inner.close_at(level, name);
+                sigma.unsafe_body.close_at(level.succ(), name);
+                return;
+            },
+            Value::Pair(ref mut fst, ref mut snd) => {
+                fst.close_at(level, name);
+                snd.close_at(level, name);
+                return;
+            },
+            Value::Fst(ref mut pair) | Value::Snd(ref mut pair) => {
+                pair.close_at(level, name);
+                return;
+            },
+            Value::RecordType(ref mut record_ty) => {
+                record_ty.unsafe_param.inner.close_at(level, name);
+                record_ty.unsafe_body.close_at(level.succ(), name);
+                return;
+            },
+            Value::RecordTypeEmpty | Value::RecordEmpty => return,
+            Value::Record(_, ref mut value, ref mut rest) => {
+                value.close_at(level, name);
+                rest.close_at(level, name);
+                return;
+            },
+            Value::Proj(ref mut record, _) => {
+                record.close_at(level, name);
+                return;
+            },
+            Value::If(ref mut cond, ref mut if_true, ref mut if_false) => {
+                cond.close_at(level, name);
+                if_true.close_at(level, name);
+                if_false.close_at(level, name);
+                return;
+            },
         };
     }
 
@@ -326,7 +1251,7 @@ impl RcValue {
 
     pub fn open_at(&self, level: Debruijn, x: &RcValue) -> RcValue {
         match *self.inner {
-            Value::Universe(_) => self.clone(),
+            Value::Universe(_) | Value::Constant(_) => self.clone(),
             Value::Var(Var::Bound(Named { inner: index, .. })) if index == level => x.clone(),
             Value::Var(Var::Bound(_)) | Value::Var(Var::Free(_)) => self.clone(),
             Value::Lam(ref lam) => {
@@ -337,6 +1262,7 @@ impl RcValue {
                 let body = lam.unsafe_body.open_at(level.succ(), x);
 
                 Value::Lam(ValueLam {
+                    plicity: lam.plicity,
                     unsafe_param: Named::new(lam.unsafe_param.name.clone(), param_ty),
                     unsafe_body: body,
                 }).into()
@@ -346,6 +1272,7 @@ impl RcValue {
                 let body = pi.unsafe_body.open_at(level.succ(), x);
 
                 Value::Pi(ValuePi {
+                    plicity: pi.plicity,
                     unsafe_param: Named::new(pi.unsafe_param.name.clone(), param_ty),
                     unsafe_body: body,
                 }).into()
@@ -356,12 +1283,56 @@ impl RcValue {
 
                 Value::App(fn_expr, arg).into()
             },
+            Value::Sigma(ref sigma) => {
+                let param_ty = sigma.unsafe_param.inner.open_at(level, x);
+                let body = sigma.unsafe_body.open_at(level.succ(), x);
+
+                Value::Sigma(ValueSigma {
+                    unsafe_param: Named::new(sigma.unsafe_param.name.clone(), param_ty),
+                    unsafe_body: body,
+                }).into()
+            },
+            Value::Pair(ref fst, ref snd) => {
+                let fst = fst.open_at(level, x);
+                let snd = snd.open_at(level, x);
+
+                Value::Pair(fst, snd).into()
+            },
+            Value::Fst(ref pair) => Value::Fst(pair.open_at(level, x)).into(),
+            Value::Snd(ref pair) => Value::Snd(pair.open_at(level, x)).into(),
+            Value::RecordType(ref record_ty) => {
+                let param_ty = record_ty.unsafe_param.inner.open_at(level, x);
+                let body = record_ty.unsafe_body.open_at(level.succ(), x);
+
+                Value::RecordType(ValueRecordType {
+                    label: record_ty.label.clone(),
+                    unsafe_param: Named::new(record_ty.unsafe_param.name.clone(), param_ty),
+                    unsafe_body: body,
+                }).into()
+            },
+            Value::RecordTypeEmpty | Value::RecordEmpty => self.clone(),
+            Value::Record(ref label, ref value, ref rest) => {
+                let value = value.open_at(level, x);
+                let rest = rest.open_at(level, x);
+
+                Value::Record(label.clone(), value, rest).into()
+            },
+            Value::Proj(ref record, ref label) => {
+                Value::Proj(record.open_at(level, x), label.clone()).into()
+            },
+            Value::If(ref cond, ref if_true, ref if_false) => {
+                let cond = cond.open_at(level, x);
+                let if_true = if_true.open_at(level, x);
+                let if_false = if_false.open_at(level, x);
+
+                Value::If(cond, if_true, if_false).into()
+            },
         }
     }
 
     pub fn subst(&mut self, name: &Name, x: &RcValue) {
         *self = match *Rc::make_mut(&mut self.inner) {
-            Value::Universe(_) => return,
+            Value::Universe(_) | Value::Constant(_) => return,
             Value::Var(Var::Free(ref n)) if n == name => x.clone(),
             Value::Var(Var::Free(_)) | Value::Var(Var::Bound(_)) => return,
             Value::Lam(ref mut lam) => {
@@ -382,6 +1353,228 @@ impl RcValue {
                 arg_expr.subst(name, x);
                 return;
             },
+            Value::Sigma(ref mut sigma) => {
+                sigma.unsafe_param.inner.subst(name, x);
+                sigma.unsafe_body.subst(name, x);
+                return;
+            },
+            Value::Pair(ref mut fst, ref mut snd) => {
+                fst.subst(name, x);
+                snd.subst(name, x);
+                return;
+            },
+            Value::Fst(ref mut pair) | Value::Snd(ref mut pair) => {
+                pair.subst(name, x);
+                return;
+            },
+            Value::RecordType(ref mut record_ty) => {
+                record_ty.unsafe_param.inner.subst(name, x);
+                record_ty.unsafe_body.subst(name, x);
+                return;
+            },
+            Value::RecordTypeEmpty | Value::RecordEmpty => return,
+            Value::Record(_, ref mut value, ref mut rest) => {
+                value.subst(name, x);
+                rest.subst(name, x);
+                return;
+            },
+            Value::Proj(ref mut record, _) => {
+                record.subst(name, x);
+                return;
+            },
+            Value::If(ref mut cond, ref mut if_true, ref mut if_false) => {
+                cond.subst(name, x);
+                if_true.subst(name, x);
+                if_false.subst(name, x);
+                return;
+            },
+        };
+    }
+
+    fn visit_vars<F: FnMut(&Var<Name, Debruijn>)>(&self, on_var: &mut F) {
+        match *self.inner {
+            Value::Universe(_) | Value::Constant(_) => {},
+            Value::Var(ref var) => on_var(var),
+            Value::Lam(ref lam) => {
+                if let Some(ref param) = lam.unsafe_param.inner {
+                    param.visit_vars(on_var);
+                }
+                lam.unsafe_body.visit_vars(on_var);
+            },
+            Value::Pi(ref pi) => {
+                pi.unsafe_param.inner.visit_vars(on_var);
+                pi.unsafe_body.visit_vars(on_var);
+            },
+            Value::App(ref fn_expr, ref arg_expr) => {
+                fn_expr.visit_vars(on_var);
+                arg_expr.visit_vars(on_var);
+            },
+            Value::Sigma(ref sigma) => {
+                sigma.unsafe_param.inner.visit_vars(on_var);
+                sigma.unsafe_body.visit_vars(on_var);
+            },
+            Value::Pair(ref fst, ref snd) => {
+                fst.visit_vars(on_var);
+                snd.visit_vars(on_var);
+            },
+            Value::Fst(ref pair) | Value::Snd(ref pair) => pair.visit_vars(on_var),
+            Value::RecordType(ref record_ty) => {
+                record_ty.unsafe_param.inner.visit_vars(on_var);
+                record_ty.unsafe_body.visit_vars(on_var);
+            },
+            Value::RecordTypeEmpty | Value::RecordEmpty => {},
+            Value::Record(_, ref value, ref rest) => {
+                value.visit_vars(on_var);
+                rest.visit_vars(on_var);
+            },
+            Value::Proj(ref record, _) => record.visit_vars(on_var),
+            Value::If(ref cond, ref if_true, ref if_false) => {
+                cond.visit_vars(on_var);
+                if_true.visit_vars(on_var);
+                if_false.visit_vars(on_var);
+            },
+        };
+    }
+
+    pub fn free_vars(&self) -> HashSet<Name> {
+        let mut free_vars = HashSet::new();
+        self.visit_vars(&mut |var| match *var {
+            Var::Bound(_) => {},
+            Var::Free(ref name) => {
+                free_vars.insert(name.clone());
+            },
+        });
+        free_vars
+    }
+
+    pub fn subst_levels(&self, mappings: &HashMap<GenId, Level>) -> RcValue {
+        match *self.inner {
+            Value::Universe(ref level) => Value::Universe(level.subst(mappings)).into(),
+            Value::Var(_) | Value::Constant(_) => self.clone(),
+            Value::Lam(ref lam) => {
+                let param_ty = lam.unsafe_param
+                    .inner
+                    .as_ref()
+                    .map(|param_ty| param_ty.subst_levels(mappings));
+                let body = lam.unsafe_body.subst_levels(mappings);
+
+                Value::Lam(ValueLam {
+                    plicity: lam.plicity,
+                    unsafe_param: Named::new(lam.unsafe_param.name.clone(), param_ty),
+                    unsafe_body: body,
+                }).into()
+            },
+            Value::Pi(ref pi) => {
+                let param_ty = pi.unsafe_param.inner.subst_levels(mappings);
+                let body = pi.unsafe_body.subst_levels(mappings);
+
+                Value::Pi(ValuePi {
+                    plicity: pi.plicity,
+                    unsafe_param: Named::new(pi.unsafe_param.name.clone(), param_ty),
+                    unsafe_body: body,
+                }).into()
+            },
+            Value::App(ref fn_expr, ref arg_expr) => {
+                let fn_expr = fn_expr.subst_levels(mappings);
+                let arg = arg_expr.subst_levels(mappings);
+
+                Value::App(fn_expr, arg).into()
+            },
+            Value::Sigma(ref sigma) => {
+                let param_ty = sigma.unsafe_param.inner.subst_levels(mappings);
+                let body = sigma.unsafe_body.subst_levels(mappings);
+
+                Value::Sigma(ValueSigma {
+                    unsafe_param: Named::new(sigma.unsafe_param.name.clone(), param_ty),
+                    unsafe_body: body,
+                }).into()
+            },
+            Value::Pair(ref fst, ref snd) => {
+                let fst = fst.subst_levels(mappings);
+                let snd = snd.subst_levels(mappings);
+
+                Value::Pair(fst, snd).into()
+            },
+            Value::Fst(ref pair) => Value::Fst(pair.subst_levels(mappings)).into(),
+            Value::Snd(ref pair) => Value::Snd(pair.subst_levels(mappings)).into(),
+            Value::RecordType(ref record_ty) => {
+                let param_ty = record_ty.unsafe_param.inner.subst_levels(mappings);
+                let body = record_ty.unsafe_body.subst_levels(mappings);
+
+                Value::RecordType(ValueRecordType {
+                    label: record_ty.label.clone(),
+                    unsafe_param: Named::new(record_ty.unsafe_param.name.clone(), param_ty),
+                    unsafe_body: body,
+                }).into()
+            },
+            Value::RecordTypeEmpty | Value::RecordEmpty => self.clone(),
+            Value::Record(ref label, ref value, ref rest) => {
+                let value = value.subst_levels(mappings);
+                let rest = rest.subst_levels(mappings);
+
+                Value::Record(label.clone(), value, rest).into()
+            },
+            Value::Proj(ref record, ref label) => {
+                Value::Proj(record.subst_levels(mappings), label.clone()).into()
+            },
+            Value::If(ref cond, ref if_true, ref if_false) => {
+                let cond = cond.subst_levels(mappings);
+                let if_true = if_true.subst_levels(mappings);
+                let if_false = if_false.subst_levels(mappings);
+
+                Value::If(cond, if_true, if_false).into()
+            },
+        }
+    }
+
+    fn visit_levels<F: FnMut(&Level)>(&self, on_level: &mut F) {
+        match *self.inner {
+            Value::Universe(ref level) => on_level(level),
+            Value::Var(_) | Value::Constant(_) => {},
+            Value::Lam(ref lam) => {
+                if let Some(ref param) = lam.unsafe_param.inner {
+                    param.visit_levels(on_level);
+                }
+                lam.unsafe_body.visit_levels(on_level);
+            },
+            Value::Pi(ref pi) => {
+                pi.unsafe_param.inner.visit_levels(on_level);
+                pi.unsafe_body.visit_levels(on_level);
+            },
+            Value::App(ref fn_expr, ref arg_expr) => {
+                fn_expr.visit_levels(on_level);
+                arg_expr.visit_levels(on_level);
+            },
+            Value::Sigma(ref sigma) => {
+                sigma.unsafe_param.inner.visit_levels(on_level);
+                sigma.unsafe_body.visit_levels(on_level);
+            },
+            Value::Pair(ref fst, ref snd) => {
+                fst.visit_levels(on_level);
+                snd.visit_levels(on_level);
+            },
+            Value::Fst(ref pair) | Value::Snd(ref pair) => pair.visit_levels(on_level),
+            Value::RecordType(ref record_ty) => {
+                record_ty.unsafe_param.inner.visit_levels(on_level);
+                record_ty.unsafe_body.visit_levels(on_level);
+            },
+            Value::RecordTypeEmpty | Value::RecordEmpty => {},
+            Value::Record(_, ref value, ref rest) => {
+                value.visit_levels(on_level);
+                rest.visit_levels(on_level);
+            },
+            Value::Proj(ref record, _) => record.visit_levels(on_level),
+            Value::If(ref cond, ref if_true, ref if_false) => {
+                cond.visit_levels(on_level);
+                if_true.visit_levels(on_level);
+                if_false.visit_levels(on_level);
+            },
         };
     }
+
+    pub fn level_vars(&self) -> BTreeSet<GenId> {
+        let mut level_vars = BTreeSet::new();
+        self.visit_levels(&mut |level| level_vars.extend(level.vars()));
+        level_vars
+    }
 }
@@ -0,0 +1,166 @@
+//! Primitive functions, implemented in Rust
+
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use syntax::var::Named;
+
+use super::{Constant, Level, Name, RcType, RcValue, Value, ValuePi};
+
+/// The implementation of a primitive function
+///
+/// This is given the normalized arguments of a fully applied primitive, and
+/// returns `None` if the application could not be reduced (for example, if
+/// some of the arguments are still neutral).
+pub type PrimImpl = Rc<Fn(&[RcValue]) -> Option<RcValue>>;
+
+/// A primitive function
+#[derive(Clone)]
+pub struct PrimFn {
+    /// The name the primitive is referred to by
+    pub name: String,
+    /// The number of arguments that must be supplied before the primitive is
+    /// reduced
+    pub arity: usize,
+    /// The type of the primitive
+    pub ann: RcType,
+    /// The implementation of the primitive
+    pub fun: PrimImpl,
+}
+
+impl PrimFn {
+    pub fn new<S, F>(name: S, arity: usize, ann: RcType, fun: F) -> PrimFn
+    where
+        S: Into<String>,
+        F: Fn(&[RcValue]) -> Option<RcValue> + 'static,
+    {
+        PrimFn {
+            name: name.into(),
+            arity,
+            ann,
+            fun: Rc::new(fun),
+        }
+    }
+}
+
+impl fmt::Debug for PrimFn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PrimFn")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .field("ann", &self.ann)
+            .finish()
+    }
+}
+
+/// Primitives are only compared by their signatures - the implementations are
+/// assumed to be the same
+impl PartialEq for PrimFn {
+    fn eq(&self, other: &PrimFn) -> bool {
+        self.name == other.name && self.arity == other.arity && self.ann == other.ann
+    }
+}
+
+/// The primitive functions that are available during type checking and
+/// normalization
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PrimRegistry {
+    prims: HashMap<Name, Rc<PrimFn>>,
+    /// The largest arity of the registered primitives, so that we can avoid
+    /// searching long application spines for primitives that could never
+    /// be saturated by them
+    max_arity: usize,
+}
+
+impl PrimRegistry {
+    /// Create a new, empty registry
+    pub fn new() -> PrimRegistry {
+        PrimRegistry::default()
+    }
+
+    /// Register a primitive, replacing any existing primitive with the same
+    /// name
+    pub fn register(&mut self, prim: PrimFn) {
+        self.max_arity = self.max_arity.max(prim.arity);
+        self.prims.insert(Name::user(prim.name.as_str()), Rc::new(prim));
+    }
+
+    /// Look up the primitive with the given name
+    pub fn lookup(&self, name: &Name) -> Option<&PrimFn> {
+        self.prims.get(name).map(|prim| &**prim)
+    }
+
+    /// The largest arity of the registered primitives
+    pub fn max_arity(&self) -> usize {
+        self.max_arity
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prims.is_empty()
+    }
+
+    /// Iterate over the registered primitives, in no particular order
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (&'a Name, &'a PrimFn)> + 'a {
+        self.prims.iter().map(|(name, prim)| (name, &**prim))
+    }
+
+    /// Create a registry containing the built-in primitives
+    ///
+    /// ```text
+    /// Bool : Type
+    /// true : Bool
+    /// false : Bool
+    /// Int : Type
+    /// add : Int -> Int -> Int
+    /// mul : Int -> Int -> Int
+    /// String : Type
+    /// ```
+    ///
+    /// Integer arithmetic wraps around on overflow.
+    pub fn builtins() -> PrimRegistry {
+        fn constant(constant: Constant) -> RcValue {
+            Value::Constant(constant).into()
+        }
+
+        fn arrow(ann: RcType, body: RcType) -> RcType {
+            let param = Named::new(Name::fresh(None::<&str>), ann);
+            Value::Pi(ValuePi::bind(param, body)).into()
+        }
+
+        fn int_binop(name: &str, f: fn(i64, i64) -> i64) -> PrimFn {
+            let int_ty = constant(Constant::IntType);
+            let ann = arrow(int_ty.clone(), arrow(int_ty.clone(), int_ty));
+
+            PrimFn::new(name, 2, ann, move |args| match (&*args[0].inner, &*args[1].inner) {
+                (&Value::Constant(Constant::Int(x)), &Value::Constant(Constant::Int(y))) => {
+                    Some(constant(Constant::Int(f(x, y))))
+                },
+                (_, _) => None,
+            })
+        }
+
+        let universe: RcType = Value::Universe(Level::ZERO).into();
+        let bool_ty = constant(Constant::BoolType);
+
+        let mut prims = PrimRegistry::new();
+        prims.register(PrimFn::new("Bool", 0, universe.clone(), |_| {
+            Some(constant(Constant::BoolType))
+        }));
+        prims.register(PrimFn::new("true", 0, bool_ty.clone(), |_| {
+            Some(constant(Constant::Bool(true)))
+        }));
+        prims.register(PrimFn::new("false", 0, bool_ty, |_| {
+            Some(constant(Constant::Bool(false)))
+        }));
+        prims.register(PrimFn::new("Int", 0, universe.clone(), |_| {
+            Some(constant(Constant::IntType))
+        }));
+        prims.register(int_binop("add", i64::wrapping_add));
+        prims.register(int_binop("mul", i64::wrapping_mul));
+        prims.register(PrimFn::new("String", 0, universe, |_| {
+            Some(constant(Constant::StringType))
+        }));
+        prims
+    }
+}
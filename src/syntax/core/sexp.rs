@@ -0,0 +1,156 @@
+//! Rendering of core terms as S-expressions
+//!
+//! This is a compact debugging format that shows the structure of the core
+//! syntax directly, including the Debruijn indices of bound variables:
+//!
+//! ```text
+//! e ::= (ann e e)           1. annotated terms
+//!     | (type i)            2. universes
+//!     | (free x)            3. free variables
+//!     | (var i)             4. bound variables
+//!     | (lam x e)           5. lambda abstractions
+//!     | (lam (x e) e)       6. annotated lambda abstractions
+//!     | (pi (x e) e)        7. dependent function types
+//!     | (app e e)           8. term application
+//!     | (let x e e)         9. let bindings
+//!     | (let (x e) e e)    10. annotated let bindings
+//!     | (sigma (x e) e)    11. dependent pair types
+//!     | (pair e e)         12. pairs
+//!     | (fst e)            13. first projections
+//!     | (snd e)            14. second projections
+//!     | (Record (l e) e)   15. record types
+//!     | (Record)           16. empty record types
+//!     | (record l e e)     17. records
+//!     | (record)           18. empty records
+//!     | (proj e l)         19. field projections
+//!     | c                  20. constants
+//!     | (if e e e)         21. conditionals
+//!     | ?                  22. holes
+//!     | (lam {x} e)        23. implicit lambda abstractions
+//!     | (lam {x e} e)      24. annotated implicit lambda abstractions
+//!     | (pi {x e} e)       25. implicit dependent function types
+//!     | (case e (p e) ...) 26. case expressions
+//!
+//! p ::= _                   1. wildcard patterns
+//!     | x                   2. binder patterns
+//!     | c                   3. constant patterns
+//!     | (pair p p)          4. pair patterns
+//! ```
+
+use syntax::var::Var;
+
+use super::{Pattern, Plicity, RcTerm, Term};
+
+/// Wrap a parameter in the delimiters for its plicity
+fn param(plicity: Plicity, param: String) -> String {
+    match plicity {
+        Plicity::Explicit => format!("({})", param),
+        Plicity::Implicit => format!("{{{}}}", param),
+    }
+}
+
+/// Render a pattern as an S-expression
+fn pattern_to_sexp(pattern: &Pattern) -> String {
+    match *pattern {
+        Pattern::Wildcard(_) => String::from("_"),
+        Pattern::Binder(_, ref binder) => binder.name.to_string(),
+        Pattern::Constant(_, ref c) => c.to_string(),
+        Pattern::Pair(_, ref fst, ref snd) => {
+            format!("(pair {} {})", pattern_to_sexp(fst), pattern_to_sexp(snd))
+        },
+    }
+}
+
+/// Render a core term as an S-expression
+pub fn to_sexp(term: &RcTerm) -> String {
+    match *term.inner {
+        Term::Ann(_, ref expr, ref ty) => format!("(ann {} {})", to_sexp(expr), to_sexp(ty)),
+        Term::Universe(_, ref level) => format!("(type {})", level),
+        Term::Var(_, Var::Free(ref name)) => format!("(free {})", name),
+        Term::Var(_, Var::Bound(ref bound)) => format!("(var {})", bound.inner.0),
+        Term::Lam(_, ref lam) => match (lam.plicity, &lam.unsafe_param.inner) {
+            (Plicity::Explicit, &None) => format!(
+                "(lam {} {})",
+                lam.unsafe_param.name,
+                to_sexp(&lam.unsafe_body),
+            ),
+            (plicity, &None) => format!(
+                "(lam {} {})",
+                param(plicity, lam.unsafe_param.name.to_string()),
+                to_sexp(&lam.unsafe_body),
+            ),
+            (plicity, &Some(ref ann)) => format!(
+                "(lam {} {})",
+                param(plicity, format!("{} {}", lam.unsafe_param.name, to_sexp(ann))),
+                to_sexp(&lam.unsafe_body),
+            ),
+        },
+        Term::Pi(_, ref pi) => format!(
+            "(pi {} {})",
+            param(
+                pi.plicity,
+                format!("{} {}", pi.unsafe_param.name, to_sexp(&pi.unsafe_param.inner)),
+            ),
+            to_sexp(&pi.unsafe_body),
+        ),
+        Term::App(_, ref fn_expr, ref arg_expr) => {
+            format!("(app {} {})", to_sexp(fn_expr), to_sexp(arg_expr))
+        },
+        Term::Let(_, ref let_) => match let_.unsafe_param.inner {
+            None => format!(
+                "(let {} {} {})",
+                let_.unsafe_param.name,
+                to_sexp(&let_.value),
+                to_sexp(&let_.unsafe_body),
+            ),
+            Some(ref ann) => format!(
+                "(let ({} {}) {} {})",
+                let_.unsafe_param.name,
+                to_sexp(ann),
+                to_sexp(&let_.value),
+                to_sexp(&let_.unsafe_body),
+            ),
+        },
+        Term::Sigma(_, ref sigma) => format!(
+            "(sigma ({} {}) {})",
+            sigma.unsafe_param.name,
+            to_sexp(&sigma.unsafe_param.inner),
+            to_sexp(&sigma.unsafe_body),
+        ),
+        Term::Pair(_, ref fst, ref snd) => format!("(pair {} {})", to_sexp(fst), to_sexp(snd)),
+        Term::Fst(_, ref pair) => format!("(fst {})", to_sexp(pair)),
+        Term::Snd(_, ref pair) => format!("(snd {})", to_sexp(pair)),
+        Term::RecordType(_, ref record_ty) => format!(
+            "(Record ({} {}) {})",
+            record_ty.label,
+            to_sexp(&record_ty.unsafe_param.inner),
+            to_sexp(&record_ty.unsafe_body),
+        ),
+        Term::RecordTypeEmpty(_) => String::from("(Record)"),
+        Term::Record(_, ref label, ref value, ref rest) => {
+            format!("(record {} {} {})", label, to_sexp(value), to_sexp(rest))
+        },
+        Term::RecordEmpty(_) => String::from("(record)"),
+        Term::Proj(_, ref record, ref label) => format!("(proj {} {})", to_sexp(record), label),
+        Term::Constant(_, ref c) => c.to_string(),
+        Term::If(_, ref cond, ref if_true, ref if_false) => format!(
+            "(if {} {} {})",
+            to_sexp(cond),
+            to_sexp(if_true),
+            to_sexp(if_false),
+        ),
+        Term::Hole(_) => String::from("?"),
+        Term::Case(_, ref scrutinee, ref clauses) => {
+            let mut sexp = format!("(case {}", to_sexp(scrutinee));
+            for clause in clauses {
+                sexp.push_str(&format!(
+                    " ({} {})",
+                    pattern_to_sexp(&clause.unsafe_pattern),
+                    to_sexp(&clause.unsafe_body),
+                ));
+            }
+            sexp.push(')');
+            sexp
+        },
+    }
+}
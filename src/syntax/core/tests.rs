@@ -60,6 +60,53 @@ mod alpha_eq {
         );
     }
 
+    #[test]
+    fn shared() {
+        let term = parse(r"\x : Type -> Type => \y : Type => x y");
+        assert_eq!(term, term.clone());
+    }
+
+    #[test]
+    fn shared_diff() {
+        let term = parse(r"\x : Type -> Type => \y : Type => x y");
+        let other = parse(r"\x : Type -> Type => \y : Type => y");
+        assert_ne!(term, other);
+    }
+
+    /// The depth of the trees built by the `shared_subterms` and
+    /// `shared_subvalues` tests. Both children of each node are the same
+    /// pointer, so unfolded these trees have `2^SHARED_DEPTH` leaves, and a
+    /// structural comparison would never finish.
+    const SHARED_DEPTH: usize = 64;
+
+    #[test]
+    fn shared_subterms() {
+        let mut term = RcTerm::from(Term::Universe(SourceMeta::default(), Level::ZERO));
+        for _ in 0..SHARED_DEPTH {
+            term = Term::App(SourceMeta::default(), term.clone(), term).into();
+        }
+
+        // The outer terms are allocated separately, so this only finishes if
+        // the comparison stops as soon as it reaches the shared subterms
+        let lhs = RcTerm::from(Term::Fst(SourceMeta::default(), term.clone()));
+        let rhs = RcTerm::from(Term::Fst(SourceMeta::default(), term));
+        assert_eq!(lhs, rhs);
+    }
+
+    #[test]
+    fn shared_subvalues() {
+        let mut value = RcValue::from(Value::Universe(Level::ZERO));
+        for _ in 0..SHARED_DEPTH {
+            value = Value::App(value.clone(), value).into();
+        }
+
+        // The outer values are allocated separately, so this only finishes if
+        // the comparison stops as soon as it reaches the shared subvalues
+        let lhs = RcValue::from(Value::Fst(value.clone()));
+        let rhs = RcValue::from(Value::Fst(value));
+        assert_eq!(lhs, rhs);
+    }
+
     #[test]
     fn lam_lam_app() {
         assert_eq!(
@@ -75,4 +122,474 @@ mod alpha_eq {
             parse(r"(a : Type -> Type) -> (b : Type) -> a b"),
         );
     }
+
+    #[test]
+    fn lam_value_generated_names() {
+        use semantics;
+
+        // Normalizing each of these generates a different fresh name for the
+        // parameter, but only the binding structure should be compared
+        let context = Context::new();
+        let x = semantics::normalize(&context, &parse(r"\x : Type => x")).unwrap();
+        let y = semantics::normalize(&context, &parse(r"\y : Type => y")).unwrap();
+
+        assert_eq!(x, y);
+    }
+
+    #[test]
+    #[should_panic]
+    fn free_generated_names_diff() {
+        let x: Var<Name, Debruijn> = Var::Free(Name::fresh(Some("x")));
+        let y: Var<Name, Debruijn> = Var::Free(Name::fresh(Some("x")));
+
+        assert_eq!(x, y);
+    }
+}
+
+mod pretty {
+    use std::usize;
+
+    use syntax::pretty::{self, ToDoc};
+
+    use super::*;
+
+    #[test]
+    fn always_show_indices() {
+        let term = parse(r"\x : Type => x");
+        let options = pretty::Options::default().with_always_show_indices(true);
+
+        assert_eq!(
+            term.to_doc(options).group().pretty(usize::MAX).to_string(),
+            r"\x : Type => x@0",
+        );
+    }
+
+    #[test]
+    fn only_indices() {
+        let term = parse(r"\x : Type => \y : Type => \x : Type => y x");
+        let options = pretty::Options::default().with_only_indices(true);
+
+        assert_eq!(
+            term.to_doc(options).group().pretty(usize::MAX).to_string(),
+            r"\x : Type => \y : Type => \x : Type => @1 @0",
+        );
+    }
+
+    #[test]
+    fn alternate_shows_indices() {
+        let term = parse(r"\x : Type => x");
+
+        assert_eq!(format!("{}", term), r"\x : Type => x");
+        assert_eq!(format!("{:#}", term), r"\x : Type => x@0");
+    }
+
+    #[test]
+    fn max_depth() {
+        let term = parse(r"\x : Type => \y : Type => \z : Type => x");
+        let options = pretty::Options::default().with_max_depth(Some(2));
+
+        assert_eq!(
+            term.to_doc(options).group().pretty(usize::MAX).to_string(),
+            r"\x : Type => \y : Type => …",
+        );
+    }
+
+    #[test]
+    fn max_depth_zero() {
+        let term = parse(r"\x : Type => x");
+        let options = pretty::Options::default().with_max_depth(Some(0));
+
+        assert_eq!(term.to_doc(options).group().pretty(usize::MAX).to_string(), "…");
+    }
+
+    #[test]
+    fn app_arg_parens() {
+        let term = parse(r"f (g x) (Type 1) y");
+
+        assert_eq!(format!("{}", term), r"f (g x) (Type 1) y");
+    }
+
+    /// Remove the ANSI escape codes from some highlighted output
+    fn strip_escapes(src: &str) -> String {
+        let mut stripped = String::new();
+        let mut chars = src.chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\x1b' => for _ in chars.by_ref().take_while(|&ch| ch != 'm') {},
+                ch => stripped.push(ch),
+            }
+        }
+        stripped
+    }
+
+    #[test]
+    fn render_color() {
+        let term = parse(r"\(a : Type 1) (x : a) => (f : a -> a) -> f x");
+        let options = pretty::Options::default();
+        let doc = term.to_doc(options).group();
+
+        for &width in &[usize::MAX, 20] {
+            let plain = pretty::render(&doc, options, width);
+            let colored = pretty::render(&doc, options.with_color(true), width);
+
+            assert_ne!(colored, plain);
+            assert_eq!(strip_escapes(&colored), plain);
+        }
+    }
+}
+
+mod instantiate {
+    use semantics;
+
+    use super::*;
+
+    fn normalize(src: &str) -> RcValue {
+        semantics::normalize(&Context::new(), &parse(src)).unwrap()
+    }
+
+    #[test]
+    fn lam() {
+        let lam = normalize(r"\(a : Type 1) (x : a) => x");
+        let arg = normalize(r"Type");
+
+        match *lam.inner {
+            Value::Lam(ref lam) => {
+                let (param, mut expected) = lam.clone().unbind();
+                expected.subst(&param.name, &arg);
+
+                assert_eq!(lam.instantiate(&arg), expected);
+                assert_eq!(lam.instantiate(&arg), normalize(r"\x : Type => x"));
+            },
+            _ => panic!("expected a lambda, found {}", lam),
+        }
+    }
+
+    #[test]
+    fn pi() {
+        let pi = normalize(r"(a : Type) -> a -> a");
+        let arg = normalize(r"Type");
+
+        match *pi.inner {
+            Value::Pi(ref pi) => {
+                let (param, mut expected) = pi.clone().unbind();
+                expected.subst(&param.name, &arg);
+
+                assert_eq!(pi.instantiate(&arg), expected);
+                assert_eq!(pi.instantiate(&arg), normalize(r"Type -> Type"));
+            },
+            _ => panic!("expected a pi type, found {}", pi),
+        }
+    }
+}
+
+mod bind_instantiate {
+    use super::*;
+
+    fn var(name: &str) -> RcTerm {
+        Term::Var(SourceMeta::default(), Var::Free(Name::user(name))).into()
+    }
+
+    #[test]
+    fn lam_round_trip() {
+        let body = parse(r"f (x : Type) x");
+        let lam = TermLam::bind(Named::new(Name::user("x"), None), body.clone());
+
+        assert_eq!(lam.instantiate(&var("x")), body);
+        assert_eq!(lam.instantiate(&var("y")), parse(r"f (y : Type) y"));
+    }
+
+    #[test]
+    fn pi_round_trip() {
+        let body = parse(r"(b : a) -> b -> a");
+        let pi = TermPi::bind(Named::new(Name::user("a"), parse(r"Type")), body.clone());
+
+        assert_eq!(pi.instantiate(&var("a")), body);
+        assert_eq!(pi.instantiate(&var("c")), parse(r"(b : c) -> b -> c"));
+    }
+
+    #[test]
+    fn nested() {
+        let lam = match *parse(r"\(a : Type) (x : a) => (x : a)").inner {
+            Term::Lam(_, ref lam) => lam.clone(),
+            ref term => panic!("expected a lambda, found {:?}", term),
+        };
+
+        assert_eq!(lam.instantiate(&parse(r"Type")), parse(r"\(x : Type) => (x : Type)"));
+    }
+}
+
+mod free_vars {
+    use std::collections::HashSet;
+
+    use semantics;
+
+    use super::*;
+
+    fn names(names: &[&str]) -> HashSet<Name> {
+        names.iter().map(|&name| Name::user(name)).collect()
+    }
+
+    #[test]
+    fn term_lam() {
+        assert_eq!(parse(r"\x => x y").free_vars(), names(&["y"]));
+    }
+
+    #[test]
+    fn term_pi() {
+        assert_eq!(parse(r"(a : t) -> (b : a) -> f a b c").free_vars(), names(&["t", "f", "c"]));
+    }
+
+    #[test]
+    fn value_lam() {
+        let ann = semantics::normalize(&Context::new(), &parse(r"Type -> Type")).unwrap();
+        let context = Context::new().declare(Name::user("f"), ann);
+        let value = semantics::normalize(&context, &parse(r"\x : Type => f x")).unwrap();
+
+        assert_eq!(value.free_vars(), names(&["f"]));
+    }
+}
+
+mod rename_shadowed {
+    use super::*;
+
+    #[test]
+    fn lam() {
+        let term = parse(r"\x => \x => x");
+        let renamed = term.rename_shadowed();
+
+        assert_eq!(renamed.to_string(), r"\x => \x1 => x1");
+        assert_eq!(parse(&renamed.to_string()), term);
+    }
+
+    #[test]
+    fn free_var_clash() {
+        let term = parse(r"\x => \x => x x1");
+        let renamed = term.rename_shadowed();
+
+        assert_eq!(renamed.to_string(), r"\x => \x2 => x2 x1");
+        assert_eq!(parse(&renamed.to_string()), term);
+    }
+
+    #[test]
+    fn siblings() {
+        let term = parse(r"(\x => x) (\x => x)");
+
+        assert_eq!(term.rename_shadowed().to_string(), r"(\x => x) (\x => x)");
+    }
+
+    #[test]
+    fn pi() {
+        let term = parse(r"(a : Type) -> (a : a) -> a");
+        let renamed = term.rename_shadowed();
+
+        assert_eq!(renamed.to_string(), r"(a : Type) -> (a1 : a) -> a1");
+        assert_eq!(parse(&renamed.to_string()), term);
+    }
+}
+
+mod traverse_vars {
+    use super::*;
+
+    fn rename(
+        from: &str,
+        to: &str,
+    ) -> impl FnMut(&Var<Name, Debruijn>) -> Result<Var<Name, Debruijn>, ()> {
+        let (from, to) = (Name::user(from), Name::user(to));
+
+        move |var| match *var {
+            Var::Free(ref name) if *name == from => Ok(Var::Free(to.clone())),
+            ref var => Ok(var.clone()),
+        }
+    }
+
+    #[test]
+    fn rename_free_var() {
+        let term = parse(r"\x : a => a x");
+        let renamed = term.traverse_vars(&mut rename("a", "b")).unwrap();
+
+        assert_eq!(renamed, parse(r"\x : b => b x"));
+        // The bound variable should still point to its binder
+        assert_eq!(format!("{:#}", renamed), r"\x : b => b x@0");
+    }
+
+    #[test]
+    fn error() {
+        let term = parse(r"(x : Type) -> a");
+        let result = term.traverse_vars(&mut |var| match *var {
+            Var::Free(ref name) => Err(name.clone()),
+            ref var => Ok(var.clone()),
+        });
+
+        assert_eq!(result, Err(Name::user("a")));
+    }
+}
+
+mod declare_define {
+    use super::*;
+
+    fn ty() -> RcValue {
+        Value::Universe(Level::ZERO).into()
+    }
+
+    #[test]
+    fn declare_then_define() {
+        let x = Name::user("x");
+        let context = Context::new()
+            .declare(x.clone(), Value::Universe(Level::ZERO.succ()).into())
+            .define(x.clone(), ty(), Transparency::Transparent)
+            .unwrap();
+
+        assert_eq!(
+            context.lookup_binder(&x),
+            Some(&Binder::Let(
+                ty(),
+                Value::Universe(Level::ZERO.succ()).into(),
+                Transparency::Transparent,
+            )),
+        );
+    }
+
+    #[test]
+    fn define_twice() {
+        let x = Name::user("x");
+        let context = Context::new()
+            .declare(x.clone(), Value::Universe(Level::ZERO.succ()).into())
+            .define(x.clone(), ty(), Transparency::Transparent)
+            .unwrap();
+
+        assert_eq!(
+            context.define(x.clone(), ty(), Transparency::Transparent),
+            Err(ContextError::AlreadyDefined { name: x }),
+        );
+    }
+
+    #[test]
+    fn define_without_declare() {
+        let x = Name::user("x");
+        let context = Context::new().extend(x.clone(), Binder::Pi(ty()));
+
+        assert_eq!(
+            Context::new().define(x.clone(), ty(), Transparency::Transparent),
+            Err(ContextError::Undeclared { name: x.clone() }),
+        );
+        assert_eq!(
+            context.define(x.clone(), ty(), Transparency::Transparent),
+            Err(ContextError::Undeclared { name: x }),
+        );
+    }
+}
+
+mod bindings {
+    use super::*;
+
+    #[test]
+    fn claim_then_define() {
+        let name = Name::user("x");
+        let ty = RcValue::from(Value::Universe(Level::ZERO.succ()));
+        let value = RcValue::from(Value::Universe(Level::ZERO));
+        let context = Context::new()
+            .declare(name.clone(), ty.clone())
+            .define(name.clone(), value.clone(), Transparency::Transparent)
+            .unwrap();
+
+        let bindings = context.iter_bindings().collect::<Vec<_>>();
+
+        assert_eq!(bindings, [(&name, &ty, Some(&value))]);
+        assert_eq!(context.lookup(&name), Some((&ty, Some(&value))));
+        assert_eq!(context.len(), 2);
+    }
+
+    #[test]
+    fn claim_without_definition() {
+        let name = Name::user("x");
+        let ty = RcValue::from(Value::Universe(Level::ZERO));
+        let context = Context::new().declare(name.clone(), ty.clone());
+
+        assert_eq!(context.iter_bindings().collect::<Vec<_>>(), [(&name, &ty, None)]);
+        assert_eq!(context.lookup(&name), Some((&ty, None)));
+        assert_eq!(context.lookup(&Name::user("y")), None);
+    }
+}
+
+mod import {
+    use super::*;
+
+    fn import_foo(context: &Context) -> Context {
+        let record = Value::RecordEmpty.into();
+        let record_ty = Value::RecordTypeEmpty.into();
+        context.import(Name::user("f"), String::from("foo"), record, record_ty)
+    }
+
+    #[test]
+    fn lookup_module() {
+        let context = import_foo(&Context::new());
+
+        assert_eq!(context.lookup_module(&Name::user("f")), Some("foo"));
+        assert_eq!(context.lookup_module(&Name::user("foo")), None);
+    }
+
+    #[test]
+    fn lookup_module_shadowed() {
+        let ty = Value::Universe(Level::ZERO).into();
+        let context = import_foo(&Context::new()).extend(Name::user("f"), Binder::Pi(ty));
+
+        assert_eq!(context.lookup_module(&Name::user("f")), None);
+        assert_eq!(import_foo(&context).lookup_module(&Name::user("f")), Some("foo"));
+    }
+}
+
+mod to_sexp {
+    use super::*;
+
+    #[test]
+    fn lam() {
+        let term = parse(r"\(f : (a : Type) -> Type) (x : Type) => f x");
+
+        assert_eq!(
+            to_sexp(&term),
+            "(lam (f (pi (a (type 0)) (type 0))) (lam (x (type 0)) (app (var 1) (var 0))))",
+        );
+    }
+
+    #[test]
+    fn unannotated_lam() {
+        let term = parse(r"\x => y x");
+
+        assert_eq!(to_sexp(&term), "(lam x (app (free y) (var 0)))");
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serialization {
+    use serde_json;
+
+    use super::*;
+
+    // Names and spans are ignored by `PartialEq`, so we compare the debug
+    // output to check that they survive the round trip as well
+
+    #[test]
+    fn pi_term() {
+        let term = parse(r"(a : Type) -> a -> a");
+        let json = serde_json::to_string(&term).unwrap();
+        let deserialized: RcTerm = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(deserialized, term);
+        assert_eq!(format!("{:?}", deserialized), format!("{:?}", term));
+    }
+
+    #[test]
+    fn pi_value_with_generated_name() {
+        let name = Name::fresh(Some("a"));
+        let value = RcValue::from(Value::Pi(ValuePi {
+            plicity: Plicity::Implicit,
+            unsafe_param: Named::new(name.clone(), Value::Universe(Level::ZERO).into()),
+            unsafe_body: Value::Var(Var::Free(name)).into(),
+        }));
+        let json = serde_json::to_string(&value).unwrap();
+        let deserialized: RcValue = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(deserialized, value);
+        assert_eq!(format!("{:?}", deserialized), format!("{:?}", value));
+    }
 }
@@ -0,0 +1,77 @@
+//! Utilities for suggesting similarly named things in error messages
+
+use std::cmp;
+
+/// The [Levenshtein distance] between two strings, ie. the number of single
+/// character insertions, deletions, or substitutions needed to get from one
+/// string to the other
+///
+/// [Levenshtein distance]: https://en.wikipedia.org/wiki/Levenshtein_distance
+pub fn lev_distance(a: &str, b: &str) -> usize {
+    let b_len = b.chars().count();
+    let mut dists = (0..b_len + 1).collect::<Vec<_>>();
+
+    for (i, a_ch) in a.chars().enumerate() {
+        let mut prev_diag = dists[0];
+        dists[0] = i + 1;
+
+        for (j, b_ch) in b.chars().enumerate() {
+            let cost = if a_ch == b_ch { 0 } else { 1 };
+            let next = cmp::min(cmp::min(dists[j] + 1, dists[j + 1] + 1), prev_diag + cost);
+            prev_diag = dists[j + 1];
+            dists[j + 1] = next;
+        }
+    }
+
+    dists[b_len]
+}
+
+/// Find the candidate that is closest to `lookup`, provided that it is no more
+/// than `max_dist` edits away. Ties are resolved in favour of the earliest
+/// candidate.
+pub fn find_best_match<'a, I>(lookup: &str, candidates: I, max_dist: usize) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    candidates
+        .into_iter()
+        .map(|candidate| (lev_distance(lookup, candidate), candidate))
+        .filter(|&(dist, _)| dist <= max_dist)
+        .min_by_key(|&(dist, _)| dist)
+        .map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lev_distance_identical() {
+        assert_eq!(lev_distance("type", "type"), 0);
+    }
+
+    #[test]
+    fn lev_distance_empty() {
+        assert_eq!(lev_distance("", "type"), 4);
+        assert_eq!(lev_distance("type", ""), 4);
+    }
+
+    #[test]
+    fn lev_distance_edits() {
+        assert_eq!(lev_distance("tpye", "type"), 2);
+        assert_eq!(lev_distance("kitten", "sitting"), 3);
+        assert_eq!(lev_distance("λx", "λy"), 1);
+    }
+
+    #[test]
+    fn find_best_match_close() {
+        let candidates = vec!["help", "quit", "type"];
+        assert_eq!(find_best_match("tpye", candidates, 2), Some("type"));
+    }
+
+    #[test]
+    fn find_best_match_far() {
+        let candidates = vec!["help", "quit", "type"];
+        assert_eq!(find_best_match("xyz", candidates, 2), None);
+    }
+}
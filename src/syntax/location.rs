@@ -0,0 +1,80 @@
+//! Utilities for converting byte offsets into line and column numbers
+
+use codespan::{ByteIndex, ByteSpan, FileMap};
+
+/// Convert a byte index into a 1-based `(line, column)` pair
+///
+/// Columns are counted in Unicode scalar values (Rust `char`s), not bytes, so
+/// a multi-byte character like `λ` only advances the column by one. Indices
+/// that fall outside of the file, or in the middle of a character, are
+/// clamped to the nearest preceding character boundary.
+pub fn index_to_location(filemap: &FileMap, index: ByteIndex) -> (usize, usize) {
+    let src = filemap.src();
+    let offset = index.0.saturating_sub(filemap.span().start().0) as usize;
+    let mut offset = offset.min(src.len());
+    while !src.is_char_boundary(offset) {
+        offset -= 1;
+    }
+
+    let before = &src[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|&ch| ch != '\n').count() + 1;
+
+    (line, column)
+}
+
+/// Convert a span into the 1-based `(line, column)` pairs of its start and
+/// end, as described in `index_to_location`
+pub fn span_to_location(filemap: &FileMap, span: ByteSpan) -> ((usize, usize), (usize, usize)) {
+    (
+        index_to_location(filemap, span.start()),
+        index_to_location(filemap, span.end()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use codespan::{CodeMap, FileName};
+
+    use super::*;
+
+    fn index(filemap: &FileMap, offset: u32) -> ByteIndex {
+        ByteIndex(filemap.span().start().0 + offset)
+    }
+
+    #[test]
+    fn ascii() {
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), "foo\nbar baz\n".into());
+
+        assert_eq!(index_to_location(&filemap, index(&filemap, 0)), (1, 1));
+        assert_eq!(index_to_location(&filemap, index(&filemap, 3)), (1, 4));
+        assert_eq!(index_to_location(&filemap, index(&filemap, 4)), (2, 1));
+        assert_eq!(index_to_location(&filemap, index(&filemap, 8)), (2, 5));
+    }
+
+    #[test]
+    fn multi_byte() {
+        let mut codemap = CodeMap::new();
+        // `λ` and `α` are two bytes each, `→` is three
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), "λx\nα → β\n".into());
+
+        let span = ByteSpan::new(index(&filemap, 0), index(&filemap, 3));
+        assert_eq!(span_to_location(&filemap, span), ((1, 1), (1, 3)));
+
+        // `α → β` starts at byte 4 and ends at byte 13
+        let span = ByteSpan::new(index(&filemap, 4), index(&filemap, 13));
+        assert_eq!(span_to_location(&filemap, span), ((2, 1), (2, 6)));
+    }
+
+    #[test]
+    fn clamped() {
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), "λ\nx".into());
+
+        // In the middle of `λ`
+        assert_eq!(index_to_location(&filemap, index(&filemap, 1)), (1, 1));
+        // Past the end of the file
+        assert_eq!(index_to_location(&filemap, index(&filemap, 100)), (2, 2));
+    }
+}
@@ -2,6 +2,8 @@
 
 pub mod concrete;
 pub mod core;
+pub mod lev_distance;
+pub mod location;
 pub mod parse;
 pub mod pretty;
 pub mod translation;
@@ -2,26 +2,62 @@ use lalrpop_util::ParseError as LalrpopError;
 use codespan::FileMap;
 use codespan::{ByteIndex, ByteSpan};
 use codespan_reporting::Diagnostic;
+use failure::Fail;
 use std::fmt;
 
+use syntax::{lev_distance, location};
 use syntax::parse::{LexerError, Token};
 
+/// The commands that are understood by the REPL, used for suggesting
+/// alternatives to unknown commands
+const REPL_COMMANDS: &[&str] = &[
+    "?", "h", "help", "all", "b", "bench", "browse", "l", "let", "load", "n", "normalize", "q",
+    "quit", "set", "t", "type",
+];
+
 #[derive(Fail, Debug, Clone, PartialEq, Eq)]
 pub enum ParseError {
     #[fail(display = "{}", _0)]
     Lexer(#[cause] LexerError),
     #[fail(display = "An identifier was expected when parsing a pi type.")]
     IdentifierExpectedInPiType { span: ByteSpan },
+    #[fail(display = "An identifier was expected when parsing a sigma type.")]
+    IdentifierExpectedInSigmaType { span: ByteSpan },
     #[fail(display = "An integer literal {} was too large for the target type.", value)]
     IntegerLiteralOverflow { span: ByteSpan, value: String },
+    #[fail(display = "The numeric literal {} has misplaced underscores.", value)]
+    MalformedNumericLiteral { span: ByteSpan, value: String },
     #[fail(display = "Unknown repl command `:{}` found.", command)]
     UnknownReplCommand { span: ByteSpan, command: String },
+    #[fail(display = "The repl command `:{}` expects an argument.", command)]
+    MissingReplCommandArgument { span: ByteSpan, command: String },
+    #[fail(display = "Unknown repl option `{}` found.", option)]
+    UnknownReplOption { span: ByteSpan, option: String },
+    #[fail(display = "Invalid value `{}` for the repl option `{}`.", value, option)]
+    InvalidReplOptionValue {
+        span: ByteSpan,
+        option: String,
+        value: String,
+    },
+    #[fail(display = "The keyword `{}` is reserved and can't be used as an identifier.", keyword)]
+    ReservedKeyword { span: ByteSpan, keyword: String },
+    #[fail(display = "`{}` declarations are not allowed in where blocks.", keyword)]
+    UnexpectedWhereDeclaration { span: ByteSpan, keyword: String },
+    #[fail(display = "The claim for `{}` in a where block has no definition.", name)]
+    WhereClaimWithoutDefinition { span: ByteSpan, name: String },
+    #[fail(display = "The name `{}` was claimed more than once in a where block.", name)]
+    DuplicateWhereClaim { span: ByteSpan, name: String },
+    /// The input ended before the parser was finished, so typing more might
+    /// fix the error
+    ///
+    /// The span is empty, and sits at the end of the input. The expected
+    /// tokens are the ones that the grammar would have accepted next.
     #[fail(display = "Unexpected EOF, expected one of: {}.", expected)]
     UnexpectedEof {
-        end: ByteIndex,
+        span: ByteSpan,
         expected: ExpectedTokens,
     },
-    #[fail(display = "Unexpected token {}, found, expected one of: {}.", token, expected)]
+    #[fail(display = "Unexpected token `{}` found, expected one of: {}.", token, expected)]
     UnexpectedToken {
         span: ByteSpan,
         token: Token<String>,
@@ -45,9 +81,12 @@ where
         LalrpopError::UnrecognizedToken {
             token: None,
             expected,
-        } => ParseError::UnexpectedEof {
-            end: filemap.span().end(),
-            expected: ExpectedTokens(expected),
+        } => {
+            let end = filemap.span().end();
+            ParseError::UnexpectedEof {
+                span: ByteSpan::new(end, end),
+                expected: ExpectedTokens(expected),
+            }
         },
         LalrpopError::UnrecognizedToken {
             token: Some((start, token, end)),
@@ -72,11 +111,29 @@ impl ParseError {
         match *self {
             ParseError::Lexer(ref err) => err.span(),
             ParseError::IdentifierExpectedInPiType { span }
+            | ParseError::IdentifierExpectedInSigmaType { span }
             | ParseError::IntegerLiteralOverflow { span, .. }
+            | ParseError::MalformedNumericLiteral { span, .. }
             | ParseError::UnknownReplCommand { span, .. }
+            | ParseError::MissingReplCommandArgument { span, .. }
+            | ParseError::UnknownReplOption { span, .. }
+            | ParseError::InvalidReplOptionValue { span, .. }
+            | ParseError::ReservedKeyword { span, .. }
+            | ParseError::UnexpectedWhereDeclaration { span, .. }
+            | ParseError::WhereClaimWithoutDefinition { span, .. }
+            | ParseError::DuplicateWhereClaim { span, .. }
+            | ParseError::UnexpectedEof { span, .. }
             | ParseError::UnexpectedToken { span, .. }
             | ParseError::ExtraToken { span, .. } => span,
-            ParseError::UnexpectedEof { end, .. } => ByteSpan::new(end, end),
+        }
+    }
+
+    /// Returns `true` if the error was caused by the input ending before the
+    /// parser was finished, meaning that more input might fix it
+    pub fn is_unexpected_eof(&self) -> bool {
+        match *self {
+            ParseError::UnexpectedEof { .. } => true,
+            _ => false,
         }
     }
 
@@ -88,13 +145,64 @@ impl ParseError {
                 Diagnostic::new_error("identifier expected when parsing dependent function type")
                     .with_primary_label(span, "ill-formed dependent function type")
             },
+            ParseError::IdentifierExpectedInSigmaType { span } => {
+                Diagnostic::new_error("identifier expected when parsing dependent pair type")
+                    .with_primary_label(span, "ill-formed dependent pair type")
+            },
             ParseError::IntegerLiteralOverflow { span, ref value } => {
                 Diagnostic::new_error(format!("integer literal overflow with value `{}`", value))
                     .with_primary_label(span, "overflowing literal")
             },
+            ParseError::MalformedNumericLiteral { span, ref value } => {
+                Diagnostic::new_error(format!("malformed numeric literal `{}`", value))
+                    .with_primary_label(span, "underscores must sit between two digits")
+            },
             ParseError::UnknownReplCommand { span, ref command } => {
+                let commands = REPL_COMMANDS.iter().cloned();
+                let label = match lev_distance::find_best_match(command, commands, 2) {
+                    Some(suggestion) => format!("did you mean `:{}`?", suggestion),
+                    None => String::from("unexpected command"),
+                };
+
                 Diagnostic::new_error(format!("unknown repl command `:{}`", command))
-                    .with_primary_label(span, "unexpected command")
+                    .with_primary_label(span, label)
+            },
+            ParseError::MissingReplCommandArgument { span, ref command } => Diagnostic::new_error(
+                format!("the repl command `:{}` expects an argument", command),
+            ).with_primary_label(span, "missing an argument"),
+            ParseError::UnknownReplOption { span, ref option } => {
+                Diagnostic::new_error(format!("unknown repl option `{}`", option))
+                    .with_primary_label(span, "expected `depth`, `timing`, or `unfold`")
+            },
+            ParseError::InvalidReplOptionValue {
+                span,
+                ref option,
+                ref value,
+            } => {
+                let expected = match option.as_str() {
+                    "depth" => "expected a natural number",
+                    "timing" => "expected `on` or `off`",
+                    _ => "expected one of `all`, `prelude`, `user`, or `none`",
+                };
+
+                Diagnostic::new_error(format!("invalid value `{}` for `{}`", value, option))
+                    .with_primary_label(span, expected)
+            },
+            ParseError::ReservedKeyword { span, ref keyword } => {
+                Diagnostic::new_error(format!("`{}` is a reserved keyword", keyword))
+                    .with_primary_label(span, format!("try renaming this to `{}_`", keyword))
+            },
+            ParseError::UnexpectedWhereDeclaration { span, ref keyword } => {
+                Diagnostic::new_error(format!("`{}` is not allowed in a `where` block", keyword))
+                    .with_primary_label(span, "move this to the top level of the module")
+            },
+            ParseError::WhereClaimWithoutDefinition { span, ref name } => {
+                Diagnostic::new_error(format!("missing a definition for `{}`", name))
+                    .with_primary_label(span, "claimed in this `where` block")
+            },
+            ParseError::DuplicateWhereClaim { span, ref name } => {
+                Diagnostic::new_error(format!("`{}` was already claimed", name))
+                    .with_primary_label(span, "claimed again in the same `where` block")
             },
             ParseError::UnexpectedToken {
                 span,
@@ -102,9 +210,9 @@ impl ParseError {
                 ref expected,
             } => Diagnostic::new_error(format!("expected one of {}, found `{}`", expected, token))
                 .with_primary_label(span, "unexpected token"),
-            ParseError::UnexpectedEof { end, ref expected } => {
+            ParseError::UnexpectedEof { span, ref expected } => {
                 Diagnostic::new_error(format!("expected one of {}, found `EOF`", expected))
-                    .with_primary_label(ByteSpan::new(end, end), "unexpected EOF")
+                    .with_primary_label(span, "unexpected EOF")
             },
             ParseError::ExtraToken { span, ref token } => {
                 Diagnostic::new_error(format!("extra token `{}`", token))
@@ -114,22 +222,63 @@ impl ParseError {
     }
 }
 
+/// Errors produced when parsing directly from a string, using `FromStr`
+///
+/// The positions of the errors are recorded as one-based line and column
+/// numbers, so that they can still be displayed once the `FileMap` that they
+/// were parsed from has been discarded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FromStrError {
+    pub errors: Vec<((usize, usize), ParseError)>,
+}
+
+impl FromStrError {
+    pub fn new(filemap: &FileMap, errors: Vec<ParseError>) -> FromStrError {
+        let errors = errors
+            .into_iter()
+            .map(|err| (location::index_to_location(filemap, err.span().start()), err))
+            .collect();
+
+        FromStrError { errors }
+    }
+}
+
+impl Fail for FromStrError {}
+
+impl fmt::Display for FromStrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, &((line, column), ref err)) in self.errors.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}:{}: {}", line, column, err)?;
+        }
+        Ok(())
+    }
+}
+
 impl From<LexerError> for ParseError {
     fn from(src: LexerError) -> ParseError {
         ParseError::Lexer(src)
     }
 }
 
+/// The tokens that the parser would have accepted in place of an unexpected
+/// token, as named by LALRPOP
+///
+/// LALRPOP quotes the names of the terminals, so these are replaced with
+/// backticks when displayed, giving something like: `` `:`, or `->` ``
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ExpectedTokens(pub Vec<String>);
 
 impl fmt::Display for ExpectedTokens {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         for (i, token) in self.0.iter().enumerate() {
+            let token = token.trim_matches('"');
             match i {
-                0 => write!(f, "{}", token)?,
-                i if i < self.0.len() - 1 => write!(f, ", {}", token)?,
-                _ => write!(f, ", or {}", token)?,
+                0 => write!(f, "`{}`", token)?,
+                i if i < self.0.len() - 1 => write!(f, ", `{}`", token)?,
+                _ => write!(f, ", or `{}`", token)?,
             }
         }
         Ok(())
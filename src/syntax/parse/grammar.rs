@@ -8,19 +8,48 @@ fn reparse_pi_type_hack<L, T>(
     binder: Term,
     body: Term,
 ) -> Result<Term, LalrpopError<L, T, ParseError>> {
+    let expected_ident = |span| ParseError::IdentifierExpectedInPiType { span };
+
+    match reparse_params_hack(binder, &expected_ident)? {
+        Ok(params) => Ok(Term::Pi(span.start(), Plicity::Explicit, params, body.into())),
+        Err(ann) => Ok(Term::Arrow(ann.into(), body.into())),
+    }
+}
+
+/// Like `reparse_pi_type_hack`, but for sigma types
+fn reparse_sigma_type_hack<L, T>(
+    span: ByteSpan,
+    binder: Term,
+    body: Term,
+) -> Result<Term, LalrpopError<L, T, ParseError>> {
+    let expected_ident = |span| ParseError::IdentifierExpectedInSigmaType { span };
+
+    match reparse_params_hack(binder, &expected_ident)? {
+        Ok(params) => Ok(Term::Sigma(span.start(), params, body.into())),
+        Err(ann) => Ok(Term::Product(ann.into(), body.into())),
+    }
+}
+
+/// Reparse a binder term of the form `(x y : t)` as the parameters of a pi or
+/// sigma type, returning the term as it was if it is not of that form
+fn reparse_params_hack<L, T>(
+    binder: Term,
+    expected_ident: &Fn(ByteSpan) -> ParseError,
+) -> Result<Result<PiParams, Term>, LalrpopError<L, T, ParseError>> {
     fn param_names<L, T>(
         term: Term,
         names: &mut Vec<(ByteSpan, String)>,
+        expected_ident: &Fn(ByteSpan) -> ParseError,
     ) -> Result<(), LalrpopError<L, T, ParseError>> {
         match term {
             Term::Var(span, name) => names.push((span, name)),
             Term::App(fn_expr, arg) => {
-                param_names(*fn_expr, names)?;
-                param_names(*arg, names)?;
+                param_names(*fn_expr, names, expected_ident)?;
+                param_names(*arg, names, expected_ident)?;
             },
             term => {
                 return Err(LalrpopError::User {
-                    error: ParseError::IdentifierExpectedInPiType { span: term.span() },
+                    error: expected_ident(term.span()),
                 });
             },
         }
@@ -33,24 +62,226 @@ fn reparse_pi_type_hack<L, T>(
             match term {
                 Term::Ann(params, ann) => {
                     let mut names = Vec::new();
-                    param_names(*params, &mut names)?;
-                    Ok(Term::Pi(span.start(), (names, ann), body.into()))
+                    param_names(*params, &mut names, expected_ident)?;
+                    Ok(Ok((names, ann)))
                 },
-                ann => {
-                    let parens = Term::Parens(paren_span, ann.into()).into();
-                    Ok(Term::Arrow(parens, body.into()))
+                ann => Ok(Err(Term::Parens(paren_span, ann.into()))),
+            }
+        },
+        ann => Ok(Err(ann)),
+    }
+}
+
+/// Reparse the application of an integer literal to `Type` as a universe
+/// level. See the comments on the `AppTerm` rule in `grammar.lalrpop` for more
+/// information.
+fn reparse_universe_level_hack<L, T>(
+    fn_expr: Term,
+    arg: Term,
+) -> Result<Term, LalrpopError<L, T, ParseError>> {
+    match (fn_expr, arg) {
+        (Term::Universe(span, None), Term::IntLit(level_span, value)) => {
+            let level = u32_from_int_literal(level_span, value)?;
+            Ok(Term::Universe(span.to(level_span), Some(level)))
+        },
+        (fn_expr, arg) => Ok(Term::App(Box::new(fn_expr), Box::new(arg))),
+    }
+}
+
+/// Interpret the argument of a `:bench` command. The optional number of
+/// iterations is parsed as an integer literal at the head of the term, because
+/// parsing it directly would be ambiguous with the term itself.
+fn reparse_repl_bench_hack<L, T>(
+    term: Term,
+) -> Result<ReplCommand, LalrpopError<L, T, ParseError>> {
+    fn take_iterations(term: Term) -> (Option<(ByteSpan, i64)>, Term) {
+        match term {
+            Term::App(fn_expr, arg) => match *fn_expr {
+                Term::IntLit(span, value) => (Some((span, value)), *arg),
+                fn_expr => {
+                    let (iterations, fn_expr) = take_iterations(fn_expr);
+                    (iterations, Term::App(Box::new(fn_expr), arg))
                 },
+            },
+            term => (None, term),
+        }
+    }
+
+    match take_iterations(term) {
+        (None, term) => Ok(ReplCommand::Bench(None, Box::new(term))),
+        (Some((span, value)), term) => {
+            let iterations = u32_from_int_literal(span, value)?;
+            Ok(ReplCommand::Bench(Some(iterations), Box::new(term)))
+        },
+    }
+}
+
+/// Interpret the argument of a `:set` command. The option and its value are
+/// parsed as a term, because parsing them directly would be ambiguous with the
+/// arguments of the other REPL commands.
+fn reparse_repl_set_hack<L, T>(term: Term) -> Result<ReplCommand, LalrpopError<L, T, ParseError>> {
+    let error = match term {
+        Term::App(option, value) => match (*option, *value) {
+            (Term::Var(_, ref option), Term::Var(span, ref value)) if option == "unfold" => {
+                if let Ok(policy) = value.parse() {
+                    return Ok(ReplCommand::SetUnfold(policy));
+                }
+                ParseError::InvalidReplOptionValue {
+                    span,
+                    option: option.clone(),
+                    value: value.clone(),
+                }
+            },
+            (Term::Var(_, ref option), Term::Var(span, ref value)) if option == "timing" => {
+                match value.as_str() {
+                    "on" => return Ok(ReplCommand::SetTiming(true)),
+                    "off" => return Ok(ReplCommand::SetTiming(false)),
+                    _ => ParseError::InvalidReplOptionValue {
+                        span,
+                        option: option.clone(),
+                        value: value.clone(),
+                    },
+                }
+            },
+            (Term::Var(_, ref option), Term::IntLit(span, value)) if option == "depth" => {
+                return Ok(ReplCommand::SetDepth(u32_from_int_literal(span, value)?));
+            },
+            (Term::Var(_, ref option), ref value) if is_repl_option(option) => {
+                ParseError::InvalidReplOptionValue {
+                    span: value.span(),
+                    option: option.clone(),
+                    value: value.to_string(),
+                }
+            },
+            (option, _) => ParseError::UnknownReplOption {
+                span: option.span(),
+                option: option.to_string(),
+            },
+        },
+        Term::Var(span, ref option) if is_repl_option(option) => {
+            ParseError::MissingReplCommandArgument {
+                span,
+                command: format!("set {}", option),
             }
         },
-        ann => Ok(Term::Arrow(ann.into(), body.into())),
+        term => ParseError::UnknownReplOption {
+            span: term.span(),
+            option: term.to_string(),
+        },
+    };
+
+    Err(LalrpopError::User { error })
+}
+
+/// Report the declarations in a `where` block that can't be desugared to
+/// local definitions, leaving them out of the block
+fn where_declarations(
+    errors: &mut Vec<ParseError>,
+    declarations: Vec<Declaration>,
+) -> Vec<Declaration> {
+    use std::collections::hash_map::{Entry, HashMap};
+
+    let mut keep = vec![true; declarations.len()];
+    // The claims that are still waiting for a definition
+    let mut claims = HashMap::new();
+
+    for (index, declaration) in declarations.iter().enumerate() {
+        match *declaration {
+            Declaration::Import { span, .. } => {
+                errors.push(ParseError::UnexpectedWhereDeclaration {
+                    span,
+                    keyword: String::from("import"),
+                });
+                keep[index] = false;
+            },
+            Declaration::Claim {
+                name: (span, ref name),
+                ..
+            } => match claims.entry(name.clone()) {
+                Entry::Occupied(_) => {
+                    errors.push(ParseError::DuplicateWhereClaim {
+                        span,
+                        name: name.clone(),
+                    });
+                    keep[index] = false;
+                },
+                Entry::Vacant(entry) => {
+                    entry.insert(index);
+                },
+            },
+            Declaration::Definition {
+                name: (_, ref name),
+                ..
+            } => {
+                claims.remove(name);
+            },
+            // Local definitions are desugared to let bindings, which can't
+            // refer to themselves
+            Declaration::Recursive { .. } => {
+                errors.push(ParseError::UnexpectedWhereDeclaration {
+                    span: declaration.span(),
+                    keyword: String::from("rec"),
+                });
+                keep[index] = false;
+            },
+            Declaration::Error(_) => {},
+        }
+    }
+
+    let mut unmatched = claims.into_iter().collect::<Vec<_>>();
+    unmatched.sort_by_key(|&(_, index)| index);
+    for (name, index) in unmatched {
+        errors.push(ParseError::WhereClaimWithoutDefinition {
+            span: declarations[index].span(),
+            name,
+        });
+        keep[index] = false;
     }
+
+    declarations
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(declaration, keep)| if keep { Some(declaration) } else { None })
+        .collect()
+}
+
+/// The options that can be changed with a `:set` command
+fn is_repl_option(option: &str) -> bool {
+    option == "depth" || option == "timing" || option == "unfold"
 }
 
-fn u32_literal<L, T>(span: ByteSpan, src: &str) -> Result<u32, LalrpopError<L, T, ParseError>> {
-    u32::from_str_radix(src, 10).map_err(|_| LalrpopError::User {
+/// Parse a decimal literal, stripping out the underscores that separate its
+/// digits. These may not lead, trail, or follow one another.
+fn int_literal<L, T>(span: ByteSpan, src: &str) -> Result<i64, LalrpopError<L, T, ParseError>> {
+    if src.starts_with('_') || src.ends_with('_') || src.contains("__") {
+        return Err(LalrpopError::User {
+            error: ParseError::MalformedNumericLiteral {
+                span,
+                value: src.to_string(),
+            },
+        });
+    }
+
+    let digits = src.replace('_', "");
+    i64::from_str_radix(&digits, 10).map_err(|_| LalrpopError::User {
         error: ParseError::IntegerLiteralOverflow {
             span,
             value: src.to_string(),
         },
     })
 }
+
+fn u32_from_int_literal<L, T>(
+    span: ByteSpan,
+    value: i64,
+) -> Result<u32, LalrpopError<L, T, ParseError>> {
+    if value < 0 || value > i64::from(u32::MAX) {
+        return Err(LalrpopError::User {
+            error: ParseError::IntegerLiteralOverflow {
+                span,
+                value: value.to_string(),
+            },
+        });
+    }
+    Ok(value as u32)
+}
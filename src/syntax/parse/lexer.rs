@@ -25,11 +25,63 @@ fn is_dec_digit(ch: char) -> bool {
     ch.is_digit(10)
 }
 
+fn is_dec_digit_or_separator(ch: char) -> bool {
+    is_dec_digit(ch) || ch == '_'
+}
+
+/// The character that an escape sequence in a string literal stands for,
+/// given the character following the backslash
+fn escaped_char(ch: char) -> Option<char> {
+    match ch {
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        '\\' => Some('\\'),
+        '"' => Some('"'),
+        _ => None,
+    }
+}
+
+/// Replace the escape sequences in the source of a string literal with the
+/// characters that they stand for. The escape sequences must have already been
+/// checked by the lexer.
+pub fn unescape_string(src: &str) -> String {
+    let mut value = String::with_capacity(src.len());
+    let mut chars = src.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => value.extend(chars.next().and_then(escaped_char)),
+            ch => value.push(ch),
+        }
+    }
+    value
+}
+
+/// Escape a string so that it can be written back out as a string literal
+pub fn escape_string(value: &str) -> String {
+    let mut src = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '\n' => src.push_str("\\n"),
+            '\t' => src.push_str("\\t"),
+            '\\' => src.push_str("\\\\"),
+            '"' => src.push_str("\\\""),
+            ch => src.push(ch),
+        }
+    }
+    src
+}
+
 /// An error that occurred while lexing the source file
 #[derive(Fail, Debug, Clone, PartialEq, Eq)]
 pub enum LexerError {
     #[fail(display = "An unexpected character {:?} was found.", found)]
     UnexpectedCharacter { start: ByteIndex, found: char },
+    #[fail(display = "A block comment was not terminated.")]
+    UnterminatedBlockComment { span: ByteSpan },
+    #[fail(display = "A string literal was not terminated.")]
+    UnterminatedStringLiteral { span: ByteSpan },
+    #[fail(display = "An unknown escape sequence \\{} was found.", found)]
+    UnknownEscapeSequence { start: ByteIndex, found: char },
 }
 
 impl LexerError {
@@ -39,6 +91,12 @@ impl LexerError {
             LexerError::UnexpectedCharacter { start, found } => {
                 ByteSpan::from_offset(start, ByteOffset::from_char_utf8(found))
             },
+            LexerError::UnterminatedBlockComment { span }
+            | LexerError::UnterminatedStringLiteral { span } => span,
+            LexerError::UnknownEscapeSequence { start, found } => {
+                let end = start + ByteOffset::from_str("\\") + ByteOffset::from_char_utf8(found);
+                ByteSpan::new(start, end)
+            },
         }
     }
 
@@ -49,6 +107,21 @@ impl LexerError {
                 Diagnostic::new_error(format!("unexpected character {:?}", found))
                     .with_label(Label::new_primary(char_span))
             },
+            LexerError::UnterminatedBlockComment { span } => {
+                let start_span = ByteSpan::from_offset(span.start(), ByteOffset::from_str("{-"));
+                Diagnostic::new_error("unterminated block comment")
+                    .with_primary_label(start_span, "comment starts here")
+            },
+            LexerError::UnterminatedStringLiteral { span } => {
+                let start_span = ByteSpan::from_offset(span.start(), ByteOffset::from_str("\""));
+                Diagnostic::new_error("unterminated string literal")
+                    .with_primary_label(start_span, "string literal starts here")
+            },
+            LexerError::UnknownEscapeSequence { found, .. } => {
+                let expected = r#"expected one of `\n`, `\t`, `\\`, or `\"`"#;
+                Diagnostic::new_error(format!("unknown escape sequence `\\{}`", found))
+                    .with_primary_label(self.span(), expected)
+            },
         }
     }
 }
@@ -60,23 +133,45 @@ pub enum Token<S> {
     Ident(S),
     DocComment(S),
     ReplCommand(S),
+    ReplPath(S),
     DecLiteral(S),
+    StrLiteral(S),
 
     // Keywords
-    As,     // as
-    Module, // module
-    Import, // import
-    Type,   // Type
+    As,         // as
+    Case,       // case
+    Else,       // else
+    Fst,        // fst
+    Hiding,     // hiding
+    If,         // if
+    Module,     // module
+    Import,     // import
+    Of,         // of
+    Opaque,     // opaque
+    Rec,        // rec
+    Record,     // record
+    RecordType, // Record
+    Snd,        // snd
+    Then,       // then
+    Type,       // Type
+
+    // Reserved keywords
+    In,    // in
+    Let,   // let
+    Where, // where
 
     // Symbols
     BSlash,    // \
     Colon,     // :
     Comma,     // ,
+    Dot,       // .
     DotDot,    // ..
     Equal,     // =
     LArrow,    // ->
     LFatArrow, // =>
+    Question,  // ?
     Semi,      // ;
+    Star,      // *
 
     // Delimiters
     LParen,   // (
@@ -93,19 +188,39 @@ impl<S: fmt::Display> fmt::Display for Token<S> {
             Token::Ident(ref name) => write!(f, "{}", name),
             Token::DocComment(ref comment) => write!(f, "||| {}", comment),
             Token::ReplCommand(ref command) => write!(f, ":{}", command),
+            Token::ReplPath(ref path) => write!(f, "{}", path),
             Token::DecLiteral(ref value) => write!(f, "{}", value),
+            Token::StrLiteral(ref src) => write!(f, "\"{}\"", src),
             Token::As => write!(f, "as"),
+            Token::Case => write!(f, "case"),
+            Token::Else => write!(f, "else"),
+            Token::Fst => write!(f, "fst"),
+            Token::Hiding => write!(f, "hiding"),
+            Token::If => write!(f, "if"),
             Token::Module => write!(f, "module"),
             Token::Import => write!(f, "import"),
+            Token::Of => write!(f, "of"),
+            Token::Opaque => write!(f, "opaque"),
+            Token::Rec => write!(f, "rec"),
+            Token::Record => write!(f, "record"),
+            Token::RecordType => write!(f, "Record"),
+            Token::Snd => write!(f, "snd"),
+            Token::Then => write!(f, "then"),
             Token::Type => write!(f, "Type"),
+            Token::In => write!(f, "in"),
+            Token::Let => write!(f, "let"),
+            Token::Where => write!(f, "where"),
             Token::BSlash => write!(f, "\\"),
             Token::Colon => write!(f, ":"),
             Token::Comma => write!(f, ","),
+            Token::Dot => write!(f, "."),
             Token::DotDot => write!(f, ".."),
             Token::Equal => write!(f, "="),
             Token::LFatArrow => write!(f, "=>"),
             Token::LArrow => write!(f, "->"),
+            Token::Question => write!(f, "?"),
             Token::Semi => write!(f, ";"),
+            Token::Star => write!(f, "*"),
             Token::LParen => write!(f, "("),
             Token::RParen => write!(f, ")"),
             Token::LBrace => write!(f, "{{"),
@@ -122,19 +237,39 @@ impl<'input> From<Token<&'input str>> for Token<String> {
             Token::Ident(name) => Token::Ident(String::from(name)),
             Token::DocComment(comment) => Token::DocComment(String::from(comment)),
             Token::ReplCommand(command) => Token::ReplCommand(String::from(command)),
+            Token::ReplPath(path) => Token::ReplPath(String::from(path)),
             Token::DecLiteral(value) => Token::DecLiteral(String::from(value)),
+            Token::StrLiteral(src) => Token::StrLiteral(String::from(src)),
             Token::As => Token::As,
+            Token::Case => Token::Case,
+            Token::Else => Token::Else,
+            Token::Fst => Token::Fst,
+            Token::Hiding => Token::Hiding,
+            Token::If => Token::If,
             Token::Module => Token::Module,
             Token::Import => Token::Import,
+            Token::Of => Token::Of,
+            Token::Opaque => Token::Opaque,
+            Token::Rec => Token::Rec,
+            Token::Record => Token::Record,
+            Token::RecordType => Token::RecordType,
+            Token::Snd => Token::Snd,
+            Token::Then => Token::Then,
             Token::Type => Token::Type,
+            Token::In => Token::In,
+            Token::Let => Token::Let,
+            Token::Where => Token::Where,
             Token::BSlash => Token::BSlash,
             Token::Colon => Token::Colon,
             Token::Comma => Token::Comma,
+            Token::Dot => Token::Dot,
             Token::DotDot => Token::DotDot,
             Token::Equal => Token::Equal,
             Token::LFatArrow => Token::LFatArrow,
             Token::LArrow => Token::LArrow,
+            Token::Question => Token::Question,
             Token::Semi => Token::Semi,
+            Token::Star => Token::Star,
             Token::LParen => Token::LParen,
             Token::RParen => Token::RParen,
             Token::LBrace => Token::LBrace,
@@ -151,6 +286,10 @@ pub struct Lexer<'input> {
     filemap: &'input FileMap,
     chars: CharIndices<'input>,
     lookahead: Option<(usize, char)>,
+    comments: Vec<(ByteSpan, String)>,
+    /// Whether the rest of the line should be lexed as a path, because we
+    /// have just seen a REPL command that takes a path as its argument
+    expect_path: bool,
 }
 
 impl<'input> Lexer<'input> {
@@ -162,9 +301,19 @@ impl<'input> Lexer<'input> {
             filemap,
             lookahead: chars.next(),
             chars,
+            comments: Vec::new(),
+            expect_path: false,
         }
     }
 
+    /// Return the line comments that were skipped over while lexing, along
+    /// with their spans. These are not passed on to the parser, but are kept
+    /// so that they can be preserved when formatting. Block comments are
+    /// discarded.
+    pub fn into_comments(self) -> Vec<(ByteSpan, String)> {
+        self.comments
+    }
+
     /// Return the next character in the source string
     fn lookahead(&self) -> Option<(ByteIndex, char)> {
         self.lookahead.map(|(index, ch)| {
@@ -233,10 +382,53 @@ impl<'input> Lexer<'input> {
         if command.is_empty() {
             (start, Token::Colon, end)
         } else {
+            self.expect_path = match command {
+                "l" | "load" | "save" => true,
+                _ => false,
+            };
             (start, Token::ReplCommand(command), end)
         }
     }
 
+    /// Consume the rest of the line as a path, without any surrounding
+    /// whitespace
+    fn repl_path(&mut self, start: ByteIndex) -> (ByteIndex, Token<&'input str>, ByteIndex) {
+        let (_, path) = self.take_until(start, |ch| ch == '\n');
+        let path = path.trim_right();
+        let end = start + ByteOffset::from_str(path);
+
+        (start, Token::ReplPath(path), end)
+    }
+
+    /// Skip over a block comment, which may contain other nested block
+    /// comments
+    fn block_comment(&mut self, start: ByteIndex) -> Result<(), LexerError> {
+        self.bump(); // Skip the `-` of the opening `{-`
+
+        let mut depth = 1;
+        while let Some((_, ch)) = self.bump() {
+            match (ch, self.lookahead()) {
+                ('{', Some((_, '-'))) => {
+                    self.bump();
+                    depth += 1;
+                },
+                ('-', Some((_, '}'))) => {
+                    self.bump();
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                },
+                (_, _) => {},
+            }
+        }
+
+        let eof = self.filemap.span().end();
+        Err(LexerError::UnterminatedBlockComment {
+            span: ByteSpan::new(start, eof),
+        })
+    }
+
     /// Consume a doc comment
     fn doc_comment(&mut self, start: ByteIndex) -> (ByteIndex, Token<&'input str>, ByteIndex) {
         let (end, mut comment) =
@@ -256,18 +448,68 @@ impl<'input> Lexer<'input> {
 
         let token = match ident {
             "as" => Token::As,
+            "case" => Token::Case,
+            "else" => Token::Else,
+            "fst" => Token::Fst,
+            "hiding" => Token::Hiding,
+            "if" => Token::If,
             "module" => Token::Module,
             "import" => Token::Import,
+            "of" => Token::Of,
+            "opaque" => Token::Opaque,
+            "rec" => Token::Rec,
+            "record" => Token::Record,
+            "Record" => Token::RecordType,
+            "snd" => Token::Snd,
+            "then" => Token::Then,
             "Type" => Token::Type,
+            "in" => Token::In,
+            "let" => Token::Let,
+            "where" => Token::Where,
             ident => Token::Ident(ident),
         };
 
         (start, token, end)
     }
 
-    /// Consume a decimal literal token
+    /// Consume a string literal token, checking its escape sequences. The
+    /// token holds the source between the quotes, with the escape sequences
+    /// left in place.
+    fn str_literal(
+        &mut self,
+        start: ByteIndex,
+    ) -> Result<(ByteIndex, Token<&'input str>, ByteIndex), LexerError> {
+        let content_start = start + ByteOffset::from_str("\"");
+        while let Some((index, ch)) = self.bump() {
+            match ch {
+                '"' => {
+                    let end = index + ByteOffset::from_str("\"");
+                    return Ok((start, Token::StrLiteral(self.slice(content_start, index)), end));
+                },
+                '\\' => match self.bump() {
+                    Some((_, ch)) if escaped_char(ch).is_some() => {},
+                    Some((_, found)) => {
+                        return Err(LexerError::UnknownEscapeSequence {
+                            start: index,
+                            found,
+                        });
+                    },
+                    None => break,
+                },
+                _ => {},
+            }
+        }
+
+        let eof = self.filemap.span().end();
+        Err(LexerError::UnterminatedStringLiteral {
+            span: ByteSpan::new(start, eof),
+        })
+    }
+
+    /// Consume a decimal literal token, including any underscores that are
+    /// used to separate its digits. These are checked by the parser.
     fn dec_literal(&mut self, start: ByteIndex) -> (ByteIndex, Token<&'input str>, ByteIndex) {
-        let (end, src) = self.take_while(start, is_dec_digit);
+        let (end, src) = self.take_while(start, is_dec_digit_or_separator);
 
         (start, Token::DecLiteral(src), end)
     }
@@ -280,6 +522,17 @@ impl<'input> Iterator for Lexer<'input> {
         while let Some((start, ch)) = self.bump() {
             let end = start + ByteOffset::from_char_utf8(ch);
 
+            if self.expect_path {
+                match ch {
+                    '\n' => self.expect_path = false,
+                    ch if ch.is_whitespace() => {},
+                    _ => {
+                        self.expect_path = false;
+                        return Some(Ok(self.repl_path(start)));
+                    },
+                }
+            }
+
             return Some(match ch {
                 ch if is_symbol(ch) => {
                     let (end, symbol) = self.take_while(start, is_symbol);
@@ -287,23 +540,40 @@ impl<'input> Iterator for Lexer<'input> {
                     match symbol {
                         ":" => Ok(self.repl_command(start)),
                         "," => Ok((start, Token::Comma, end)),
+                        "." => Ok((start, Token::Dot, end)),
                         ".." => Ok((start, Token::DotDot, end)),
                         "=" => Ok((start, Token::Equal, end)),
                         "->" => Ok((start, Token::LArrow, end)),
                         "=>" => Ok((start, Token::LFatArrow, end)),
                         ";" => Ok((start, Token::Semi, end)),
+                        "*" => Ok((start, Token::Star, end)),
                         symbol if symbol.starts_with("|||") => Ok(self.doc_comment(start)),
                         symbol if symbol.starts_with("--") => {
-                            self.take_until(start, |ch| ch == '\n');
+                            let (end, comment) = self.take_until(start, |ch| ch == '\n');
+                            let comment = comment["--".len()..].trim_right();
+                            let comment = match comment.starts_with(' ') {
+                                true => &comment[1..],
+                                false => comment,
+                            };
+                            self.comments
+                                .push((ByteSpan::new(start, end), String::from(comment)));
                             continue;
                         },
                         _ => Err(LexerError::UnexpectedCharacter { start, found: ch }),
                     }
                 },
                 '\\' => Ok((start, Token::BSlash, end)),
+                '"' => self.str_literal(start),
+                '?' => Ok((start, Token::Question, end)),
                 '(' => Ok((start, Token::LParen, end)),
                 ')' => Ok((start, Token::RParen, end)),
-                '{' => Ok((start, Token::LBrace, end)),
+                '{' => match self.lookahead() {
+                    Some((_, '-')) => match self.block_comment(start) {
+                        Ok(()) => continue,
+                        Err(err) => Err(err),
+                    },
+                    _ => Ok((start, Token::LBrace, end)),
+                },
                 '}' => Ok((start, Token::RBrace, end)),
                 '[' => Ok((start, Token::LBracket, end)),
                 ']' => Ok((start, Token::RBracket, end)),
@@ -360,29 +630,166 @@ mod tests {
         };
     }
 
+    #[test]
+    fn block_comment() {
+        test! {
+            "  x {- a {- nested -} comment -} y  ",
+            "  ~                                 " => Token::Ident("x"),
+            "                                 ~  " => Token::Ident("y"),
+        };
+    }
+
+    #[test]
+    fn block_comment_unterminated() {
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), "x {- {- -}".into());
+
+        let tokens = Lexer::new(&filemap).collect::<Vec<_>>();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Ok((ByteIndex(1), Token::Ident("x"), ByteIndex(2))),
+                Err(LexerError::UnterminatedBlockComment {
+                    span: ByteSpan::new(ByteIndex(3), ByteIndex(11)),
+                }),
+            ],
+        );
+    }
+
+    #[test]
+    fn str_literal() {
+        test! {
+            r#"  "a\n\"b" ""  "#,
+            r#"  ~~~~~~~~     "# => Token::StrLiteral(r#"a\n\"b"#),
+            r#"           ~~  "# => Token::StrLiteral(""),
+        };
+    }
+
+    #[test]
+    fn str_literal_unterminated() {
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), r#"x "abc"#.into());
+
+        let tokens = Lexer::new(&filemap).collect::<Vec<_>>();
+
+        assert_eq!(
+            tokens,
+            vec![
+                Ok((ByteIndex(1), Token::Ident("x"), ByteIndex(2))),
+                Err(LexerError::UnterminatedStringLiteral {
+                    span: ByteSpan::new(ByteIndex(3), ByteIndex(7)),
+                }),
+            ],
+        );
+    }
+
+    #[test]
+    fn escape_round_trip() {
+        let src = r#"a\n\t\\\"b"#;
+
+        assert_eq!(unescape_string(src), "a\n\t\\\"b");
+        assert_eq!(escape_string(&unescape_string(src)), src);
+    }
+
+    #[test]
+    fn line_comment() {
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), "x -- hello\ny".into());
+
+        let mut lexer = Lexer::new(&filemap);
+        let tokens = lexer.by_ref().count();
+
+        assert_eq!(tokens, 2);
+        assert_eq!(
+            lexer.into_comments(),
+            vec![(
+                ByteSpan::new(ByteIndex(3), ByteIndex(11)),
+                String::from("hello"),
+            )],
+        );
+    }
+
+    #[test]
+    fn dec_literal_separators() {
+        test! {
+            "  1_000 _1 1__0  ",
+            "  ~~~~~          " => Token::DecLiteral("1_000"),
+            "        ~~       " => Token::Ident("_1"),
+            "           ~~~~  " => Token::DecLiteral("1__0"),
+        };
+    }
+
     #[test]
     fn keywords() {
         test! {
-            "  as module import Type  ",
-            "  ~~                     " => Token::As,
-            "     ~~~~~~              " => Token::Module,
-            "            ~~~~~~       " => Token::Import,
-            "                   ~~~~  " => Token::Type,
+            "  as fst hiding module import opaque rec record Record snd Type",
+            "  ~~                                                           " => Token::As,
+            "     ~~~                                                       " => Token::Fst,
+            "         ~~~~~~                                                " => Token::Hiding,
+            "                ~~~~~~                                         " => Token::Module,
+            "                       ~~~~~~                                  " => Token::Import,
+            "                              ~~~~~~                           " => Token::Opaque,
+            "                                     ~~~                       " => Token::Rec,
+            "                                         ~~~~~~                " => Token::Record,
+            "                                                ~~~~~~         " => Token::RecordType,
+            "                                                       ~~~     " => Token::Snd,
+            "                                                           ~~~~" => Token::Type,
+        };
+    }
+
+    #[test]
+    fn conditional_keywords() {
+        test! {
+            "  if then else  ",
+            "  ~~            " => Token::If,
+            "     ~~~~       " => Token::Then,
+            "          ~~~~  " => Token::Else,
+        };
+    }
+
+    #[test]
+    fn case_keywords() {
+        test! {
+            "  case of  ",
+            "  ~~~~     " => Token::Case,
+            "       ~~  " => Token::Of,
+        };
+    }
+
+    #[test]
+    fn reserved_keywords() {
+        test! {
+            "  in let where  ",
+            "  ~~            " => Token::In,
+            "     ~~~        " => Token::Let,
+            "         ~~~~~  " => Token::Where,
         };
     }
 
     #[test]
     fn symbols() {
         test! {
-            r" \ : , .. = -> => ; ",
-            r" ~                  " => Token::BSlash,
-            r"   ~                " => Token::Colon,
-            r"     ~              " => Token::Comma,
-            r"       ~~           " => Token::DotDot,
-            r"          ~         " => Token::Equal,
-            r"            ~~      " => Token::LArrow,
-            r"               ~~   " => Token::LFatArrow,
-            r"                  ~ " => Token::Semi,
+            r" \ : , . .. = -> => ; * ",
+            r" ~                      " => Token::BSlash,
+            r"   ~                    " => Token::Colon,
+            r"     ~                  " => Token::Comma,
+            r"       ~                " => Token::Dot,
+            r"         ~~             " => Token::DotDot,
+            r"            ~           " => Token::Equal,
+            r"              ~~        " => Token::LArrow,
+            r"                 ~~     " => Token::LFatArrow,
+            r"                    ~   " => Token::Semi,
+            r"                      ~ " => Token::Star,
+        }
+    }
+
+    #[test]
+    fn question() {
+        test! {
+            r" ?: ",
+            r" ~  " => Token::Question,
+            r"  ~ " => Token::Colon,
         }
     }
 
@@ -398,4 +805,13 @@ mod tests {
             "           ~ " => Token::RBracket,
         }
     }
+
+    #[test]
+    fn repl_path() {
+        test! {
+            ":load examples/id.pi  ",
+            "~~~~~                 " => Token::ReplCommand("load"),
+            "      ~~~~~~~~~~~~~~  " => Token::ReplPath("examples/id.pi"),
+        }
+    }
 }
@@ -1,17 +1,16 @@
 //! Parser utilities
 
 use lalrpop_util::ParseError as LalrpopError;
-use codespan::FileMap;
+use codespan::{CodeMap, FileMap, FileName};
 
 use syntax::concrete;
-use syntax::parse::lexer::Lexer;
 
 mod grammar;
 mod lexer;
 mod errors;
 
-pub use self::lexer::{LexerError, Token};
-pub use self::errors::{ExpectedTokens, ParseError};
+pub use self::lexer::{escape_string, unescape_string, Lexer, LexerError, Token};
+pub use self::errors::{ExpectedTokens, FromStrError, ParseError};
 
 // TODO: DRY up these wrappers...
 
@@ -29,9 +28,23 @@ pub fn repl_command<'input>(filemap: &'input FileMap) -> (concrete::ReplCommand,
 
 pub fn module<'input>(filemap: &'input FileMap) -> (concrete::Module, Vec<ParseError>) {
     let mut errors = Vec::new();
-    let lexer = Lexer::new(filemap).map(|x| x.map_err(ParseError::from));
-    match grammar::parse_Module(&mut errors, filemap, lexer) {
-        Ok(value) => (value, errors),
+    let mut lexer = Lexer::new(filemap);
+    let result = {
+        let tokens = lexer.by_ref().map(|x| x.map_err(ParseError::from));
+        grammar::parse_Module(&mut errors, filemap, tokens)
+    };
+    match result {
+        Ok(mut value) => {
+            // Keep hold of the comments, so that we can preserve them when
+            // pretty printing the module
+            if let concrete::Module::Valid {
+                ref mut comments, ..
+            } = value
+            {
+                *comments = lexer.into_comments();
+            }
+            (value, errors)
+        },
         Err(err) => {
             errors.push(errors::from_lalrpop(filemap, err));
             (concrete::Module::Error(filemap.span()), errors)
@@ -51,10 +64,21 @@ pub fn term<'input>(filemap: &'input FileMap) -> (concrete::Term, Vec<ParseError
     }
 }
 
+/// Parse a term from a string, using a throwaway file map
+///
+/// The spans in the term and the errors will only make sense relative to the
+/// start of `src`, so prefer `term` if you need to report the errors.
+pub fn term_from_str(src: &str) -> (concrete::Term, Vec<ParseError>) {
+    let mut codemap = CodeMap::new();
+    let filemap = codemap.add_filemap(FileName::virtual_("unnamed"), src.into());
+
+    term(&filemap)
+}
+
 #[cfg(test)]
 mod tests {
     use codespan::{CodeMap, FileName};
-    use codespan::{ByteIndex, ByteSpan};
+    use codespan::{ByteIndex, ByteOffset, ByteSpan};
 
     use super::*;
 
@@ -79,6 +103,619 @@ mod tests {
         );
     }
 
+    #[test]
+    fn lam_unexpected_eof() {
+        let src = r"\x =>";
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), src.into());
+
+        let (_, errors) = term(&filemap);
+
+        assert_eq!(errors.len(), 1);
+        match errors[0] {
+            ParseError::UnexpectedEof { span, ref expected } => {
+                let end = filemap.span().end();
+                assert_eq!(span, ByteSpan::new(end, end));
+                // The body of the lambda could begin with any term
+                assert!(expected.0.contains(&String::from(r#""identifier""#)));
+                assert!(expected.0.contains(&String::from(r#""Type""#)));
+                assert!(expected.0.contains(&String::from(r#""(""#)));
+            },
+            ref err => panic!("expected an unexpected EOF error, found {:?}", err),
+        }
+    }
+
+    #[test]
+    fn lam_unexpected_token() {
+        let (_, errors) = term_from_str(r"\x : => x");
+
+        assert_eq!(errors.len(), 1);
+        match errors[0] {
+            ParseError::UnexpectedToken {
+                ref token,
+                ref expected,
+                ..
+            } => {
+                assert_eq!(*token, Token::LFatArrow);
+                // The annotation could begin with any term
+                assert!(expected.0.contains(&String::from(r#""Type""#)));
+            },
+            ref err => panic!("expected an unexpected token error, found {:?}", err),
+        }
+        assert!(errors[0].to_string().starts_with("Unexpected token `=>` found"));
+    }
+
+    #[test]
+    fn expected_tokens_display() {
+        let expected = ExpectedTokens(vec![String::from(r#"":""#), String::from(r#""->""#)]);
+
+        assert_eq!(expected.to_string(), "`:`, or `->`");
+    }
+
+    #[test]
+    fn repl_command_unknown() {
+        let src = ":xyz";
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), src.into());
+
+        let (_, errors) = repl_command(&filemap);
+
+        assert_eq!(
+            errors,
+            vec![
+                ParseError::UnknownReplCommand {
+                    span: ByteSpan::new(ByteIndex(1), ByteIndex(5)),
+                    command: String::from("xyz"),
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn repl_command_missing_argument() {
+        let src = ":type";
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), src.into());
+
+        let (_, errors) = repl_command(&filemap);
+
+        assert_eq!(
+            errors,
+            vec![
+                ParseError::MissingReplCommandArgument {
+                    span: ByteSpan::new(ByteIndex(1), ByteIndex(6)),
+                    command: String::from("type"),
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn repl_command_unknown_option() {
+        let src = ":set width 3";
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), src.into());
+
+        let (_, errors) = repl_command(&filemap);
+
+        assert_eq!(
+            errors,
+            vec![
+                ParseError::UnknownReplOption {
+                    span: ByteSpan::new(ByteIndex(6), ByteIndex(11)),
+                    option: String::from("width"),
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn repl_command_set_unfold() {
+        use syntax::core::UnfoldPolicy;
+
+        let src = ":set unfold prelude";
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), src.into());
+
+        let (command, errors) = repl_command(&filemap);
+
+        assert!(errors.is_empty());
+        match command {
+            concrete::ReplCommand::SetUnfold(policy) => assert_eq!(policy, UnfoldPolicy::Prelude),
+            command => panic!("expected `:set unfold`, found {:?}", command),
+        }
+    }
+
+    #[test]
+    fn repl_command_set_unfold_invalid() {
+        let src = ":set unfold some";
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), src.into());
+
+        let (_, errors) = repl_command(&filemap);
+
+        assert_eq!(
+            errors,
+            vec![
+                ParseError::InvalidReplOptionValue {
+                    span: ByteSpan::new(ByteIndex(13), ByteIndex(17)),
+                    option: String::from("unfold"),
+                    value: String::from("some"),
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn repl_command_set_depth() {
+        let src = ":set depth 3";
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), src.into());
+
+        let (command, errors) = repl_command(&filemap);
+
+        assert!(errors.is_empty());
+        match command {
+            concrete::ReplCommand::SetDepth(depth) => assert_eq!(depth, 3),
+            command => panic!("expected `:set depth`, found {:?}", command),
+        }
+    }
+
+    #[test]
+    fn repl_command_set_timing() {
+        let src = ":set timing on";
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), src.into());
+
+        let (command, errors) = repl_command(&filemap);
+
+        assert!(errors.is_empty());
+        match command {
+            concrete::ReplCommand::SetTiming(timing) => assert!(timing),
+            command => panic!("expected `:set timing`, found {:?}", command),
+        }
+    }
+
+    #[test]
+    fn repl_command_save() {
+        let src = ":save session.pi";
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), src.into());
+
+        let (command, errors) = repl_command(&filemap);
+
+        assert!(errors.is_empty());
+        match command {
+            concrete::ReplCommand::Save(path) => assert_eq!(path, "session.pi"),
+            command => panic!("expected `:save`, found {:?}", command),
+        }
+    }
+
+    #[test]
+    fn repl_command_bench_iterations() {
+        let src = ":bench 10 add 1 2";
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), src.into());
+
+        let (command, errors) = repl_command(&filemap);
+
+        assert!(errors.is_empty());
+        match command {
+            concrete::ReplCommand::Bench(iterations, term) => {
+                assert_eq!(iterations, Some(10));
+                assert_eq!(term.to_string(), "add 1 2");
+            },
+            command => panic!("expected `:bench`, found {:?}", command),
+        }
+    }
+
+    #[test]
+    fn from_str_term() {
+        let term = r"\x : Type => x".parse::<concrete::Term>().unwrap();
+
+        assert_eq!(term.to_string(), r"\(x : Type) => x");
+    }
+
+    #[test]
+    fn from_str_term_let() {
+        let src = "let x = Type; y : Type 1 = x in y";
+        let term = src.parse::<concrete::Term>().unwrap();
+
+        match term {
+            concrete::Term::Let(_, ref bindings, _) => assert_eq!(bindings.len(), 2),
+            ref term => panic!("expected a let binding, found {:?}", term),
+        }
+        assert_eq!(term.to_string(), src);
+    }
+
+    #[test]
+    fn from_str_term_sigma() {
+        let src = "(a : Type) * a * fst (a, a)";
+        let term = src.parse::<concrete::Term>().unwrap();
+
+        match term {
+            concrete::Term::Sigma(_, _, ref body) => match **body {
+                concrete::Term::Product(_, _) => {},
+                ref body => panic!("expected a product type, found {:?}", body),
+            },
+            ref term => panic!("expected a sigma type, found {:?}", term),
+        }
+        assert_eq!(term.to_string(), src);
+    }
+
+    #[test]
+    fn from_str_term_record() {
+        let src = "record { a = Type; b = Record {} } : Record { a : Type 1; b : Type }";
+        let term = src.parse::<concrete::Term>().unwrap();
+
+        assert_eq!(term.to_string(), src);
+    }
+
+    #[test]
+    fn from_str_term_proj() {
+        let src = "(record { a = Type }).a";
+        let term = src.parse::<concrete::Term>().unwrap();
+
+        match term {
+            concrete::Term::Proj(_, _, ref label) => assert_eq!(label, "a"),
+            ref term => panic!("expected a field projection, found {:?}", term),
+        }
+        assert_eq!(term.to_string(), src);
+    }
+
+    #[test]
+    fn from_str_term_int() {
+        let src = "add 2 (mul 3 4) : Int";
+        let term = src.parse::<concrete::Term>().unwrap();
+
+        assert_eq!(term.to_string(), src);
+    }
+
+    #[test]
+    fn from_str_term_universe_level() {
+        let term = "Type 2".parse::<concrete::Term>().unwrap();
+
+        match term {
+            concrete::Term::Universe(_, level) => assert_eq!(level, Some(2)),
+            term => panic!("expected a universe, found {:?}", term),
+        }
+    }
+
+    #[test]
+    fn from_str_term_universe_level_separators() {
+        let term = "Type 1_000".parse::<concrete::Term>().unwrap();
+
+        match term {
+            concrete::Term::Universe(_, level) => assert_eq!(level, Some(1000)),
+            term => panic!("expected a universe, found {:?}", term),
+        }
+    }
+
+    #[test]
+    fn from_str_term_string_literal() {
+        let src = r#""tab\there\n \"quoted\" \\""#;
+        let term = src.parse::<concrete::Term>().unwrap();
+
+        match term {
+            concrete::Term::StringLit(_, ref value) => {
+                assert_eq!(value, "tab\there\n \"quoted\" \\");
+            },
+            ref term => panic!("expected a string literal, found {:?}", term),
+        }
+        assert_eq!(term.to_string(), src);
+    }
+
+    #[test]
+    fn from_str_term_if() {
+        let src = r"if b then \x => x else if c then y else z";
+        let term = src.parse::<concrete::Term>().unwrap();
+
+        match term {
+            concrete::Term::If(_, _, _, ref if_false) => match **if_false {
+                concrete::Term::If(_, _, _, _) => {},
+                ref term => panic!("expected a nested conditional, found {:?}", term),
+            },
+            ref term => panic!("expected a conditional, found {:?}", term),
+        }
+        assert_eq!(term.to_string(), src);
+    }
+
+    #[test]
+    fn from_str_term_error() {
+        let err = "Type\n  )".parse::<concrete::Term>().unwrap_err();
+
+        assert_eq!(err.errors.len(), 1);
+        assert_eq!(err.errors[0].0, (2, 3));
+        assert!(err.to_string().starts_with("2:3: "));
+    }
+
+    #[test]
+    fn term_from_str_lam() {
+        let (term, errors) = term_from_str(r"\x => x");
+
+        assert!(errors.is_empty());
+        match term {
+            concrete::Term::Lam(_, ref params, ref body) => {
+                assert_eq!(params.len(), 1);
+                assert_eq!(body.to_string(), "x");
+            },
+            ref term => panic!("expected a lambda, found {:?}", term),
+        }
+    }
+
+    #[test]
+    fn term_from_str_error() {
+        let (_, errors) = term_from_str(r"\x =>");
+
+        match errors.as_slice() {
+            [ParseError::UnexpectedEof { .. }] => {},
+            errors => panic!("expected an unexpected EOF, found {:?}", errors),
+        }
+    }
+
+    #[test]
+    fn from_str_module() {
+        let module = "module foo;\n\nbar = Type;".parse::<concrete::Module>().unwrap();
+
+        match module {
+            concrete::Module::Valid { ref name, .. } => assert_eq!(name.1, "foo"),
+            concrete::Module::Error(_) => panic!("expected a valid module"),
+        }
+    }
+
+    #[test]
+    fn from_str_term_case() {
+        let src = "case (x, b) of { (_, true) => x; (y, false) => y }";
+        let term = src.parse::<concrete::Term>().unwrap();
+
+        match term {
+            concrete::Term::Case(_, _, ref clauses) => match clauses[0].0 {
+                concrete::Pattern::Pair(_, ref fst, _) => match **fst {
+                    concrete::Pattern::Wildcard(_) => {},
+                    ref pattern => panic!("expected a wildcard pattern, found {:?}", pattern),
+                },
+                ref pattern => panic!("expected a pair pattern, found {:?}", pattern),
+            },
+            ref term => panic!("expected a case expression, found {:?}", term),
+        }
+        assert_eq!(term.to_string(), src);
+    }
+
+    #[test]
+    fn reserved_keyword_binder() {
+        for &keyword in &["case", "in", "let", "of", "where"] {
+            let src = format!(r"\{} : Type => Type", keyword);
+            let mut codemap = CodeMap::new();
+            let filemap = codemap.add_filemap(FileName::virtual_("test"), src);
+
+            let (_, errors) = term(&filemap);
+            let span = ByteSpan::from_offset(ByteIndex(2), ByteOffset::from_str(keyword));
+
+            assert_eq!(
+                errors,
+                vec![
+                    ParseError::ReservedKeyword {
+                        span,
+                        keyword: String::from(keyword),
+                    },
+                ],
+            );
+        }
+    }
+
+    /// Check that a term that was built without any parentheses is printed
+    /// with only the parentheses that it needs, and that the printed term
+    /// parses back to the same term
+    fn assert_round_trip(term: concrete::Term, expected: &str) {
+        use syntax::translation::ToCore;
+
+        let src = term.to_string();
+        assert_eq!(src, expected);
+
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), src);
+        let (parsed, errors) = super::term(&filemap);
+        assert!(errors.is_empty());
+
+        assert_eq!(parsed.to_core(), term.to_core());
+    }
+
+    fn var(name: &str) -> Box<concrete::Term> {
+        Box::new(concrete::Term::Var(ByteSpan::none(), String::from(name)))
+    }
+
+    #[test]
+    fn print_nested_app() {
+        use syntax::concrete::Term::App;
+
+        assert_round_trip(App(Box::new(App(var("f"), var("x"))), var("y")), "f x y");
+        assert_round_trip(App(var("f"), Box::new(App(var("g"), var("x")))), "f (g x)");
+    }
+
+    #[test]
+    fn print_chained_arrows() {
+        use syntax::concrete::Term::Arrow;
+
+        assert_round_trip(
+            Arrow(var("a"), Box::new(Arrow(var("b"), var("c")))),
+            "a -> b -> c",
+        );
+        assert_round_trip(
+            Arrow(Box::new(Arrow(var("a"), var("b"))), var("c")),
+            "(a -> b) -> c",
+        );
+    }
+
+    #[test]
+    fn print_lam_in_app_arg() {
+        use syntax::concrete::Term::{Ann, App, Lam};
+        use syntax::core::Plicity;
+
+        let lam = Lam(
+            ByteIndex::none(),
+            vec![(Plicity::Explicit, vec![(ByteSpan::none(), String::from("x"))], None)],
+            var("x"),
+        );
+        let app = App(var("f"), Box::new(lam));
+
+        assert_round_trip(app.clone(), r"f (\x => x)");
+        assert_round_trip(Ann(Box::new(app), var("a")), r"f (\x => x) : a");
+    }
+
+    #[test]
+    fn where_block_span() {
+        let src = "module test;\nfoo = bar where { bar = Type; };\n";
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), src.into());
+
+        let (module, errors) = module(&filemap);
+        assert!(errors.is_empty());
+
+        match module {
+            concrete::Module::Valid {
+                ref declarations, ..
+            } => {
+                let definition = "foo = bar where { bar = Type; }";
+                let span = ByteSpan::from_offset(ByteIndex(14), ByteOffset::from_str(definition));
+                assert_eq!(declarations[0].span(), span);
+            },
+            concrete::Module::Error(_) => panic!("expected a valid module"),
+        }
+    }
+
+    #[test]
+    fn where_block_invalid_declarations() {
+        let src = "module test;\nfoo = bar where { import baz; bar : Type; bar : Type; bar = Type; \
+                   qux : Type; };\n";
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), src.into());
+
+        let (module, errors) = module(&filemap);
+        match errors.as_slice() {
+            [
+                ParseError::UnexpectedWhereDeclaration { ref keyword, .. },
+                ParseError::DuplicateWhereClaim { name: ref duplicate, .. },
+                ParseError::WhereClaimWithoutDefinition { name: ref unmatched, .. },
+            ] => {
+                assert_eq!(keyword, "import");
+                assert_eq!(duplicate, "bar");
+                assert_eq!(unmatched, "qux");
+            },
+            errors => panic!("unexpected errors: {:?}", errors),
+        }
+
+        // The offending declarations are left out of the block
+        match module {
+            concrete::Module::Valid {
+                ref declarations, ..
+            } => match declarations[0] {
+                concrete::Declaration::Definition {
+                    where_block: Some((_, ref where_declarations)),
+                    ..
+                } => assert_eq!(where_declarations.len(), 2),
+                _ => panic!("expected a definition with a where block"),
+            },
+            concrete::Module::Error(_) => panic!("expected a valid module"),
+        }
+    }
+
+    #[test]
+    fn where_block_recursive_definition() {
+        let src = "module test;\nfoo = bar where { rec bar : Type = bar; };\n";
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), src.into());
+
+        let (_, errors) = module(&filemap);
+        match errors.as_slice() {
+            [ParseError::UnexpectedWhereDeclaration { ref keyword, .. }] => {
+                assert_eq!(keyword, "rec");
+            },
+            errors => panic!("unexpected errors: {:?}", errors),
+        }
+    }
+
+    #[test]
+    fn recursive_definition() {
+        let src = "module test;\nrec loop : Type = loop;\n";
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), src.into());
+
+        let (module, errors) = module(&filemap);
+        assert!(errors.is_empty());
+
+        match module {
+            concrete::Module::Valid {
+                ref declarations, ..
+            } => match declarations[0] {
+                concrete::Declaration::Recursive {
+                    ref name, ref ann, ..
+                } => {
+                    assert_eq!(name.1, "loop");
+                    let span = ByteSpan::new(ByteIndex(25), ByteIndex(29));
+                    assert_eq!(*ann, concrete::Term::Universe(span, None));
+                },
+                ref declaration => panic!("expected a recursive definition, found {}", declaration),
+            },
+            concrete::Module::Error(_) => panic!("expected a valid module"),
+        }
+        assert_eq!(module.to_string(), "module test;\n\nrec loop : Type = loop;");
+    }
+
+    #[test]
+    fn module_comments() {
+        let src = "module test;\n\n-- the first\nid : Type;\n\n-- the second\n-- more\nT = Type;\n-- the end\n";
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), src.into());
+
+        let (module, errors) = module(&filemap);
+        assert!(errors.is_empty());
+
+        assert_eq!(
+            module.to_string(),
+            "module test;\n\n-- the first\nid : Type;\n\n-- the second\n-- more\nT = Type;\n\n-- the end",
+        );
+    }
+
+    #[test]
+    fn module_interleaved_comments() {
+        let comments = [
+            "-- a line comment",
+            "{- a block comment -}",
+            "{- an {- outer {- inner -} -} comment -}",
+            "{-\n-- commented out:\nid : Type -> Type;\n-}",
+        ];
+        let src = format!(
+            "module test; {}\n\nid {} : (a : Type) -> a -> a;\n{}\nid a {} x = x;\n",
+            comments[0], comments[1], comments[2], comments[3],
+        );
+
+        // Blank out the comments, so that the tokens that follow them remain
+        // at the same positions
+        let blank_src = comments.iter().fold(src.clone(), |src, comment| {
+            let blank = comment
+                .chars()
+                .map(|ch| if ch == '\n' { '\n' } else { ' ' })
+                .collect::<String>();
+            src.replace(comment, &blank)
+        });
+
+        let parse_module = |src: String| {
+            let mut codemap = CodeMap::new();
+            let filemap = codemap.add_filemap(FileName::virtual_("test"), src);
+            let (module, errors) = module(&filemap);
+            assert!(errors.is_empty());
+
+            match module {
+                concrete::Module::Valid {
+                    name, declarations, ..
+                } => (name, declarations),
+                concrete::Module::Error(_) => panic!("expected a valid module"),
+            }
+        };
+
+        assert_eq!(parse_module(src), parse_module(blank_src));
+    }
+
     #[test]
     fn integer_overflow() {
         let src = "Type 111111111111111111111111111111";
@@ -100,4 +737,22 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn malformed_numeric_literal() {
+        for literal in &["1__0", "1_"] {
+            let src = format!("Type {}", literal);
+            let mut codemap = CodeMap::new();
+            let filemap = codemap.add_filemap(FileName::virtual_("test"), src.clone());
+
+            let (_, errors) = term(&filemap);
+
+            match errors.as_slice() {
+                [ParseError::MalformedNumericLiteral { ref value, .. }] => {
+                    assert_eq!(value, literal);
+                },
+                errors => panic!("expected a malformed literal, found {:?}", errors),
+            }
+        }
+    }
 }
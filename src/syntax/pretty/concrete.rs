@@ -1,10 +1,14 @@
 //! Pretty printing for the concrete syntax
 
+use codespan::ByteSpan;
 use pretty::Doc;
 
-use syntax::concrete::{Declaration, Exposing, LamParams, Module, PiParams, Term};
+use syntax::concrete::{CaseClauses, Declaration, Exposing, LamParams, LetBindings, Module};
+use syntax::concrete::{Pattern, PiParams, Term};
+use syntax::core::Plicity;
+use syntax::parse::escape_string;
 
-use super::{Options, StaticDoc, ToDoc};
+use super::{parens_if, pretty_docs, Options, Prec, StaticDoc, ToDoc};
 
 impl ToDoc for Module {
     fn to_doc(&self, options: Options) -> StaticDoc {
@@ -12,19 +16,48 @@ impl ToDoc for Module {
             Module::Valid {
                 ref name,
                 ref declarations,
-            } => Doc::group(
-                Doc::text("module")
-                    .append(Doc::space())
-                    .append(Doc::as_string(&name.1))
-                    .append(Doc::text(";")),
-            ).append(Doc::newline())
-                .append(Doc::newline())
-                .append(Doc::intersperse(
-                    declarations
-                        .iter()
-                        .map(|declaration| declaration.to_doc(options)),
-                    Doc::newline().append(Doc::newline()),
-                )),
+                ref comments,
+            } => {
+                let mut comments = comments.iter().peekable();
+                let mut declaration_docs = Vec::with_capacity(declarations.len() + 1);
+
+                // Attach each comment to the declaration that follows it
+                for declaration in declarations {
+                    let start = declaration.span().start();
+                    let mut leading_comments = Doc::nil();
+                    while let Some(&&(span, ref comment)) = comments.peek() {
+                        if span.start() > start {
+                            break;
+                        }
+                        leading_comments = leading_comments
+                            .append(pretty_comment(comment))
+                            .append(Doc::newline());
+                        comments.next();
+                    }
+
+                    declaration_docs.push(leading_comments.append(declaration.to_doc(options)));
+                }
+
+                // Any remaining comments trail after the last declaration
+                let trailing_comments = comments
+                    .map(|&(_, ref comment)| pretty_comment(comment))
+                    .collect::<Vec<_>>();
+                if !trailing_comments.is_empty() {
+                    declaration_docs.push(Doc::intersperse(trailing_comments, Doc::newline()));
+                }
+
+                Doc::group(
+                    Doc::text("module")
+                        .append(Doc::space())
+                        .append(Doc::as_string(&name.1))
+                        .append(Doc::text(";")),
+                ).append(Doc::newline())
+                    .append(Doc::newline())
+                    .append(Doc::intersperse(
+                        declaration_docs,
+                        Doc::newline().append(Doc::newline()),
+                    ))
+            },
             Module::Error(_) => Doc::text("<error>"),
         }
     }
@@ -51,22 +84,61 @@ impl ToDoc for Declaration {
                     Doc::space().append(exposing.to_doc(options))
                 })),
             Declaration::Claim {
-                ref name, ref ann, ..
-            } => Doc::as_string(&name.1)
+                ref docs,
+                ref name,
+                ref ann,
+            } => pretty_docs(docs)
+                .append(Doc::as_string(&name.1))
                 .append(Doc::space())
                 .append(Doc::text(":"))
                 .append(Doc::space())
                 .append(ann.to_doc(options)),
             Declaration::Definition {
+                ref docs,
                 ref name,
                 ref params,
                 ref body,
-                ..
-            } => Doc::as_string(&name.1)
+                ref where_block,
+                opaque,
+            } => pretty_docs(docs)
+                .append(match opaque {
+                    true => Doc::text("opaque").append(Doc::space()),
+                    false => Doc::nil(),
+                })
+                .append(Doc::as_string(&name.1))
                 .append(Doc::space())
                 .append(pretty_lam_params(options, params))
                 .append(Doc::text("="))
                 .append(Doc::space())
+                .append(body.to_doc(options).nest(options.indent_width as usize))
+                .append(match *where_block {
+                    Some((_, ref declarations)) => Doc::space()
+                        .append(Doc::text("where {"))
+                        .append(
+                            Doc::concat(declarations.iter().map(|declaration| {
+                                Doc::newline().append(declaration.to_doc(options))
+                            })).nest(options.indent_width as usize),
+                        )
+                        .append(Doc::newline())
+                        .append(Doc::text("}")),
+                    None => Doc::nil(),
+                }),
+            Declaration::Recursive {
+                ref docs,
+                ref name,
+                ref ann,
+                ref body,
+            } => pretty_docs(docs)
+                .append(Doc::text("rec"))
+                .append(Doc::space())
+                .append(Doc::as_string(&name.1))
+                .append(Doc::space())
+                .append(Doc::text(":"))
+                .append(Doc::space())
+                .append(ann.to_doc(options))
+                .append(Doc::space())
+                .append(Doc::text("="))
+                .append(Doc::space())
                 .append(body.to_doc(options).nest(options.indent_width as usize)),
             Declaration::Error(_) => Doc::text("<error>"),
         }.append(Doc::text(";"))
@@ -77,75 +149,217 @@ impl ToDoc for Exposing {
     fn to_doc(&self, _: Options) -> StaticDoc {
         match *self {
             Exposing::All(_) => Doc::text("(..)"),
-            Exposing::Exact(_, ref imports) => Doc::intersperse(
-                imports.iter().map(|&((_, ref name), ref rename)| {
-                    Doc::as_string(name).append(rename.as_ref().map_or(
-                        Doc::nil(),
-                        |&(_, ref rename)| {
-                            Doc::space()
-                                .append(Doc::text("as"))
-                                .append(Doc::space())
-                                .append(Doc::as_string(rename))
-                        },
-                    ))
-                }),
-                Doc::text(",").append(Doc::space()),
-            ),
+            Exposing::Exact(_, ref imports) => Doc::text("(")
+                .append(Doc::intersperse(
+                    imports.iter().map(|&((_, ref name), ref rename)| {
+                        Doc::as_string(name).append(rename.as_ref().map_or(
+                            Doc::nil(),
+                            |&(_, ref rename)| {
+                                Doc::space()
+                                    .append(Doc::text("as"))
+                                    .append(Doc::space())
+                                    .append(Doc::as_string(rename))
+                            },
+                        ))
+                    }),
+                    Doc::text(",").append(Doc::space()),
+                ))
+                .append(Doc::text(")")),
+            Exposing::Hiding(_, ref names) => Doc::text("hiding")
+                .append(Doc::space())
+                .append(Doc::text("("))
+                .append(Doc::intersperse(
+                    names.iter().map(|&(_, ref name)| Doc::as_string(name)),
+                    Doc::text(",").append(Doc::space()),
+                ))
+                .append(Doc::text(")")),
             Exposing::Error(_) => Doc::text("<error>"),
         }
     }
 }
 
 impl ToDoc for Term {
+    /// Print a term, adding the parentheses needed for it to be parsed back
+    /// into the same syntax tree. Parentheses that were written in the source
+    /// are kept as they are.
     fn to_doc(&self, options: Options) -> StaticDoc {
+        let no_wrap = options.with_prec(Prec::NO_WRAP);
+
         match *self {
             Term::Parens(_, ref term) => Doc::text("(")
-                .append(term.to_doc(options))
+                .append(term.to_doc(no_wrap))
                 .append(Doc::text(")")),
-            Term::Ann(ref term, ref ty) => term.to_doc(options)
-                .append(Doc::space())
-                .append(Doc::text(":"))
-                .append(Doc::space())
-                .append(ty.to_doc(options)),
-            Term::Universe(_, level) => {
-                Doc::text("Type").append(level.map_or(Doc::nil(), |level| {
-                    Doc::space().append(Doc::as_string(level))
-                }))
-            },
+            Term::Ann(ref term, ref ty) => parens_if(
+                Prec::ANN < options.prec,
+                term.to_doc(options.with_prec(Prec::LAM))
+                    .append(Doc::space())
+                    .append(Doc::text(":"))
+                    .append(Doc::space())
+                    .append(ty.to_doc(options.with_prec(Prec::ANN))),
+            ),
+            Term::Universe(_, None) => Doc::text("Type"),
+            Term::Universe(_, Some(level)) => parens_if(
+                Prec::APP < options.prec,
+                Doc::text("Type")
+                    .append(Doc::space())
+                    .append(Doc::as_string(level)),
+            ),
+            Term::IntLit(_, value) => Doc::as_string(value),
+            Term::StringLit(_, ref value) => Doc::text(format!("\"{}\"", escape_string(value))),
+            Term::Hole(_) => Doc::text("?"),
             Term::Var(_, ref name) => Doc::as_string(name),
-            Term::Lam(_, ref params, ref body) => Doc::text("\\")
-                .append(pretty_lam_params(options, params))
-                .append(Doc::space())
-                .append(Doc::text("=>"))
+            Term::Lam(_, ref params, ref body) => parens_if(
+                Prec::LAM < options.prec,
+                Doc::text("\\")
+                    .append(pretty_lam_params(options, params))
+                    .append(Doc::space())
+                    .append(Doc::text("=>"))
+                    .append(Doc::space())
+                    .append(body.to_doc(options.with_prec(Prec::LAM))),
+            ),
+            Term::Pi(_, plicity, ref params, ref body) => parens_if(
+                Prec::PI < options.prec,
+                match plicity {
+                    Plicity::Explicit => Doc::text("(")
+                        .append(pretty_pi_params(options, params))
+                        .append(Doc::text(")")),
+                    Plicity::Implicit => {
+                        pretty_implicit_params(options, &params.0, Some(&*params.1))
+                    },
+                }.append(Doc::space())
+                    .append(Doc::text("->"))
+                    .append(Doc::space())
+                    .append(body.to_doc(options.with_prec(Prec::LAM))),
+            ),
+            // Arrows are right associative, so only the parameter type needs
+            // to be wrapped if it is another arrow
+            Term::Arrow(ref ann, ref body) => parens_if(
+                Prec::PI < options.prec,
+                ann.to_doc(options.with_prec(Prec::SIGMA))
+                    .append(Doc::space())
+                    .append(Doc::text("->"))
+                    .append(Doc::space())
+                    .append(body.to_doc(options.with_prec(Prec::LAM))),
+            ),
+            Term::Sigma(_, ref params, ref body) => parens_if(
+                Prec::SIGMA < options.prec,
+                Doc::text("(")
+                    .append(pretty_pi_params(options, params))
+                    .append(Doc::text(")"))
+                    .append(Doc::space())
+                    .append(Doc::text("*"))
+                    .append(Doc::space())
+                    .append(body.to_doc(options.with_prec(Prec::SIGMA))),
+            ),
+            Term::Product(ref ann, ref body) => parens_if(
+                Prec::SIGMA < options.prec,
+                ann.to_doc(options.with_prec(Prec::APP))
+                    .append(Doc::space())
+                    .append(Doc::text("*"))
+                    .append(Doc::space())
+                    .append(body.to_doc(options.with_prec(Prec::SIGMA))),
+            ),
+            Term::Pair(_, ref fst, ref snd) => Doc::text("(")
+                .append(fst.to_doc(no_wrap))
+                .append(Doc::text(","))
                 .append(Doc::space())
-                .append(body.to_doc(options)),
-            Term::Pi(_, ref params, ref body) => Doc::text("(")
-                .append(pretty_pi_params(options, params))
-                .append(Doc::text(")"))
+                .append(snd.to_doc(no_wrap))
+                .append(Doc::text(")")),
+            Term::Fst(_, ref pair) => parens_if(
+                Prec::APP < options.prec,
+                Doc::text("fst")
+                    .append(Doc::space())
+                    .append(pair.to_doc(options.with_prec(Prec::ARG))),
+            ),
+            Term::Snd(_, ref pair) => parens_if(
+                Prec::APP < options.prec,
+                Doc::text("snd")
+                    .append(Doc::space())
+                    .append(pair.to_doc(options.with_prec(Prec::ARG))),
+            ),
+            Term::RecordType(_, ref fields) => Doc::text("Record")
                 .append(Doc::space())
-                .append(Doc::text("->"))
+                .append(pretty_record_fields(no_wrap, ":", fields)),
+            Term::Record(_, ref fields) => Doc::text("record")
                 .append(Doc::space())
-                .append(body.to_doc(options)),
-            Term::Arrow(ref ann, ref body) => ann.to_doc(options)
+                .append(pretty_record_fields(no_wrap, "=", fields)),
+            Term::Proj(ref record, _, ref label) => record
+                .to_doc(options.with_prec(Prec::ARG))
+                .append(Doc::text("."))
+                .append(Doc::as_string(label)),
+            // Application is left associative, so only the argument needs to
+            // be wrapped if it is another application
+            Term::App(ref fn_term, ref arg) => parens_if(
+                Prec::APP < options.prec,
+                fn_term
+                    .to_doc(options.with_prec(Prec::APP))
+                    .append(Doc::space())
+                    .append(arg.to_doc(options.with_prec(Prec::ARG))),
+            ),
+            Term::Let(_, ref bindings, ref body) => parens_if(
+                Prec::LAM < options.prec,
+                Doc::text("let")
+                    .append(Doc::space())
+                    .append(pretty_let_bindings(options, bindings))
+                    .append(Doc::space())
+                    .append(Doc::text("in"))
+                    .append(Doc::space())
+                    .append(body.to_doc(options.with_prec(Prec::LAM))),
+            ),
+            Term::If(_, ref cond, ref if_true, ref if_false) => parens_if(
+                Prec::LAM < options.prec,
+                Doc::text("if")
+                    .append(Doc::space())
+                    .append(cond.to_doc(no_wrap))
+                    .append(Doc::space())
+                    .append(Doc::text("then"))
+                    .append(Doc::space())
+                    .append(if_true.to_doc(no_wrap))
+                    .append(Doc::space())
+                    .append(Doc::text("else"))
+                    .append(Doc::space())
+                    .append(if_false.to_doc(options.with_prec(Prec::LAM))),
+            ),
+            Term::Case(_, ref scrutinee, ref clauses) => Doc::text("case")
                 .append(Doc::space())
-                .append(Doc::text("->"))
+                .append(scrutinee.to_doc(no_wrap))
                 .append(Doc::space())
-                .append(body.to_doc(options)),
-            Term::App(ref fn_term, ref arg) => fn_term
-                .to_doc(options)
+                .append(Doc::text("of"))
                 .append(Doc::space())
-                .append(arg.to_doc(options)),
+                .append(pretty_case_clauses(no_wrap, clauses)),
             Term::Error(_) => Doc::text("<error>"),
         }
     }
 }
 
+impl ToDoc for Pattern {
+    fn to_doc(&self, options: Options) -> StaticDoc {
+        match *self {
+            Pattern::Wildcard(_) => Doc::text("_"),
+            Pattern::Var(_, ref name) => Doc::as_string(name),
+            Pattern::Pair(_, ref fst, ref snd) => Doc::text("(")
+                .append(fst.to_doc(options))
+                .append(Doc::text(","))
+                .append(Doc::space())
+                .append(snd.to_doc(options))
+                .append(Doc::text(")")),
+        }
+    }
+}
+
+fn pretty_comment(comment: &str) -> StaticDoc {
+    Doc::text("--").append(Doc::space()).append(Doc::as_string(comment))
+}
+
 fn pretty_lam_params(options: Options, params: &LamParams) -> StaticDoc {
     Doc::intersperse(
-        params.iter().map(|&(ref names, ref ann)| match *ann {
-            None if names.len() == 1 => Doc::as_string(&names[0].1),
-            None => unreachable!(), // FIXME - shouldn't be possible in AST
-            Some(ref ann) => Doc::text("(")
+        params.iter().map(|&(plicity, ref names, ref ann)| match (plicity, ann) {
+            (Plicity::Implicit, _) => {
+                pretty_implicit_params(options, names, ann.as_ref().map(|ann| &**ann))
+            },
+            (Plicity::Explicit, &None) if names.len() == 1 => Doc::as_string(&names[0].1),
+            (Plicity::Explicit, &None) => unreachable!(), // FIXME - shouldn't be possible in AST
+            (Plicity::Explicit, &Some(ref ann)) => Doc::text("(")
                 .append(Doc::intersperse(
                     names.iter().map(|name| Doc::as_string(&name.1)),
                     Doc::space(),
@@ -153,13 +367,100 @@ fn pretty_lam_params(options: Options, params: &LamParams) -> StaticDoc {
                 .append(Doc::space())
                 .append(Doc::text(":"))
                 .append(Doc::space())
-                .append(ann.to_doc(options))
+                .append(ann.to_doc(options.with_prec(Prec::PI)))
                 .append(Doc::text(")")),
         }),
         Doc::space(),
     )
 }
 
+fn pretty_implicit_params(
+    options: Options,
+    names: &[(ByteSpan, String)],
+    ann: Option<&Term>,
+) -> StaticDoc {
+    Doc::text("{")
+        .append(Doc::intersperse(
+            names.iter().map(|name| Doc::as_string(&name.1)),
+            Doc::space(),
+        ))
+        .append(match ann {
+            None => Doc::nil(),
+            Some(ann) => Doc::space()
+                .append(Doc::text(":"))
+                .append(Doc::space())
+                .append(ann.to_doc(options.with_prec(Prec::PI))),
+        })
+        .append(Doc::text("}"))
+}
+
+fn pretty_record_fields(
+    options: Options,
+    separator: &'static str,
+    fields: &[(ByteSpan, String, Box<Term>)],
+) -> StaticDoc {
+    if fields.is_empty() {
+        return Doc::text("{}");
+    }
+
+    Doc::text("{")
+        .append(Doc::space())
+        .append(Doc::intersperse(
+            fields.iter().map(|&(_, ref label, ref term)| {
+                Doc::as_string(label)
+                    .append(Doc::space())
+                    .append(Doc::text(separator))
+                    .append(Doc::space())
+                    .append(term.to_doc(options.with_prec(Prec::NO_WRAP)))
+            }),
+            Doc::text(";").append(Doc::space()),
+        ))
+        .append(Doc::space())
+        .append(Doc::text("}"))
+}
+
+fn pretty_case_clauses(options: Options, clauses: &CaseClauses) -> StaticDoc {
+    if clauses.is_empty() {
+        return Doc::text("{}");
+    }
+
+    Doc::text("{")
+        .append(Doc::space())
+        .append(Doc::intersperse(
+            clauses.iter().map(|&(ref pattern, ref body)| {
+                pattern
+                    .to_doc(options)
+                    .append(Doc::space())
+                    .append(Doc::text("=>"))
+                    .append(Doc::space())
+                    .append(body.to_doc(options.with_prec(Prec::NO_WRAP)))
+            }),
+            Doc::text(";").append(Doc::space()),
+        ))
+        .append(Doc::space())
+        .append(Doc::text("}"))
+}
+
+fn pretty_let_bindings(options: Options, bindings: &LetBindings) -> StaticDoc {
+    Doc::intersperse(
+        bindings.iter().map(|&(_, ref name, ref ann, ref value)| {
+            Doc::as_string(name)
+                .append(match *ann {
+                    None => Doc::nil(),
+                    Some(ref ann) => Doc::space()
+                        .append(Doc::text(":"))
+                        .append(Doc::space())
+                        .append(ann.to_doc(options.with_prec(Prec::PI))),
+                })
+                .append(Doc::space())
+                .append(Doc::text("="))
+                .append(Doc::space())
+                .append(value.to_doc(options.with_prec(Prec::NO_WRAP)))
+        }),
+        Doc::text(";").append(Doc::space()),
+    )
+}
+
 fn pretty_pi_params(options: Options, &(ref names, ref ann): &PiParams) -> StaticDoc {
     Doc::text("(")
         .append(Doc::intersperse(
@@ -169,6 +470,6 @@ fn pretty_pi_params(options: Options, &(ref names, ref ann): &PiParams) -> Stati
         .append(Doc::space())
         .append(Doc::text(":"))
         .append(Doc::space())
-        .append(ann.to_doc(options))
+        .append(ann.to_doc(options.with_prec(Prec::PI)))
         .append(Doc::text(")"))
 }
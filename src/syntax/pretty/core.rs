@@ -2,11 +2,12 @@
 
 use pretty::Doc;
 
-use syntax::core::{Definition, Module};
-use syntax::core::{Binder, Context, Level, Name, RcTerm, RcValue, Term, Value};
-use syntax::var::{Debruijn, Var};
+use syntax::core::{Constant, Definition, Exposing, Import, Module};
+use syntax::core::{Binder, Context, Level, Name, Pattern, Plicity, RcTerm, RcValue, Term};
+use syntax::core::{Transparency, Value};
+use syntax::var::{Debruijn, Named, Var};
 
-use super::{parens_if, Options, Prec, StaticDoc, ToDoc};
+use super::{parens_if, pretty_docs, pretty_ellipsis, Options, Prec, StaticDoc, ToDoc};
 
 pub fn pretty_ann<E: ToDoc, T: ToDoc>(options: Options, expr: &E, ty: &T) -> StaticDoc {
     parens_if(
@@ -23,8 +24,8 @@ pub fn pretty_ann<E: ToDoc, T: ToDoc>(options: Options, expr: &E, ty: &T) -> Sta
     )
 }
 
-pub fn pretty_universe(options: Options, level: Level) -> StaticDoc {
-    if level == Level(0) {
+pub fn pretty_universe(options: Options, level: &Level) -> StaticDoc {
+    if *level == Level::ZERO {
         Doc::text("Type")
     } else {
         parens_if(
@@ -35,9 +36,12 @@ pub fn pretty_universe(options: Options, level: Level) -> StaticDoc {
 }
 
 pub fn pretty_var(options: Options, var: &Var<Name, Debruijn>) -> StaticDoc {
-    match options.debug_indices {
-        true => Doc::text(format!("{:#}", var)),
-        false => Doc::as_string(var),
+    match *var {
+        Var::Bound(ref bound) if options.only_indices => Doc::as_string(bound.inner),
+        Var::Bound(ref bound) if options.debug_indices || options.always_show_indices => {
+            Doc::text(format!("{}{}", bound.name, bound.inner))
+        },
+        Var::Bound(Named { ref name, .. }) | Var::Free(ref name) => Doc::as_string(name),
     }
 }
 
@@ -51,21 +55,26 @@ pub fn pretty_name(options: Options, name: &Name) -> StaticDoc {
 
 pub fn pretty_lam<A: ToDoc, B: ToDoc>(
     options: Options,
+    plicity: Plicity,
     name: &Name,
     ann: Option<&A>,
     body: &B,
 ) -> StaticDoc {
+    let param = Doc::as_string(name).append(match ann.as_ref() {
+        Some(ann) => Doc::space()
+            .append(Doc::text(":"))
+            .append(Doc::space())
+            .append(ann.to_doc(options.with_prec(Prec::PI)).group()),
+        None => Doc::nil(),
+    });
+
     parens_if(
         Prec::LAM < options.prec,
         Doc::group(
             Doc::text(r"\")
-                .append(Doc::as_string(name))
-                .append(match ann.as_ref() {
-                    Some(ann) => Doc::space()
-                        .append(Doc::text(":"))
-                        .append(Doc::space())
-                        .append(ann.to_doc(options.with_prec(Prec::PI)).group()),
-                    None => Doc::nil(),
+                .append(match plicity {
+                    Plicity::Explicit => param,
+                    Plicity::Implicit => Doc::text("{").append(param).append(Doc::text("}")),
                 })
                 .append(Doc::space())
                 .append(Doc::text("=>")),
@@ -79,12 +88,44 @@ pub fn pretty_lam<A: ToDoc, B: ToDoc>(
 
 pub fn pretty_pi<A: ToDoc, B: ToDoc>(
     options: Options,
+    plicity: Plicity,
     name: &Name,
     ann: &A,
     body: &B,
 ) -> StaticDoc {
+    let (open, close) = match plicity {
+        Plicity::Explicit => ("(", ")"),
+        Plicity::Implicit => ("{", "}"),
+    };
+
     parens_if(
         Prec::PI < options.prec,
+        Doc::group(
+            Doc::text(open)
+                .append(Doc::as_string(name))
+                .append(Doc::space())
+                .append(Doc::text(":"))
+                .append(Doc::space())
+                .append(ann.to_doc(options.with_prec(Prec::PI)))
+                .append(Doc::text(close))
+                .append(Doc::space())
+                .append(Doc::text("->")),
+        ).append(Doc::group(
+            Doc::space()
+                .append(body.to_doc(options.with_prec(Prec::NO_WRAP)))
+                .nest(options.indent_width as usize),
+        )),
+    )
+}
+
+pub fn pretty_sigma<A: ToDoc, B: ToDoc>(
+    options: Options,
+    name: &Name,
+    ann: &A,
+    body: &B,
+) -> StaticDoc {
+    parens_if(
+        Prec::SIGMA < options.prec,
         Doc::group(
             Doc::text("(")
                 .append(Doc::as_string(name))
@@ -94,7 +135,109 @@ pub fn pretty_pi<A: ToDoc, B: ToDoc>(
                 .append(ann.to_doc(options.with_prec(Prec::PI)))
                 .append(Doc::text(")"))
                 .append(Doc::space())
-                .append(Doc::text("->")),
+                .append(Doc::text("*")),
+        ).append(Doc::group(
+            Doc::space()
+                .append(body.to_doc(options.with_prec(Prec::SIGMA)))
+                .nest(options.indent_width as usize),
+        )),
+    )
+}
+
+pub fn pretty_pair<A: ToDoc, B: ToDoc>(options: Options, fst: &A, snd: &B) -> StaticDoc {
+    Doc::group(
+        Doc::text("(")
+            .append(fst.to_doc(options.with_prec(Prec::NO_WRAP)))
+            .append(Doc::text(","))
+            .append(Doc::space())
+            .append(snd.to_doc(options.with_prec(Prec::NO_WRAP)))
+            .append(Doc::text(")")),
+    )
+}
+
+pub fn pretty_pair_proj<P: ToDoc>(options: Options, label: &'static str, pair: &P) -> StaticDoc {
+    parens_if(
+        Prec::APP < options.prec,
+        Doc::text(label)
+            .append(Doc::space())
+            .append(pair.to_doc(options.with_prec(Prec::ARG))),
+    )
+}
+
+pub fn pretty_record_ty<A: ToDoc>(options: Options, fields: &[(&str, &A)]) -> StaticDoc {
+    pretty_record_fields(options, "Record", ":", fields)
+}
+
+pub fn pretty_record<V: ToDoc>(options: Options, fields: &[(&str, &V)]) -> StaticDoc {
+    pretty_record_fields(options, "record", "=", fields)
+}
+
+fn pretty_record_fields<T: ToDoc>(
+    options: Options,
+    keyword: &'static str,
+    separator: &'static str,
+    fields: &[(&str, &T)],
+) -> StaticDoc {
+    if fields.is_empty() {
+        return Doc::text(keyword).append(Doc::space()).append(Doc::text("{}"));
+    }
+
+    Doc::group(
+        Doc::text(keyword)
+            .append(Doc::space())
+            .append(Doc::text("{"))
+            .append(
+                Doc::space()
+                    .append(Doc::intersperse(
+                        fields.iter().map(|&(label, term)| {
+                            Doc::text(label.to_owned())
+                                .append(Doc::space())
+                                .append(Doc::text(separator))
+                                .append(Doc::space())
+                                .append(term.to_doc(options.with_prec(Prec::NO_WRAP)))
+                        }),
+                        Doc::text(";").append(Doc::space()),
+                    ))
+                    .nest(options.indent_width as usize),
+            )
+            .append(Doc::space())
+            .append(Doc::text("}")),
+    )
+}
+
+pub fn pretty_proj<R: ToDoc>(options: Options, record: &R, label: &str) -> StaticDoc {
+    record
+        .to_doc(options.with_prec(Prec::ARG))
+        .append(Doc::text("."))
+        .append(Doc::text(label.to_owned()))
+}
+
+pub fn pretty_let<A: ToDoc, V: ToDoc, B: ToDoc>(
+    options: Options,
+    name: &Name,
+    ann: Option<&A>,
+    value: &V,
+    body: &B,
+) -> StaticDoc {
+    parens_if(
+        Prec::LAM < options.prec,
+        Doc::group(
+            Doc::text("let")
+                .append(Doc::space())
+                .append(Doc::as_string(name))
+                .append(match ann.as_ref() {
+                    Some(ann) => Doc::space()
+                        .append(Doc::text(":"))
+                        .append(Doc::space())
+                        .append(ann.to_doc(options.with_prec(Prec::PI)).group()),
+                    None => Doc::nil(),
+                })
+                .append(Doc::space())
+                .append(Doc::text("="))
+                .append(Doc::space())
+                .append(value.to_doc(options.with_prec(Prec::NO_WRAP)))
+                .append(Doc::space())
+                .append(Doc::text("in")),
         ).append(Doc::group(
             Doc::space()
                 .append(body.to_doc(options.with_prec(Prec::NO_WRAP)))
@@ -103,35 +246,173 @@ pub fn pretty_pi<A: ToDoc, B: ToDoc>(
     )
 }
 
+pub fn pretty_constant(constant: &Constant) -> StaticDoc {
+    Doc::as_string(constant)
+}
+
+pub fn pretty_if<C: ToDoc, T: ToDoc, F: ToDoc>(
+    options: Options,
+    cond: &C,
+    if_true: &T,
+    if_false: &F,
+) -> StaticDoc {
+    parens_if(
+        Prec::LAM < options.prec,
+        Doc::group(
+            Doc::text("if")
+                .append(Doc::space())
+                .append(cond.to_doc(options.with_prec(Prec::NO_WRAP)))
+                .append(Doc::space())
+                .append(Doc::text("then"))
+                .append(Doc::group(
+                    Doc::space()
+                        .append(if_true.to_doc(options.with_prec(Prec::NO_WRAP)))
+                        .nest(options.indent_width as usize),
+                ))
+                .append(Doc::space())
+                .append(Doc::text("else"))
+                .append(Doc::group(
+                    Doc::space()
+                        .append(if_false.to_doc(options.with_prec(Prec::NO_WRAP)))
+                        .nest(options.indent_width as usize),
+                )),
+        ),
+    )
+}
+
+pub fn pretty_case<S: ToDoc, B: ToDoc>(
+    options: Options,
+    scrutinee: &S,
+    clauses: &[(&Pattern, &B)],
+) -> StaticDoc {
+    let clauses = if clauses.is_empty() {
+        Doc::text("{}")
+    } else {
+        Doc::text("{")
+            .append(
+                Doc::space()
+                    .append(Doc::intersperse(
+                        clauses.iter().map(|&(pattern, body)| {
+                            pattern
+                                .to_doc(options)
+                                .append(Doc::space())
+                                .append(Doc::text("=>"))
+                                .append(Doc::space())
+                                .append(body.to_doc(options.with_prec(Prec::NO_WRAP)))
+                        }),
+                        Doc::text(";").append(Doc::space()),
+                    ))
+                    .nest(options.indent_width as usize),
+            )
+            .append(Doc::space())
+            .append(Doc::text("}"))
+    };
+
+    Doc::group(
+        Doc::text("case")
+            .append(Doc::space())
+            .append(scrutinee.to_doc(options.with_prec(Prec::NO_WRAP)))
+            .append(Doc::space())
+            .append(Doc::text("of"))
+            .append(Doc::space())
+            .append(clauses),
+    )
+}
+
 pub fn pretty_app<F: ToDoc, A: ToDoc>(options: Options, fn_term: &F, arg_term: &A) -> StaticDoc {
     parens_if(
         Prec::APP < options.prec,
         Doc::nil()
             .append(fn_term.to_doc(options.with_prec(Prec::APP)))
             .append(Doc::space())
-            .append(arg_term.to_doc(options.with_prec(Prec::APP))),
+            .append(arg_term.to_doc(options.with_prec(Prec::ARG))),
     )
 }
 
 impl ToDoc for Term {
     fn to_doc(&self, options: Options) -> StaticDoc {
+        let options = match options.descend() {
+            Some(options) => options,
+            None => return pretty_ellipsis(),
+        };
+
         match *self {
             Term::Ann(_, ref expr, ref ty) => pretty_ann(options, expr, ty),
-            Term::Universe(_, level) => pretty_universe(options, level),
+            Term::Universe(_, ref level) => pretty_universe(options, level),
             Term::Var(_, ref var) => pretty_var(options, var),
             Term::Lam(_, ref lam) => pretty_lam(
                 options,
+                lam.plicity,
                 &lam.unsafe_param.name,
                 lam.unsafe_param.inner.as_ref(),
                 &lam.unsafe_body,
             ),
             Term::Pi(_, ref pi) => pretty_pi(
                 options,
+                pi.plicity,
                 &pi.unsafe_param.name,
                 &pi.unsafe_param.inner,
                 &pi.unsafe_body,
             ),
             Term::App(_, ref f, ref a) => pretty_app(options, f, a),
+            Term::Let(_, ref let_) => pretty_let(
+                options,
+                &let_.unsafe_param.name,
+                let_.unsafe_param.inner.as_ref(),
+                &let_.value,
+                &let_.unsafe_body,
+            ),
+            Term::Sigma(_, ref sigma) => pretty_sigma(
+                options,
+                &sigma.unsafe_param.name,
+                &sigma.unsafe_param.inner,
+                &sigma.unsafe_body,
+            ),
+            Term::Pair(_, ref fst, ref snd) => pretty_pair(options, fst, snd),
+            Term::Fst(_, ref pair) => pretty_pair_proj(options, "fst", pair),
+            Term::Snd(_, ref pair) => pretty_pair_proj(options, "snd", pair),
+            Term::RecordType(_, _) | Term::RecordTypeEmpty(_) => {
+                let mut fields = Vec::new();
+                let mut current = self;
+                while let Term::RecordType(_, ref record_ty) = *current {
+                    fields.push((record_ty.label.as_str(), &record_ty.unsafe_param.inner));
+                    current = &*record_ty.unsafe_body.inner;
+                }
+                pretty_record_ty(options, &fields)
+            },
+            Term::Record(_, _, _, _) | Term::RecordEmpty(_) => {
+                let mut fields = Vec::new();
+                let mut current = self;
+                while let Term::Record(_, ref label, ref value, ref rest) = *current {
+                    fields.push((label.as_str(), value));
+                    current = &*rest.inner;
+                }
+                pretty_record(options, &fields)
+            },
+            Term::Proj(_, ref record, ref label) => pretty_proj(options, record, label),
+            Term::Constant(_, ref constant) => pretty_constant(constant),
+            Term::If(_, ref cond, ref if_true, ref if_false) => {
+                pretty_if(options, cond, if_true, if_false)
+            },
+            Term::Hole(_) => Doc::text("?"),
+            Term::Case(_, ref scrutinee, ref clauses) => {
+                let clauses = clauses
+                    .iter()
+                    .map(|clause| (&clause.unsafe_pattern, &clause.unsafe_body))
+                    .collect::<Vec<_>>();
+                pretty_case(options, scrutinee, &clauses)
+            },
+        }
+    }
+}
+
+impl ToDoc for Pattern {
+    fn to_doc(&self, options: Options) -> StaticDoc {
+        match *self {
+            Pattern::Wildcard(_) => Doc::text("_"),
+            Pattern::Binder(_, ref binder) => pretty_name(options, &binder.name),
+            Pattern::Constant(_, ref constant) => pretty_constant(constant),
+            Pattern::Pair(_, ref fst, ref snd) => pretty_pair(options, &**fst, &**snd),
         }
     }
 }
@@ -144,22 +425,61 @@ impl ToDoc for RcTerm {
 
 impl ToDoc for Value {
     fn to_doc(&self, options: Options) -> StaticDoc {
+        let options = match options.descend() {
+            Some(options) => options,
+            None => return pretty_ellipsis(),
+        };
+
         match *self {
-            Value::Universe(level) => pretty_universe(options, level),
+            Value::Universe(ref level) => pretty_universe(options, level),
             Value::Lam(ref lam) => pretty_lam(
                 options,
+                lam.plicity,
                 &lam.unsafe_param.name,
                 lam.unsafe_param.inner.as_ref(),
                 &lam.unsafe_body,
             ),
             Value::Pi(ref pi) => pretty_pi(
                 options,
+                pi.plicity,
                 &pi.unsafe_param.name,
                 &pi.unsafe_param.inner,
                 &pi.unsafe_body,
             ),
             Value::Var(ref var) => pretty_var(options, var),
             Value::App(ref fn_term, ref arg_term) => pretty_app(options, fn_term, arg_term),
+            Value::Sigma(ref sigma) => pretty_sigma(
+                options,
+                &sigma.unsafe_param.name,
+                &sigma.unsafe_param.inner,
+                &sigma.unsafe_body,
+            ),
+            Value::Pair(ref fst, ref snd) => pretty_pair(options, fst, snd),
+            Value::Fst(ref pair) => pretty_pair_proj(options, "fst", pair),
+            Value::Snd(ref pair) => pretty_pair_proj(options, "snd", pair),
+            Value::RecordType(_) | Value::RecordTypeEmpty => {
+                let mut fields = Vec::new();
+                let mut current = self;
+                while let Value::RecordType(ref record_ty) = *current {
+                    fields.push((record_ty.label.as_str(), &record_ty.unsafe_param.inner));
+                    current = &*record_ty.unsafe_body.inner;
+                }
+                pretty_record_ty(options, &fields)
+            },
+            Value::Record(_, _, _) | Value::RecordEmpty => {
+                let mut fields = Vec::new();
+                let mut current = self;
+                while let Value::Record(ref label, ref value, ref rest) = *current {
+                    fields.push((label.as_str(), value));
+                    current = &*rest.inner;
+                }
+                pretty_record(options, &fields)
+            },
+            Value::Proj(ref record, ref label) => pretty_proj(options, record, label),
+            Value::Constant(ref constant) => pretty_constant(constant),
+            Value::If(ref cond, ref if_true, ref if_false) => {
+                pretty_if(options, cond, if_true, if_false)
+            },
         }
     }
 }
@@ -176,7 +496,7 @@ impl ToDoc for Context {
             .append(Doc::intersperse(
                 self.binders
                     .iter()
-                    .map(|&(ref name, ref binder)| match *binder {
+                    .map(|&(ref name, ref binder, _)| match *binder {
                         Binder::Lam(ref ann) => Doc::group(
                             Doc::text(r"\").append(pretty_name(options, name)).append(
                                 match ann.as_ref() {
@@ -197,19 +517,33 @@ impl ToDoc for Context {
                                 .append(ann.to_doc(options.with_prec(Prec::PI)))
                                 .append(Doc::text(")")),
                         ),
-                        Binder::Let(ref ann, ref value) => Doc::group(
-                            Doc::text("let")
-                                .append(Doc::space())
-                                .append(pretty_name(options, name))
+                        Binder::Claim(ref ann) => Doc::group(
+                            pretty_name(options, name)
                                 .append(Doc::space())
                                 .append(Doc::text(":"))
                                 .append(Doc::space())
-                                .append(ann.to_doc(options.with_prec(Prec::PI)))
-                                .append(Doc::space())
-                                .append(Doc::text("="))
-                                .append(Doc::space())
-                                .append(value.to_doc(options.with_prec(Prec::PI))),
+                                .append(ann.to_doc(options.with_prec(Prec::PI))),
                         ),
+                        Binder::Let(ref value, ref ann, transparency) => {
+                            let keyword = match transparency {
+                                Transparency::Transparent => "let",
+                                Transparency::Opaque => "opaque",
+                            };
+
+                            Doc::group(
+                                Doc::text(keyword)
+                                    .append(Doc::space())
+                                    .append(pretty_name(options, name))
+                                    .append(Doc::space())
+                                    .append(Doc::text(":"))
+                                    .append(Doc::space())
+                                    .append(ann.to_doc(options.with_prec(Prec::PI)))
+                                    .append(Doc::space())
+                                    .append(Doc::text("="))
+                                    .append(Doc::space())
+                                    .append(value.to_doc(options.with_prec(Prec::PI))),
+                            )
+                        },
                     }),
                 Doc::text(",").append(Doc::space()),
             ))
@@ -219,7 +553,30 @@ impl ToDoc for Context {
 
 impl ToDoc for Definition {
     fn to_doc(&self, options: Options) -> StaticDoc {
-        match self.ann {
+        let opaque = match self.transparency {
+            Transparency::Transparent => Doc::nil(),
+            Transparency::Opaque => Doc::text("opaque").append(Doc::space()),
+        };
+
+        // Recursive definitions are declared along with their type
+        if let (true, Some(ann)) = (self.recursive, self.ann.as_ref()) {
+            return pretty_docs(&self.docs).append(Doc::group(
+                Doc::text("rec")
+                    .append(Doc::space())
+                    .append(Doc::as_string(&self.name))
+                    .append(Doc::space())
+                    .append(Doc::text(":"))
+                    .append(Doc::space())
+                    .append(ann.to_doc(options.with_prec(Prec::NO_WRAP)))
+                    .append(Doc::space())
+                    .append(Doc::text("="))
+                    .append(Doc::space())
+                    .append(self.term.to_doc(options.with_prec(Prec::NO_WRAP)))
+                    .append(Doc::text(";")),
+            ));
+        }
+
+        pretty_docs(&self.docs).append(match self.ann {
             None => Doc::nil(),
             Some(ref ann) => Doc::group(
                 Doc::as_string(&self.name)
@@ -229,8 +586,9 @@ impl ToDoc for Definition {
                     .append(ann.to_doc(options.with_prec(Prec::NO_WRAP)))
                     .append(Doc::text(";")),
             ).append(Doc::newline()),
-        }.append(Doc::group(
-            Doc::as_string(&self.name)
+        }).append(Doc::group(
+            opaque
+                .append(Doc::as_string(&self.name))
                 .append(Doc::space())
                 .append(Doc::text("="))
                 .append(Doc::space())
@@ -240,6 +598,46 @@ impl ToDoc for Definition {
     }
 }
 
+impl ToDoc for Import {
+    fn to_doc(&self, _: Options) -> StaticDoc {
+        let as_name = |name: &str| {
+            Doc::space()
+                .append(Doc::text("as"))
+                .append(Doc::space())
+                .append(Doc::as_string(name))
+        };
+
+        Doc::text("import")
+            .append(Doc::space())
+            .append(Doc::as_string(&self.name))
+            .append(self.rename.as_ref().map_or(Doc::nil(), |rename| as_name(rename)))
+            .append(match self.exposing {
+                Exposing::Nothing => Doc::nil(),
+                Exposing::All => Doc::space().append(Doc::text("(..)")),
+                Exposing::Exact(ref names) => Doc::space()
+                    .append(Doc::text("("))
+                    .append(Doc::intersperse(
+                        names.iter().map(|&(_, ref name, ref rename)| {
+                            let rename = if name == rename { Doc::nil() } else { as_name(rename) };
+                            Doc::as_string(name).append(rename)
+                        }),
+                        Doc::text(",").append(Doc::space()),
+                    ))
+                    .append(Doc::text(")")),
+                Exposing::Hiding(ref names) => Doc::space()
+                    .append(Doc::text("hiding"))
+                    .append(Doc::space())
+                    .append(Doc::text("("))
+                    .append(Doc::intersperse(
+                        names.iter().map(|&(_, ref name)| Doc::as_string(name)),
+                        Doc::text(",").append(Doc::space()),
+                    ))
+                    .append(Doc::text(")")),
+            })
+            .append(Doc::text(";"))
+    }
+}
+
 impl ToDoc for Module {
     fn to_doc(&self, options: Options) -> StaticDoc {
         Doc::group(
@@ -249,6 +647,16 @@ impl ToDoc for Module {
                 .append(Doc::text(";")),
         ).append(Doc::newline())
             .append(Doc::newline())
+            .append(Doc::intersperse(
+                self.imports
+                    .iter()
+                    .map(|import| import.to_doc(options).append(Doc::newline())),
+                Doc::nil(),
+            ))
+            .append(match self.imports.is_empty() {
+                true => Doc::nil(),
+                false => Doc::newline(),
+            })
             .append(Doc::intersperse(
                 self.definitions
                     .iter()
@@ -1,7 +1,10 @@
 //! Pretty printing utilities
 
+use codespan::{CodeMap, FileName};
 use pretty::{BoxDoc, Doc};
 
+use syntax::parse::{Lexer, Token};
+
 mod concrete;
 mod core;
 
@@ -13,7 +16,17 @@ pub use self::core::pretty_ann;
 pub struct Options {
     pub indent_width: u8,
     pub debug_indices: bool,
+    pub always_show_indices: bool,
+    /// Whether to print bound variables as their Debruijn indices alone,
+    /// without the names they were given
+    pub only_indices: bool,
     pub prec: Prec,
+    /// The depth past which nested terms are replaced with an ellipsis
+    pub max_depth: Option<u32>,
+    /// The depth of the term currently being printed
+    pub depth: u32,
+    /// Whether to highlight the output of `render` with ANSI escape codes
+    pub color: bool,
 }
 
 impl Default for Options {
@@ -21,7 +34,12 @@ impl Default for Options {
         Options {
             indent_width: 4,
             debug_indices: false,
+            always_show_indices: false,
+            only_indices: false,
             prec: Prec::NO_WRAP,
+            max_depth: None,
+            depth: 0,
+            color: false,
         }
     }
 }
@@ -43,10 +61,51 @@ impl Options {
         }
     }
 
+    /// Set whether the Debruijn indices should always be displayed alongside
+    /// the names of bound variables, regardless of `debug_indices`
+    pub fn with_always_show_indices(self, always_show_indices: bool) -> Options {
+        Options {
+            always_show_indices,
+            ..self
+        }
+    }
+
+    /// Set whether bound variables should be displayed as their Debruijn
+    /// indices alone, like `@0`, regardless of `debug_indices`. This is
+    /// useful for checking the indices that were assigned when binding.
+    pub fn with_only_indices(self, only_indices: bool) -> Options {
+        Options {
+            only_indices,
+            ..self
+        }
+    }
+
     /// Set the current precedence of the pretty printer
     pub fn with_prec(self, prec: Prec) -> Options {
         Options { prec, ..self }
     }
+
+    /// Set the depth past which nested terms are printed as `…`
+    pub fn with_max_depth(self, max_depth: Option<u32>) -> Options {
+        Options { max_depth, ..self }
+    }
+
+    /// Set whether to highlight the output of `render` with ANSI escape codes
+    pub fn with_color(self, color: bool) -> Options {
+        Options { color, ..self }
+    }
+
+    /// Move one level deeper into the term being printed, returning `None` if
+    /// this would exceed the maximum depth
+    fn descend(self) -> Option<Options> {
+        match self.max_depth {
+            Some(max_depth) if self.depth >= max_depth => None,
+            _ => Some(Options {
+                depth: self.depth + 1,
+                ..self
+            }),
+        }
+    }
 }
 
 /// The precedence of the pretty printer
@@ -61,7 +120,9 @@ impl Prec {
     pub const ANN: Prec = Prec(0);
     pub const LAM: Prec = Prec(1);
     pub const PI: Prec = Prec(2);
+    pub const SIGMA: Prec = Prec(3);
     pub const APP: Prec = Prec(10);
+    pub const ARG: Prec = Prec(11);
 }
 
 pub type StaticDoc = Doc<'static, BoxDoc<'static>>;
@@ -71,6 +132,92 @@ pub trait ToDoc {
     fn to_doc(&self, options: Options) -> StaticDoc;
 }
 
+/// The ANSI escape codes that are used to highlight the output of `render`
+mod ansi {
+    pub const KEYWORD: &str = "\x1b[35m";
+    pub const NAME: &str = "\x1b[36m";
+    pub const LITERAL: &str = "\x1b[33m";
+    pub const RESET: &str = "\x1b[0m";
+}
+
+/// Render a document so that it fits within the given width, highlighting it
+/// with ANSI escape codes if `options.color` is set
+///
+/// The document is laid out before it is highlighted, so the escape codes
+/// are never counted towards the width of the lines.
+pub fn render(doc: &StaticDoc, options: Options, width: usize) -> String {
+    let plain = doc.pretty(width).to_string();
+    if options.color {
+        highlight(&plain)
+    } else {
+        plain
+    }
+}
+
+/// Highlight the keywords, names, and literals in some rendered source code.
+/// Anything that can't be lexed is left as it is.
+fn highlight(src: &str) -> String {
+    let mut codemap = CodeMap::new();
+    let filemap = codemap.add_filemap(FileName::virtual_("<pretty>"), String::from(src));
+    let file_start = filemap.span().start();
+
+    let mut highlighted = String::with_capacity(src.len());
+    let mut last_end = 0;
+    for (start, token, end) in Lexer::new(&filemap).filter_map(Result::ok) {
+        let color = match token {
+            Token::Ident(_) => ansi::NAME,
+            Token::DecLiteral(_) | Token::StrLiteral(_) => ansi::LITERAL,
+            Token::As
+            | Token::Case
+            | Token::Else
+            | Token::Fst
+            | Token::If
+            | Token::Module
+            | Token::Import
+            | Token::Of
+            | Token::Opaque
+            | Token::Record
+            | Token::RecordType
+            | Token::Snd
+            | Token::Then
+            | Token::Type
+            | Token::In
+            | Token::Let
+            | Token::Where
+            | Token::BSlash
+            | Token::LArrow
+            | Token::LFatArrow => ansi::KEYWORD,
+            _ => continue,
+        };
+        let start = (start - file_start).0 as usize;
+        let end = (end - file_start).0 as usize;
+
+        highlighted.push_str(&src[last_end..start]);
+        highlighted.push_str(color);
+        highlighted.push_str(&src[start..end]);
+        highlighted.push_str(ansi::RESET);
+        last_end = end;
+    }
+    highlighted.push_str(&src[last_end..]);
+
+    highlighted
+}
+
+/// Print doc comments, with each line of the comment on a separate line
+fn pretty_docs(docs: &[String]) -> StaticDoc {
+    Doc::concat(docs.iter().map(|doc| {
+        Doc::text("|||")
+            .append(Doc::space())
+            .append(Doc::as_string(doc))
+            .append(Doc::newline())
+    }))
+}
+
+/// Printed in place of terms that are nested past the maximum depth
+fn pretty_ellipsis() -> StaticDoc {
+    Doc::text("…")
+}
+
 fn parens_if(should_wrap: bool, inner: StaticDoc) -> StaticDoc {
     match should_wrap {
         false => inner,
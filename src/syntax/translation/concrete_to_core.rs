@@ -2,7 +2,7 @@ use codespan::ByteSpan;
 
 use syntax::concrete;
 use syntax::core;
-use syntax::var::{Debruijn, Named, Var};
+use syntax::var::{Named, Var};
 
 /// Translate something to the corresponding core representation
 pub trait ToCore<T> {
@@ -20,28 +20,19 @@ pub trait ToCore<T> {
 /// ```text
 /// \(a : t1) => \(b : t1) => \c => \(d : t2) => t3
 /// ```
-fn lam_to_core(
-    params: &[(Vec<(ByteSpan, String)>, Option<Box<concrete::Term>>)],
-    body: &concrete::Term,
-) -> core::RcTerm {
-    let mut term = body.to_core();
+fn lam_to_core(params: &concrete::LamParams, body: core::RcTerm) -> core::RcTerm {
+    let mut term = body;
 
-    for &(ref names, ref ann) in params.iter().rev() {
+    for &(plicity, ref names, ref ann) in params.iter().rev() {
         for &(span, ref name) in names.iter().rev() {
             let name = core::Name::User(name.clone());
             let meta = core::SourceMeta {
                 span: span.to(term.span()),
             };
-            term = match *ann {
-                None => {
-                    core::Term::Lam(meta, core::TermLam::bind(Named::new(name, None), term)).into()
-                },
-                Some(ref ann) => {
-                    let ann = ann.to_core();
-                    core::Term::Lam(meta, core::TermLam::bind(Named::new(name, Some(ann)), term))
-                        .into()
-                },
-            };
+            let ann = ann.as_ref().map(|ann| ann.to_core());
+            let lam = core::TermLam::bind(Named::new(name, ann), term).with_plicity(plicity);
+
+            term = core::Term::Lam(meta, lam).into();
         }
     }
 
@@ -60,6 +51,7 @@ fn lam_to_core(
 /// (a : t1) -> (b : t1) -> t3
 /// ```
 fn pi_to_core(
+    plicity: core::Plicity,
     param_names: &[(ByteSpan, String)],
     ann: &concrete::Term,
     body: &concrete::Term,
@@ -76,6 +68,40 @@ fn pi_to_core(
             core::TermPi::bind(
                 Named::new(core::Name::User(name.clone()), ann.clone()),
                 term,
+            ).with_plicity(plicity),
+        ).into();
+    }
+
+    term
+}
+
+/// Convert a sugary sigma type from something like:
+///
+/// ```text
+/// (a b : t1) * t3
+/// ```
+///
+/// To a bunch of nested sigma types like:
+///
+/// ```text
+/// (a : t1) * (b : t1) * t3
+/// ```
+fn sigma_to_core(
+    param_names: &[(ByteSpan, String)],
+    ann: &concrete::Term,
+    body: &concrete::Term,
+) -> core::RcTerm {
+    let ann = ann.to_core();
+    let mut term = body.to_core();
+
+    for &(span, ref name) in param_names.iter().rev() {
+        term = core::Term::Sigma(
+            core::SourceMeta {
+                span: span.to(term.span()),
+            },
+            core::TermSigma::bind(
+                Named::new(core::Name::User(name.clone()), ann.clone()),
+                term,
             ),
         ).into();
     }
@@ -83,6 +109,152 @@ fn pi_to_core(
     term
 }
 
+/// Convert a record type from something like:
+///
+/// ```text
+/// Record { x : t1; y : t2 }
+/// ```
+///
+/// To a chain of fields ending in the empty record type, where the label of
+/// each field is bound in the types of the fields that follow it:
+///
+/// ```text
+/// Record { x : t1; Record { y : t2; Record {} } }
+/// ```
+fn record_ty_to_core(
+    span: ByteSpan,
+    fields: &[(ByteSpan, String, Box<concrete::Term>)],
+) -> core::RcTerm {
+    let mut term = core::Term::RecordTypeEmpty(core::SourceMeta { span }).into();
+
+    for &(label_span, ref label, ref ann) in fields.iter().rev() {
+        let meta = core::SourceMeta {
+            span: label_span.to(term.span()),
+        };
+        let param = Named::new(core::Name::user(label.as_str()), ann.to_core());
+        let record_ty = core::TermRecordType::bind(label.clone(), param, term);
+
+        term = core::Term::RecordType(meta, record_ty).into();
+    }
+
+    term
+}
+
+/// Convert a record into a chain of fields ending in the empty record
+fn record_to_core(
+    span: ByteSpan,
+    fields: &[(ByteSpan, String, Box<concrete::Term>)],
+) -> core::RcTerm {
+    let mut term = core::Term::RecordEmpty(core::SourceMeta { span }).into();
+
+    for &(label_span, ref label, ref value) in fields.iter().rev() {
+        let meta = core::SourceMeta {
+            span: label_span.to(term.span()),
+        };
+
+        term = core::Term::Record(meta, label.clone(), value.to_core(), term).into();
+    }
+
+    term
+}
+
+/// Convert a sugary let binding from something like:
+///
+/// ```text
+/// let x = t1; y : t2 = t3 in t4
+/// ```
+///
+/// To a bunch of nested let bindings like:
+///
+/// ```text
+/// let x = t1 in let y : t2 = t3 in t4
+/// ```
+fn let_to_core(
+    bindings: &[(ByteSpan, String, Option<Box<concrete::Term>>, Box<concrete::Term>)],
+    body: &concrete::Term,
+) -> core::RcTerm {
+    let mut term = body.to_core();
+
+    for &(span, ref name, ref ann, ref value) in bindings.iter().rev() {
+        let name = core::Name::User(name.clone());
+        let meta = core::SourceMeta {
+            span: span.to(term.span()),
+        };
+        let ann = ann.as_ref().map(|ann| ann.to_core());
+        let value = value.to_core();
+
+        let let_ = core::TermLet::bind(Named::new(name, ann), value, term);
+        term = core::Term::Let(meta, let_).into();
+    }
+
+    term
+}
+
+/// Convert the local declarations in a `where` block to let bindings around
+/// the body of a definition, so that they are only in scope in the body:
+///
+/// ```text
+/// foo x = t1 where { y : t2; y z = t3; }
+/// ```
+///
+/// Becomes:
+///
+/// ```text
+/// foo x = let y : t2 = \z => t3 in t1
+/// ```
+fn where_to_core(
+    where_block: &Option<(ByteSpan, Vec<concrete::Declaration>)>,
+    body: &concrete::Term,
+) -> core::RcTerm {
+    use std::collections::BTreeMap;
+
+    let declarations = match *where_block {
+        Some((_, ref declarations)) => declarations,
+        None => return body.to_core(),
+    };
+
+    let mut claims = BTreeMap::new();
+    let mut bindings = Vec::new();
+    for declaration in declarations {
+        match *declaration {
+            concrete::Declaration::Claim {
+                name: (_, ref name),
+                ref ann,
+                ..
+            } => {
+                claims.insert(name, ann.to_core());
+            },
+            concrete::Declaration::Definition {
+                name: (span, ref name),
+                ref params,
+                ref body,
+                ref where_block,
+                ..
+            } => {
+                let value = lam_to_core(params, where_to_core(where_block, body));
+                bindings.push((span, name, claims.remove(name), value));
+            },
+            // The parser has already reported these, so we can skip them
+            concrete::Declaration::Import { .. }
+            | concrete::Declaration::Recursive { .. }
+            | concrete::Declaration::Error(_) => {},
+        }
+    }
+
+    let mut term = body.to_core();
+    for (span, name, ann, value) in bindings.into_iter().rev() {
+        let meta = core::SourceMeta {
+            span: span.to(term.span()),
+        };
+        let param = Named::new(core::Name::User(name.clone()), ann);
+        let let_ = core::TermLet::bind(param, value, term);
+
+        term = core::Term::Let(meta, let_).into();
+    }
+
+    term
+}
+
 impl ToCore<core::Module> for concrete::Module {
     /// Convert the module in the concrete syntax to a module in the core syntax
     fn to_core(&self) -> core::Module {
@@ -93,69 +265,131 @@ impl ToCore<core::Module> for concrete::Module {
             concrete::Module::Valid {
                 ref name,
                 ref declarations,
+                ..
             } => {
                 // The type claims that we have encountered so far! We'll use these when
                 // we encounter their corresponding definitions later as type annotations
                 let mut claims = BTreeMap::new();
+                // The modules that are imported, to be resolved during type checking
+                let mut imports = Vec::new();
                 // The definitions, desugared from the concrete syntax
                 let mut definitions = Vec::<core::Definition>::new();
+                // Whether any declarations failed to parse
+                let mut recovered = false;
 
                 for declaration in declarations {
                     match *declaration {
-                        concrete::Declaration::Import { .. } => {
-                            unimplemented!("import declarations")
+                        concrete::Declaration::Import {
+                            span,
+                            name: (_, ref name),
+                            ref rename,
+                            ref exposing,
+                        } => {
+                            let exposing = match *exposing {
+                                // FIXME: Better error recovery
+                                None | Some(concrete::Exposing::Error(_)) => {
+                                    core::Exposing::Nothing
+                                },
+                                Some(concrete::Exposing::All(_)) => core::Exposing::All,
+                                Some(concrete::Exposing::Exact(_, ref names)) => {
+                                    let names = names.iter().map(|&((span, ref name), ref rename)| {
+                                        let rename = rename.as_ref().map_or(name, |r| &r.1);
+                                        (span, name.clone(), rename.clone())
+                                    });
+                                    core::Exposing::Exact(names.collect())
+                                },
+                                Some(concrete::Exposing::Hiding(_, ref names)) => {
+                                    core::Exposing::Hiding(names.clone())
+                                },
+                            };
+
+                            imports.push(core::Import {
+                                span,
+                                name: name.clone(),
+                                rename: rename.as_ref().map(|&(_, ref rename)| rename.clone()),
+                                exposing,
+                            });
                         },
                         // We've enountered a claim! Let's try to add it to the claims
                         // that we've seen so far...
                         concrete::Declaration::Claim {
+                            ref docs,
                             name: (_, ref name),
                             ref ann,
-                            ..
                         } => {
                             match claims.entry(name) {
                                 // Oh no! We've already seen a claim for this name!
                                 Entry::Occupied(_) => panic!(), // FIXME: Better error
-                                // This name does not yet have a claim associated with it
+                                // This name does not yet have a claim associated with it.
+                                // References to earlier definitions are left free, to be
+                                // looked up in the context during type checking.
                                 Entry::Vacant(mut entry) => {
-                                    let mut ann = ann.to_core();
-
-                                    for (level, definition) in definitions.iter().rev().enumerate()
-                                    {
-                                        let name = core::Name::user(definition.name.clone());
-                                        ann.close_at(Debruijn(level as u32), &name);
-                                    }
-
-                                    entry.insert(ann)
+                                    entry.insert((docs.clone(), ann.to_core()))
                                 },
                             };
                         },
                         // We've encountered a definition. Let's desugar it!
                         concrete::Declaration::Definition {
+                            docs: ref definition_docs,
                             name: (_, ref name),
                             ref params,
                             ref body,
-                            ..
+                            ref where_block,
+                            opaque,
                         } => {
                             let name = name.clone();
-                            let mut term = lam_to_core(params, body);
-                            let ann = claims.remove(&name);
+                            let term = lam_to_core(params, where_to_core(where_block, body));
+                            let (mut docs, ann) = match claims.remove(&name) {
+                                Some((docs, ann)) => (docs, Some(ann)),
+                                None => (vec![], None),
+                            };
+                            docs.extend(definition_docs.iter().cloned());
 
-                            for (level, definition) in definitions.iter().rev().enumerate() {
-                                let name = core::Name::user(definition.name.clone());
-                                term.close_at(Debruijn(level as u32), &name);
-                            }
+                            let transparency = match opaque {
+                                true => core::Transparency::Opaque,
+                                false => core::Transparency::Transparent,
+                            };
 
-                            definitions.push(core::Definition { name, term, ann });
+                            definitions.push(core::Definition {
+                                name,
+                                docs,
+                                term,
+                                ann,
+                                transparency,
+                                recursive: false,
+                            });
+                        },
+                        // Recursive definitions carry their own type annotation,
+                        // which the body is checked against
+                        concrete::Declaration::Recursive {
+                            ref docs,
+                            name: (_, ref name),
+                            ref ann,
+                            ref body,
+                        } => {
+                            definitions.push(core::Definition {
+                                name: name.clone(),
+                                docs: docs.clone(),
+                                term: body.to_core(),
+                                ann: Some(ann.to_core()),
+                                transparency: core::Transparency::Transparent,
+                                recursive: true,
+                            });
                         },
-                        concrete::Declaration::Error(_) => unimplemented!("error recovery"),
+                        // The parser has already reported these, so we skip them
+                        // and carry on checking the rest of the module
+                        concrete::Declaration::Error(_) => recovered = true,
                     }
                 }
 
+                // The definitions of some claims may have been among the
+                // declarations that failed to parse
                 // FIXME: Better error
-                assert!(claims.is_empty());
+                assert!(recovered || claims.is_empty());
 
                 core::Module {
                     name: name.1.clone(),
+                    imports,
                     definitions,
                 }
             },
@@ -164,6 +398,30 @@ impl ToCore<core::Module> for concrete::Module {
     }
 }
 
+impl ToCore<core::Pattern> for concrete::Pattern {
+    /// Convert a pattern in the concrete syntax into a core pattern
+    fn to_core(&self) -> core::Pattern {
+        let meta = core::SourceMeta { span: self.span() };
+        match *self {
+            concrete::Pattern::Wildcard(_) => core::Pattern::Wildcard(meta),
+            // The boolean literals are primitives rather than keywords, so we
+            // pick them out by name
+            concrete::Pattern::Var(_, ref name) if name == "true" => {
+                core::Pattern::Constant(meta, core::Constant::Bool(true))
+            },
+            concrete::Pattern::Var(_, ref name) if name == "false" => {
+                core::Pattern::Constant(meta, core::Constant::Bool(false))
+            },
+            concrete::Pattern::Var(_, ref name) => {
+                core::Pattern::Binder(meta, Named::new(core::Name::User(name.clone()), ()))
+            },
+            concrete::Pattern::Pair(_, ref fst, ref snd) => {
+                core::Pattern::Pair(meta, Box::new(fst.to_core()), Box::new(snd.to_core()))
+            },
+        }
+    }
+}
+
 impl ToCore<core::RcTerm> for concrete::Term {
     /// Convert a term in the concrete syntax into a core term
     fn to_core(&self) -> core::RcTerm {
@@ -177,15 +435,25 @@ impl ToCore<core::RcTerm> for concrete::Term {
                 core::Term::Ann(meta, expr, ty).into()
             },
             concrete::Term::Universe(_, level) => {
-                core::Term::Universe(meta, core::Level(level.unwrap_or(0))).into()
+                core::Term::Universe(meta, core::Level::Const(level.unwrap_or(0))).into()
+            },
+            concrete::Term::IntLit(_, value) => {
+                core::Term::Constant(meta, core::Constant::Int(value)).into()
             },
+            concrete::Term::StringLit(_, ref value) => {
+                core::Term::Constant(meta, core::Constant::String(value.clone())).into()
+            },
+            concrete::Term::Hole(_) => core::Term::Hole(meta).into(),
             concrete::Term::Var(_, ref x) => {
                 let var = Var::Free(core::Name::User(x.clone()));
 
                 core::Term::Var(meta, var).into()
             },
-            concrete::Term::Lam(_, ref params, ref body) => lam_to_core(params, body),
-            concrete::Term::Pi(_, (ref names, ref ann), ref body) => pi_to_core(names, ann, body),
+            concrete::Term::Lam(_, ref params, ref body) => lam_to_core(params, body.to_core()),
+            concrete::Term::Pi(_, plicity, (ref names, ref ann), ref body) => {
+                pi_to_core(plicity, names, ann, body)
+            },
+            concrete::Term::Let(_, ref bindings, ref body) => let_to_core(bindings, body),
             concrete::Term::Arrow(ref ann, ref body) => {
                 let name = core::Name::fresh(None::<&str>);
                 let ann = ann.to_core();
@@ -193,13 +461,66 @@ impl ToCore<core::RcTerm> for concrete::Term {
 
                 core::Term::Pi(meta, core::TermPi::bind(Named::new(name, ann), body)).into()
             },
-            concrete::Term::App(ref fn_expr, ref arg) => {
-                let fn_expr = fn_expr.to_core();
-                let arg = arg.to_core();
+            concrete::Term::Sigma(_, (ref names, ref ann), ref body) => {
+                sigma_to_core(names, ann, body)
+            },
+            concrete::Term::Product(ref ann, ref body) => {
+                let name = core::Name::fresh(None::<&str>);
+                let ann = ann.to_core();
+                let body = body.to_core();
+
+                core::Term::Sigma(meta, core::TermSigma::bind(Named::new(name, ann), body)).into()
+            },
+            concrete::Term::Pair(_, ref fst, ref snd) => {
+                core::Term::Pair(meta, fst.to_core(), snd.to_core()).into()
+            },
+            concrete::Term::Fst(_, ref pair) => core::Term::Fst(meta, pair.to_core()).into(),
+            concrete::Term::Snd(_, ref pair) => core::Term::Snd(meta, pair.to_core()).into(),
+            concrete::Term::RecordType(span, ref fields) => record_ty_to_core(span, fields),
+            concrete::Term::Record(span, ref fields) => record_to_core(span, fields),
+            concrete::Term::Proj(ref record, _, ref label) => {
+                core::Term::Proj(meta, record.to_core(), label.clone()).into()
+            },
+            concrete::Term::If(_, ref cond, ref if_true, ref if_false) => {
+                let cond = cond.to_core();
+                let if_true = if_true.to_core();
+                let if_false = if_false.to_core();
 
-                core::Term::App(meta, fn_expr, arg).into()
+                core::Term::If(meta, cond, if_true, if_false).into()
             },
-            concrete::Term::Error(_) => unimplemented!("error recovery"),
+            concrete::Term::Case(_, ref scrutinee, ref clauses) => {
+                let scrutinee = scrutinee.to_core();
+                let clauses = clauses
+                    .iter()
+                    .map(|&(ref pattern, ref body)| {
+                        core::TermClause::bind(pattern.to_core(), body.to_core())
+                    })
+                    .collect();
+
+                core::Term::Case(meta, scrutinee, clauses).into()
+            },
+            concrete::Term::App(_, _) => {
+                // Collect the arguments of the application spine, so that we
+                // can convert long spines without overflowing the stack
+                let mut args = Vec::new();
+                let mut head = self;
+                while let concrete::Term::App(ref fn_expr, ref arg) = *head {
+                    args.push(arg);
+                    head = fn_expr;
+                }
+
+                args.iter().rev().fold(head.to_core(), |fn_expr, arg| {
+                    let arg = arg.to_core();
+                    let meta = core::SourceMeta {
+                        span: fn_expr.span().to(arg.span()),
+                    };
+
+                    core::Term::App(meta, fn_expr, arg).into()
+                })
+            },
+            // The parser has already reported the error, so we leave a hole in
+            // its place and carry on checking the rest of the term
+            concrete::Term::Error(_) => core::Term::Hole(meta).into(),
         }
     }
 }
@@ -241,7 +562,8 @@ mod to_core {
     mod term {
         use super::*;
 
-        use syntax::core::{Level, Name, SourceMeta, Term, TermLam, TermPi};
+        use syntax::core::{Constant, Level, Name, Pattern, Plicity, SourceMeta, Term};
+        use syntax::core::{TermClause, TermLam, TermPi};
 
         #[test]
         fn var() {
@@ -631,9 +953,110 @@ mod to_core {
             );
         }
 
+        #[test]
+        fn id_implicit() {
+            let x = Name::user("x");
+            let a = Name::user("a");
+
+            assert_eq!(
+                parse(r"\{a : Type} (x : a) => x"),
+                Term::Lam(
+                    SourceMeta::default(),
+                    TermLam::bind(
+                        Named::new(
+                            a.clone(),
+                            Some(Term::Universe(SourceMeta::default(), Level::ZERO).into())
+                        ),
+                        Term::Lam(
+                            SourceMeta::default(),
+                            TermLam::bind(
+                                Named::new(
+                                    x.clone(),
+                                    Some(Term::Var(SourceMeta::default(), Var::Free(a)).into())
+                                ),
+                                Term::Var(SourceMeta::default(), Var::Free(x)).into(),
+                            )
+                        ).into(),
+                    ).with_plicity(Plicity::Implicit)
+                ).into(),
+            );
+        }
+
+        #[test]
+        fn id_ty_implicit() {
+            let a = Name::user("a");
+
+            assert_eq!(
+                parse(r"{a : Type} -> a -> a"),
+                Term::Pi(
+                    SourceMeta::default(),
+                    TermPi::bind(
+                        Named::new(
+                            a.clone(),
+                            Term::Universe(SourceMeta::default(), Level::ZERO).into()
+                        ),
+                        Term::Pi(
+                            SourceMeta::default(),
+                            TermPi::bind(
+                                Named::new(
+                                    Name::user("_"),
+                                    Term::Var(SourceMeta::default(), Var::Free(a.clone())).into()
+                                ),
+                                Term::Var(SourceMeta::default(), Var::Free(a)).into(),
+                            )
+                        ).into(),
+                    ).with_plicity(Plicity::Implicit)
+                ).into(),
+            );
+        }
+
+        #[test]
+        fn case_pair() {
+            let x = Name::user("x");
+            let pattern = Pattern::Pair(
+                SourceMeta::default(),
+                Box::new(Pattern::Binder(SourceMeta::default(), Named::new(x.clone(), ()))),
+                Box::new(Pattern::Constant(SourceMeta::default(), Constant::Bool(true))),
+            );
+
+            assert_eq!(
+                parse(r"case p of { (x, true) => x; _ => q }"),
+                Term::Case(
+                    SourceMeta::default(),
+                    Term::Var(SourceMeta::default(), Var::Free(Name::user("p"))).into(),
+                    vec![
+                        TermClause::bind(
+                            pattern,
+                            Term::Var(SourceMeta::default(), Var::Free(x)).into(),
+                        ),
+                        TermClause::bind(
+                            Pattern::Wildcard(SourceMeta::default()),
+                            Term::Var(SourceMeta::default(), Var::Free(Name::user("q"))).into(),
+                        ),
+                    ],
+                ).into(),
+            );
+        }
+
         mod sugar {
             use super::*;
 
+            #[test]
+            fn lam_args_implicit() {
+                assert_eq!(
+                    parse(r"\{a b} (x : a) => x"),
+                    parse(r"\{a} => \{b} => \x : a => x"),
+                );
+            }
+
+            #[test]
+            fn pi_args_implicit() {
+                assert_eq!(
+                    parse(r"{a b : Type} -> a -> b"),
+                    parse(r"{a : Type} -> {b : Type} -> a -> b"),
+                );
+            }
+
             #[test]
             fn lam_args() {
                 assert_eq!(
@@ -665,6 +1088,22 @@ mod to_core {
                     parse(r"(a : Type) -> (x : a) -> a"),
                 )
             }
+
+            #[test]
+            fn sigma_args() {
+                assert_eq!(
+                    parse(r"(a : Type) * (x y : a) * x"),
+                    parse(r"(a : Type) * (x : a) * (y : a) * x"),
+                );
+            }
+
+            #[test]
+            fn product() {
+                assert_eq!(
+                    parse(r"(a : Type) * a * a"),
+                    parse(r"(a : Type) * (x : a) * a"),
+                )
+            }
         }
     }
 }
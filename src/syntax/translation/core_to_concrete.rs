@@ -31,9 +31,21 @@ impl ToConcrete<concrete::Module> for core::Module {
             .flat_map(|definition| {
                 let name = (ByteSpan::none(), definition.name.clone());
 
+                // recursive definitions are declared along with their type
+                if let (true, Some(ann)) = (definition.recursive, definition.ann.as_ref()) {
+                    let new_definition = concrete::Declaration::Recursive {
+                        docs: definition.docs.clone(),
+                        name,
+                        ann: ann.to_concrete(env),
+                        body: definition.term.to_concrete(env),
+                    };
+                    return None.into_iter().chain(iter::once(new_definition));
+                }
+
                 // build up the type claim, if it exists
                 let ann = definition.ann.as_ref();
                 let new_ann = ann.map(|ann| concrete::Declaration::Claim {
+                    docs: definition.docs.clone(),
                     name: name.clone(),
                     ann: ann.to_concrete(env),
                 });
@@ -46,7 +58,22 @@ impl ToConcrete<concrete::Module> for core::Module {
                         body => (vec![], body),
                     };
 
-                    concrete::Declaration::Definition { name, params, body }
+                    let opaque = definition.transparency == core::Transparency::Opaque;
+
+                    // attach the docs to the claim, if it exists
+                    let docs = match new_ann {
+                        Some(_) => vec![],
+                        None => definition.docs.clone(),
+                    };
+
+                    concrete::Declaration::Definition {
+                        docs,
+                        name,
+                        params,
+                        body,
+                        where_block: None,
+                        opaque,
+                    }
                 };
 
                 new_ann.into_iter().chain(iter::once(new_definition))
@@ -56,37 +83,57 @@ impl ToConcrete<concrete::Module> for core::Module {
         concrete::Module::Valid {
             name: (ByteSpan::none(), self.name.clone()),
             declarations,
+            comments: Vec::new(),
         }
     }
 }
 
-impl ToConcrete<Option<u32>> for core::Level {
-    fn to_concrete(&self, _env: &Env) -> Option<u32> {
+impl ToConcrete<concrete::Pattern> for core::Pattern {
+    fn to_concrete(&self, env: &Env) -> concrete::Pattern {
         match *self {
-            core::Level(0) => None,
-            core::Level(level) => Some(level),
+            core::Pattern::Wildcard(meta) => concrete::Pattern::Wildcard(meta.span),
+            core::Pattern::Binder(meta, ref binder) => match binder.name {
+                core::Name::User(ref name) => concrete::Pattern::Var(meta.span, name.clone()),
+                // TODO: pick a pretty name, as for lambdas
+                ref name @ core::Name::Gen(_) => {
+                    concrete::Pattern::Var(meta.span, name.to_string())
+                },
+            },
+            // The boolean literals are referred to by the names of the
+            // primitives that introduce them
+            core::Pattern::Constant(meta, ref constant) => {
+                concrete::Pattern::Var(meta.span, constant.to_string())
+            },
+            core::Pattern::Pair(meta, ref fst, ref snd) => concrete::Pattern::Pair(
+                meta.span,
+                Box::new(fst.to_concrete(env)),
+                Box::new(snd.to_concrete(env)),
+            ),
         }
     }
 }
 
 impl ToConcrete<concrete::Term> for core::RcTerm {
     fn to_concrete(&self, env: &Env) -> concrete::Term {
-        // FIXME: add concrete::Term::Paren where needed
+        // Parentheses are added where they are needed by the pretty printer
         match *self.inner {
             core::Term::Ann(_, ref term, ref ty) => concrete::Term::Ann(
                 Box::new(term.to_concrete(env)),
                 Box::new(ty.to_concrete(env)),
             ),
-            core::Term::Universe(meta, level) => {
-                concrete::Term::Universe(meta.span, level.to_concrete(env))
+            core::Term::Universe(meta, ref level) => match level.to_const() {
+                Some(0) => concrete::Term::Universe(meta.span, None),
+                Some(level) => concrete::Term::Universe(meta.span, Some(level)),
+                // There is no concrete syntax for level variables yet
+                None => concrete::Term::Hole(meta.span),
             },
             core::Term::Var(meta, Var::Free(core::Name::User(ref name))) => {
                 concrete::Term::Var(meta.span, name.clone())
             },
-            core::Term::Var(_, Var::Free(core::Name::Gen(ref _gen))) => {
+            core::Term::Var(meta, Var::Free(ref name @ core::Name::Gen(_))) => {
                 // TODO: use name if it is present, and not used in the current scope
                 // otherwise create a pretty name
-                unimplemented!()
+                concrete::Term::Var(meta.span, name.to_string())
             },
             core::Term::Var(_, Var::Bound(_)) => {
                 // TODO: Better message
@@ -109,7 +156,8 @@ impl ToConcrete<concrete::Term> for core::RcTerm {
             },
             core::Term::Pi(_, ref pi) => {
                 let (param, body) = pi.clone().unbind();
-                if body.free_vars().contains(&param.name) {
+                let is_dependent = body.free_vars().contains(&param.name);
+                if pi.plicity == core::Plicity::Implicit || is_dependent {
                     // use name if it is present, and not used in the current scope
                     // otherwise create a pretty name
                     // add the used name to the environment
@@ -130,10 +178,213 @@ impl ToConcrete<concrete::Term> for core::RcTerm {
                     )
                 }
             },
-            core::Term::App(_, ref fn_term, ref arg) => concrete::Term::Ann(
+            core::Term::Let(meta, ref let_) => {
+                let (param, value, body) = let_.clone().unbind();
+                // TODO: pick a pretty name, as for lambdas
+                let binding = (
+                    ByteSpan::none(),
+                    param.name.to_string(),
+                    param.inner.map(|ann| Box::new(ann.to_concrete(env))),
+                    Box::new(value.to_concrete(env)),
+                );
+
+                match body.to_concrete(env) {
+                    // Collapse nested lets into a single let, as the
+                    // bindings are checked in sequence
+                    concrete::Term::Let(_, mut bindings, body) => {
+                        bindings.insert(0, binding);
+                        concrete::Term::Let(meta.span.start(), bindings, body)
+                    },
+                    body => concrete::Term::Let(meta.span.start(), vec![binding], Box::new(body)),
+                }
+            },
+            core::Term::App(_, ref fn_term, ref arg) => concrete::Term::App(
                 Box::new(fn_term.to_concrete(env)),
                 Box::new(arg.to_concrete(env)),
             ),
+            core::Term::Sigma(meta, ref sigma) => {
+                let (param, body) = sigma.clone().unbind();
+                if body.free_vars().contains(&param.name) {
+                    // TODO: pick a pretty name, as for pi types
+                    let names = vec![(ByteSpan::none(), param.name.to_string())];
+                    let params = (names, Box::new(param.inner.to_concrete(env)));
+                    let body = Box::new(body.to_concrete(env));
+
+                    concrete::Term::Sigma(meta.span.start(), params, body)
+                } else {
+                    // The body is not dependent on the parameter - so let's use a product!
+                    concrete::Term::Product(
+                        Box::new(param.inner.to_concrete(env)),
+                        Box::new(body.to_concrete(env)),
+                    )
+                }
+            },
+            core::Term::Pair(meta, ref fst, ref snd) => concrete::Term::Pair(
+                meta.span,
+                Box::new(fst.to_concrete(env)),
+                Box::new(snd.to_concrete(env)),
+            ),
+            core::Term::Fst(meta, ref pair) => {
+                concrete::Term::Fst(meta.span.start(), Box::new(pair.to_concrete(env)))
+            },
+            core::Term::Snd(meta, ref pair) => {
+                concrete::Term::Snd(meta.span.start(), Box::new(pair.to_concrete(env)))
+            },
+            core::Term::RecordType(meta, _) | core::Term::RecordTypeEmpty(meta) => {
+                let mut fields = Vec::new();
+                let mut current = self.clone();
+                loop {
+                    let next = match *current.inner {
+                        core::Term::RecordType(_, ref record_ty) => {
+                            // Later fields refer to earlier fields by their labels
+                            let label = record_ty.label.clone();
+                            let var = Var::Free(core::Name::user(label.as_str()));
+                            let ann = record_ty.unsafe_param.inner.to_concrete(env);
+
+                            fields.push((ByteSpan::none(), label, Box::new(ann)));
+                            record_ty.instantiate(&core::Term::Var(meta, var).into())
+                        },
+                        _ => break,
+                    };
+                    current = next;
+                }
+
+                concrete::Term::RecordType(meta.span, fields)
+            },
+            core::Term::Record(meta, _, _, _) | core::Term::RecordEmpty(meta) => {
+                let mut fields = Vec::new();
+                let mut current = self;
+                while let core::Term::Record(_, ref label, ref value, ref rest) = *current.inner {
+                    let value = value.to_concrete(env);
+
+                    fields.push((ByteSpan::none(), label.clone(), Box::new(value)));
+                    current = rest;
+                }
+
+                concrete::Term::Record(meta.span, fields)
+            },
+            core::Term::Proj(_, ref record, ref label) => concrete::Term::Proj(
+                Box::new(record.to_concrete(env)),
+                ByteSpan::none(),
+                label.clone(),
+            ),
+            core::Term::Constant(meta, core::Constant::Int(value)) => {
+                concrete::Term::IntLit(meta.span, value)
+            },
+            core::Term::Constant(meta, core::Constant::String(ref value)) => {
+                concrete::Term::StringLit(meta.span, value.clone())
+            },
+            // The remaining constants are referred to by the names of the
+            // primitives that introduce them
+            core::Term::Constant(meta, ref constant) => {
+                concrete::Term::Var(meta.span, constant.to_string())
+            },
+            core::Term::If(meta, ref cond, ref if_true, ref if_false) => concrete::Term::If(
+                meta.span.start(),
+                Box::new(cond.to_concrete(env)),
+                Box::new(if_true.to_concrete(env)),
+                Box::new(if_false.to_concrete(env)),
+            ),
+            core::Term::Hole(meta) => concrete::Term::Hole(meta.span),
+            core::Term::Case(meta, ref scrutinee, ref clauses) => {
+                let clauses = clauses
+                    .iter()
+                    .map(|clause| {
+                        // The variables bound by the pattern are referred to
+                        // by their names in the body
+                        let vars = clause
+                            .unsafe_pattern
+                            .binders()
+                            .into_iter()
+                            .map(|name| core::Term::Var(meta, Var::Free(name)).into())
+                            .collect::<Vec<_>>();
+                        let body = clause.instantiate(&vars).to_concrete(env);
+
+                        (clause.unsafe_pattern.to_concrete(env), Box::new(body))
+                    })
+                    .collect();
+
+                concrete::Term::Case(meta.span, Box::new(scrutinee.to_concrete(env)), clauses)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod to_concrete {
+    use codespan::{CodeMap, FileName};
+
+    use syntax::parse;
+    use syntax::translation::ToCore;
+    use syntax::var::Named;
+
+    use super::*;
+
+    fn parse(src: &str) -> core::RcTerm {
+        let mut codemap = CodeMap::new();
+        let filemap = codemap.add_filemap(FileName::virtual_("test"), src.into());
+
+        let (concrete_term, errors) = parse::term(&filemap);
+        assert!(errors.is_empty());
+
+        concrete_term.to_core()
+    }
+
+    #[test]
+    fn let_bindings() {
+        let term = parse(r"let x = Type; y : Type 1 = x in y");
+
+        match term.to_concrete(&Env::default()) {
+            concrete::Term::Let(_, ref bindings, ref body) => {
+                assert_eq!(bindings.len(), 2);
+                assert!(bindings[0].2.is_none());
+                assert!(bindings[1].2.is_some());
+                match **body {
+                    concrete::Term::Var(_, ref name) => assert_eq!(*name, bindings[1].1),
+                    ref body => panic!("expected a variable, found {:?}", body),
+                }
+            },
+            concrete => panic!("expected a let expression, found {:?}", concrete),
+        }
+    }
+
+    #[test]
+    fn sigma_dependent() {
+        let term = parse(r"(a : Type) * a");
+
+        match term.to_concrete(&Env::default()) {
+            concrete::Term::Sigma(_, (ref names, ref ann), ref body) => {
+                assert_eq!(names.len(), 1);
+                match **ann {
+                    concrete::Term::Universe(_, None) => {},
+                    ref ann => panic!("expected `Type`, found {:?}", ann),
+                }
+                match **body {
+                    concrete::Term::Var(_, ref name) => assert_eq!(*name, names[0].1),
+                    ref body => panic!("expected a variable, found {:?}", body),
+                }
+            },
+            concrete => panic!("expected a dependent pair type, found {:?}", concrete),
+        }
+    }
+
+    #[test]
+    fn sigma_non_dependent() {
+        match parse(r"Type * Type").to_concrete(&Env::default()) {
+            concrete::Term::Product(_, _) => {},
+            concrete => panic!("expected a pair type, found {:?}", concrete),
+        }
+    }
+
+    #[test]
+    fn pattern_binder_generated_name() {
+        let name = core::Name::fresh(Some("x"));
+        let binder = Named::new(name.clone(), ());
+        let pattern = core::Pattern::Binder(core::SourceMeta::default(), binder);
+
+        match pattern.to_concrete(&Env::default()) {
+            concrete::Pattern::Var(_, ref found) => assert_eq!(*found, name.to_string()),
+            concrete => panic!("expected a variable pattern, found {:?}", concrete),
         }
     }
 }
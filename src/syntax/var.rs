@@ -10,24 +10,44 @@
 //! - [A Locally-nameless Backend for Ott](http://www.di.ens.fr/~zappa/projects/ln_ott/)
 //! - [Library STLC_Tutorial](https://www.cis.upenn.edu/~plclub/popl08-tutorial/code/coqdoc/STLC_Tutorial.html)
 
+use std::cell::Cell;
 use std::fmt;
 use std::hash::{Hash, Hasher};
 
 /// A generated id
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+///
+/// Ids are kept exactly when serialized, but deserializing them does not
+/// reserve them on the current thread. Ids are ordered by when they were
+/// generated.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct GenId(u32);
 
+thread_local! {
+    // Terms are reference counted with `Rc`, so they never leave the thread
+    // they were created on. This means that ids only need to be unique within
+    // a single thread.
+    static NEXT_ID: Cell<u32> = Cell::new(0);
+}
+
 impl GenId {
-    /// Generate a new, globally unique id
+    /// Generate a new id, unique within the current thread
     pub fn fresh() -> GenId {
-        use std::sync::atomic::{AtomicUsize, Ordering};
-
-        lazy_static! {
-            static ref NEXT_ID : AtomicUsize = AtomicUsize::new(0);
-        }
+        NEXT_ID.with(|next_id| {
+            let id = next_id.get();
+            next_id.set(id.checked_add(1).expect("generated id overflow"));
+            GenId(id)
+        })
+    }
 
-        // FIXME: check for integer overflow
-        GenId(NEXT_ID.fetch_add(1, Ordering::SeqCst) as u32)
+    /// Restart id generation on the current thread from the given id
+    ///
+    /// This allows the names generated by a run of the pipeline to be
+    /// reproduced exactly, and is exposed through `pikelet check --seed`.
+    /// Any terms that were created using ids generated before the reset must
+    /// be discarded, otherwise their ids might clash with the new ones.
+    pub(crate) fn reset(next_id: u32) {
+        NEXT_ID.with(|id| id.set(next_id));
     }
 }
 
@@ -41,6 +61,7 @@ impl fmt::Display for GenId {
 ///
 /// The name is ignored for equality comparisons
 #[derive(Debug, Clone, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Named<N, T> {
     pub name: N,
     pub inner: T,
@@ -50,6 +71,19 @@ impl<N, T> Named<N, T> {
     pub fn new(name: N, inner: T) -> Named<N, T> {
         Named { name, inner }
     }
+
+    /// Apply a function to the inner value, keeping the name
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Named<N, U> {
+        Named::new(self.name, f(self.inner))
+    }
+
+    /// Apply a fallible function to the inner value, keeping the name
+    pub fn try_map<U, E, F>(self, f: F) -> Result<Named<N, U>, E>
+    where
+        F: FnOnce(T) -> Result<U, E>,
+    {
+        Ok(Named::new(self.name, f(self.inner)?))
+    }
 }
 
 impl<N, T: PartialEq> PartialEq for Named<N, T> {
@@ -75,17 +109,30 @@ impl<N, T: Hash> Hash for Named<N, T> {
 ///
 /// [debruijn index]: https://en.wikipedia.org/wiki/De_Bruijn_index
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
-pub struct Debruijn(pub u32);
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Debruijn(pub u64);
 
 impl Debruijn {
     /// The debruijn index of the current binder
     pub const ZERO: Debruijn = Debruijn(0);
 
     /// Move the current debruijn index into an inner binder
+    ///
+    /// An index can be no larger than the number of binders that enclose the
+    /// variable, and each of these is a separate allocation, so a 64-bit
+    /// index can't overflow before memory runs out. Terms that are nested
+    /// too deeply to be worked with are rejected earlier, by
+    /// `semantics::check_binder_depth`.
     pub fn succ(self) -> Debruijn {
         Debruijn(self.0 + 1)
     }
 
+    /// Move the current debruijn index into an inner binder, returning `None`
+    /// if the index would overflow
+    pub fn checked_succ(self) -> Option<Debruijn> {
+        self.0.checked_add(1).map(Debruijn)
+    }
+
     pub fn pred(self) -> Option<Debruijn> {
         match self {
             Debruijn::ZERO => None,
@@ -101,7 +148,13 @@ impl fmt::Display for Debruijn {
 }
 
 /// A variable that can either be free or bound
+///
+/// Equality is alpha equivalence: bound variables are compared by their
+/// debruijn indices alone, because `Named` ignores the name. Free variables
+/// are compared by name, including the ids of generated names, since two
+/// different free variables are never interchangeable.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Var<N, B> {
     /// A free variable
     Free(N),
@@ -0,0 +1,61 @@
+//! Integration tests for the `check` subcommand
+
+#![cfg(feature = "cli")]
+
+extern crate codespan;
+extern crate codespan_reporting;
+extern crate pikelet;
+extern crate structopt;
+
+use codespan::{CodeMap, FileName};
+use codespan_reporting::Severity;
+use pikelet::cli::check;
+use std::fs;
+use structopt::StructOpt;
+
+fn fixture(name: &str) -> String {
+    format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+#[test]
+fn mixed_reports_ill_typed_definition() {
+    let path = fixture("check-mixed.pi");
+    let mut codemap = CodeMap::new();
+    let src = fs::read_to_string(&path).unwrap();
+    let file = codemap.add_filemap(FileName::real(path.as_str()), src);
+
+    let diagnostics = pikelet::load_file(&file).err().unwrap();
+
+    assert_eq!(diagnostics.len(), 1);
+    match diagnostics[0].severity {
+        Severity::Error => {},
+        _ => panic!("expected the diagnostic to be an error"),
+    }
+}
+
+#[test]
+fn mixed_fails_to_check() {
+    let opts = check::Opts::from_iter(vec!["check", &fixture("check-mixed.pi")]);
+
+    match check::run(opts) {
+        Ok(()) => panic!("expected `check-mixed.pi` to fail to check"),
+        Err(err) => assert_eq!(err.to_string(), "1 file(s) failed to check"),
+    }
+}
+
+#[test]
+fn emit_core_is_reproducible_with_seed() {
+    fn emit_core() -> Vec<u8> {
+        let path = fixture("check-emit.pi");
+        let args = vec!["check", "--emit", "core", "--seed", "0", &path];
+        let mut out = Vec::new();
+        check::run_to(check::Opts::from_iter(args), &mut out).unwrap();
+        out
+    }
+
+    let first = emit_core();
+    let second = emit_core();
+
+    assert!(String::from_utf8_lossy(&first).starts_with("(define id "));
+    assert_eq!(first, second);
+}